@@ -0,0 +1,282 @@
+use anyhow::{Context, Result, anyhow, bail};
+use clap::parser::ValueSource;
+use clap::{ArgAction, CommandFactory, Parser};
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+use crate::constants::DEFAULT_CONFIG_PATH;
+use crate::types::Cli;
+
+/// Parse CLI arguments, merging in values from the config file if one is found.
+///
+/// Options given on the command line override the file (see `merge_config`).
+pub fn load_cli() -> Result<Cli> {
+    let args: Vec<String> = std::env::args().collect();
+    let cli = Cli::parse_from(&args);
+
+    let path = match &cli.config {
+        Some(path) => PathBuf::from(path),
+        None => match default_config_path() {
+            Some(path) if path.exists() => path,
+            _ => {
+                if let Some(profile) = &cli.profile {
+                    bail!("Profile '{}' requires a config file", profile);
+                }
+                return Ok(cli);
+            }
+        },
+    };
+
+    let mut table = read_config_file(&path)?;
+    apply_profile(&mut table, cli.profile.as_deref())?;
+
+    Ok(Cli::parse_from(merge_config(table, &args)?))
+}
+
+/// Arguments with the config values injected ahead of the real ones
+///
+/// Values of options given on the command line are dropped from the config,
+/// since list options would otherwise collect the values of both.
+pub fn merge_config(mut table: Table, args: &[String]) -> Result<Vec<String>> {
+    let matches = Cli::command().try_get_matches_from(args)?;
+    table.retain(|key, _| {
+        let id = key.replace('-', "_");
+        !matches.ids().any(|arg| arg.as_str() == id)
+            || matches.value_source(&id) != Some(ValueSource::CommandLine)
+    });
+
+    let mut merged_args = vec![args[0].clone()];
+    merged_args.extend(config_to_args(&table)?);
+    merged_args.extend(args.iter().skip(1).cloned());
+    Ok(merged_args)
+}
+
+/// Default config location (~/.config/reqs/config.toml)
+fn default_config_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(DEFAULT_CONFIG_PATH))
+}
+
+/// Read and parse a TOML config file
+fn read_config_file(path: &PathBuf) -> Result<Table> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))
+}
+
+/// Merge the selected `[profile.<name>]` section over the top-level values
+///
+/// Profile sections are always removed from the table, so unselected profiles
+/// never leak into the arguments.
+pub fn apply_profile(table: &mut Table, profile: Option<&str>) -> Result<()> {
+    let profiles = match table.remove("profile") {
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => bail!("Config key 'profile' must be a table of named profiles"),
+        None => Table::new(),
+    };
+
+    let Some(name) = profile else {
+        return Ok(());
+    };
+
+    match profiles.get(name) {
+        Some(Value::Table(values)) => {
+            for (key, value) in values {
+                table.insert(key.clone(), value.clone());
+            }
+            Ok(())
+        }
+        Some(_) => bail!("Profile '{}' must be a table", name),
+        None => bail!("Profile '{}' not found in config file", name),
+    }
+}
+
+/// Convert a config table into equivalent command-line arguments
+pub fn config_to_args(table: &Table) -> Result<Vec<String>> {
+    let command = Cli::command();
+    let mut args = Vec::new();
+
+    for (key, value) in table {
+        let id = key.replace('-', "_");
+        let (flag, action) = command
+            .get_arguments()
+            .filter(|arg| arg.get_id() != "config" && arg.get_id() != "profile")
+            .find(|arg| arg.get_id() == id.as_str())
+            .and_then(|arg| arg.get_long().map(|long| (long, arg.get_action().clone())))
+            .ok_or_else(|| anyhow!("Unknown config key: {}", key))?;
+
+        match value {
+            Value::Boolean(enabled) if !action.takes_values() => {
+                if *enabled {
+                    args.push(format!("--{}", flag));
+                }
+            }
+            Value::Integer(count) if matches!(action, ArgAction::Count) => {
+                for _ in 0..*count {
+                    args.push(format!("--{}", flag));
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    args.push(format!("--{}={}", flag, config_value_to_string(key, item)?));
+                }
+            }
+            other => {
+                args.push(format!(
+                    "--{}={}",
+                    flag,
+                    config_value_to_string(key, other)?
+                ));
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+/// Convert a scalar config value into its command-line string form
+fn config_value_to_string(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        _ => bail!("Unsupported value for config key '{}'", key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::StatusMatcher;
+    use crate::types::OutputFormat;
+
+    fn parse_with_config(config: &str, cli_args: &[&str]) -> Cli {
+        let table: Table = toml::from_str(config).unwrap();
+        let mut args = vec!["reqs".to_string()];
+        args.extend(cli_args.iter().map(|s| s.to_string()));
+        Cli::try_parse_from(merge_config(table, &args).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_config_to_args() {
+        let table: Table = toml::from_str(
+            r#"
+            timeout = 5
+            http2 = true
+            verify_ssl = false
+            rate-limit = 100
+            headers = ["User-Agent: reqs", "X-Test: 1"]
+            "#,
+        )
+        .unwrap();
+        let args = config_to_args(&table).unwrap();
+        assert!(args.contains(&"--timeout=5".to_string()));
+        assert!(args.contains(&"--http2".to_string()));
+        assert!(args.contains(&"--rate-limit=100".to_string()));
+        assert!(args.contains(&"--headers=User-Agent: reqs".to_string()));
+        assert!(args.contains(&"--headers=X-Test: 1".to_string()));
+        assert!(!args.iter().any(|a| a.starts_with("--verify-ssl")));
+    }
+
+    #[test]
+    fn test_config_unknown_key() {
+        let table: Table = toml::from_str("not_an_option = 1").unwrap();
+        assert!(config_to_args(&table).is_err());
+
+        let table: Table = toml::from_str("config = \"other.toml\"").unwrap();
+        assert!(config_to_args(&table).is_err());
+    }
+
+    #[test]
+    fn test_config_values_applied() {
+        let cli = parse_with_config(
+            r#"
+            timeout = 3
+            format = "jsonl"
+            follow_redirect = false
+            filter_status = [200, 404]
+            proxy = "http://127.0.0.1:8080"
+            verbose = 2
+            "#,
+            &[],
+        );
+        assert_eq!(cli.timeout, 3);
+        assert!(matches!(cli.format, OutputFormat::Jsonl));
+        assert!(!cli.follow_redirect);
+        assert_eq!(
+            cli.filter_status,
+            vec![StatusMatcher::code(200), StatusMatcher::code(404)]
+        );
+        assert_eq!(cli.proxy.as_deref(), Some("http://127.0.0.1:8080"));
+        assert_eq!(cli.verbose, 2);
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let config = r#"
+            timeout = 3
+            headers = ["X-Base: 1"]
+
+            [profile.bugbounty]
+            rate_limit = 10
+            headers = ["X-Bug-Bounty: hahwul"]
+            scope = ["*.example.com"]
+
+            [profile.internal]
+            proxy = "http://127.0.0.1:8080"
+        "#;
+
+        let mut table: Table = toml::from_str(config).unwrap();
+        apply_profile(&mut table, Some("bugbounty")).unwrap();
+        assert!(!table.contains_key("profile"));
+
+        let mut args = vec!["reqs".to_string()];
+        args.extend(config_to_args(&table).unwrap());
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert_eq!(cli.timeout, 3);
+        assert_eq!(
+            cli.rate_limit
+                .and_then(|rules| rules.default_value().copied()),
+            Some(10)
+        );
+        assert_eq!(cli.headers, vec!["X-Bug-Bounty: hahwul".to_string()]);
+        assert_eq!(cli.scope, vec!["*.example.com".to_string()]);
+        assert_eq!(cli.proxy, None);
+    }
+
+    #[test]
+    fn test_apply_profile_missing() {
+        let mut table: Table = toml::from_str("[profile.internal]\ntimeout = 1\n").unwrap();
+        assert!(apply_profile(&mut table, Some("unknown")).is_err());
+
+        let mut table: Table = toml::from_str("[profile.internal]\ntimeout = 1\n").unwrap();
+        apply_profile(&mut table, None).unwrap();
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_cli_overrides_config() {
+        let cli = parse_with_config(
+            "timeout = 3\nformat = \"jsonl\"\n",
+            &["--timeout", "20", "-f", "csv"],
+        );
+        assert_eq!(cli.timeout, 20);
+        assert!(matches!(cli.format, OutputFormat::Csv));
+    }
+
+    #[test]
+    fn test_cli_overrides_config_lists() {
+        let config = "filter_status = [\"200\"]\nheaders = [\"X-Base: 1\"]\n";
+        let cli = parse_with_config(config, &["--filter-status", "404"]);
+        assert_eq!(cli.filter_status, vec![StatusMatcher::code(404)]);
+        assert_eq!(cli.headers, vec!["X-Base: 1".to_string()]);
+
+        let cli = parse_with_config(config, &["-H", "X-Cli: 1", "--filter-status=500,503"]);
+        assert_eq!(
+            cli.filter_status,
+            vec![StatusMatcher::code(500), StatusMatcher::code(503)]
+        );
+        assert_eq!(cli.headers, vec!["X-Cli: 1".to_string()]);
+    }
+}
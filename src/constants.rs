@@ -1,13 +1,207 @@
+use std::time::Duration;
+
 /// HTTP constants
 pub const DEFAULT_REDIRECT_LIMIT: usize = 10;
 pub const HTTP_VERSION_2: &str = "HTTP/2.0";
 pub const HTTP_VERSION_1_1: &str = "HTTP/1.1";
+pub const TLS_SESSION_CACHE_SIZE: usize = 256;
+
+/// Clients kept for `@host:NAME` and --host-header address pins, least recently used dropped first
+pub const PINNED_CLIENT_LIMIT: usize = 64;
+
+/// User-Agent presets for --user-agent
+pub const USER_AGENT_PRESETS: [(&str, &str); 5] = [
+    (
+        "chrome",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36",
+    ),
+    (
+        "firefox",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:133.0) Gecko/20100101 Firefox/133.0",
+    ),
+    (
+        "safari",
+        "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_7_1) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.1 Safari/605.1.15",
+    ),
+    (
+        "mobile",
+        "Mozilla/5.0 (iPhone; CPU iPhone OS 18_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.1 Mobile/15E148 Safari/604.1",
+    ),
+    (
+        "googlebot",
+        "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+    ),
+];
+
+/// User-Agent strings rotated by --random-user-agent
+pub const RANDOM_USER_AGENTS: [&str; 6] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:133.0) Gecko/20100101 Firefox/133.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_7_1) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.1 Safari/605.1.15",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 18_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.1 Mobile/15E148 Safari/604.1",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36 Edg/131.0.0.0",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36",
+];
 
 /// HTML parsing constants
 pub const TITLE_SELECTOR: &str = "title";
+pub const META_SELECTOR: &str = "meta[content]";
+pub const CANONICAL_SELECTOR: &str = "link[rel=canonical][href]";
+pub const FORM_SELECTOR: &str = "form";
+pub const FORM_FIELD_SELECTOR: &str = "input[name], select[name], textarea[name], button[name]";
+
+/// LinkFinder-style pattern for endpoints quoted in JavaScript (group 1 is the endpoint)
+pub const JS_ENDPOINT_PATTERN: &str = r#"["'`]((?:[a-zA-Z]{1,10}://|//)[^"'`/]+\.[a-zA-Z]{2,}[^"'`]*|(?:/|\.\./|\./)[^"'`><,;| *()%$^/\\\[\]][^"'`><,;|()]+|[a-zA-Z0-9_\-/]+/[a-zA-Z0-9_\-/]+\.(?:[a-zA-Z]{1,4}|action)(?:[?#][^"'`]*)?|[a-zA-Z0-9_\-/]+/[a-zA-Z0-9_\-/]{3,}(?:[?#][^"'`]*)?|[a-zA-Z0-9_\-]+\.(?:php|asp|aspx|jsp|json|action|html|js|txt|xml)(?:[?#][^"'`]*)?)["'`]"#;
+
+/// Three base64url parts whose first two start with `{"` (--decode-jwt)
+pub const JWT_PATTERN: &str = r"eyJ[A-Za-z0-9_-]+\.eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*";
+/// Tokens valid for longer than this are flagged as long_expiry
+pub const JWT_LONG_EXPIRY: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Start of the canaries injected into query parameters (--detect-reflection)
+pub const REFLECTION_CANARY_PREFIX: &str = "reqs";
+
+/// Lowercase markers of directory indexes generated by common servers (--detect-listing)
+pub const DIRECTORY_LISTING_MARKERS: &[&str] = &[
+    "<title>index of /",
+    "<h1>index of /",
+    "<title>directory listing for /",
+    "[to parent directory]",
+    ">parent directory</a>",
+];
+
+/// Suffixes of backup copies probed for each input file (--check-backups)
+pub const BACKUP_SUFFIXES: &[&str] = &[".bak", "~", ".old"];
+
+/// Name fragments of hidden fields that carry a CSRF token (lowercase)
+pub const CSRF_FIELD_NAMES: &[&str] = &[
+    "csrf",
+    "xsrf",
+    "_token",
+    "authenticity_token",
+    "__requestverificationtoken",
+    "nonce",
+];
 
 /// Time conversion constants
 pub const MICROSECONDS_PER_SECOND: u64 = 1_000_000;
 
+/// Files kept open at once by --output-split
+pub const SPLIT_OPEN_FILES: usize = 128;
+
 /// HTTP methods
 pub const HTTP_METHODS: [&str; 7] = ["GET", "POST", "PUT", "DELETE", "HEAD", "PATCH", "OPTIONS"];
+
+/// Methods allowed by --safe-methods-only
+pub const SAFE_METHODS: [&str; 3] = ["GET", "HEAD", "OPTIONS"];
+
+/// Number of event payloads kept as samples by --sse
+pub const SSE_SAMPLE_EVENTS: usize = 3;
+
+/// Most requests a single input line may expand to
+pub const MAX_EXPANSION: u64 = 1_000_000;
+
+/// Longest duration accepted on the command line (100 years)
+pub const MAX_DURATION: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+/// Refresh interval of the --tui screen
+pub const TUI_TICK: Duration = Duration::from_millis(100);
+
+/// Largest concurrency --tui and --control can raise a run to
+pub const CONTROL_MAX_CONCURRENCY: usize = 1024;
+
+/// Lines read ahead of the requests in flight by --follow
+pub const FOLLOW_BUFFER: usize = 1024;
+
+/// How often --follow checks a file for appended lines
+pub const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long --input-redis waits for an item before checking for shutdown
+pub const QUEUE_POLL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Suffix of the list holding --input-redis items being processed
+pub const QUEUE_PROCESSING_SUFFIX: &str = ":processing";
+
+/// First wait before retrying a failed --input-redis read, doubled on each failure
+pub const QUEUE_RETRY_MIN: Duration = Duration::from_secs(1);
+
+/// Longest wait between retries of a failed --input-redis read
+pub const QUEUE_RETRY_MAX: Duration = Duration::from_secs(30);
+
+/// Steps of the --tui concurrency and rate limit keys
+pub const CONTROL_CONCURRENCY_STEP: isize = 1;
+pub const CONTROL_RATE_STEP: i64 = 10;
+
+/// Top-level fields of JSONL/CSV records that --fields can select
+pub const RECORD_FIELDS: &[&str] = &[
+    "method",
+    "url",
+    "scheme",
+    "host",
+    "port",
+    "path",
+    "ip_address",
+    "resolved_ips",
+    "asn",
+    "as_org",
+    "country",
+    "cdn",
+    "waf",
+    "cert_expires",
+    "cert_days_left",
+    "cert_expiring",
+    "bytes_up",
+    "bytes_down",
+    "trace_id",
+    "tags",
+    "status_code",
+    "content_length",
+    "response_time_ms",
+    "title",
+    "meta",
+    "forms",
+    "endpoints",
+    "jwts",
+    "reflected",
+    "directory_listing",
+    "score",
+    "changed",
+    "state",
+    "extracted",
+    "timing",
+    "websocket",
+    "sse",
+    "tcp",
+    "error",
+    "error_kind",
+    "proxy",
+    "failed_at",
+    "finding",
+    "backup_of",
+    "raw_request",
+    "response_body",
+];
+
+/// Export constants
+pub const ELASTIC_BULK_SIZE: usize = 500;
+
+/// Webhook notifications waiting to be sent before new ones are dropped
+pub const NOTIFY_QUEUE_SIZE: usize = 256;
+
+/// How long the end of the run waits for queued webhook notifications
+pub const NOTIFY_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Exit codes for --fail-on-match, --fail-without-match, --fail-on-errors and expectations
+pub const EXIT_MATCH: i32 = 2;
+pub const EXIT_NO_MATCH: i32 = 3;
+pub const EXIT_ERRORS: i32 = 4;
+pub const EXIT_EXPECT_FAILED: i32 = 5;
+
+/// MMDB files used by --enrich-asn when no path is given, relative to the home directory
+pub const DEFAULT_MMDB_PATHS: &[&str] = &[
+    ".config/reqs/GeoLite2-ASN.mmdb",
+    ".config/reqs/GeoLite2-Country.mmdb",
+];
+
+/// Config constants
+pub const DEFAULT_CONFIG_PATH: &str = ".config/reqs/config.toml";
@@ -0,0 +1,281 @@
+use anyhow::{Context, Result, bail};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tracing::{debug, info};
+
+use crate::constants::CONTROL_MAX_CONCURRENCY;
+
+/// Settings that can be changed while a run is going on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ControlStatus {
+    pub paused: bool,
+    /// Maximum number of in-flight requests (0 for unlimited)
+    pub concurrency: usize,
+    /// Requests per second, None for unlimited
+    pub rate_limit: Option<u64>,
+    pub in_flight: usize,
+}
+
+impl fmt::Display for ControlStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "paused={} concurrency={} rate={} in_flight={}",
+            self.paused,
+            self.concurrency,
+            self.rate_limit
+                .map_or("off".to_string(), |rate| rate.to_string()),
+            self.in_flight
+        )
+    }
+}
+
+/// Pause/resume, concurrency and rate limit of the worker pool (--tui, --control)
+///
+/// Workers take a permit before each request; permits are held back while
+/// the run is paused or the concurrency limit is reached.
+pub struct RunControl {
+    status: Mutex<ControlStatus>,
+    /// Upper bound of the worker pool, None when it is unbounded
+    pool_limit: Option<usize>,
+    changed: Notify,
+}
+
+/// A running request; releases its slot when dropped
+pub struct ControlPermit<'a> {
+    control: &'a RunControl,
+}
+
+impl Drop for ControlPermit<'_> {
+    fn drop(&mut self) {
+        self.control.status.lock().unwrap().in_flight -= 1;
+        self.control.changed.notify_waiters();
+    }
+}
+
+impl RunControl {
+    pub fn new(concurrency: usize, rate_limit: Option<u64>) -> Self {
+        RunControl {
+            status: Mutex::new(ControlStatus {
+                paused: false,
+                concurrency,
+                rate_limit,
+                in_flight: 0,
+            }),
+            pool_limit: (concurrency > 0).then(|| concurrency.max(CONTROL_MAX_CONCURRENCY)),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Number of workers the pool has to run so that the concurrency can be raised
+    pub fn pool_limit(&self) -> Option<usize> {
+        self.pool_limit
+    }
+
+    pub fn status(&self) -> ControlStatus {
+        *self.status.lock().unwrap()
+    }
+
+    pub fn rate_limit(&self) -> Option<u64> {
+        self.status.lock().unwrap().rate_limit
+    }
+
+    /// Wait until the run is not paused and a request slot is free
+    pub async fn acquire(&self) -> ControlPermit<'_> {
+        loop {
+            // Registered before checking, so a change in between is not missed
+            let changed = self.changed.notified();
+            {
+                let mut status = self.status.lock().unwrap();
+                if !status.paused
+                    && (status.concurrency == 0 || status.in_flight < status.concurrency)
+                {
+                    status.in_flight += 1;
+                    return ControlPermit { control: self };
+                }
+            }
+            changed.await;
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ControlStatus)) -> ControlStatus {
+        let status = {
+            let mut status = self.status.lock().unwrap();
+            f(&mut status);
+            *status
+        };
+        self.changed.notify_waiters();
+        status
+    }
+
+    pub fn set_paused(&self, paused: bool) -> ControlStatus {
+        self.update(|status| status.paused = paused)
+    }
+
+    pub fn toggle_pause(&self) -> ControlStatus {
+        self.update(|status| status.paused = !status.paused)
+    }
+
+    /// Set the concurrency limit, capped by the worker pool (0 for as many as the pool allows)
+    pub fn set_concurrency(&self, concurrency: usize) -> ControlStatus {
+        let concurrency = match self.pool_limit {
+            Some(limit) if concurrency == 0 || concurrency > limit => limit,
+            _ => concurrency,
+        };
+        self.update(|status| status.concurrency = concurrency)
+    }
+
+    /// Change the concurrency limit by `delta`; lowering an unlimited run starts from the in-flight count
+    pub fn adjust_concurrency(&self, delta: isize) -> ControlStatus {
+        let status = self.status();
+        let current = match status.concurrency {
+            0 if delta > 0 => return status,
+            0 => status.in_flight.max(1),
+            concurrency => concurrency,
+        };
+        self.set_concurrency(current.saturating_add_signed(delta).max(1))
+    }
+
+    pub fn set_rate_limit(&self, rate_limit: Option<u64>) -> ControlStatus {
+        self.update(|status| status.rate_limit = rate_limit.filter(|&rate| rate > 0))
+    }
+
+    /// Change the rate limit by `delta` requests per second; an unlimited rate is only lowered
+    pub fn adjust_rate_limit(&self, delta: i64) -> ControlStatus {
+        let status = self.status();
+        let rate = match status.rate_limit {
+            Some(rate) => rate.saturating_add_signed(delta).max(1),
+            None if delta < 0 => delta.unsigned_abs(),
+            None => return status,
+        };
+        self.set_rate_limit(Some(rate))
+    }
+
+    /// Run a control command and return the resulting settings
+    ///
+    /// Commands are `pause`, `resume`, `status`, `concurrency N` and `rate N|off`.
+    pub fn execute(&self, command: &str) -> Result<ControlStatus> {
+        let mut words = command.split_whitespace();
+        let status = match (words.next(), words.next()) {
+            (Some("pause"), None) => self.set_paused(true),
+            (Some("resume"), None) => self.set_paused(false),
+            (Some("status"), None) => self.status(),
+            (Some("concurrency"), Some(value)) => self.set_concurrency(
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid concurrency: {}", value))?,
+            ),
+            (Some("rate"), Some("off")) => self.set_rate_limit(None),
+            (Some("rate"), Some(value)) => self.set_rate_limit(Some(
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid rate: {}", value))?,
+            )),
+            _ => bail!("Unknown command: {}", command.trim()),
+        };
+        if words.next().is_some() {
+            bail!("Unknown command: {}", command.trim());
+        }
+        Ok(status)
+    }
+
+    /// Listen for control commands on `addr`, one per line
+    ///
+    /// Each command is answered with `ok <settings>` or `error: <reason>`.
+    pub async fn listen(self: &Arc<Self>, addr: &str) -> Result<()> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind control socket: {}", addr))?;
+        info!("Control socket listening on {}", listener.local_addr()?);
+        let control = self.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, peer)) = listener.accept().await {
+                debug!("Control connection from {}", peer);
+                tokio::spawn(control.clone().serve(stream));
+            }
+        });
+        Ok(())
+    }
+
+    async fn serve(self: Arc<Self>, stream: TcpStream) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = match self.execute(&line) {
+                Ok(status) => {
+                    info!("Control: {} -> {}", line.trim(), status);
+                    format!("ok {}\n", status)
+                }
+                Err(e) => format!("error: {:#}\n", e),
+            };
+            if writer.write_all(reply.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_execute_commands() {
+        let control = RunControl::new(10, None);
+        assert!(control.execute("pause").unwrap().paused);
+        assert!(!control.execute("resume").unwrap().paused);
+        assert_eq!(control.execute("concurrency 4").unwrap().concurrency, 4);
+        assert_eq!(
+            control.execute("concurrency 5000").unwrap().concurrency,
+            CONTROL_MAX_CONCURRENCY
+        );
+        assert_eq!(control.execute("rate 20").unwrap().rate_limit, Some(20));
+        assert_eq!(control.execute("rate off").unwrap().rate_limit, None);
+        assert!(control.execute("rate fast").is_err());
+        assert!(control.execute("pause now").is_err());
+        assert!(control.execute("stop").is_err());
+        assert_eq!(
+            control.status().to_string(),
+            format!(
+                "paused=false concurrency={} rate=off in_flight=0",
+                CONTROL_MAX_CONCURRENCY
+            )
+        );
+    }
+
+    #[test]
+    fn test_adjust() {
+        let control = RunControl::new(0, None);
+        assert_eq!(control.adjust_concurrency(1).concurrency, 0);
+        assert_eq!(control.adjust_concurrency(-1).concurrency, 1);
+        assert_eq!(control.adjust_concurrency(2).concurrency, 3);
+        assert_eq!(control.adjust_rate_limit(10).rate_limit, None);
+        assert_eq!(control.adjust_rate_limit(-10).rate_limit, Some(10));
+        assert_eq!(control.adjust_rate_limit(-10).rate_limit, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_resume_and_slots() {
+        let control = RunControl::new(1, None);
+        let first = control.acquire().await;
+        let wait = Duration::from_millis(50);
+        assert!(tokio::time::timeout(wait, control.acquire()).await.is_err());
+        drop(first);
+
+        control.set_paused(true);
+        assert!(tokio::time::timeout(wait, control.acquire()).await.is_err());
+        let (permit, _) = tokio::join!(control.acquire(), async {
+            control.set_paused(false);
+        });
+        assert_eq!(control.status().in_flight, 1);
+        drop(permit);
+        assert_eq!(control.status().in_flight, 0);
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use colored::*;
+use regex::Regex;
+
+/// Assertions checked against each response (--expect-* or inline expect-* options)
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    status: Vec<u16>,
+    body_regex: Option<Regex>,
+}
+
+impl Expectation {
+    /// Build an expectation, or None if nothing is expected
+    pub fn new(status: &[u16], body_regex: Option<&str>) -> Result<Option<Self>> {
+        let body_regex = body_regex
+            .map(|re| Regex::new(re).with_context(|| format!("Invalid expect regex: {}", re)))
+            .transpose()?;
+        if status.is_empty() && body_regex.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Expectation {
+            status: status.to_vec(),
+            body_regex,
+        }))
+    }
+
+    /// Combine global expectations with inline ones, which take precedence per kind
+    pub fn merge(
+        global: Option<&Expectation>,
+        status: &[u16],
+        body_regex: Option<&str>,
+    ) -> Result<Option<Self>> {
+        let status = match (status.is_empty(), global) {
+            (true, Some(global)) => global.status.clone(),
+            _ => status.to_vec(),
+        };
+        let inline = Expectation::new(&status, body_regex)?;
+        Ok(match (inline, global) {
+            (Some(mut inline), Some(global)) => {
+                if inline.body_regex.is_none() {
+                    inline.body_regex = global.body_regex.clone();
+                }
+                Some(inline)
+            }
+            (Some(inline), None) => Some(inline),
+            (None, global) => global.cloned(),
+        })
+    }
+
+    /// Whether checking the expectation requires the response body
+    pub fn needs_body(&self) -> bool {
+        self.body_regex.is_some()
+    }
+
+    /// Check a response, returning the reason of the first failed assertion
+    pub fn check(&self, status: u16, body: Option<&str>) -> Result<(), String> {
+        if !self.status.is_empty() && !self.status.contains(&status) {
+            let expected: Vec<String> = self.status.iter().map(u16::to_string).collect();
+            return Err(format!(
+                "expected status {}, got {}",
+                expected.join("|"),
+                status
+            ));
+        }
+        if let Some(re) = &self.body_regex
+            && !re.is_match(body.unwrap_or_default())
+        {
+            return Err(format!("body does not match /{}/", re));
+        }
+        Ok(())
+    }
+}
+
+/// PASS/FAIL label of a verdict
+pub fn verdict_label(passed: bool, colored: bool) -> String {
+    match (passed, colored) {
+        (true, true) => "[PASS]".green().bold().to_string(),
+        (true, false) => "[PASS]".to_string(),
+        (false, true) => "[FAIL]".red().bold().to_string(),
+        (false, false) => "[FAIL]".to_string(),
+    }
+}
+
+/// Format the PASS/FAIL line reported for a request
+pub fn format_verdict(
+    method: &str,
+    url: &str,
+    status: Option<u16>,
+    result: &Result<(), String>,
+    colored: bool,
+) -> String {
+    let mut line = format!(
+        "{} {} {}",
+        verdict_label(result.is_ok(), colored),
+        method,
+        url
+    );
+    if let Some(status) = status {
+        line.push_str(&format!(" -> {}", status));
+    }
+    if let Err(reason) = result {
+        line.push_str(&format!(": {}", reason));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expectation_check() {
+        assert!(Expectation::new(&[], None).unwrap().is_none());
+        assert!(Expectation::new(&[], Some("(")).is_err());
+
+        let exp = Expectation::new(&[200, 204], Some("ok")).unwrap().unwrap();
+        assert!(exp.needs_body());
+        assert_eq!(exp.check(200, Some("status: ok")), Ok(()));
+        assert_eq!(
+            exp.check(500, Some("ok")),
+            Err("expected status 200|204, got 500".to_string())
+        );
+        assert_eq!(
+            exp.check(204, None),
+            Err("body does not match /ok/".to_string())
+        );
+    }
+
+    #[test]
+    fn test_expectation_merge() {
+        let global = Expectation::new(&[200], Some("ok")).unwrap();
+        let merged = Expectation::merge(global.as_ref(), &[302], None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.status, vec![302]);
+        assert_eq!(merged.body_regex.unwrap().as_str(), "ok");
+
+        let merged = Expectation::merge(global.as_ref(), &[], Some("up"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.status, vec![200]);
+        assert_eq!(merged.body_regex.unwrap().as_str(), "up");
+
+        assert!(Expectation::merge(None, &[], None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_format_verdict() {
+        assert_eq!(
+            format_verdict("GET", "https://a.com", Some(200), &Ok(()), false),
+            "[PASS] GET https://a.com -> 200"
+        );
+        assert_eq!(
+            format_verdict(
+                "GET",
+                "https://a.com",
+                None,
+                &Err("connection refused".to_string()),
+                false
+            ),
+            "[FAIL] GET https://a.com: connection refused"
+        );
+    }
+}
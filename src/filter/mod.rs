@@ -1,121 +1,358 @@
-use regex::Regex;
+pub mod status;
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde_json::{Value, json};
+use std::ops::Range;
+
+use crate::types::FilterMode;
+use crate::utils::surrounding;
+
+pub use status::{StatusMatcher, parse_status_matcher, status_matches};
+
+/// Check if a status code is excluded by --filter-status
+///
+/// Used before the body is read, so filtered responses never download it.
+pub fn is_status_filtered(status: u16, filter_status: &[StatusMatcher]) -> bool {
+    !status_matches(status, filter_status)
+}
+
+/// Body matchers of --filter-string, --filter-string-not and --filter-regex
+#[derive(Debug, Clone, Default)]
+pub struct BodyFilter {
+    /// Literal strings, compiled to regexes so --filter-string-ci can ignore case
+    strings: Vec<Regex>,
+    /// Strings the body must not contain, whatever the mode
+    excluded: Vec<Regex>,
+    regexes: Vec<Regex>,
+    mode: FilterMode,
+}
+
+impl BodyFilter {
+    pub fn new(
+        strings: &[String],
+        excluded: &[String],
+        regexes: Vec<Regex>,
+        mode: FilterMode,
+        case_insensitive: bool,
+    ) -> Self {
+        let literal = |s: &String| {
+            RegexBuilder::new(&regex::escape(s))
+                .case_insensitive(case_insensitive)
+                .build()
+                .expect("escaped literal is a valid regex")
+        };
+        BodyFilter {
+            strings: strings.iter().map(literal).collect(),
+            excluded: excluded.iter().map(literal).collect(),
+            regexes,
+            mode,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty() && self.excluded.is_empty() && self.regexes.is_empty()
+    }
+
+    /// Whether the body passes the filter
+    ///
+    /// With [`FilterMode::And`] the body has to contain every filter string and
+    /// match every filter regex; with [`FilterMode::Or`] any one of them is
+    /// enough. Excluded strings reject the body in both modes.
+    pub fn matches(&self, body: &str) -> bool {
+        if self.excluded.iter().any(|re| re.is_match(body)) {
+            return false;
+        }
+        let mut matchers = self.strings.iter().chain(&self.regexes).peekable();
+        if matchers.peek().is_none() {
+            return true;
+        }
+        match self.mode {
+            FilterMode::And => matchers.all(|re| re.is_match(body)),
+            FilterMode::Or => matchers.any(|re| re.is_match(body)),
+        }
+    }
+
+    /// Byte range of the first --filter-string match in the body, or else the first --filter-regex match
+    pub fn find_match(&self, body: &str) -> Option<Range<usize>> {
+        self.strings
+            .iter()
+            .chain(&self.regexes)
+            .find_map(|re| re.find(body))
+            .map(|m| m.range())
+    }
+}
+
+/// Regexes on the page title (--filter-title, --filter-title-not)
+#[derive(Debug, Clone)]
+pub struct TitleFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+}
+
+impl TitleFilter {
+    pub fn new(include: Option<&str>, exclude: Option<&str>) -> Result<Option<Self>> {
+        let compile = |re: &str| {
+            Regex::new(re).with_context(|| format!("Invalid title filter regex: {}", re))
+        };
+        let include = include.map(compile).transpose()?;
+        let exclude = exclude.map(compile).transpose()?;
+        if include.is_none() && exclude.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(TitleFilter { include, exclude }))
+    }
+
+    /// Whether a page with this title passes; pages without a title only pass exclusions
+    pub fn matches(&self, title: Option<&str>) -> bool {
+        let included = match (&self.include, title) {
+            (Some(re), Some(title)) => re.is_match(title),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        let excluded = match (&self.exclude, title) {
+            (Some(re), Some(title)) => re.is_match(title),
+            _ => false,
+        };
+        included && !excluded
+    }
+}
+
+/// Input line matchers of --match-url and --exclude-url
+#[derive(Debug, Clone)]
+pub struct UrlFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl UrlFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Option<Self>> {
+        let compile = |res: &[String]| {
+            res.iter()
+                .map(|re| {
+                    Regex::new(re).with_context(|| format!("Invalid URL filter regex: {}", re))
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        let include = compile(include)?;
+        let exclude = compile(exclude)?;
+        if include.is_empty() && exclude.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(UrlFilter { include, exclude }))
+    }
+
+    /// Whether an input line matches one of the include patterns, if any, and none of the excludes
+    pub fn matches(&self, line: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|re| re.is_match(line)))
+            && !self.exclude.iter().any(|re| re.is_match(line))
+    }
+}
 
 /// Check if response should be filtered out based on criteria
 pub fn should_filter_response(
     status: u16,
     body: &Option<String>,
-    filter_status: &[u16],
-    filter_string: &Option<String>,
-    filter_regex: &Option<Regex>,
+    filter_status: &[StatusMatcher],
+    body_filter: &BodyFilter,
 ) -> bool {
     // Filter by status codes
-    if !filter_status.is_empty() && !filter_status.contains(&status) {
+    if is_status_filtered(status, filter_status) {
         return true;
     }
 
-    // Filter by string in response body
-    if let (Some(filter_str), Some(body_text)) = (filter_string, body) {
-        if !body_text.contains(filter_str) {
-            return true;
-        }
-    } else if filter_string.is_some() {
-        return true;
+    // Filter by strings and regexes in response body
+    if body_filter.is_empty() {
+        return false;
+    }
+    match body {
+        Some(body_text) => !body_filter.matches(body_text),
+        None => true,
     }
+}
 
-    // Filter by regex in response body
-    if let (Some(re), Some(body_text)) = (filter_regex, body) {
-        if !re.is_match(body_text) {
-            return true;
+/// Text matched by a filter and its surroundings (--match-context)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchContext {
+    pub matched: String,
+    /// The match with up to the configured number of characters on each side
+    pub context: String,
+}
+
+impl MatchContext {
+    /// Context of the match at byte range `range` of `body`, `chars` characters on each side
+    pub fn new(body: &str, range: Range<usize>, chars: usize) -> Self {
+        MatchContext {
+            matched: body[range.clone()].to_string(),
+            context: surrounding(body, range, chars),
         }
-    } else if filter_regex.is_some() {
-        return true;
     }
 
-    false
+    pub fn to_json(&self) -> Value {
+        json!({
+            "match": self.matched,
+            "context": self.context,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn body_filter(strings: &[&str], regexes: &[&str], mode: FilterMode) -> BodyFilter {
+        let strings: Vec<String> = strings.iter().map(|s| s.to_string()).collect();
+        let regexes = regexes.iter().map(|re| Regex::new(re).unwrap()).collect();
+        BodyFilter::new(&strings, &[], regexes, mode, false)
+    }
+
     #[test]
     fn test_filter_by_status() {
-        let filter_status = vec![200, 404];
-        assert!(!should_filter_response(
-            200,
-            &None,
-            &filter_status,
-            &None,
-            &None
-        ));
-        assert!(should_filter_response(
-            500,
-            &None,
-            &filter_status,
-            &None,
-            &None
-        ));
+        let filter_status = vec![StatusMatcher::code(200), StatusMatcher::code(404)];
+        let none = BodyFilter::default();
+        assert!(!should_filter_response(200, &None, &filter_status, &none));
+        assert!(should_filter_response(500, &None, &filter_status, &none));
+    }
+
+    #[test]
+    fn test_is_status_filtered() {
+        let filter_status = [StatusMatcher::code(200), StatusMatcher::code(301)];
+        assert!(!is_status_filtered(500, &[]));
+        assert!(!is_status_filtered(200, &filter_status));
+        assert!(is_status_filtered(404, &filter_status));
     }
 
     #[test]
     fn test_filter_by_string() {
         let body = Some("test content".to_string());
-        let filter_string = Some("test".to_string());
-        assert!(!should_filter_response(
-            200,
-            &body,
-            &[],
-            &filter_string,
-            &None
-        ));
+        let filter = body_filter(&["test"], &[], FilterMode::And);
+        assert!(!should_filter_response(200, &body, &[], &filter));
 
-        let filter_string = Some("missing".to_string());
-        assert!(should_filter_response(
-            200,
-            &body,
-            &[],
-            &filter_string,
-            &None
-        ));
+        let filter = body_filter(&["missing"], &[], FilterMode::And);
+        assert!(should_filter_response(200, &body, &[], &filter));
     }
 
     #[test]
     fn test_filter_by_regex() {
         let body = Some("test content".to_string());
-        let filter_regex = Some(Regex::new(r"content$").unwrap());
+        let filter = body_filter(&[], &[r"content$"], FilterMode::And);
 
         // Regex matches the body, so it shouldn't filter
-        assert!(!should_filter_response(
-            200,
-            &body,
-            &[],
-            &None,
-            &filter_regex
-        ));
+        assert!(!should_filter_response(200, &body, &[], &filter));
 
-        let filter_regex = Some(Regex::new(r"^missing").unwrap());
+        let filter = body_filter(&[], &[r"^missing"], FilterMode::And);
 
         // Regex does not match the body, so it should filter
-        assert!(should_filter_response(
-            200,
-            &body,
-            &[],
-            &None,
-            &filter_regex
-        ));
+        assert!(should_filter_response(200, &body, &[], &filter));
 
         // Regex provided but no body, so it should filter
-        assert!(should_filter_response(
+        assert!(should_filter_response(200, &None, &[], &filter));
+    }
+
+    #[test]
+    fn test_filter_mode() {
+        let body = Some("login failed: invalid password".to_string());
+        let strings = ["login", "welcome"];
+        let regexes = [r"invalid \w+"];
+
+        // "welcome" is missing, so not every matcher matches
+        let filter = body_filter(&strings, &regexes, FilterMode::And);
+        assert!(should_filter_response(200, &body, &[], &filter));
+        let filter = body_filter(&strings, &regexes, FilterMode::Or);
+        assert!(!should_filter_response(200, &body, &[], &filter));
+        let filter = body_filter(&strings[..1], &regexes, FilterMode::And);
+        assert!(!should_filter_response(200, &body, &[], &filter));
+        let filter = body_filter(&strings[1..], &[], FilterMode::Or);
+        assert!(should_filter_response(200, &body, &[], &filter));
+    }
+
+    #[test]
+    fn test_filter_case_insensitive_and_excluded() {
+        let body = "Internal Server ERROR";
+        let strings = ["error".to_string()];
+        let filter = BodyFilter::new(&strings, &[], Vec::new(), FilterMode::And, false);
+        assert!(!filter.matches(body));
+        let filter = BodyFilter::new(&strings, &[], Vec::new(), FilterMode::And, true);
+        assert!(filter.matches(body));
+        assert_eq!(filter.find_match(body), Some(16..21));
+
+        // Excluded strings reject the body in either mode, and alone select the rest
+        let excluded = ["error".to_string()];
+        let filter = BodyFilter::new(&[], &excluded, Vec::new(), FilterMode::Or, true);
+        assert!(!filter.matches(body));
+        assert!(filter.matches("OK"));
+        let filter = BodyFilter::new(&excluded, &excluded, Vec::new(), FilterMode::Or, false);
+        assert!(!filter.matches("error"));
+    }
+
+    #[test]
+    fn test_url_filter() {
+        assert!(UrlFilter::new(&[], &[]).unwrap().is_none());
+        assert!(UrlFilter::new(&["(".to_string()], &[]).is_err());
+
+        let include = ["/api/".to_string(), "/graphql$".to_string()];
+        let exclude = [r"\.(png|jpg|css)$".to_string()];
+        let filter = UrlFilter::new(&include, &exclude).unwrap().unwrap();
+        assert!(filter.matches("https://example.com/api/users"));
+        assert!(filter.matches("POST https://example.com/graphql"));
+        assert!(!filter.matches("https://example.com/about"));
+        assert!(!filter.matches("https://example.com/api/logo.png"));
+
+        let filter = UrlFilter::new(&[], &exclude).unwrap().unwrap();
+        assert!(filter.matches("https://example.com/about"));
+        assert!(!filter.matches("https://example.com/style.css"));
+    }
+
+    #[test]
+    fn test_title_filter() {
+        assert!(TitleFilter::new(None, None).unwrap().is_none());
+        assert!(TitleFilter::new(Some("("), None).is_err());
+
+        let filter = TitleFilter::new(Some("(?i)admin"), None).unwrap().unwrap();
+        assert!(filter.matches(Some("Admin Console")));
+        assert!(!filter.matches(Some("Home")));
+        assert!(!filter.matches(None));
+
+        let filter = TitleFilter::new(None, Some("404 Not Found|Welcome to nginx"))
+            .unwrap()
+            .unwrap();
+        assert!(!filter.matches(Some("Welcome to nginx!")));
+        assert!(filter.matches(Some("Dashboard")));
+        assert!(filter.matches(None));
+    }
+
+    #[test]
+    fn test_no_filter() {
+        assert!(!should_filter_response(
             200,
             &None,
             &[],
-            &None,
-            &filter_regex
+            &BodyFilter::default()
         ));
     }
 
     #[test]
-    fn test_no_filter() {
-        assert!(!should_filter_response(200, &None, &[], &None, &None));
+    fn test_find_filter_match() {
+        let body = "error: token=abc123 expired";
+        let filter = body_filter(
+            &["missing", "expired"],
+            &["token=[a-z0-9]+"],
+            FilterMode::And,
+        );
+        assert_eq!(filter.find_match(body), Some(20..27));
+        let filter = body_filter(&[], &["token=[a-z0-9]+"], FilterMode::And);
+        assert_eq!(filter.find_match(body), Some(7..19));
+        let filter = body_filter(&["missing"], &[], FilterMode::And);
+        assert_eq!(filter.find_match(body), None);
+    }
+
+    #[test]
+    fn test_match_context() {
+        let body = "<p>Welcome back, admin! Your session token is ready.</p>";
+        let start = body.find("admin").unwrap();
+        let context = MatchContext::new(body, start..start + 5, 8);
+        assert_eq!(context.matched, "admin");
+        assert_eq!(context.context, "...e back, admin! Your s...");
+        assert_eq!(context.to_json()["match"], "admin");
     }
 }
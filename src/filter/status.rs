@@ -0,0 +1,129 @@
+use std::fmt;
+
+/// A --filter-status item: a code, a range or a class, optionally negated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusMatcher {
+    pub low: u16,
+    pub high: u16,
+    /// Excludes the matched codes instead of selecting them ("!404")
+    pub negated: bool,
+}
+
+impl StatusMatcher {
+    /// Matcher for a single status code
+    pub fn code(code: u16) -> Self {
+        StatusMatcher {
+            low: code,
+            high: code,
+            negated: false,
+        }
+    }
+
+    /// Whether `status` falls in the code, range or class, ignoring negation
+    pub fn contains(&self, status: u16) -> bool {
+        (self.low..=self.high).contains(&status)
+    }
+}
+
+impl fmt::Display for StatusMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "!")?;
+        }
+        if self.low == self.high {
+            write!(f, "{}", self.low)
+        } else if self.low.is_multiple_of(100) && self.high == self.low + 99 {
+            write!(f, "{}xx", self.low / 100)
+        } else {
+            write!(f, "{}-{}", self.low, self.high)
+        }
+    }
+}
+
+/// Parse a --filter-status item: "200", "200-299", "3xx", or any of them prefixed with "!"
+pub fn parse_status_matcher(s: &str) -> Result<StatusMatcher, String> {
+    let s = s.trim();
+    let (negated, spec) = match s.strip_prefix('!') {
+        Some(spec) => (true, spec.trim()),
+        None => (false, s),
+    };
+    let code = |code: &str| {
+        code.trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|code| (100..=999).contains(code))
+            .ok_or_else(|| format!("invalid status code '{}'", code.trim()))
+    };
+
+    let (low, high) =
+        if let Some(class) = spec.strip_suffix("xx").or_else(|| spec.strip_suffix("XX")) {
+            let class: u16 = class
+                .parse()
+                .ok()
+                .filter(|class| (1..=9).contains(class))
+                .ok_or_else(|| format!("invalid status class '{}'", spec))?;
+            (class * 100, class * 100 + 99)
+        } else if let Some((low, high)) = spec.split_once('-') {
+            let (low, high) = (code(low)?, code(high)?);
+            if low > high {
+                return Err(format!("invalid status range '{}'", spec));
+            }
+            (low, high)
+        } else {
+            let code = code(spec)?;
+            (code, code)
+        };
+    Ok(StatusMatcher { low, high, negated })
+}
+
+/// Whether `status` passes the matchers
+///
+/// It has to match one of the plain matchers, if there are any, and none of
+/// the negated ones.
+pub fn status_matches(status: u16, matchers: &[StatusMatcher]) -> bool {
+    let mut selectors = matchers.iter().filter(|m| !m.negated).peekable();
+    let selected = selectors.peek().is_none() || selectors.any(|m| m.contains(status));
+    selected && !matchers.iter().any(|m| m.negated && m.contains(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matchers(spec: &str) -> Vec<StatusMatcher> {
+        spec.split(',')
+            .map(|item| parse_status_matcher(item).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_status_matcher() {
+        for spec in ["200", "200-250", "3xx", "!404", "!5xx"] {
+            assert_eq!(parse_status_matcher(spec).unwrap().to_string(), spec);
+        }
+        assert_eq!(
+            parse_status_matcher("2XX").unwrap(),
+            parse_status_matcher("200-299").unwrap()
+        );
+        for invalid in ["", "abc", "42", "299-200", "0xx", "!", "1000"] {
+            assert!(parse_status_matcher(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_status_matches() {
+        let filter = matchers("200-299,3xx,!204,!301");
+        assert!(status_matches(200, &filter));
+        assert!(status_matches(302, &filter));
+        assert!(!status_matches(204, &filter));
+        assert!(!status_matches(301, &filter));
+        assert!(!status_matches(404, &filter));
+
+        let filter = matchers("!404,!5xx");
+        assert!(status_matches(200, &filter));
+        assert!(!status_matches(404, &filter));
+        assert!(!status_matches(503, &filter));
+
+        assert!(status_matches(418, &[]));
+    }
+}
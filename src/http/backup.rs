@@ -0,0 +1,93 @@
+use reqwest::StatusCode;
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
+use serde_json::{Value, json};
+
+use crate::constants::BACKUP_SUFFIXES;
+
+/// A backup copy found next to an input file (--check-backups)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupFinding {
+    /// URL of the backup copy
+    pub url: String,
+    /// The input URL it is a copy of
+    pub source: String,
+    pub status: u16,
+    pub size: u64,
+}
+
+impl BackupFinding {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "finding": "backup_file",
+            "url": self.url,
+            "backup_of": self.source,
+            "status_code": self.status,
+            "content_length": self.size,
+        })
+    }
+}
+
+/// URLs of the backup copies probed for a URL, e.g. /app/config.php.bak
+///
+/// Only URLs that name a file have backups; the query and fragment are dropped.
+pub fn backup_urls(url: &str) -> Vec<String> {
+    let Ok(mut url) = reqwest::Url::parse(url) else {
+        return Vec::new();
+    };
+    let path = url.path().to_string();
+    if path.ends_with('/') {
+        return Vec::new();
+    }
+    url.set_query(None);
+    url.set_fragment(None);
+    BACKUP_SUFFIXES
+        .iter()
+        .map(|suffix| {
+            url.set_path(&format!("{}{}", path, suffix));
+            url.to_string()
+        })
+        .collect()
+}
+
+/// Whether a probe response is a backup copy rather than an error page
+///
+/// Servers send unknown extensions as binary or text, while soft 404s are
+/// HTML pages.
+pub fn is_backup_hit(status: StatusCode, headers: &HeaderMap) -> bool {
+    let html = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim_start().starts_with("text/html"));
+    status == StatusCode::OK && !html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_urls() {
+        assert_eq!(
+            backup_urls("https://example.com/app/config.php?debug=1"),
+            vec![
+                "https://example.com/app/config.php.bak",
+                "https://example.com/app/config.php~",
+                "https://example.com/app/config.php.old",
+            ]
+        );
+        assert!(backup_urls("https://example.com/app/").is_empty());
+        assert!(backup_urls("https://example.com").is_empty());
+    }
+
+    #[test]
+    fn test_is_backup_hit() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, "application/octet-stream".parse().unwrap());
+        assert!(is_backup_hit(StatusCode::OK, &headers));
+        assert!(is_backup_hit(StatusCode::OK, &HeaderMap::new()));
+        assert!(!is_backup_hit(StatusCode::NOT_FOUND, &headers));
+
+        headers.insert(CONTENT_TYPE, "text/html; charset=utf-8".parse().unwrap());
+        assert!(!is_backup_hit(StatusCode::OK, &headers));
+    }
+}
@@ -0,0 +1,115 @@
+use reqwest::Response;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::utils::parse_size;
+
+/// Parse --max-bandwidth: bytes per second such as "5MB/s" or "500KB"
+pub fn parse_bandwidth(value: &str) -> Result<u64, String> {
+    let trimmed = value.trim();
+    let size = trimmed
+        .strip_suffix("/s")
+        .or_else(|| trimmed.strip_suffix("/S"))
+        .unwrap_or(trimmed);
+    match parse_size(size) {
+        Ok(0) => Err(format!(
+            "Invalid bandwidth '{}'. Must be above 0 bytes per second",
+            value
+        )),
+        Ok(rate) => Ok(rate),
+        Err(_) => Err(format!(
+            "Invalid bandwidth '{}'. Expected a rate such as 5MB/s or 500KB/s",
+            value
+        )),
+    }
+}
+
+/// Download rate shared by every request (--max-bandwidth)
+///
+/// Each chunk read books its share of the rate; readers sleep until the
+/// booked time, so concurrent downloads together stay under the limit.
+pub struct Bandwidth {
+    bytes_per_second: u64,
+    /// When the bytes read so far will have been paid for
+    next: Mutex<Instant>,
+}
+
+impl Bandwidth {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Bandwidth {
+            bytes_per_second,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until `bytes` more may be read
+    pub async fn consume(&self, bytes: usize) {
+        let cost = Duration::from_secs_f64(bytes as f64 / self.bytes_per_second as f64);
+        let until = {
+            let mut next = self.next.lock().await;
+            *next = (*next).max(Instant::now()) + cost;
+            *next
+        };
+        tokio::time::sleep_until(until).await;
+    }
+
+    /// Read a response body chunk by chunk within the limit
+    pub async fn read_body(&self, mut resp: Response) -> reqwest::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            self.consume(chunk.len()).await;
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    /// Read a response body within the limit and decode it as `Response::text` does,
+    /// using the charset of its Content-Type
+    pub async fn read_text(&self, resp: Response) -> reqwest::Result<String> {
+        let headers = resp.headers().clone();
+        let body = self.read_body(resp).await?;
+        let mut decoded = http::Response::new(body);
+        *decoded.headers_mut() = headers;
+        Response::from(decoded).text().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bandwidth() {
+        assert_eq!(parse_bandwidth("5MB/s"), Ok(5 * 1024 * 1024));
+        assert_eq!(parse_bandwidth("500kb"), Ok(500 * 1024));
+        assert_eq!(parse_bandwidth("100"), Ok(100));
+        assert!(parse_bandwidth("0/s").is_err());
+        assert!(parse_bandwidth("fast").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_shared() {
+        let bandwidth = Bandwidth::new(1000);
+        let start = Instant::now();
+        // Two readers of 200 bytes each take 400ms together at 1000 B/s
+        tokio::join!(bandwidth.consume(200), bandwidth.consume(200));
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(390));
+        assert!(elapsed < Duration::from_millis(800));
+    }
+
+    #[tokio::test]
+    async fn test_read_text_charset() {
+        let mut resp = http::Response::new(b"caf\xe9".to_vec());
+        resp.headers_mut().insert(
+            reqwest::header::CONTENT_TYPE,
+            "text/plain; charset=iso-8859-1".parse().unwrap(),
+        );
+        let text = Bandwidth::new(1_000_000)
+            .read_text(Response::from(resp))
+            .await
+            .unwrap();
+        assert_eq!(text, "café");
+    }
+}
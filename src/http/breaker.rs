@@ -0,0 +1,95 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::http::liveness::{HostKey, host_key};
+
+#[derive(Debug, Default)]
+struct HostErrors {
+    consecutive: u32,
+    skipped: u64,
+}
+
+/// Per-host circuit breaker used by --max-host-errors
+///
+/// After `max` consecutive failed requests to a host:port, its remaining
+/// requests are skipped. A response in between resets the count.
+pub struct HostBreaker {
+    max: u32,
+    hosts: Mutex<HashMap<HostKey, HostErrors>>,
+}
+
+impl HostBreaker {
+    pub fn new(max: u32) -> Self {
+        HostBreaker {
+            max,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a request to `url` may be sent; refused requests are counted as skipped
+    pub fn allow(&self, url: &str) -> bool {
+        let (Some(key), Ok(mut hosts)) = (host_key(url), self.hosts.lock()) else {
+            return true;
+        };
+        match hosts.get_mut(&key) {
+            Some(host) if host.consecutive >= self.max => {
+                host.skipped += 1;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Record the result of a request; returns true if this failure opened the breaker
+    pub fn record(&self, url: &str, success: bool) -> bool {
+        let (Some(key), Ok(mut hosts)) = (host_key(url), self.hosts.lock()) else {
+            return false;
+        };
+        let host = hosts.entry(key).or_default();
+        if success {
+            host.consecutive = 0;
+            return false;
+        }
+        host.consecutive += 1;
+        host.consecutive == self.max
+    }
+
+    /// Skipped requests per host:port, for hosts that had any
+    pub fn skipped(&self) -> BTreeMap<String, u64> {
+        let Ok(hosts) = self.hosts.lock() else {
+            return BTreeMap::new();
+        };
+        hosts
+            .iter()
+            .filter(|(_, host)| host.skipped > 0)
+            .map(|((host, port), host_errors)| (format!("{}:{}", host, port), host_errors.skipped))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_breaker() {
+        let breaker = HostBreaker::new(2);
+        let dead = "http://dead.example.com/a";
+
+        assert!(breaker.allow(dead));
+        assert!(!breaker.record(dead, false));
+        // A response resets the count
+        assert!(!breaker.record(dead, true));
+        assert!(!breaker.record(dead, false));
+        assert!(breaker.record("http://dead.example.com/b", false));
+
+        assert!(!breaker.allow("http://dead.example.com/c"));
+        assert!(!breaker.allow(dead));
+        assert!(breaker.allow("https://dead.example.com/"));
+        assert!(breaker.allow("not a url"));
+        assert_eq!(
+            breaker.skipped(),
+            BTreeMap::from([("dead.example.com:80".to_string(), 2)])
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use reqwest::header::{HOST, HeaderMap};
+use reqwest::{Request, StatusCode};
+
+/// Bytes sent and received for a request (--count-bytes)
+///
+/// Counted from the HTTP/1.1 form of the request and response; TLS records,
+/// HTTP/2 framing and headers added by the connection are not included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ByteCount {
+    pub up: u64,
+    pub down: u64,
+}
+
+/// Size of a request line, its headers and body
+pub fn request_size(req: &Request) -> u64 {
+    let url = req.url();
+    let target = url.path().len() + url.query().map_or(0, |query| query.len() + 1);
+    // "METHOD TARGET HTTP/1.1\r\n" and the blank line ending the headers
+    let mut size = req.method().as_str().len() + 1 + target + 11 + 2;
+    size += headers_size(req.headers());
+    if !req.headers().contains_key(HOST) {
+        let host = url.host_str().unwrap_or_default();
+        let port = url.port().map_or(0, |port| port.to_string().len() + 1);
+        size += "Host: \r\n".len() + host.len() + port;
+    }
+    let body = req
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map_or(0, <[u8]>::len);
+    (size + body) as u64
+}
+
+/// Size of a response status line and headers
+pub fn response_head_size(status: StatusCode, headers: &HeaderMap) -> u64 {
+    // "HTTP/1.1 200 OK\r\n" and the blank line ending the headers
+    let reason = status.canonical_reason().unwrap_or_default();
+    (9 + 3 + 1 + reason.len() + 2 + headers_size(headers) + 2) as u64
+}
+
+fn headers_size(headers: &HeaderMap) -> usize {
+    headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + 2 + value.len() + 2)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Client;
+
+    #[test]
+    fn test_request_size() {
+        let req = Client::new()
+            .post("https://example.com/path?q=1")
+            .header("X-Test", "1")
+            .body("a=b")
+            .build()
+            .unwrap();
+        // POST /path?q=1 HTTP/1.1, X-Test: 1, Host: example.com, blank line, body
+        assert_eq!(request_size(&req), 25 + 11 + 19 + 2 + 3);
+    }
+
+    #[test]
+    fn test_response_head_size() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-length", "5".parse().unwrap());
+        // HTTP/1.1 200 OK, content-length: 5, blank line
+        assert_eq!(response_head_size(StatusCode::OK, &headers), 17 + 19 + 2);
+    }
+}
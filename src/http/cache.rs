@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+/// A response stored in the cache directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub(crate) key: String,
+    pub(crate) stored_at: u64,
+    pub status: u16,
+    pub size: u64,
+    pub elapsed_ms: u64,
+    pub ip_addr: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl CachedResponse {
+    /// Response headers as a HeaderMap (invalid entries are skipped)
+    pub fn header_map(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        headers
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.elapsed_ms)
+    }
+
+    /// Convert response headers for storage
+    pub fn headers_from(headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// On-disk response cache (--cache)
+///
+/// Entries are keyed by method, URL, body and request headers (except the
+/// User-Agent, so --random-user-agent does not defeat the cache).
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    /// Numbers temporary files, so concurrent writes of one key never share one
+    writes: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn new(dir: &str, ttl: Option<Duration>) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cache directory: {}", dir))?;
+        Ok(ResponseCache {
+            dir: PathBuf::from(dir),
+            ttl,
+            writes: AtomicU64::new(0),
+        })
+    }
+
+    /// Cache key describing a request
+    pub fn key(req: &reqwest::Request) -> String {
+        let mut key = format!("{} {}\n", req.method(), req.url());
+        let mut headers: Vec<String> = req
+            .headers()
+            .iter()
+            .filter(|(name, _)| *name != USER_AGENT)
+            .map(|(name, value)| format!("{}: {}", name, String::from_utf8_lossy(value.as_bytes())))
+            .collect();
+        headers.sort();
+        for header in headers {
+            key.push_str(&header);
+            key.push('\n');
+        }
+        if let Some(body) = req.body().and_then(|b| b.as_bytes()) {
+            key.push('\n');
+            key.push_str(&String::from_utf8_lossy(body));
+        }
+        key
+    }
+
+    /// Look up a fresh entry for `key`
+    pub async fn get(&self, key: &str) -> Option<CachedResponse> {
+        let content = fs::read_to_string(self.path(key)).await.ok()?;
+        let entry: CachedResponse = serde_json::from_str(&content).ok()?;
+        if entry.key != key {
+            return None;
+        }
+        if let Some(ttl) = self.ttl
+            && unix_time().saturating_sub(entry.stored_at) >= ttl.as_secs()
+        {
+            return None;
+        }
+        Some(entry)
+    }
+
+    /// Store a response for `key`
+    pub async fn put(&self, key: &str, mut entry: CachedResponse) -> Result<()> {
+        entry.key = key.to_string();
+        entry.stored_at = unix_time();
+
+        // Write to a temporary file first so concurrent readers never see partial entries
+        let path = self.path(key);
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+        let tmp = path.with_extension(format!("tmp{}-{}", std::process::id(), write));
+        let result = async {
+            fs::write(&tmp, serde_json::to_vec(&entry)?).await?;
+            fs::rename(&tmp, &path).await?;
+            Ok(())
+        }
+        .await;
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp).await;
+        }
+        result
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+    }
+}
+
+/// 64-bit FNV-1a hash (stable across builds, unlike the std hasher)
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Client;
+
+    #[test]
+    fn test_cache_key_ignores_user_agent() {
+        let client = Client::new();
+        let a = client
+            .post("https://example.com/a")
+            .header("User-Agent", "one")
+            .header("X-Token", "1")
+            .body("x=1")
+            .build()
+            .unwrap();
+        let b = client
+            .post("https://example.com/a")
+            .header("X-Token", "1")
+            .header("User-Agent", "two")
+            .body("x=1")
+            .build()
+            .unwrap();
+        let c = client.get("https://example.com/a").build().unwrap();
+
+        assert_eq!(ResponseCache::key(&a), ResponseCache::key(&b));
+        assert_ne!(ResponseCache::key(&a), ResponseCache::key(&c));
+    }
+
+    #[tokio::test]
+    async fn test_cache_put_get() {
+        let dir = std::env::temp_dir().join(format!("reqs-cache-{}", std::process::id()));
+        let cache = ResponseCache::new(dir.to_str().unwrap(), None).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "text/html".parse().unwrap());
+
+        assert!(cache.get("GET https://a.com/\n").await.is_none());
+        let response = CachedResponse {
+            status: 200,
+            size: 5,
+            elapsed_ms: 12,
+            ip_addr: "1.2.3.4".to_string(),
+            headers: CachedResponse::headers_from(&headers),
+            body: "hello".to_string(),
+            ..Default::default()
+        };
+        cache.put("GET https://a.com/\n", response).await.unwrap();
+
+        let entry = cache.get("GET https://a.com/\n").await.unwrap();
+        assert_eq!(entry.status, 200);
+        assert_eq!(entry.body, "hello");
+        assert_eq!(entry.elapsed(), Duration::from_millis(12));
+        assert_eq!(entry.header_map().get("content-type").unwrap(), "text/html");
+        assert!(cache.get("GET https://b.com/\n").await.is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_concurrent_puts() {
+        let dir = std::env::temp_dir().join(format!("reqs-cache-race-{}", std::process::id()));
+        let cache = std::sync::Arc::new(ResponseCache::new(dir.to_str().unwrap(), None).unwrap());
+        let writes: Vec<_> = (0..16)
+            .map(|i| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    let response = CachedResponse {
+                        status: 200,
+                        body: format!("{:02}", i).repeat(5_000),
+                        ..Default::default()
+                    };
+                    cache.put("GET https://a.com/\n", response).await
+                })
+            })
+            .collect();
+        for write in writes {
+            write.await.unwrap().unwrap();
+        }
+
+        let entry = cache.get("GET https://a.com/\n").await.unwrap();
+        assert_eq!(entry.body, entry.body[..2].repeat(5_000));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
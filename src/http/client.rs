@@ -1,40 +1,259 @@
 use anyhow::Result;
 use reqwest::{Client, redirect::Policy};
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
 use std::time::Duration;
+use tracing::debug;
 
-use crate::constants::DEFAULT_REDIRECT_LIMIT;
-use crate::http::headers::parse_headers;
+use crate::constants::{DEFAULT_REDIRECT_LIMIT, PINNED_CLIENT_LIMIT};
+use crate::http::dns::DnsResolver;
+use crate::http::exact::{ExactClient, build_exact_client};
+use crate::http::timing::{
+    TimingLayer, TimingResolver, build_client_tls_config, build_timing_tls_config,
+};
 use crate::types::Cli;
 
+/// Options that require a dedicated HTTP client when they vary between requests
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientOptions {
+    pub proxy: Option<String>,
+    pub http2: bool,
+    pub follow_redirect: bool,
+    /// Host name pinned to an address, for `@host:NAME` and --host-header
+    pub resolve: Option<(String, IpAddr)>,
+    /// Whether the TLS ClientHello carries an SNI extension (off with --no-sni)
+    pub sni: bool,
+}
+
+impl ClientOptions {
+    /// Client options as configured on the command line
+    pub fn from_cli(cli: &Cli) -> Self {
+        ClientOptions {
+            proxy: cli.proxy.clone(),
+            http2: cli.http2,
+            follow_redirect: cli.follow_redirect,
+            resolve: None,
+            sni: !cli.no_sni,
+        }
+    }
+}
+
+/// Clients of one kind keyed by their options
+struct ClientCache<T> {
+    clients: HashMap<ClientOptions, T>,
+    /// Clients with a pinned address, most recently used last
+    pinned: VecDeque<(ClientOptions, T)>,
+}
+
+impl<T: Clone> ClientCache<T> {
+    fn new() -> Self {
+        ClientCache {
+            clients: HashMap::new(),
+            pinned: VecDeque::new(),
+        }
+    }
+
+    /// Get the client for the options, building it on first use
+    ///
+    /// Past the limit, the least recently used client with a pinned address is dropped.
+    fn get(&mut self, options: &ClientOptions, build: impl FnOnce() -> Result<T>) -> Result<T> {
+        if options.resolve.is_none() {
+            if let Some(client) = self.clients.get(options) {
+                return Ok(client.clone());
+            }
+            let client = build()?;
+            self.clients.insert(options.clone(), client.clone());
+            return Ok(client);
+        }
+
+        if let Some(at) = self.pinned.iter().position(|(pin, _)| pin == options) {
+            let entry = self.pinned.remove(at).expect("position is in range");
+            let client = entry.1.clone();
+            self.pinned.push_back(entry);
+            return Ok(client);
+        }
+        let client = build()?;
+        self.pinned.push_back((options.clone(), client.clone()));
+        if self.pinned.len() > PINNED_CLIENT_LIMIT {
+            self.pinned.pop_front();
+        }
+        Ok(client)
+    }
+}
+
+/// Cache of HTTP clients keyed by their option fingerprint
+///
+/// Clients are built lazily the first time a combination of options is requested
+/// and reused afterwards, so they keep their connection pools. Each address pin
+/// needs a client of its own, so only the most recently used of those are kept.
+pub struct ClientPool {
+    cli: Cli,
+    default_options: ClientOptions,
+    resolver: DnsResolver,
+    clients: Mutex<ClientCache<Client>>,
+    /// Clients that send the request headers as they are (see `ExactClient`)
+    exact: Mutex<ClientCache<ExactClient>>,
+}
+
+impl ClientPool {
+    /// Create a pool and eagerly build the client for the CLI defaults
+    pub fn new(cli: &Cli) -> Result<Self> {
+        let default_options = ClientOptions::from_cli(cli);
+        let resolver = DnsResolver::from_cli(cli)?;
+        let mut clients = ClientCache::new();
+        clients.get(&default_options, || {
+            build_client_with_options(cli, &default_options, &resolver)
+        })?;
+
+        Ok(ClientPool {
+            cli: cli.clone(),
+            resolver,
+            clients: Mutex::new(clients),
+            exact: Mutex::new(ClientCache::new()),
+            default_options,
+        })
+    }
+
+    /// Options of the default client
+    pub fn default_options(&self) -> &ClientOptions {
+        &self.default_options
+    }
+
+    /// Resolver shared by the clients
+    pub fn resolver(&self) -> &DnsResolver {
+        &self.resolver
+    }
+
+    /// Get the client for the given options, building it on first use
+    ///
+    /// NTLM authenticates a connection rather than a request, so with
+    /// --auth-ntlm every call builds a client of its own.
+    pub fn get(&self, options: &ClientOptions) -> Result<Client> {
+        if self.cli.auth_ntlm.is_some() {
+            return build_client_with_options(&self.cli, options, &self.resolver);
+        }
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        clients.get(options, || {
+            build_client_with_options(&self.cli, options, &self.resolver)
+        })
+    }
+
+    /// Get the client that sends headers as they are for the given options, building it on first use
+    pub fn exact(&self, options: &ClientOptions) -> Result<ExactClient> {
+        let mut clients = self.exact.lock().unwrap_or_else(|e| e.into_inner());
+        clients.get(options, || {
+            build_exact_client(&self.cli, options, &self.resolver)
+        })
+    }
+}
+
 /// Build HTTP client from CLI configuration
 pub fn build_http_client(cli: &Cli) -> Result<Client> {
-    let redirect_policy = if cli.follow_redirect {
-        Policy::limited(DEFAULT_REDIRECT_LIMIT)
+    build_client_with_options(
+        cli,
+        &ClientOptions::from_cli(cli),
+        &DnsResolver::from_cli(cli)?,
+    )
+}
+
+/// Build HTTP client from CLI configuration with per-client option overrides
+fn build_client_with_options(
+    cli: &Cli,
+    options: &ClientOptions,
+    resolver: &DnsResolver,
+) -> Result<Client> {
+    let redirect_policy = if options.follow_redirect {
+        Policy::custom(|attempt| {
+            if attempt.previous().len() > DEFAULT_REDIRECT_LIMIT {
+                return attempt.error("too many redirects");
+            }
+            debug!(
+                from = %attempt.previous().last().map(|u| u.as_str()).unwrap_or_default(),
+                to = %attempt.url(),
+                status = attempt.status().as_u16(),
+                "Following redirect"
+            );
+            attempt.follow()
+        })
     } else {
         Policy::none()
     };
 
-    let default_headers = parse_headers(&cli.headers);
-
+    // Headers are applied per request (see `request_headers`), not on the client
     let mut client_builder = Client::builder()
         .timeout(Duration::from_secs(cli.timeout))
-        .redirect(redirect_policy)
-        .default_headers(default_headers);
+        .redirect(redirect_policy);
 
     // Disable SSL verification by default
     if !cli.verify_ssl {
         client_builder = client_builder.danger_accept_invalid_certs(true);
     }
 
-    if let Some(proxy_url) = &cli.proxy {
+    if let Some(proxy_url) = &options.proxy {
         let proxy = reqwest::Proxy::all(proxy_url)?;
         client_builder = client_builder.proxy(proxy);
     }
 
-    if !cli.http2 {
+    // NTLM needs a single HTTP/1.1 connection kept open through the handshake
+    let http2 = options.http2 && cli.auth_ntlm.is_none();
+    if !http2 {
         client_builder = client_builder.http1_only();
     }
 
+    // Exposes the peer certificate for --cert-expiry-warn
+    if cli.cert_expiry_warn.is_some() {
+        client_builder = client_builder.tls_info(true);
+    }
+
+    // Port 0 keeps the port of the URL
+    if let Some((host, ip)) = &options.resolve {
+        client_builder = client_builder.resolve(host, SocketAddr::new(*ip, 0));
+    }
+
+    // Connection pool tuning
+    if cli.auth_ntlm.is_some() {
+        client_builder = client_builder.pool_max_idle_per_host(1);
+    } else if cli.no_reuse {
+        client_builder = client_builder.pool_max_idle_per_host(0);
+    } else if let Some(max_idle) = cli.pool_max_idle_per_host {
+        client_builder = client_builder.pool_max_idle_per_host(max_idle);
+    }
+
+    if let Some(idle_timeout) = cli.pool_idle_timeout {
+        client_builder = client_builder.pool_idle_timeout(idle_timeout);
+    }
+
+    if let Some(keepalive) = cli.tcp_keepalive {
+        client_builder = client_builder.tcp_keepalive(keepalive);
+    }
+
+    if let Some(nodelay) = cli.tcp_nodelay {
+        client_builder = client_builder.tcp_nodelay(nodelay);
+    }
+
+    // Instrument DNS, connect and TLS phases for --timing-detail
+    if cli.timing_detail {
+        client_builder = client_builder
+            .dns_resolver(TimingResolver {
+                resolver: resolver.clone(),
+            })
+            .connector_layer(TimingLayer);
+    } else {
+        client_builder = client_builder.dns_resolver(resolver.clone());
+    }
+
+    // reqwest has no switch for SNI, so without it the client gets its own rustls configuration
+    if cli.timing_detail || !options.sni {
+        let mut tls_config = if cli.timing_detail {
+            build_timing_tls_config(cli.verify_ssl, http2)?
+        } else {
+            build_client_tls_config(cli.verify_ssl, http2)?
+        };
+        tls_config.enable_sni = options.sni;
+        client_builder = client_builder.tls_backend_preconfigured(tls_config);
+    }
+
     Ok(client_builder.build()?)
 }
 
@@ -45,7 +264,7 @@ mod tests {
 
     #[test]
     fn test_build_http_client_default() {
-        let cli = Cli::parse_from(&["reqs"]);
+        let cli = Cli::parse_from(["reqs"]);
         let client = build_http_client(&cli);
         assert!(
             client.is_ok(),
@@ -55,21 +274,21 @@ mod tests {
 
     #[test]
     fn test_build_http_client_with_custom_headers() {
-        let cli = Cli::parse_from(&["reqs", "-H", "User-Agent: test-agent"]);
+        let cli = Cli::parse_from(["reqs", "-H", "User-Agent: test-agent"]);
         let client = build_http_client(&cli);
         assert!(client.is_ok(), "Should build a client with custom headers");
     }
 
     #[test]
     fn test_build_http_client_with_proxy() {
-        let cli = Cli::parse_from(&["reqs", "--proxy", "http://127.0.0.1:8080"]);
+        let cli = Cli::parse_from(["reqs", "--proxy", "http://127.0.0.1:8080"]);
         let client = build_http_client(&cli);
         assert!(client.is_ok(), "Should build a client with a proxy");
     }
 
     #[test]
     fn test_build_http_client_with_invalid_proxy() {
-        let cli = Cli::parse_from(&["reqs", "--proxy", "htt\0p://127.0.0.1:8080"]);
+        let cli = Cli::parse_from(["reqs", "--proxy", "htt\0p://127.0.0.1:8080"]);
         let client = build_http_client(&cli);
         assert!(
             client.is_err(),
@@ -79,7 +298,7 @@ mod tests {
 
     #[test]
     fn test_build_http_client_ssl_verification() {
-        let cli = Cli::parse_from(&["reqs", "--verify-ssl"]);
+        let cli = Cli::parse_from(["reqs", "--verify-ssl"]);
         let client = build_http_client(&cli);
         assert!(
             client.is_ok(),
@@ -89,8 +308,113 @@ mod tests {
 
     #[test]
     fn test_build_http_client_http2() {
-        let cli = Cli::parse_from(&["reqs", "--http2"]);
+        let cli = Cli::parse_from(["reqs", "--http2"]);
         let client = build_http_client(&cli);
         assert!(client.is_ok(), "Should build a client with HTTP2 enabled");
     }
+
+    #[test]
+    fn test_build_http_client_pool_options() {
+        let cli = Cli::parse_from([
+            "reqs",
+            "--pool-max-idle-per-host",
+            "4",
+            "--pool-idle-timeout",
+            "30s",
+            "--tcp-keepalive",
+            "60s",
+            "--tcp-nodelay",
+            "false",
+        ]);
+        let client = build_http_client(&cli);
+        assert!(client.is_ok(), "Should build a client with pool tuning");
+
+        let cli = Cli::parse_from(["reqs", "--no-reuse"]);
+        let client = build_http_client(&cli);
+        assert!(client.is_ok(), "Should build a client without reuse");
+    }
+
+    #[test]
+    fn test_build_http_client_timing_detail() {
+        let cli = Cli::parse_from(["reqs", "--timing-detail", "--verify-ssl"]);
+        let client = build_http_client(&cli);
+        assert!(
+            client.is_ok(),
+            "Should build a client with timing instrumentation"
+        );
+    }
+
+    #[test]
+    fn test_build_http_client_doh() {
+        let cli = Cli::parse_from(["reqs", "--doh", "https://cloudflare-dns.com/dns-query"]);
+        assert!(build_http_client(&cli).is_ok());
+
+        let cli = Cli::parse_from(["reqs", "--doh", "dns.example.com"]);
+        assert!(build_http_client(&cli).is_err());
+    }
+
+    #[test]
+    fn test_client_pool_caches_by_options() {
+        let cli = Cli::parse_from(["reqs"]);
+        let pool = ClientPool::new(&cli).unwrap();
+        assert_eq!(pool.clients.lock().unwrap().clients.len(), 1);
+
+        let mut options = pool.default_options().clone();
+        options.http2 = true;
+        options.follow_redirect = false;
+        assert!(pool.get(&options).is_ok());
+        options.resolve = Some(("app.example.com".to_string(), "127.0.0.1".parse().unwrap()));
+        assert!(pool.get(&options).is_ok());
+        assert!(pool.get(&options).is_ok());
+        assert!(pool.get(pool.default_options()).is_ok());
+        assert_eq!(pool.clients.lock().unwrap().clients.len(), 2);
+        assert_eq!(pool.clients.lock().unwrap().pinned.len(), 1);
+    }
+
+    #[test]
+    fn test_client_pool_bounds_pinned_clients() {
+        let cli = Cli::parse_from(["reqs"]);
+        let pool = ClientPool::new(&cli).unwrap();
+        let pin = |n: usize| ClientOptions {
+            resolve: Some((
+                format!("host{}.example.com", n),
+                "127.0.0.1".parse().unwrap(),
+            )),
+            ..pool.default_options().clone()
+        };
+
+        for n in 0..PINNED_CLIENT_LIMIT {
+            pool.get(&pin(n)).unwrap();
+        }
+        // Using the oldest pin again keeps it over the next one
+        pool.get(&pin(0)).unwrap();
+        pool.get(&pin(PINNED_CLIENT_LIMIT)).unwrap();
+
+        let clients = pool.clients.lock().unwrap();
+        assert_eq!(clients.pinned.len(), PINNED_CLIENT_LIMIT);
+        assert!(clients.pinned.iter().any(|(options, _)| *options == pin(0)));
+        assert!(!clients.pinned.iter().any(|(options, _)| *options == pin(1)));
+        assert_eq!(clients.clients.len(), 1);
+    }
+
+    #[test]
+    fn test_build_http_client_no_sni() {
+        let cli = Cli::parse_from(["reqs", "--no-sni"]);
+        assert!(!ClientOptions::from_cli(&cli).sni);
+        assert!(build_http_client(&cli).is_ok());
+
+        let cli = Cli::parse_from(["reqs", "--no-sni", "--timing-detail", "--http2"]);
+        assert!(build_http_client(&cli).is_ok());
+    }
+
+    #[test]
+    fn test_client_pool_invalid_proxy() {
+        let cli = Cli::parse_from(["reqs"]);
+        let pool = ClientPool::new(&cli).unwrap();
+
+        let mut options = pool.default_options().clone();
+        options.proxy = Some("htt\0p://127.0.0.1:8080".to_string());
+        assert!(pool.get(&options).is_err());
+        assert_eq!(pool.clients.lock().unwrap().clients.len(), 1);
+    }
 }
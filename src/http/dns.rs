@@ -0,0 +1,373 @@
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{Client, Url};
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::types::Cli;
+
+/// DNS record type of IPv4 addresses
+const TYPE_A: u16 = 1;
+/// DNS record type of IPv6 addresses
+const TYPE_AAAA: u16 = 28;
+
+/// Addresses of each looked up host and when they expire
+type DohCache = HashMap<String, (Vec<IpAddr>, Instant)>;
+
+/// Name resolution used by the HTTP clients
+///
+/// Looks up names with the system resolver or over DoH (--doh). Addresses
+/// that won a connection race (see `ConnectRacer`) are returned first.
+#[derive(Clone, Default)]
+pub struct DnsResolver {
+    doh: Option<DohResolver>,
+    preferred: Arc<Mutex<HashMap<String, IpAddr>>>,
+}
+
+impl DnsResolver {
+    pub fn from_cli(cli: &Cli) -> Result<Self> {
+        Ok(DnsResolver {
+            doh: cli
+                .doh
+                .as_deref()
+                .map(|endpoint| DohResolver::new(endpoint, cli))
+                .transpose()?,
+            preferred: Arc::default(),
+        })
+    }
+
+    /// Resolve a host name to socket addresses with the given port
+    pub async fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        let mut addrs: Vec<SocketAddr> = match &self.doh {
+            None => tokio::net::lookup_host((host, port)).await?.collect(),
+            Some(doh) => doh
+                .lookup(host)
+                .await
+                .map_err(io::Error::other)?
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, port))
+                .collect(),
+        };
+        if let Some(ip) = self.preferred.lock().unwrap().get(host)
+            && let Some(at) = addrs.iter().position(|addr| addr.ip() == *ip)
+        {
+            addrs[..=at].rotate_right(1);
+        }
+        Ok(addrs)
+    }
+
+    /// Address requests to `url` connect to: its IP, or the first address of its host
+    pub async fn connect_ip(&self, url: &str) -> Option<IpAddr> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.trim_matches(['[', ']']);
+        match host.parse() {
+            Ok(ip) => Some(ip),
+            Err(_) => Some(self.lookup(host, 0).await.ok()?.first()?.ip()),
+        }
+    }
+
+    /// Return `ip` first when looking up `host`
+    pub fn prefer(&self, host: &str, ip: IpAddr) {
+        self.preferred.lock().unwrap().insert(host.to_string(), ip);
+    }
+}
+
+impl Resolve for DnsResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addrs = resolver.lookup(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// All addresses of each host, looked up once per run (--resolved-ips)
+pub struct HostAddresses {
+    resolver: DnsResolver,
+    hosts: Mutex<HashMap<String, Arc<OnceCell<Vec<String>>>>>,
+}
+
+impl HostAddresses {
+    pub fn new(resolver: DnsResolver) -> Self {
+        HostAddresses {
+            resolver,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sorted addresses of the host of `url`, empty if it does not resolve
+    pub async fn get(&self, url: &str) -> Option<Vec<String>> {
+        let host = Url::parse(url).ok()?.host_str()?.to_string();
+        let cell = self
+            .hosts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(host.clone())
+            .or_default()
+            .clone();
+        let ips = cell
+            .get_or_init(|| async {
+                let host = host.trim_matches(['[', ']']);
+                let mut ips: Vec<IpAddr> = match self.resolver.lookup(host, 0).await {
+                    Ok(addrs) => addrs.iter().map(SocketAddr::ip).collect(),
+                    Err(e) => {
+                        debug!(host = %host, "Failed to resolve addresses: {}", e);
+                        Vec::new()
+                    }
+                };
+                ips.sort();
+                ips.dedup();
+                ips.iter().map(IpAddr::to_string).collect()
+            })
+            .await;
+        Some(ips.clone())
+    }
+}
+
+/// Resolver sending RFC 8484 DNS queries to a DoH endpoint
+///
+/// Answers are cached for their TTL. The endpoint's own host name is resolved
+/// by the system resolver.
+#[derive(Clone)]
+pub struct DohResolver {
+    endpoint: Url,
+    client: Client,
+    cache: Arc<Mutex<DohCache>>,
+}
+
+impl DohResolver {
+    pub fn new(endpoint: &str, cli: &Cli) -> Result<Self> {
+        let endpoint = Url::parse(endpoint)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "https" | "http") && url.has_host())
+            .with_context(|| format!("Invalid DoH URL: {}", endpoint))?;
+
+        let mut builder = Client::builder().timeout(Duration::from_secs(cli.timeout));
+        if !cli.verify_ssl {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(proxy_url) = &cli.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy_url)?);
+        }
+
+        Ok(DohResolver {
+            endpoint,
+            client: builder.build()?,
+            cache: Arc::default(),
+        })
+    }
+
+    /// IPv4 and IPv6 addresses of `host`
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![ip]);
+        }
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if let Some((ips, expires)) = self.cache.lock().unwrap().get(&host)
+            && *expires > Instant::now()
+        {
+            return Ok(ips.clone());
+        }
+
+        let (v4, v6) = futures::join!(self.query(&host, TYPE_A), self.query(&host, TYPE_AAAA));
+        let mut answers = Vec::new();
+        for answer in [v4, v6] {
+            match answer {
+                Ok(records) => answers.extend(records),
+                Err(e) => debug!("DoH query for {} failed: {:#}", host, e),
+            }
+        }
+        if answers.is_empty() {
+            bail!("DoH lookup returned no addresses for {}", host);
+        }
+
+        let ttl = answers.iter().map(|(_, ttl)| *ttl).min().unwrap_or(0);
+        let ips: Vec<IpAddr> = answers.into_iter().map(|(ip, _)| ip).collect();
+        self.cache.lock().unwrap().insert(
+            host,
+            (
+                ips.clone(),
+                Instant::now() + Duration::from_secs(ttl.into()),
+            ),
+        );
+        Ok(ips)
+    }
+
+    /// Send one query and return the addresses in the answer with their TTLs
+    async fn query(&self, host: &str, record_type: u16) -> Result<Vec<(IpAddr, u32)>> {
+        let mut url = self.endpoint.clone();
+        url.query_pairs_mut().append_pair(
+            "dns",
+            &URL_SAFE_NO_PAD.encode(encode_query(host, record_type)?),
+        );
+        let response = self
+            .client
+            .get(url)
+            .header(reqwest::header::ACCEPT, "application/dns-message")
+            .send()
+            .await?
+            .error_for_status()?;
+        parse_answer(&response.bytes().await?)
+    }
+}
+
+/// DNS query message for one question, with recursion desired
+fn encode_query(host: &str, record_type: u16) -> Result<Vec<u8>> {
+    // ID 0 (RFC 8484 section 4.1), RD flag, one question
+    let mut message = vec![0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in host.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            bail!("Invalid host name for DNS query: {}", host);
+        }
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    message.extend_from_slice(&record_type.to_be_bytes());
+    message.extend_from_slice(&1u16.to_be_bytes());
+    Ok(message)
+}
+
+/// A and AAAA records in the answer section of a DNS response
+fn parse_answer(message: &[u8]) -> Result<Vec<(IpAddr, u32)>> {
+    let truncated = || anyhow::anyhow!("Truncated DNS response");
+    let u16_at = |at: usize| -> Result<u16> {
+        let bytes = message.get(at..at + 2).ok_or_else(truncated)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+
+    let rcode = u16_at(2)? & 0x000f;
+    if rcode != 0 {
+        bail!("DNS server returned rcode {}", rcode);
+    }
+    let questions = u16_at(4)?;
+    let answers = u16_at(6)?;
+
+    let mut at = 12;
+    for _ in 0..questions {
+        at = skip_name(message, at).ok_or_else(truncated)? + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        at = skip_name(message, at).ok_or_else(truncated)?;
+        let record_type = u16_at(at)?;
+        let ttl = message.get(at + 4..at + 8).ok_or_else(truncated)?;
+        let ttl = u32::from_be_bytes([ttl[0], ttl[1], ttl[2], ttl[3]]);
+        let length = u16_at(at + 8)? as usize;
+        let data = message
+            .get(at + 10..at + 10 + length)
+            .ok_or_else(truncated)?;
+        match (record_type, data.len()) {
+            (TYPE_A, 4) => {
+                let octets: [u8; 4] = data.try_into()?;
+                records.push((IpAddr::V4(Ipv4Addr::from(octets)), ttl));
+            }
+            (TYPE_AAAA, 16) => {
+                let octets: [u8; 16] = data.try_into()?;
+                records.push((IpAddr::V6(Ipv6Addr::from(octets)), ttl));
+            }
+            // CNAMEs and other records in the chain
+            _ => {}
+        }
+        at += 10 + length;
+    }
+    Ok(records)
+}
+
+/// Offset just past the (possibly compressed) name starting at `at`
+fn skip_name(message: &[u8], mut at: usize) -> Option<usize> {
+    loop {
+        let length = *message.get(at)?;
+        match length {
+            0 => return Some(at + 1),
+            // A compression pointer ends the name
+            l if l & 0xc0 == 0xc0 => return Some(at + 2),
+            l => at += 1 + l as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_encode_query() {
+        let query = encode_query("example.com", TYPE_AAAA).unwrap();
+        assert_eq!(&query[..12], &[0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&query[12..25], b"\x07example\x03com\x00");
+        assert_eq!(&query[25..], &[0, 28, 0, 1]);
+        assert!(encode_query("bad..host", TYPE_A).is_err());
+    }
+
+    #[test]
+    fn test_parse_answer() {
+        let mut message = vec![0, 0, 0x81, 0x80, 0, 1, 0, 3, 0, 0, 0, 0];
+        message.extend_from_slice(b"\x03www\x07example\x03com\x00\x00\x01\x00\x01");
+        // www.example.com CNAME example.com
+        message.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xc0, 16]);
+        // example.com A 93.184.216.34, TTL 300
+        message.extend_from_slice(&[0xc0, 16, 0, 1, 0, 1, 0, 0, 1, 44, 0, 4, 93, 184, 216, 34]);
+        // example.com A 93.184.216.35, TTL 120
+        message.extend_from_slice(&[0xc0, 16, 0, 1, 0, 1, 0, 0, 0, 120, 0, 4, 93, 184, 216, 35]);
+
+        assert_eq!(
+            parse_answer(&message).unwrap(),
+            vec![
+                ("93.184.216.34".parse().unwrap(), 300),
+                ("93.184.216.35".parse().unwrap(), 120),
+            ]
+        );
+        assert!(parse_answer(&message[..message.len() - 2]).is_err());
+
+        // NXDOMAIN
+        message[3] = 0x83;
+        assert!(parse_answer(&message).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dns_resolver_preferred() {
+        let resolver = DnsResolver::default();
+        let ips = |addrs: Vec<SocketAddr>| addrs.iter().map(|a| a.ip()).collect::<Vec<_>>();
+        let addrs = resolver.lookup("localhost", 80).await.unwrap();
+        assert!(addrs.iter().all(|addr| addr.port() == 80));
+        let Some(last) = addrs.last().map(|addr| addr.ip()) else {
+            return;
+        };
+        resolver.prefer("localhost", last);
+        let preferred = ips(resolver.lookup("localhost", 80).await.unwrap());
+        assert_eq!(preferred[0], last);
+        assert_eq!(preferred.len(), addrs.len());
+    }
+
+    #[tokio::test]
+    async fn test_host_addresses() {
+        let hosts = HostAddresses::new(DnsResolver::default());
+        assert_eq!(
+            hosts.get("http://127.0.0.1:8080/a").await,
+            Some(vec!["127.0.0.1".to_string()])
+        );
+        assert_eq!(
+            hosts.get("https://[::1]/").await,
+            Some(vec!["::1".to_string()])
+        );
+        assert_eq!(hosts.get("not a url").await, None);
+    }
+
+    #[test]
+    fn test_doh_resolver_url() {
+        let cli = Cli::parse_from(["reqs"]);
+        assert!(DohResolver::new("https://cloudflare-dns.com/dns-query", &cli).is_ok());
+        assert!(DohResolver::new("ftp://dns.example.com", &cli).is_err());
+        assert!(DohResolver::new("not a url", &cli).is_err());
+    }
+}
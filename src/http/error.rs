@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::http::exact::ExactError;
+
+/// Category of a failed request, the `error_kind` of error records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Dns,
+    ConnectRefused,
+    ConnectTimeout,
+    Tls,
+    ReadTimeout,
+    TooManyRedirects,
+    UnsafeMethod,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Dns => "dns",
+            ErrorKind::ConnectRefused => "connect_refused",
+            ErrorKind::ConnectTimeout => "connect_timeout",
+            ErrorKind::Tls => "tls",
+            ErrorKind::ReadTimeout => "read_timeout",
+            ErrorKind::TooManyRedirects => "too_many_redirects",
+            ErrorKind::UnsafeMethod => "unsafe_method",
+            ErrorKind::Other => "other",
+        }
+    }
+}
+
+/// Where a request sent through a proxy failed, the `failed_at` of error records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailedAt {
+    Proxy,
+    Target,
+}
+
+impl FailedAt {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailedAt::Proxy => "proxy",
+            FailedAt::Target => "target",
+        }
+    }
+}
+
+/// A request that got no response: the error message and its category
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestError {
+    pub message: String,
+    pub kind: ErrorKind,
+    /// Proxy the request went through, without its credentials
+    pub proxy: Option<String>,
+    /// Whether the proxy or the target failed, for requests through a proxy
+    pub failed_at: Option<FailedAt>,
+}
+
+/// Error of a request sent with reqwest or, without its implicit headers, with [`ExactClient`]
+#[derive(Debug)]
+pub enum SendError {
+    Client(reqwest::Error),
+    Exact(ExactError),
+    /// Not sent because of --safe-methods-only
+    UnsafeMethod(String),
+}
+
+impl SendError {
+    /// Whether the connection could not be established
+    pub fn is_connect(&self) -> bool {
+        match self {
+            SendError::Client(err) => err.is_connect(),
+            SendError::Exact(err) => err.is_connect(),
+            SendError::UnsafeMethod(_) => false,
+        }
+    }
+
+    /// Category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SendError::Client(err) => classify_error(err),
+            SendError::Exact(ExactError::Timeout(_)) => ErrorKind::ReadTimeout,
+            SendError::Exact(ExactError::TooManyRedirects(_)) => ErrorKind::TooManyRedirects,
+            SendError::Exact(err) => classify_chain(err),
+            SendError::UnsafeMethod(_) => ErrorKind::UnsafeMethod,
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Client(err) => err.fmt(f),
+            SendError::Exact(err) => err.fmt(f),
+            SendError::UnsafeMethod(method) => {
+                write!(f, "{} refused by --safe-methods-only", method)
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for SendError {
+    fn from(err: reqwest::Error) -> Self {
+        SendError::Client(err)
+    }
+}
+
+impl From<ExactError> for SendError {
+    fn from(err: ExactError) -> Self {
+        SendError::Exact(err)
+    }
+}
+
+/// Classify a request error
+pub fn classify_error(err: &reqwest::Error) -> ErrorKind {
+    if err.is_redirect() {
+        return ErrorKind::TooManyRedirects;
+    }
+    match classify_chain(err) {
+        ErrorKind::Other if err.is_timeout() && err.is_connect() => ErrorKind::ConnectTimeout,
+        ErrorKind::Other if err.is_timeout() => ErrorKind::ReadTimeout,
+        kind => kind,
+    }
+}
+
+/// Classify an error by the causes it wraps
+///
+/// I/O errors hide the error they carry from `source()`, so it is looked at
+/// through `get_ref()`. Resolver failures are reported by the connector as
+/// "dns error".
+pub fn classify_chain(err: &(dyn Error + 'static)) -> ErrorKind {
+    let mut cause = Some(err);
+    while let Some(err) = cause {
+        if err.is::<rustls::Error>() {
+            return ErrorKind::Tls;
+        }
+        if err.to_string().starts_with("dns error") {
+            return ErrorKind::Dns;
+        }
+        cause = match err.downcast_ref::<io::Error>() {
+            Some(io_err) => match io_err.kind() {
+                io::ErrorKind::ConnectionRefused => return ErrorKind::ConnectRefused,
+                io::ErrorKind::TimedOut => return ErrorKind::ConnectTimeout,
+                _ => io_err
+                    .get_ref()
+                    .map(|inner| inner as &(dyn Error + 'static)),
+            },
+            None => err.source(),
+        };
+    }
+    ErrorKind::Other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_chain() {
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert_eq!(classify_chain(&refused), ErrorKind::ConnectRefused);
+
+        let tls = io::Error::new(
+            io::ErrorKind::InvalidData,
+            rustls::Error::General("handshake failure".to_string()),
+        );
+        assert_eq!(classify_chain(&tls), ErrorKind::Tls);
+
+        let other = io::Error::other("connection reset");
+        assert_eq!(classify_chain(&other), ErrorKind::Other);
+    }
+
+    #[tokio::test]
+    async fn test_classify_error() {
+        let client = reqwest::Client::new();
+        // Nothing listens on port 1
+        let err = client.get("http://127.0.0.1:1/").send().await.unwrap_err();
+        assert_eq!(classify_error(&err), ErrorKind::ConnectRefused);
+
+        let err = client
+            .get("http://nonexistent.invalid/")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(classify_error(&err), ErrorKind::Dns);
+    }
+}
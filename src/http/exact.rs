@@ -0,0 +1,388 @@
+use anyhow::Result;
+use http::header::{
+    AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, PROXY_AUTHORIZATION,
+    TRANSFER_ENCODING, WWW_AUTHENTICATE,
+};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::connect::dns::Name;
+use hyper_util::rt::TokioExecutor;
+use reqwest::header::LOCATION;
+use reqwest::{Body, Method, Request, Response, ResponseBuilderExt, StatusCode, Url};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::debug;
+
+use crate::constants::DEFAULT_REDIRECT_LIMIT;
+use crate::http::client::ClientOptions;
+use crate::http::dns::DnsResolver;
+use crate::http::timing::{
+    TimingLayer, TimingResolver, TimingService, build_timing_tls_config, build_tls_config,
+};
+use crate::types::Cli;
+
+type Connector = HttpsConnector<HttpConnector<ExactResolver>>;
+
+/// HTTP client that sends exactly the headers of the request
+///
+/// reqwest adds `Accept: */*` to every request that has none and offers no
+/// way to leave it out, so requests sent with --no-default-headers or
+/// `-H "Accept:"` go through this client instead. It connects directly,
+/// without a proxy.
+#[derive(Clone)]
+pub struct ExactClient {
+    client: Client<TimingService<Connector>, Body>,
+    timeout: Duration,
+    follow_redirect: bool,
+}
+
+/// Why a request sent with [`ExactClient`] got no response
+#[derive(Debug)]
+pub enum ExactError {
+    /// No response within the timeout
+    Timeout(Url),
+    /// More redirects than `DEFAULT_REDIRECT_LIMIT`
+    TooManyRedirects(Url),
+    /// The request could not be converted or sent
+    Request(Url, Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl ExactError {
+    /// Whether the connection could not be established
+    pub fn is_connect(&self) -> bool {
+        match self {
+            ExactError::Request(_, err) => err
+                .downcast_ref::<hyper_util::client::legacy::Error>()
+                .is_some_and(|err| err.is_connect()),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ExactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExactError::Timeout(url) => {
+                write!(f, "error sending request for url ({}): timed out", url)
+            }
+            ExactError::TooManyRedirects(url) => {
+                write!(
+                    f,
+                    "error following redirect for url ({}): too many redirects",
+                    url
+                )
+            }
+            ExactError::Request(url, _) => write!(f, "error sending request for url ({})", url),
+        }
+    }
+}
+
+impl std::error::Error for ExactError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExactError::Request(_, err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Resolver of the exact client, honouring the address pin of its options
+#[derive(Clone)]
+struct ExactResolver {
+    resolver: TimingResolver,
+    pin: Option<(String, IpAddr)>,
+}
+
+impl Service<Name> for ExactResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        // Port 0 keeps the port of the URL
+        if let Some((host, ip)) = &self.pin
+            && host.eq_ignore_ascii_case(name.as_str())
+        {
+            let addr = SocketAddr::new(*ip, 0);
+            return Box::pin(async move { Ok(vec![addr].into_iter()) });
+        }
+        let lookup = self.resolver.lookup(name.as_str());
+        Box::pin(async move { Ok(lookup.await?.into_iter()) })
+    }
+}
+
+/// Build the exact client for the given options
+///
+/// Proxies are not supported; `options.proxy` is ignored.
+pub fn build_exact_client(
+    cli: &Cli,
+    options: &ClientOptions,
+    resolver: &DnsResolver,
+) -> Result<ExactClient> {
+    let mut http = HttpConnector::new_with_resolver(ExactResolver {
+        resolver: TimingResolver {
+            resolver: resolver.clone(),
+        },
+        pin: options.resolve.clone(),
+    });
+    http.enforce_http(false);
+    http.set_connect_timeout(Some(Duration::from_secs(cli.timeout)));
+    http.set_keepalive(cli.tcp_keepalive);
+    http.set_nodelay(cli.tcp_nodelay.unwrap_or(true));
+
+    // The connector sets the ALPN protocols itself
+    let mut tls_config = if cli.timing_detail {
+        build_timing_tls_config(cli.verify_ssl, options.http2)?
+    } else {
+        build_tls_config(cli.verify_ssl)?
+    };
+    tls_config.alpn_protocols.clear();
+    tls_config.enable_sni = options.sni;
+    let https = HttpsConnectorBuilder::new()
+        .with_tls_config(tls_config)
+        .https_or_http()
+        .enable_http1();
+    let connector = if options.http2 {
+        https.enable_http2().wrap_connector(http)
+    } else {
+        https.wrap_connector(http)
+    };
+
+    let mut builder = Client::builder(TokioExecutor::new());
+    if cli.no_reuse {
+        builder.pool_max_idle_per_host(0);
+    } else if let Some(max_idle) = cli.pool_max_idle_per_host {
+        builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(idle_timeout) = cli.pool_idle_timeout {
+        builder.pool_idle_timeout(idle_timeout);
+    }
+
+    Ok(ExactClient {
+        client: builder.build(TimingLayer.layer(connector)),
+        timeout: Duration::from_secs(cli.timeout),
+        follow_redirect: options.follow_redirect,
+    })
+}
+
+impl ExactClient {
+    /// Send a request with its headers as they are, following redirects if enabled
+    pub async fn send(&self, request: Request) -> Result<Response, ExactError> {
+        let timeout = request.timeout().copied().unwrap_or(self.timeout);
+        let url = request.url().clone();
+        match tokio::time::timeout(timeout, self.send_following(request)).await {
+            Ok(result) => result,
+            Err(_) => Err(ExactError::Timeout(url)),
+        }
+    }
+
+    async fn send_following(&self, request: Request) -> Result<Response, ExactError> {
+        let mut method = request.method().clone();
+        let mut url = request.url().clone();
+        let mut headers = request.headers().clone();
+        let mut body = request.body().and_then(Body::as_bytes).map(<[u8]>::to_vec);
+        let mut redirects = 0;
+
+        loop {
+            let mut builder = http::Request::builder()
+                .method(method.clone())
+                .uri(url.as_str());
+            if let Some(request_headers) = builder.headers_mut() {
+                *request_headers = headers.clone();
+            }
+            let hyper_request = builder
+                .body(
+                    body.clone()
+                        .map(Body::from)
+                        .unwrap_or_else(|| Body::from("")),
+                )
+                .map_err(|e| ExactError::Request(url.clone(), e.into()))?;
+            let response = self
+                .client
+                .request(hyper_request)
+                .await
+                .map_err(|e| ExactError::Request(url.clone(), e.into()))?;
+
+            let status = response.status();
+            let next = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok());
+            let next = match next {
+                Some(next) if self.follow_redirect && is_followed(status) => next,
+                _ => {
+                    // Keep the connection info of the response for `remote_addr`
+                    let (parts, body) = response.into_parts();
+                    let mut builder = http::Response::builder()
+                        .status(parts.status)
+                        .version(parts.version)
+                        .url(url.clone());
+                    if let Some(headers) = builder.headers_mut() {
+                        *headers = parts.headers;
+                    }
+                    if let Some(extensions) = builder.extensions_mut() {
+                        extensions.extend(parts.extensions);
+                    }
+                    return builder
+                        .body(Body::wrap(body))
+                        .map(Response::from)
+                        .map_err(|e| ExactError::Request(url, e.into()));
+                }
+            };
+
+            redirects += 1;
+            if redirects > DEFAULT_REDIRECT_LIMIT {
+                return Err(ExactError::TooManyRedirects(url));
+            }
+            debug!(
+                from = %url,
+                to = %next,
+                status = status.as_u16(),
+                "Following redirect"
+            );
+
+            // Browsers resend only 307 and 308 as they were
+            let resend_as_get = match status {
+                StatusCode::SEE_OTHER => method != Method::HEAD,
+                StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => method == Method::POST,
+                _ => false,
+            };
+            if resend_as_get {
+                method = Method::GET;
+                body = None;
+                for name in [
+                    CONTENT_TYPE,
+                    CONTENT_LENGTH,
+                    CONTENT_ENCODING,
+                    TRANSFER_ENCODING,
+                ] {
+                    headers.remove(name);
+                }
+            }
+            if (next.host_str(), next.port_or_known_default())
+                != (url.host_str(), url.port_or_known_default())
+            {
+                for name in [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, WWW_AUTHENTICATE] {
+                    headers.remove(name);
+                }
+            }
+            url = next;
+        }
+    }
+}
+
+/// Redirect statuses that are followed
+fn is_followed(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve `responses` in turn, one connection each, and return the request heads received
+    async fn serve(listener: TcpListener, responses: Vec<String>) -> Vec<String> {
+        let mut heads = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            let mut buf = [0; 1024];
+            while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                head.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(response.as_bytes()).await.unwrap();
+            heads.push(String::from_utf8(head).unwrap().to_lowercase());
+        }
+        heads
+    }
+
+    fn exact_client(args: &[&str]) -> ExactClient {
+        let cli = Cli::parse_from(args);
+        build_exact_client(
+            &cli,
+            &ClientOptions::from_cli(&cli),
+            &DnsResolver::default(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_exact_client_sends_only_request_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(
+            listener,
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok".to_string()],
+        ));
+
+        let client = exact_client(&["reqs", "--no-default-headers"]);
+        let request = reqwest::Client::new()
+            .get(&url)
+            .header("X-Test", "1")
+            .build()
+            .unwrap();
+        let response = client.send(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.remote_addr().is_some());
+        assert_eq!(response.text().await.unwrap(), "ok");
+
+        let heads = server.await.unwrap();
+        assert!(heads[0].contains("x-test: 1\r\n"));
+        assert!(heads[0].contains("host: "));
+        assert!(!heads[0].contains("accept"));
+        assert!(!heads[0].contains("user-agent"));
+    }
+
+    #[tokio::test]
+    async fn test_exact_client_follows_redirects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/old", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(
+            listener,
+            vec![
+                "HTTP/1.1 303 See Other\r\nLocation: /new\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            ],
+        ));
+
+        let client = exact_client(&["reqs", "--no-default-headers"]);
+        let request = reqwest::Client::new()
+            .post(&url)
+            .header("Content-Type", "text/plain")
+            .body("data")
+            .build()
+            .unwrap();
+        let response = client.send(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.url().path(), "/new");
+
+        let heads = server.await.unwrap();
+        assert!(heads[0].starts_with("post /old "));
+        assert!(heads[1].starts_with("get /new "));
+        assert!(!heads[1].contains("content-type"));
+    }
+}
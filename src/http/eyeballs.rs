@@ -0,0 +1,213 @@
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::OnceCell;
+use tracing::{debug, info};
+
+use crate::http::dns::DnsResolver;
+use crate::http::liveness::{HostKey, host_key};
+
+/// Delay before the next address is tried while earlier attempts are pending (RFC 8305)
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Outcome of racing connections to the addresses of a host
+#[derive(Debug, Default)]
+pub struct ConnectReport {
+    /// Address that accepted the first connection
+    pub connected: Option<SocketAddr>,
+    /// Addresses that failed before it, with the error
+    pub failed: Vec<(SocketAddr, String)>,
+}
+
+impl fmt::Display for ConnectReport {
+    /// The failed addresses, e.g. "10.0.0.1:443 (connection refused), [::1]:443 (timed out)"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (addr, err)) in self.failed.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} ({})", addr, err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Order addresses for connection attempts, alternating IPv6 and IPv4 (RFC 8305 section 4)
+///
+/// The family of the first address goes first.
+pub fn interleave(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return Vec::new();
+    };
+    let (mut preferred, mut other): (VecDeque<_>, VecDeque<_>) = addrs
+        .iter()
+        .copied()
+        .partition(|addr| addr.is_ipv6() == first.is_ipv6());
+    let mut ordered = Vec::with_capacity(addrs.len());
+    while !preferred.is_empty() || !other.is_empty() {
+        ordered.extend(preferred.pop_front());
+        ordered.extend(other.pop_front());
+    }
+    ordered
+}
+
+/// Connect to `addrs` with happy-eyeballs semantics
+///
+/// Attempts start in `interleave` order, each one after the previous attempt
+/// failed or `CONNECTION_ATTEMPT_DELAY` passed, and the first connection wins.
+/// Attempts still pending after `timeout` count as timed out.
+pub async fn connect_race(addrs: &[SocketAddr], timeout: Duration) -> ConnectReport {
+    let mut pending: VecDeque<SocketAddr> = interleave(addrs).into();
+    let mut attempts = FuturesUnordered::new();
+    let mut in_flight = Vec::new();
+    let mut report = ConnectReport::default();
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(deadline);
+
+    loop {
+        if attempts.is_empty() {
+            let Some(addr) = pending.pop_front() else {
+                break;
+            };
+            in_flight.push(addr);
+            attempts.push(attempt(addr));
+        }
+        tokio::select! {
+            Some((addr, result)) = attempts.next() => {
+                in_flight.retain(|a| *a != addr);
+                match result {
+                    Ok(_) => {
+                        report.connected = Some(addr);
+                        break;
+                    }
+                    Err(e) => report.failed.push((addr, e.to_string())),
+                }
+            }
+            _ = tokio::time::sleep(CONNECTION_ATTEMPT_DELAY), if !pending.is_empty() => {
+                if let Some(addr) = pending.pop_front() {
+                    in_flight.push(addr);
+                    attempts.push(attempt(addr));
+                }
+            }
+            _ = &mut deadline => {
+                report
+                    .failed
+                    .extend(in_flight.drain(..).map(|addr| (addr, "timed out".to_string())));
+                break;
+            }
+        }
+    }
+    report
+}
+
+/// One connection attempt, tagged with its address
+async fn attempt(addr: SocketAddr) -> (SocketAddr, io::Result<TcpStream>) {
+    (addr, TcpStream::connect(addr).await)
+}
+
+/// Race report of a host:port, set once by the first request to it
+type RaceCell = Arc<OnceCell<Option<Arc<ConnectReport>>>>;
+
+/// Per-host connection races
+///
+/// The first request to a host:port with several addresses races connections
+/// to all of them. The winner is preferred by the resolver from then on, and
+/// the addresses that failed are logged and added to connect errors.
+pub struct ConnectRacer {
+    resolver: DnsResolver,
+    timeout: Duration,
+    hosts: Mutex<HashMap<HostKey, RaceCell>>,
+}
+
+impl ConnectRacer {
+    pub fn new(resolver: DnsResolver, timeout: Duration) -> Self {
+        ConnectRacer {
+            resolver,
+            timeout,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Report of the race for the host of `url`, None if it has a single address
+    pub async fn race(&self, url: &str) -> Option<Arc<ConnectReport>> {
+        let key = host_key(url)?;
+        let cell = self
+            .hosts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        cell.get_or_init(|| async {
+            let host = key.0.trim_matches(['[', ']']);
+            let addrs = match self.resolver.lookup(host, key.1).await {
+                Ok(addrs) if addrs.len() > 1 => addrs,
+                Ok(_) => return None,
+                Err(e) => {
+                    debug!(host = %host, "Lookup before connection race failed: {}", e);
+                    return None;
+                }
+            };
+
+            let report = connect_race(&addrs, self.timeout).await;
+            let (host, port) = (host.to_string(), key.1);
+            match (report.connected, report.failed.is_empty()) {
+                (Some(addr), true) => info!(host, port, "Connected via {}", addr),
+                (Some(addr), false) => {
+                    info!(host, port, "Connected via {}, failed: {}", addr, report)
+                }
+                (None, _) => info!(host, port, "No address accepted a connection: {}", report),
+            }
+            if let Some(addr) = report.connected {
+                self.resolver.prefer(&host, addr.ip());
+            }
+            Some(Arc::new(report))
+        })
+        .await
+        .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_interleave() {
+        let addrs: Vec<SocketAddr> = ["[::1]:80", "[::2]:80", "[::3]:80", "10.0.0.1:80"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        let ordered: Vec<String> = interleave(&addrs).iter().map(|a| a.to_string()).collect();
+        assert_eq!(ordered, ["[::1]:80", "10.0.0.1:80", "[::2]:80", "[::3]:80"]);
+        assert!(interleave(&[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connect_race() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let report = connect_race(&[closed, open], Duration::from_secs(2)).await;
+        assert_eq!(report.connected, Some(open));
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, closed);
+        assert!(report.to_string().starts_with(&format!("{} (", closed)));
+
+        let report = connect_race(&[closed], Duration::from_secs(2)).await;
+        assert_eq!(report.connected, None);
+        assert_eq!(report.failed.len(), 1);
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+use crate::http::RequestOverrides;
+use crate::utils::is_in_scope;
+
+#[derive(Debug, Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarLog {
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarEntry {
+    request: HarRequest,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Vec<HarHeader>,
+    #[serde(default)]
+    post_data: Option<HarPostData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarHeader {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarPostData {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// Headers of recorded requests that are not replayed
+///
+/// Host and Content-Length are derived again, HTTP/2 pseudo-headers start with
+/// ':' and compressed bodies could not be read as text.
+const SKIPPED_HEADERS: &[&str] = &["host", "content-length", "connection", "accept-encoding"];
+
+/// A recorded request as an input line, written by [`load_har`]
+#[derive(Debug, Deserialize)]
+struct HarLine {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+/// Read the requests of a HAR file as input lines (--har)
+///
+/// Each line is a JSON object read back by [`parse_har_request`].
+///
+/// Requests can be limited to hosts matching `hosts` (same patterns as --scope)
+/// and to `methods`. Headers given with -H (`cli_headers`) replace the recorded ones.
+pub fn load_har(
+    path: &str,
+    hosts: &[String],
+    methods: &[String],
+    cli_headers: &HeaderMap,
+) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read HAR file: {}", path))?;
+    let har: Har =
+        serde_json::from_str(&content).with_context(|| format!("Invalid HAR file: {}", path))?;
+
+    Ok(har
+        .log
+        .entries
+        .into_iter()
+        .map(|entry| entry.request)
+        .filter(|request| is_in_scope(&request.url, hosts))
+        .filter(|request| {
+            methods.is_empty()
+                || methods
+                    .iter()
+                    .any(|method| method.eq_ignore_ascii_case(&request.method))
+        })
+        .map(|request| har_request_line(request, cli_headers))
+        .collect())
+}
+
+/// Parse an input line written by [`load_har`] into a request line and its overrides
+///
+/// The recorded body is sent verbatim, whitespace included, along with the recorded headers.
+pub fn parse_har_request(line: &str) -> Result<(String, RequestOverrides), serde_json::Error> {
+    let request: HarLine = serde_json::from_str(line)?;
+    let mut overrides = RequestOverrides {
+        body: request.body,
+        ..Default::default()
+    };
+    for (name, value) in &request.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.trim().as_bytes()),
+            HeaderValue::from_str(value.trim()),
+        ) {
+            overrides.headers.append(name, value);
+        }
+    }
+    Ok((
+        format!("{} {}", request.method.to_uppercase(), request.url),
+        overrides,
+    ))
+}
+
+/// Input line for a recorded request
+fn har_request_line(request: HarRequest, cli_headers: &HeaderMap) -> String {
+    let mut headers = Map::new();
+    for header in request.headers {
+        let name = header.name.to_ascii_lowercase();
+        if name.starts_with(':')
+            || SKIPPED_HEADERS.contains(&name.as_str())
+            || cli_headers.contains_key(name.as_str())
+        {
+            continue;
+        }
+        // JSON objects hold one value per name, so repeated headers are combined
+        let separator = if name == "cookie" { "; " } else { ", " };
+        let value = match headers.get(&name).and_then(Value::as_str) {
+            Some(previous) => format!("{}{}{}", previous, separator, header.value),
+            None => header.value,
+        };
+        headers.insert(name, value.into());
+    }
+
+    let mut line = json!({
+        "method": request.method,
+        "url": request.url,
+        "headers": headers,
+    });
+    if let Some(body) = request.post_data.and_then(|data| data.text) {
+        line["body"] = body.into();
+    }
+    line.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAR: &str = r#"{"log": {"version": "1.2", "entries": [
+        {"request": {"method": "GET", "url": "https://app.example.com/api/me",
+            "headers": [{"name": ":authority", "value": "app.example.com"},
+                        {"name": "Cookie", "value": "a=1"},
+                        {"name": "Cookie", "value": "b=2"},
+                        {"name": "Authorization", "value": "Bearer old"},
+                        {"name": "Accept-Encoding", "value": "gzip, br"}]},
+         "response": {"status": 200}},
+        {"request": {"method": "POST", "url": "https://app.example.com/api/items",
+            "headers": [{"name": "Content-Type", "value": "application/json"}],
+            "postData": {"mimeType": "application/json", "text": "{\n  \"name\": \"x\"\n}"}}},
+        {"request": {"method": "GET", "url": "https://cdn.other.com/app.js"}}
+    ]}}"#;
+
+    #[test]
+    fn test_load_har() {
+        let path = std::env::temp_dir().join(format!("reqs-{}.har", std::process::id()));
+        std::fs::write(&path, HAR).unwrap();
+        let path = path.to_str().unwrap();
+
+        let mut cli_headers = HeaderMap::new();
+        cli_headers.insert("authorization", "Bearer new".parse().unwrap());
+        let lines = load_har(path, &[], &[], &cli_headers).unwrap();
+        assert_eq!(lines.len(), 3);
+
+        let (line, overrides) = parse_har_request(&lines[0]).unwrap();
+        assert_eq!(line, "GET https://app.example.com/api/me");
+        assert_eq!(overrides.headers.len(), 1);
+        assert_eq!(overrides.headers["cookie"], "a=1; b=2");
+
+        let (line, overrides) = parse_har_request(&lines[1]).unwrap();
+        assert_eq!(line, "POST https://app.example.com/api/items");
+        assert_eq!(overrides.body.as_deref(), Some("{\n  \"name\": \"x\"\n}"));
+
+        let hosts = ["*.example.com".to_string()];
+        assert_eq!(load_har(path, &hosts, &[], &cli_headers).unwrap().len(), 2);
+        let methods = ["post".to_string()];
+        assert_eq!(
+            load_har(path, &hosts, &methods, &cli_headers)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        std::fs::remove_file(path).unwrap();
+        assert!(load_har(path, &[], &[], &cli_headers).is_err());
+    }
+}
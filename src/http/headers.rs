@@ -1,32 +1,156 @@
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use rand::seq::SliceRandom;
+use reqwest::header::{ACCEPT, HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use tracing::warn;
+
+use crate::constants::{RANDOM_USER_AGENTS, USER_AGENT_PRESETS};
+use crate::types::Cli;
+
+/// A single -H entry: header name, value (None to remove the header) and replace flag
+type HeaderEntry = (HeaderName, Option<HeaderValue>, bool);
+
+/// Parse a "Key: Value" header string
+///
+/// An empty value ("Accept-Encoding:") marks the header for removal and a
+/// leading `=` on the name marks it as replacing earlier values.
+fn parse_header_entry(header_str: &str) -> Option<HeaderEntry> {
+    let Some((key, value)) = header_str.split_once(':') else {
+        warn!(
+            "Invalid header format. Expected 'Key: Value'. Got: {}",
+            header_str
+        );
+        return None;
+    };
+    let (key, replace) = match key.strip_prefix('=') {
+        Some(key) => (key, true),
+        None => (key, false),
+    };
+    let Ok(header_name) = HeaderName::from_bytes(key.trim().as_bytes()) else {
+        warn!("Invalid header name: {}", key);
+        return None;
+    };
+
+    let value = value.trim();
+    if value.is_empty() {
+        return Some((header_name, None, true));
+    }
+    match HeaderValue::from_str(value) {
+        Ok(header_value) => Some((header_name, Some(header_value), replace)),
+        Err(_) => {
+            warn!("Invalid header value for key '{}'", key);
+            None
+        }
+    }
+}
 
 /// Parse header strings into HeaderMap
+///
+/// Repeated header names are all kept (e.g. two `Cookie` headers). Prefixing the
+/// name with `=` (e.g. "=Cookie: a=1") replaces any values collected before it,
+/// and an empty value (e.g. "Cookie:") removes them.
 pub fn parse_headers(headers: &[String]) -> HeaderMap {
     let mut header_map = HeaderMap::new();
-    for header_str in headers {
-        if let Some((key, value)) = header_str.split_once(": ") {
-            if let Ok(header_name) = HeaderName::from_bytes(key.as_bytes()) {
-                if let Ok(header_value) = HeaderValue::from_str(value.trim()) {
-                    header_map.insert(header_name, header_value);
-                } else {
-                    eprintln!("[Warning] Invalid header value for key '{}'", key);
-                }
-            } else {
-                eprintln!("[Warning] Invalid header name: {}", key);
+    for (name, value, replace) in headers.iter().filter_map(|h| parse_header_entry(h)) {
+        match value {
+            Some(value) if replace => {
+                header_map.insert(name, value);
+            }
+            Some(value) => {
+                header_map.append(name, value);
+            }
+            None => {
+                header_map.remove(name);
             }
-        } else {
-            eprintln!(
-                "[Warning] Invalid header format. Expected 'Key: Value'. Got: {}",
-                header_str
-            );
         }
     }
     header_map
 }
 
+/// Header names explicitly removed with an empty value (e.g. "User-Agent:")
+pub fn removed_headers(headers: &[String]) -> Vec<HeaderName> {
+    headers
+        .iter()
+        .filter(|h| h.split_once(':').is_some_and(|(_, v)| v.trim().is_empty()))
+        .filter_map(|h| parse_header_entry(h))
+        .map(|(name, _, _)| name)
+        .collect()
+}
+
+/// Whether requests go out without the `Accept: */*` reqwest adds when they have none
+pub fn omits_client_accept(cli: &Cli) -> bool {
+    cli.no_default_headers || removed_headers(&cli.headers).contains(&ACCEPT)
+}
+
+/// Resolve a --user-agent value, expanding preset names (chrome, firefox, ...)
+pub fn resolve_user_agent(value: &str) -> &str {
+    USER_AGENT_PRESETS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, user_agent)| *user_agent)
+        .unwrap_or(value)
+}
+
+/// Build the headers sent with every request from CLI configuration
+///
+/// A User-Agent given (or removed) with custom headers (-H) takes precedence over
+/// --user-agent, which is ignored entirely with --no-default-headers.
+pub fn default_request_headers(cli: &Cli) -> HeaderMap {
+    let mut headers = parse_headers(&cli.headers);
+    if let Some(user_agent) = &cli.user_agent
+        && !cli.no_default_headers
+        && !sets_user_agent(cli)
+    {
+        match HeaderValue::from_str(resolve_user_agent(user_agent)) {
+            Ok(value) => {
+                headers.insert(USER_AGENT, value);
+            }
+            Err(_) => warn!("Invalid --user-agent value: {}", user_agent),
+        }
+    }
+    headers
+}
+
+/// Whether a random User-Agent should be chosen for every request
+pub fn uses_random_user_agent(cli: &Cli) -> bool {
+    cli.random_user_agent && !cli.no_default_headers && !sets_user_agent(cli)
+}
+
+/// Whether custom headers (-H) set or remove the User-Agent themselves
+fn sets_user_agent(cli: &Cli) -> bool {
+    parse_headers(&cli.headers).contains_key(USER_AGENT)
+        || removed_headers(&cli.headers).contains(&USER_AGENT)
+}
+
+/// Headers for a single request, rotating the User-Agent if requested
+pub fn request_headers(defaults: &HeaderMap, random_user_agent: bool) -> HeaderMap {
+    let mut headers = defaults.clone();
+    if random_user_agent
+        && let Some(user_agent) = RANDOM_USER_AGENTS.choose(&mut rand::thread_rng())
+    {
+        headers.insert(USER_AGENT, HeaderValue::from_static(user_agent));
+    }
+    headers
+}
+
+/// Random UUID (version 4) identifying a request (--trace-header)
+pub fn new_trace_id() -> String {
+    let mut bytes = rand::random::<u128>().to_be_bytes();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use clap::Parser;
 
     #[test]
     fn test_parse_headers() {
@@ -40,10 +164,130 @@ mod tests {
         assert_eq!(header_map.get("Content-Type").unwrap(), "application/json");
     }
 
+    #[test]
+    fn test_new_trace_id() {
+        let id = new_trace_id();
+        let groups: Vec<usize> = id.split('-').map(str::len).collect();
+        assert_eq!(groups, vec![8, 4, 4, 4, 12]);
+        assert_eq!(&id[14..15], "4");
+        assert!(matches!(&id[19..20], "8" | "9" | "a" | "b"));
+        assert_ne!(id, new_trace_id());
+    }
+
     #[test]
     fn test_parse_headers_invalid() {
         let headers = vec!["Invalid Header".to_string()];
         let header_map = parse_headers(&headers);
         assert_eq!(header_map.len(), 0);
     }
+
+    #[test]
+    fn test_parse_headers_duplicates() {
+        let headers = vec![
+            "Cookie: a=1".to_string(),
+            "Cookie: b=2".to_string(),
+            "X-Test: 1".to_string(),
+        ];
+        let header_map = parse_headers(&headers);
+        let cookies: Vec<_> = header_map.get_all("Cookie").iter().collect();
+        assert_eq!(cookies, vec!["a=1", "b=2"]);
+        assert_eq!(header_map.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_headers_replace() {
+        let headers = vec![
+            "Cookie: a=1".to_string(),
+            "Cookie: b=2".to_string(),
+            "=Cookie: c=3".to_string(),
+        ];
+        let header_map = parse_headers(&headers);
+        let cookies: Vec<_> = header_map.get_all("Cookie").iter().collect();
+        assert_eq!(cookies, vec!["c=3"]);
+    }
+
+    #[test]
+    fn test_parse_headers_remove() {
+        let headers = vec![
+            "Cookie: a=1".to_string(),
+            "X-Test: 1".to_string(),
+            "Cookie:".to_string(),
+        ];
+        let header_map = parse_headers(&headers);
+        assert!(!header_map.contains_key("Cookie"));
+        assert_eq!(header_map.len(), 1);
+        assert_eq!(
+            removed_headers(&headers),
+            vec![HeaderName::from_static("cookie")]
+        );
+    }
+
+    #[test]
+    fn test_default_headers_user_agent_removed() {
+        let cli = Cli::parse_from(["reqs", "--user-agent", "chrome", "-H", "User-Agent:"]);
+        assert!(default_request_headers(&cli).get(USER_AGENT).is_none());
+
+        let cli = Cli::parse_from(["reqs", "--random-user-agent", "-H", "User-Agent:"]);
+        assert!(!uses_random_user_agent(&cli));
+    }
+
+    #[test]
+    fn test_no_default_headers() {
+        let cli = Cli::parse_from([
+            "reqs",
+            "--user-agent",
+            "chrome",
+            "--no-default-headers",
+            "-H",
+            "X-Test: 1",
+        ]);
+        let headers = default_request_headers(&cli);
+        assert!(headers.get(USER_AGENT).is_none());
+        assert_eq!(headers.get("X-Test").unwrap(), "1");
+
+        let cli = Cli::parse_from(["reqs", "--random-user-agent", "--no-default-headers"]);
+        assert!(!uses_random_user_agent(&cli));
+    }
+
+    #[test]
+    fn test_resolve_user_agent() {
+        assert!(resolve_user_agent("chrome").contains("Chrome/"));
+        assert!(resolve_user_agent("Googlebot").contains("Googlebot/2.1"));
+        assert_eq!(resolve_user_agent("my-agent/1.0"), "my-agent/1.0");
+    }
+
+    #[test]
+    fn test_default_request_headers() {
+        let cli = Cli::parse_from(["reqs", "--user-agent", "firefox", "-H", "X-Test: 1"]);
+        let headers = default_request_headers(&cli);
+        assert!(
+            headers
+                .get(USER_AGENT)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .contains("Firefox/")
+        );
+        assert_eq!(headers.get("X-Test").unwrap(), "1");
+
+        let cli = Cli::parse_from([
+            "reqs",
+            "--user-agent",
+            "firefox",
+            "-H",
+            "User-Agent: custom",
+        ]);
+        let headers = default_request_headers(&cli);
+        assert_eq!(headers.get(USER_AGENT).unwrap(), "custom");
+    }
+
+    #[test]
+    fn test_request_headers_random_user_agent() {
+        let headers = request_headers(&HeaderMap::new(), true);
+        let user_agent = headers.get(USER_AGENT).unwrap().to_str().unwrap();
+        assert!(RANDOM_USER_AGENTS.contains(&user_agent));
+
+        let headers = request_headers(&HeaderMap::new(), false);
+        assert!(headers.get(USER_AGENT).is_none());
+    }
 }
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::OnceCell;
+
+/// Host and port of a checked URL
+pub(crate) type HostKey = (String, u16);
+
+/// Host and port of `url`, None if it has no host
+pub(crate) fn host_key(url: &str) -> Option<HostKey> {
+    let url = reqwest::Url::parse(url).ok()?;
+    Some((url.host_str()?.to_string(), url.port_or_known_default()?))
+}
+
+/// Per-host liveness cache used by --pre-check
+///
+/// Each host:port is checked with a single TCP connect; concurrent requests for
+/// the same host wait for that check instead of connecting again.
+pub struct LivenessCache {
+    timeout: Duration,
+    hosts: Mutex<HashMap<HostKey, Arc<OnceCell<bool>>>>,
+}
+
+impl LivenessCache {
+    pub fn new(timeout: Duration) -> Self {
+        LivenessCache {
+            timeout,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the host of `url` accepts TCP connections (unparsable URLs count as alive)
+    pub async fn is_alive(&self, url: &str) -> bool {
+        let Some(key) = host_key(url) else {
+            return true;
+        };
+
+        let cell = match self.hosts.lock() {
+            Ok(mut hosts) => hosts.entry(key.clone()).or_default().clone(),
+            Err(_) => return true,
+        };
+        *cell
+            .get_or_init(|| async {
+                let (host, port) = (key.0.trim_matches(['[', ']']), key.1);
+                matches!(
+                    tokio::time::timeout(self.timeout, TcpStream::connect((host, port))).await,
+                    Ok(Ok(_))
+                )
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_liveness_cache() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let cache = LivenessCache::new(Duration::from_secs(1));
+
+        let url = format!("http://127.0.0.1:{}/a", port);
+        assert!(cache.is_alive(&url).await);
+
+        // The result is cached per host:port, even once the listener is gone
+        drop(listener);
+        assert!(
+            cache
+                .is_alive(&format!("http://127.0.0.1:{}/b", port))
+                .await
+        );
+        assert!(!cache.is_alive("http://127.0.0.1:1/").await);
+        assert!(cache.is_alive("not a url").await);
+    }
+}
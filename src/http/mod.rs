@@ -1,7 +1,54 @@
+pub mod backup;
+pub mod bandwidth;
+pub mod breaker;
+pub mod bytes;
+pub mod cache;
 pub mod client;
+pub mod dns;
+pub mod error;
+pub mod exact;
+pub mod eyeballs;
+pub mod har;
 pub mod headers;
+pub mod liveness;
+pub mod mutate;
+pub mod ntlm;
+pub mod proxy;
+pub mod reflection;
 pub mod request;
+pub mod revalidate;
+pub mod sse;
+pub mod tcp;
+pub mod timing;
+pub mod websocket;
 
-pub use client::build_http_client;
-pub use headers::parse_headers;
-pub use request::{build_request, format_raw_request, parse_request_line};
+pub use backup::{BackupFinding, backup_urls, is_backup_hit};
+pub use bandwidth::{Bandwidth, parse_bandwidth};
+pub use breaker::HostBreaker;
+pub use bytes::{ByteCount, request_size, response_head_size};
+pub use cache::{CachedResponse, ResponseCache};
+pub use client::{ClientOptions, ClientPool};
+pub use dns::HostAddresses;
+pub use error::{ErrorKind, FailedAt, RequestError, SendError, classify_chain};
+pub use exact::ExactClient;
+pub use eyeballs::ConnectRacer;
+pub use har::{load_har, parse_har_request};
+pub use headers::{
+    default_request_headers, new_trace_id, omits_client_accept, parse_headers, removed_headers,
+    request_headers, uses_random_user_agent,
+};
+pub use liveness::LivenessCache;
+pub use mutate::{Mutation, mutate_headers, mutate_method, parse_mutation};
+pub use ntlm::{NtlmCredentials, parse_ntlm_credentials};
+pub use proxy::{ProxyPool, failed_at};
+pub use reflection::Reflection;
+pub use request::{
+    RequestOverrides, build_request, credentials_header, format_dry_run, format_raw_request,
+    is_safe_method, parse_jsonl_request, parse_reqs_record, parse_request_line,
+    parse_request_overrides, send_request, split_host_override, split_tags,
+};
+pub use revalidate::Validators;
+pub use sse::{SseInfo, is_event_stream, read_event_stream};
+pub use tcp::{PortState, TcpProbe, probe_tcp};
+pub use timing::{TimingDetail, with_timing};
+pub use websocket::{WebSocketInfo, WsProbeOptions, is_websocket_url, probe_websocket};
@@ -0,0 +1,113 @@
+use reqwest::header::{COOKIE, HeaderMap, HeaderName};
+
+use crate::constants::HTTP_METHODS;
+
+/// A transformation applied to every request before it is sent (--mutate)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mutation {
+    /// Remove the Cookie header
+    StripCookies,
+    /// Send the request with another method, keeping its body
+    SwapMethod(String),
+    /// Remove a header
+    DropHeader(HeaderName),
+}
+
+/// Parse a mutation such as "strip-cookies", "swap-method:GET" or "drop-header:Authorization"
+pub fn parse_mutation(value: &str) -> Result<Mutation, String> {
+    let (name, argument) = match value.trim().split_once(':') {
+        Some((name, argument)) => (name, Some(argument.trim())),
+        None => (value.trim(), None),
+    };
+    match (name, argument) {
+        ("strip-cookies", None) => Ok(Mutation::StripCookies),
+        ("swap-method", Some(method)) => {
+            let method = method.to_uppercase();
+            if HTTP_METHODS.contains(&method.as_str()) {
+                Ok(Mutation::SwapMethod(method))
+            } else {
+                Err(format!(
+                    "Unsupported method '{}'. Use one of {}",
+                    method,
+                    HTTP_METHODS.join(", ")
+                ))
+            }
+        }
+        ("drop-header", Some(header)) => HeaderName::from_bytes(header.as_bytes())
+            .map(Mutation::DropHeader)
+            .map_err(|_| format!("Invalid header name '{}'", header)),
+        _ => Err(format!(
+            "Invalid mutation '{}'. Expected strip-cookies, swap-method:METHOD or drop-header:NAME",
+            value
+        )),
+    }
+}
+
+/// Method of a request after the mutations; the last swap-method wins
+pub fn mutate_method(mutations: &[Mutation], method: String) -> String {
+    mutations
+        .iter()
+        .rev()
+        .find_map(|mutation| match mutation {
+            Mutation::SwapMethod(method) => Some(method.clone()),
+            _ => None,
+        })
+        .unwrap_or(method)
+}
+
+/// Remove the headers dropped by the mutations
+pub fn mutate_headers(mutations: &[Mutation], headers: &mut HeaderMap) {
+    for mutation in mutations {
+        match mutation {
+            Mutation::StripCookies => {
+                headers.remove(COOKIE);
+            }
+            Mutation::DropHeader(name) => {
+                headers.remove(name);
+            }
+            Mutation::SwapMethod(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::AUTHORIZATION;
+
+    #[test]
+    fn test_parse_mutation() {
+        assert_eq!(parse_mutation("strip-cookies"), Ok(Mutation::StripCookies));
+        assert_eq!(
+            parse_mutation("swap-method:get"),
+            Ok(Mutation::SwapMethod("GET".to_string()))
+        );
+        assert_eq!(
+            parse_mutation("drop-header:Authorization"),
+            Ok(Mutation::DropHeader(AUTHORIZATION))
+        );
+        assert!(parse_mutation("swap-method:TRACE").is_err());
+        assert!(parse_mutation("drop-header:bad header").is_err());
+        assert!(parse_mutation("strip-cookies:all").is_err());
+        assert!(parse_mutation("fuzz").is_err());
+    }
+
+    #[test]
+    fn test_apply_mutations() {
+        let mutations = [
+            Mutation::StripCookies,
+            Mutation::DropHeader(AUTHORIZATION),
+            Mutation::SwapMethod("PUT".to_string()),
+        ];
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, "session=1".parse().unwrap());
+        headers.insert(AUTHORIZATION, "Bearer x".parse().unwrap());
+        headers.insert("x-keep", "1".parse().unwrap());
+
+        mutate_headers(&mutations, &mut headers);
+        assert_eq!(headers.len(), 1);
+        assert!(headers.contains_key("x-keep"));
+        assert_eq!(mutate_method(&mutations, "POST".to_string()), "PUT");
+        assert_eq!(mutate_method(&[], "POST".to_string()), "POST");
+    }
+}
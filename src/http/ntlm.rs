@@ -0,0 +1,334 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use hmac::{Hmac, Mac};
+use md4::{Digest, Md4};
+use md5::Md5;
+use reqwest::header::{AUTHORIZATION, HeaderValue, WWW_AUTHENTICATE};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+/// Unicode, OEM, request target, NTLM, always sign, extended session security, 128 and 56 bit
+const NEGOTIATE_FLAGS: u32 = 0xa008_8207;
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+/// Seconds between 1601-01-01 (Windows FILETIME epoch) and 1970-01-01
+const FILETIME_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+/// Credentials for --auth-ntlm
+#[derive(Clone, PartialEq)]
+pub struct NtlmCredentials {
+    pub domain: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for NtlmCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NtlmCredentials({}\\{})", self.domain, self.username)
+    }
+}
+
+/// Parse --auth-ntlm: "DOMAIN\user:pass" or "user:pass"
+pub fn parse_ntlm_credentials(value: &str) -> Result<NtlmCredentials, String> {
+    let (account, password) = value.split_once(':').ok_or_else(|| {
+        format!(
+            "Invalid credentials '{}'. Expected DOMAIN\\user:pass",
+            value
+        )
+    })?;
+    let (domain, username) = account.split_once('\\').unwrap_or(("", account));
+    if username.is_empty() {
+        return Err(format!("Missing user name in '{}'", value));
+    }
+    Ok(NtlmCredentials {
+        domain: domain.to_string(),
+        username: username.to_string(),
+        password: password.to_string(),
+    })
+}
+
+/// Server challenge from an NTLM type 2 message
+#[derive(Debug, PartialEq)]
+pub struct Challenge {
+    pub flags: u32,
+    pub server_challenge: [u8; 8],
+    pub target_info: Vec<u8>,
+}
+
+/// Type 1 message opening the handshake
+pub fn negotiate_message() -> Vec<u8> {
+    let mut message = SIGNATURE.to_vec();
+    message.extend_from_slice(&1u32.to_le_bytes());
+    message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+    // Empty domain and workstation fields
+    message.extend_from_slice(&[0; 16]);
+    message
+}
+
+/// Parse a type 2 message
+pub fn parse_challenge(message: &[u8]) -> Option<Challenge> {
+    if message.len() < 32 || &message[..8] != SIGNATURE || read_u32(message, 8)? != 2 {
+        return None;
+    }
+    let target_info = if message.len() >= 48 {
+        let len = read_u16(message, 40)? as usize;
+        let offset = read_u32(message, 44)? as usize;
+        message.get(offset..offset + len)?.to_vec()
+    } else {
+        Vec::new()
+    };
+    Some(Challenge {
+        flags: read_u32(message, 20)?,
+        server_challenge: message[24..32].try_into().ok()?,
+        target_info,
+    })
+}
+
+/// Type 3 message answering `challenge` with NTLMv2 responses
+///
+/// `timestamp` is a Windows FILETIME, in 100ns intervals since 1601.
+pub fn authenticate_message(
+    credentials: &NtlmCredentials,
+    challenge: &Challenge,
+    client_challenge: [u8; 8],
+    timestamp: u64,
+) -> Vec<u8> {
+    let nt_hash = Md4::digest(utf16le(&credentials.password));
+    let identity = format!(
+        "{}{}",
+        credentials.username.to_uppercase(),
+        credentials.domain
+    );
+    let v2_hash = hmac_md5(&nt_hash, &[&utf16le(&identity)]);
+
+    let mut blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+    blob.extend_from_slice(&timestamp.to_le_bytes());
+    blob.extend_from_slice(&client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(&challenge.target_info);
+    blob.extend_from_slice(&[0; 4]);
+    let proof = hmac_md5(&v2_hash, &[&challenge.server_challenge, &blob]);
+    let nt_response = [proof.as_slice(), &blob].concat();
+    let lm_response = [
+        hmac_md5(&v2_hash, &[&challenge.server_challenge, &client_challenge]).as_slice(),
+        &client_challenge,
+    ]
+    .concat();
+
+    let fields = [
+        lm_response,
+        nt_response,
+        utf16le(&credentials.domain),
+        utf16le(&credentials.username),
+        Vec::new(),
+        Vec::new(),
+    ];
+    // Signature, type, six field descriptors and the flags
+    let header_len = 8 + 4 + fields.len() * 8 + 4;
+    let mut message = SIGNATURE.to_vec();
+    message.extend_from_slice(&3u32.to_le_bytes());
+    // Payload in the order domain, user, workstation, LM, NT, session key
+    let mut offsets = [0usize; 6];
+    let mut offset = header_len;
+    for index in [2, 3, 4, 0, 1, 5] {
+        offsets[index] = offset;
+        offset += fields[index].len();
+    }
+    for (field, offset) in fields.iter().zip(offsets) {
+        message.extend_from_slice(&(field.len() as u16).to_le_bytes());
+        message.extend_from_slice(&(field.len() as u16).to_le_bytes());
+        message.extend_from_slice(&(offset as u32).to_le_bytes());
+    }
+    let flags = (challenge.flags & NEGOTIATE_FLAGS) | NEGOTIATE_UNICODE;
+    message.extend_from_slice(&flags.to_le_bytes());
+    for index in [2, 3, 4, 0, 1, 5] {
+        message.extend_from_slice(&fields[index]);
+    }
+    message
+}
+
+/// Send a request, authenticating with NTLM if the server asks for it
+///
+/// The handshake is bound to one connection, so `request` must come from a
+/// client that keeps a single connection per host (see `ClientPool`). A server
+/// offering only Negotiate gets the NTLM tokens under that scheme.
+pub async fn send_with_ntlm(
+    request: RequestBuilder,
+    credentials: &NtlmCredentials,
+) -> reqwest::Result<Response> {
+    let (Some(negotiate), Some(authenticate)) = (request.try_clone(), request.try_clone()) else {
+        return request.send().await;
+    };
+    let resp = request.send().await?;
+    let Some(scheme) = auth_scheme(&resp) else {
+        return Ok(resp);
+    };
+    debug!(url = %resp.url(), scheme, "Starting NTLM handshake");
+    // Read the body so the connection is free for the next message
+    let _ = resp.bytes().await;
+
+    let resp = negotiate
+        .header(AUTHORIZATION, token(scheme, &negotiate_message()))
+        .send()
+        .await?;
+    let challenge = resp
+        .headers()
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(|value| value.strip_prefix(scheme)?.trim().split(',').next())
+        .and_then(|value| STANDARD.decode(value.trim()).ok())
+        .and_then(|message| parse_challenge(&message));
+    let Some(challenge) = challenge else {
+        debug!(url = %resp.url(), "No NTLM challenge in the response");
+        return Ok(resp);
+    };
+    let _ = resp.bytes().await;
+
+    let message = authenticate_message(
+        credentials,
+        &challenge,
+        rand::random(),
+        filetime(SystemTime::now()),
+    );
+    authenticate
+        .header(AUTHORIZATION, token(scheme, &message))
+        .send()
+        .await
+}
+
+/// "NTLM" or "Negotiate" when a 401 response offers it, preferring NTLM
+fn auth_scheme(resp: &Response) -> Option<&'static str> {
+    if resp.status() != StatusCode::UNAUTHORIZED {
+        return None;
+    }
+    let offered: Vec<&str> = resp
+        .headers()
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .map(|value| value.split_whitespace().next().unwrap_or_default())
+        .collect();
+    ["NTLM", "Negotiate"]
+        .into_iter()
+        .find(|scheme| offered.iter().any(|o| o.eq_ignore_ascii_case(scheme)))
+}
+
+fn token(scheme: &str, message: &[u8]) -> HeaderValue {
+    let mut value = HeaderValue::from_str(&format!("{} {}", scheme, STANDARD.encode(message)))
+        .expect("base64 is a valid header value");
+    value.set_sensitive(true);
+    value
+}
+
+fn filetime(time: SystemTime) -> u64 {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since_epoch.as_secs() + FILETIME_EPOCH_OFFSET) * 10_000_000
+        + u64::from(since_epoch.subsec_nanos() / 100)
+}
+
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = Hmac::<Md5>::new_from_slice(key).expect("HMAC takes keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+fn utf16le(value: &str) -> Vec<u8> {
+    value.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn read_u16(message: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        message.get(at..at + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(message: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        message.get(at..at + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn unhex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_ntlm_credentials() {
+        let credentials = parse_ntlm_credentials("CORP\\alice:s3:cret").unwrap();
+        assert_eq!(credentials.domain, "CORP");
+        assert_eq!(credentials.username, "alice");
+        assert_eq!(credentials.password, "s3:cret");
+        assert_eq!(parse_ntlm_credentials("bob:pw").unwrap().domain, "");
+        assert!(parse_ntlm_credentials("CORP\\alice").is_err());
+        assert!(parse_ntlm_credentials("CORP\\:pw").is_err());
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        let mut message = SIGNATURE.to_vec();
+        message.extend_from_slice(&2u32.to_le_bytes());
+        message.extend_from_slice(&[0, 0, 0, 0, 48, 0, 0, 0]);
+        message.extend_from_slice(&0x0289_8205u32.to_le_bytes());
+        message.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&[4, 0, 4, 0, 48, 0, 0, 0]);
+        message.extend_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(
+            parse_challenge(&message),
+            Some(Challenge {
+                flags: 0x0289_8205,
+                server_challenge: [1, 2, 3, 4, 5, 6, 7, 8],
+                target_info: vec![0, 0, 0, 0],
+            })
+        );
+        assert_eq!(parse_challenge(&negotiate_message()), None);
+        assert_eq!(parse_challenge(&message[..20]), None);
+    }
+
+    #[test]
+    fn test_authenticate_message() {
+        // Example values from [MS-NLMP] 4.2.4 (NTLMv2 authentication)
+        let credentials = NtlmCredentials {
+            domain: "Domain".to_string(),
+            username: "User".to_string(),
+            password: "Password".to_string(),
+        };
+        let challenge = Challenge {
+            flags: 0xe28a_8233,
+            server_challenge: unhex("0123456789abcdef").try_into().unwrap(),
+            target_info: unhex(
+                "02000c0044006f006d00610069006e0001000c0053006500720076006500720000000000",
+            ),
+        };
+        let message = authenticate_message(&credentials, &challenge, [0xaa; 8], 0);
+        assert_eq!(&message[..8], SIGNATURE);
+
+        let field = |index: usize| {
+            let at = 12 + index * 8;
+            let len = read_u16(&message, at).unwrap() as usize;
+            let offset = read_u32(&message, at + 4).unwrap() as usize;
+            message[offset..offset + len].to_vec()
+        };
+        assert_eq!(
+            hex(&field(0)),
+            "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa"
+        );
+        assert_eq!(hex(&field(1)[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+        assert_eq!(field(2), utf16le("Domain"));
+        assert_eq!(field(3), utf16le("User"));
+    }
+}
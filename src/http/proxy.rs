@@ -0,0 +1,126 @@
+use anyhow::{Context, Result, bail};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::http::error::{ErrorKind, FailedAt, SendError};
+
+/// Proxies that requests are spread across (--proxy-pool)
+///
+/// Each request takes the next proxy in turn; with --retry-rotate-proxy its
+/// retries move on through the pool from there.
+pub struct ProxyPool {
+    proxies: Vec<String>,
+    next: AtomicUsize,
+}
+
+impl ProxyPool {
+    /// Load proxy URLs from a file, one per line, skipping blanks and # comments
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read proxy pool: {}", path))?;
+        Self::parse(&content).with_context(|| format!("Invalid proxy pool: {}", path))
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        let mut proxies = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            reqwest::Proxy::all(line).with_context(|| format!("Invalid proxy URL: {}", line))?;
+            proxies.push(line.to_string());
+        }
+        if proxies.is_empty() {
+            bail!("No proxies listed");
+        }
+        Ok(ProxyPool {
+            proxies,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Number of proxies in the pool
+    pub fn count(&self) -> usize {
+        self.proxies.len()
+    }
+
+    /// Position of the proxy for the next request
+    pub fn start(&self) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Proxy `offset` places after `start`, wrapping around the pool
+    pub fn get(&self, start: usize, offset: usize) -> &str {
+        &self.proxies[start.wrapping_add(offset) % self.proxies.len()]
+    }
+}
+
+/// Where a request sent through a proxy failed
+///
+/// The client only resolves and connects to the proxy, which reaches the
+/// target for it. Failing to look up or connect to an address is therefore
+/// the proxy's failure; a proxy that answered but could not open the tunnel,
+/// or any later error, is the target's.
+pub fn failed_at(err: &SendError) -> FailedAt {
+    let unreachable = matches!(
+        err.kind(),
+        ErrorKind::Dns | ErrorKind::ConnectRefused | ErrorKind::ConnectTimeout
+    );
+    if err.is_connect() && unreachable {
+        FailedAt::Proxy
+    } else {
+        FailedAt::Target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_proxy_pool() {
+        let pool = ProxyPool::parse(
+            "# office\nhttp://10.0.0.1:8080\n\nhttp://10.0.0.2:1080\nhttp://10.0.0.3:3128\n",
+        )
+        .unwrap();
+        assert_eq!(pool.count(), 3);
+        assert_eq!(pool.start(), 0);
+        assert_eq!(pool.start(), 1);
+        assert_eq!(pool.get(1, 0), "http://10.0.0.2:1080");
+        assert_eq!(pool.get(1, 2), "http://10.0.0.1:8080");
+
+        assert!(ProxyPool::parse("# none\n").is_err());
+        assert!(ProxyPool::parse("not a proxy\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_at() {
+        let send = |proxy: String| async move {
+            let client = reqwest::Client::builder()
+                .proxy(reqwest::Proxy::all(proxy).unwrap())
+                .build()
+                .unwrap();
+            SendError::from(client.get("https://example.com/").send().await.unwrap_err())
+        };
+
+        // Nothing listens on port 1
+        let err = send("http://127.0.0.1:1".to_string()).await;
+        assert_eq!(failed_at(&err), FailedAt::Proxy);
+
+        // A proxy that cannot reach the target refuses the tunnel
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let err = send(proxy).await;
+        assert_eq!(failed_at(&err), FailedAt::Target);
+    }
+}
@@ -0,0 +1,113 @@
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+
+use crate::constants::REFLECTION_CANARY_PREFIX;
+
+/// Query parameters of a request tagged with canaries (--detect-reflection)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reflection {
+    /// The URL to send, with a canary appended to each parameter value
+    pub url: String,
+    /// Parameter names and their canaries
+    canaries: Vec<(String, String)>,
+}
+
+impl Reflection {
+    /// Append a unique canary to every query parameter of a URL
+    ///
+    /// The rest of the URL is left as written. Returns None for URLs without
+    /// query parameters.
+    pub fn inject(url: &str) -> Option<Self> {
+        let query_start = url.find('?')?;
+        if url.find('#').is_some_and(|hash| hash < query_start) {
+            return None;
+        }
+        let (base, rest) = url.split_at(query_start + 1);
+        let (query, fragment) = match rest.split_once('#') {
+            Some((query, fragment)) => (query, Some(fragment)),
+            None => (rest, None),
+        };
+
+        let mut canaries = Vec::new();
+        let pieces: Vec<String> = query
+            .split('&')
+            .map(|piece| {
+                if piece.is_empty() {
+                    return String::new();
+                }
+                let canary = new_canary();
+                match piece.split_once('=') {
+                    Some((name, _)) => {
+                        canaries.push((name.to_string(), canary.clone()));
+                        format!("{}{}", piece, canary)
+                    }
+                    None => {
+                        canaries.push((piece.to_string(), canary.clone()));
+                        format!("{}={}", piece, canary)
+                    }
+                }
+            })
+            .collect();
+        if canaries.is_empty() {
+            return None;
+        }
+
+        let mut url = format!("{}{}", base, pieces.join("&"));
+        if let Some(fragment) = fragment {
+            url.push('#');
+            url.push_str(fragment);
+        }
+        Some(Reflection { url, canaries })
+    }
+
+    /// Names of the parameters whose canary appears in a response body
+    pub fn reflected(&self, body: &str) -> Vec<String> {
+        self.canaries
+            .iter()
+            .filter(|(_, canary)| body.contains(canary.as_str()))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+fn new_canary() -> String {
+    let suffix: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    format!("{}{}", REFLECTION_CANARY_PREFIX, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reflection() {
+        let reflection =
+            Reflection::inject("https://example.com/search?q=shoes&page=2&debug#top").unwrap();
+        let (q, page, debug) = (
+            &reflection.canaries[0].1,
+            &reflection.canaries[1].1,
+            &reflection.canaries[2].1,
+        );
+        assert!(q.starts_with(REFLECTION_CANARY_PREFIX));
+        assert_ne!(q, page);
+        assert_eq!(
+            reflection.url,
+            format!(
+                "https://example.com/search?q=shoes{}&page=2{}&debug={}#top",
+                q, page, debug
+            )
+        );
+
+        let body = format!("<p>Results for shoes{}</p><a href=\"?debug={}\">", q, debug);
+        assert_eq!(reflection.reflected(&body), vec!["q", "debug"]);
+        assert!(reflection.reflected("<p>No results</p>").is_empty());
+
+        assert_eq!(Reflection::inject("https://example.com/"), None);
+        assert_eq!(Reflection::inject("https://example.com/?"), None);
+        assert_eq!(Reflection::inject("https://example.com/#a?b=1"), None);
+    }
+}
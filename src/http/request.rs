@@ -1,7 +1,229 @@
-use reqwest::Client;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::header::{CONTENT_LENGTH, HOST, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::constants::{HTTP_METHODS, HTTP_VERSION_1_1, HTTP_VERSION_2, SAFE_METHODS};
+use crate::http::client::ClientOptions;
+use crate::http::error::SendError;
+use crate::http::exact::ExactClient;
+use crate::http::ntlm::send_with_ntlm;
+use crate::types::{Cli, UrlAuth};
+use crate::utils::{UrlCredentials, parse_duration};
+
+/// Per-request option overrides given at the end of an input line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestOverrides {
+    pub follow_redirect: Option<bool>,
+    pub http2: Option<bool>,
+    pub timeout: Option<Duration>,
+    pub retries: Option<u32>,
+    /// `Some(None)` disables the proxy for this request
+    pub proxy: Option<Option<String>>,
+    pub expect_status: Vec<u16>,
+    pub expect_body: Option<String>,
+    /// Headers replacing the configured ones (--input-format reqs-jsonl)
+    pub headers: HeaderMap,
+    /// Method sent instead of the one in the request line (--input-format jsonl)
+    pub method: Option<String>,
+    /// Body sent as-is instead of the one in the request line
+    pub body: Option<String>,
+    /// Host and SNI sent instead of the URL's host (`@host:NAME`, --host-header)
+    pub host: Option<String>,
+    /// TLS SNI sent instead of the host name; `Some(None)` sends none (--sni, --no-sni)
+    pub sni: Option<Option<String>>,
+    /// Opaque labels passed through to the output (` #TAG`, `tags` in JSONL input)
+    pub tags: Vec<String>,
+}
+
+impl RequestOverrides {
+    /// Apply the overrides on top of the default client options
+    pub fn apply(&self, options: &ClientOptions) -> ClientOptions {
+        ClientOptions {
+            proxy: self.proxy.clone().unwrap_or_else(|| options.proxy.clone()),
+            http2: self.http2.unwrap_or(options.http2),
+            follow_redirect: self.follow_redirect.unwrap_or(options.follow_redirect),
+            resolve: options.resolve.clone(),
+            sni: self.sni.as_ref().map_or(options.sni, Option::is_some),
+        }
+    }
 
-use crate::constants::{HTTP_METHODS, HTTP_VERSION_1_1, HTTP_VERSION_2};
-use crate::http::headers::parse_headers;
+    /// Name put in the URL so it is sent as the SNI: the --sni name, else the Host override
+    pub fn connect_name(&self) -> Option<&str> {
+        match &self.sni {
+            Some(Some(sni)) => Some(sni),
+            _ => self.host.as_deref(),
+        }
+    }
+
+    /// Parse a comma-separated override list, returning None if any item is unknown
+    fn parse(list: &str) -> Option<Self> {
+        let mut overrides = RequestOverrides::default();
+        for item in list.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            match item.split_once('=') {
+                None => match item {
+                    "redirect" => overrides.follow_redirect = Some(true),
+                    "no-redirect" => overrides.follow_redirect = Some(false),
+                    "http2" => overrides.http2 = Some(true),
+                    "http1" => overrides.http2 = Some(false),
+                    "no-proxy" => overrides.proxy = Some(None),
+                    "no-sni" => overrides.sni = Some(None),
+                    _ => return None,
+                },
+                Some(("timeout", value)) => overrides.timeout = Some(parse_duration(value).ok()?),
+                Some(("retries", value)) => overrides.retries = Some(value.parse().ok()?),
+                Some(("proxy", value)) => overrides.proxy = Some(Some(value.to_string())),
+                Some(("sni", value)) if !value.is_empty() => {
+                    overrides.sni = Some(Some(value.to_string()))
+                }
+                Some(("expect-status", value)) => {
+                    overrides.expect_status = value
+                        .split('|')
+                        .map(|status| status.parse().ok())
+                        .collect::<Option<_>>()?;
+                }
+                Some(("expect-body", value)) => overrides.expect_body = Some(value.to_string()),
+                Some(_) => return None,
+            }
+        }
+        Some(overrides)
+    }
+}
+
+/// Split trailing option overrides (e.g. "[no-redirect,http2,timeout=3]") from a request line
+///
+/// The bracket group is only treated as overrides when every item in it is a known
+/// option, so request bodies ending in a JSON array are left untouched.
+pub fn parse_request_overrides(line: &str) -> (String, RequestOverrides) {
+    let trimmed = line.trim_end();
+    if trimmed.ends_with(']')
+        && let Some(open) = trimmed.rfind(" [")
+        && let Some(overrides) = RequestOverrides::parse(&trimmed[open + 2..trimmed.len() - 1])
+    {
+        return (trimmed[..open].to_string(), overrides);
+    }
+
+    (line.to_string(), RequestOverrides::default())
+}
+
+/// Split a trailing "@host:NAME" from a request line
+///
+/// The request connects to the URL's address but sends NAME as the Host header and SNI.
+pub fn split_host_override(line: &str) -> (String, Option<String>) {
+    let trimmed = line.trim_end();
+    if let Some(at) = trimmed.rfind(" @host:") {
+        let name = &trimmed[at + " @host:".len()..];
+        if !name.is_empty() && !name.contains(char::is_whitespace) {
+            return (trimmed[..at].to_string(), Some(name.to_string()));
+        }
+    }
+    (line.to_string(), None)
+}
+
+/// Split trailing " #TAG" labels from a request line
+///
+/// Only words starting with `#` after whitespace at the end of the line are
+/// tags, so URL fragments stay part of the URL.
+pub fn split_tags(line: &str) -> (String, Vec<String>) {
+    let mut rest = line.trim_end();
+    let mut tags = Vec::new();
+    while let Some(space) = rest.rfind(char::is_whitespace) {
+        match rest[space + 1..].strip_prefix('#') {
+            Some(tag) if !tag.is_empty() => tags.push(tag.to_string()),
+            _ => break,
+        }
+        rest = rest[..space].trim_end();
+    }
+    if tags.is_empty() {
+        return (line.to_string(), tags);
+    }
+    tags.reverse();
+    (rest.to_string(), tags)
+}
+
+/// A request read with --input-format jsonl
+#[derive(Debug, Deserialize)]
+struct JsonlRequest {
+    url: String,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    #[serde(default)]
+    retries: Option<u32>,
+    #[serde(default)]
+    follow_redirect: Option<bool>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Parse a JSON input line into its URL and option overrides
+///
+/// The method and body are returned as overrides so they are sent exactly as
+/// given. Unknown fields are ignored, so records carrying extra metadata can
+/// be read as-is.
+pub fn parse_jsonl_request(line: &str) -> Result<(String, RequestOverrides), serde_json::Error> {
+    let request: JsonlRequest = serde_json::from_str(line)?;
+    let overrides = RequestOverrides {
+        method: request.method.map(|method| method.to_uppercase()),
+        body: request.body,
+        timeout: request.timeout_ms.map(Duration::from_millis),
+        retries: request.retries,
+        follow_redirect: request.follow_redirect,
+        tags: request.tags,
+        ..Default::default()
+    };
+    Ok((request.url, overrides))
+}
+
+/// A record written by reqs with --format jsonl, read with --input-format reqs-jsonl
+#[derive(Debug, Deserialize)]
+struct ReqsRecord {
+    method: String,
+    url: String,
+    #[serde(default)]
+    raw_request: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Parse a reqs JSONL output record back into a request
+///
+/// With the `raw_request` of --include-req, the recorded headers, body and HTTP
+/// version are replayed too. Host and Content-Length are derived again when sending.
+pub fn parse_reqs_record(line: &str) -> Result<(String, RequestOverrides), serde_json::Error> {
+    let record: ReqsRecord = serde_json::from_str(line)?;
+    let mut overrides = RequestOverrides {
+        tags: record.tags,
+        ..Default::default()
+    };
+    if let Some(raw) = &record.raw_request {
+        let (head, body) = match raw.split_once("\n\n") {
+            Some((head, body)) => (head, Some(body)),
+            None => (raw.as_str(), None),
+        };
+        let mut lines = head.lines();
+        if let Some(request_line) = lines.next() {
+            overrides.http2 = Some(request_line.trim_end().ends_with(HTTP_VERSION_2));
+        }
+        for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.trim().as_bytes()),
+                HeaderValue::from_str(value.trim()),
+            ) && name != HOST
+                && name != CONTENT_LENGTH
+            {
+                overrides.headers.append(name, value);
+            }
+        }
+        overrides.body = body.map(str::to_string);
+    }
+    Ok((format!("{} {}", record.method, record.url), overrides))
+}
 
 /// Parse request line to extract method, URL, and optional body
 pub fn parse_request_line(line: &str) -> (String, String, Option<String>) {
@@ -25,6 +247,31 @@ pub fn parse_request_line(line: &str) -> (String, String, Option<String>) {
     }
 }
 
+/// Authorization header for credentials embedded in an input URL (--url-auth)
+pub fn credentials_header(credentials: &UrlCredentials, mode: UrlAuth) -> Option<HeaderValue> {
+    let value = match mode {
+        UrlAuth::Basic => format!(
+            "Basic {}",
+            STANDARD.encode(format!(
+                "{}:{}",
+                credentials.username,
+                credentials.password.as_deref().unwrap_or_default()
+            ))
+        ),
+        UrlAuth::Bearer => format!(
+            "Bearer {}",
+            credentials
+                .password
+                .as_deref()
+                .unwrap_or(&credentials.username)
+        ),
+        UrlAuth::None => return None,
+    };
+    let mut value = HeaderValue::from_str(&value).ok()?;
+    value.set_sensitive(true);
+    Some(value)
+}
+
 /// Build HTTP request from method, URL, and optional body
 pub fn build_request(
     client: &Client,
@@ -39,7 +286,11 @@ pub fn build_request(
         "HEAD" => client.head(url),
         "PATCH" => client.patch(url),
         "OPTIONS" => client.request(reqwest::Method::OPTIONS, url),
-        _ => client.get(url),
+        // Other valid method names (e.g. PURGE) are sent as they are
+        _ => match reqwest::Method::from_bytes(method.as_bytes()) {
+            Ok(method) if method != reqwest::Method::GET => client.request(method, url),
+            _ => client.get(url),
+        },
     };
 
     if let Some(body_content) = body {
@@ -49,12 +300,36 @@ pub fn build_request(
     request_builder
 }
 
+/// Whether the method is allowed by --safe-methods-only
+pub fn is_safe_method(method: &str) -> bool {
+    SAFE_METHODS.contains(&method)
+}
+
+/// Send a request built with `build_request`
+///
+/// Requests with a method refused by --safe-methods-only are not sent. The request goes through the
+/// exact client if one is given, or with the NTLM handshake if --auth-ntlm is set.
+pub async fn send_request(
+    cli: &Cli,
+    exact: Option<&ExactClient>,
+    request: RequestBuilder,
+) -> Result<Response, SendError> {
+    let (client, request) = request.build_split();
+    let request = request?;
+    if cli.safe_methods_only && !is_safe_method(request.method().as_str()) {
+        return Err(SendError::UnsafeMethod(request.method().to_string()));
+    }
+    match (exact, &cli.auth_ntlm) {
+        (Some(exact), _) => Ok(exact.send(request).await?),
+        (None, Some(credentials)) => {
+            Ok(send_with_ntlm(RequestBuilder::from_parts(client, request), credentials).await?)
+        }
+        (None, None) => Ok(client.execute(request).await?),
+    }
+}
+
 /// Format raw HTTP request for display
-pub fn format_raw_request(
-    req: &reqwest::Request,
-    http2: bool,
-    custom_headers: Option<&[String]>,
-) -> String {
+pub fn format_raw_request(req: &reqwest::Request, http2: bool) -> String {
     let method = req.method();
     let url = req.url();
     let path_and_query = if let Some(query) = url.query() {
@@ -70,16 +345,8 @@ pub fn format_raw_request(
     let mut raw_req = format!("{} {} {}\n", method, path_and_query, version);
     raw_req.push_str(&format!("Host: {}\n", url.host_str().unwrap_or("")));
 
-    // Create a temporary HeaderMap for display to handle overrides correctly
-    let mut display_headers = req.headers().clone();
-
-    // Add/overwrite with custom headers if provided
-    if let Some(headers) = custom_headers {
-        display_headers.extend(parse_headers(headers));
-    }
-
-    // Print the combined headers
-    for (name, value) in &display_headers {
+    // Headers are set per request, so the request carries everything that is sent
+    for (name, value) in req.headers() {
         raw_req.push_str(&format!(
             "{}: {}\n",
             name,
@@ -96,9 +363,20 @@ pub fn format_raw_request(
     raw_req
 }
 
+/// Format a request for --dry-run: the target URL followed by the raw request
+pub fn format_dry_run(req: &reqwest::Request, http2: bool) -> String {
+    format!(
+        "[Dry Run] {} {}\n{}\n",
+        req.method(),
+        req.url(),
+        format_raw_request(req, http2).trim_end()
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use clap::Parser;
 
     #[test]
     fn test_parse_request_line_get() {
@@ -124,6 +402,225 @@ mod tests {
         assert_eq!(body, None);
     }
 
+    #[test]
+    fn test_parse_request_overrides() {
+        let (line, overrides) =
+            parse_request_overrides("GET https://a.com [no-redirect,http2,timeout=3,retries=2]");
+        assert_eq!(line, "GET https://a.com");
+        assert_eq!(overrides.retries, Some(2));
+        assert_eq!(overrides.follow_redirect, Some(false));
+        assert_eq!(overrides.http2, Some(true));
+        assert_eq!(overrides.timeout, Some(Duration::from_secs(3)));
+
+        let (line, overrides) =
+            parse_request_overrides("https://a.com [proxy=http://127.0.0.1:8080]");
+        assert_eq!(line, "https://a.com");
+        assert_eq!(
+            overrides.proxy,
+            Some(Some("http://127.0.0.1:8080".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_jsonl_request() {
+        let (line, overrides) = parse_jsonl_request(
+            r#"{"url":"https://a.com/slow","method":"post","body":"a=1","timeout_ms":30000,"retries":3,"follow_redirect":false,"status_code":200}"#,
+        )
+        .unwrap();
+        assert_eq!(line, "https://a.com/slow");
+        assert_eq!(overrides.method.as_deref(), Some("POST"));
+        assert_eq!(overrides.body.as_deref(), Some("a=1"));
+        assert_eq!(overrides.timeout, Some(Duration::from_secs(30)));
+        assert_eq!(overrides.retries, Some(3));
+        assert_eq!(overrides.follow_redirect, Some(false));
+
+        let (line, overrides) = parse_jsonl_request(r#"{"url":"https://a.com"}"#).unwrap();
+        assert_eq!(line, "https://a.com");
+        assert_eq!(overrides, RequestOverrides::default());
+
+        let (_, overrides) =
+            parse_jsonl_request(r#"{"url":"https://a.com","tags":["batch=login"]}"#).unwrap();
+        assert_eq!(overrides.tags, vec!["batch=login"]);
+
+        assert!(parse_jsonl_request("https://a.com").is_err());
+    }
+
+    #[test]
+    fn test_parse_jsonl_request_body_and_custom_method() {
+        let (line, overrides) = parse_jsonl_request(
+            r#"{"url":"https://a.com/api","method":"put","body":"{\n  \"a\":  1\n}\nline2  x"}"#,
+        )
+        .unwrap();
+        assert_eq!(line, "https://a.com/api");
+        assert_eq!(
+            overrides.body.as_deref(),
+            Some("{\n  \"a\":  1\n}\nline2  x")
+        );
+
+        let (line, overrides) =
+            parse_jsonl_request(r#"{"url":"https://cdn.a.com/app.js","method":"PURGE"}"#).unwrap();
+        assert_eq!(line, "https://cdn.a.com/app.js");
+        assert_eq!(overrides.method.as_deref(), Some("PURGE"));
+
+        let client = Client::new();
+        let req = build_request(&client, "PURGE", &line, &None)
+            .build()
+            .unwrap();
+        assert_eq!(req.method().as_str(), "PURGE");
+        assert_eq!(req.url().as_str(), "https://cdn.a.com/app.js");
+    }
+
+    #[test]
+    fn test_parse_reqs_record() {
+        let record = serde_json::json!({
+            "method": "POST",
+            "url": "https://a.com/api?x=1",
+            "status_code": 200,
+            "raw_request": "POST /api?x=1 HTTP/2.0\nHost: a.com\nx-token: abc\ncontent-length: 13\n\n{\"a\":  \"b\"}\n",
+        });
+        let (line, overrides) = parse_reqs_record(&record.to_string()).unwrap();
+        assert_eq!(line, "POST https://a.com/api?x=1");
+        assert_eq!(overrides.http2, Some(true));
+        assert_eq!(overrides.headers.len(), 1);
+        assert_eq!(overrides.headers["x-token"], "abc");
+        assert_eq!(overrides.body.as_deref(), Some("{\"a\":  \"b\"}\n"));
+
+        // Without --include-req only the method and URL are replayed
+        let (line, overrides) =
+            parse_reqs_record(r#"{"method":"GET","url":"https://a.com"}"#).unwrap();
+        assert_eq!(line, "GET https://a.com");
+        assert_eq!(overrides, RequestOverrides::default());
+
+        assert!(parse_reqs_record(r#"{"url":"https://a.com"}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_request_overrides_expectations() {
+        let (line, overrides) =
+            parse_request_overrides("https://a.com/health [expect-status=200|204,expect-body=ok]");
+        assert_eq!(line, "https://a.com/health");
+        assert_eq!(overrides.expect_status, vec![200, 204]);
+        assert_eq!(overrides.expect_body, Some("ok".to_string()));
+
+        let (line, _) = parse_request_overrides("https://a.com [expect-status=abc]");
+        assert_eq!(line, "https://a.com [expect-status=abc]");
+    }
+
+    #[test]
+    fn test_parse_request_overrides_keeps_json_body() {
+        let (line, overrides) = parse_request_overrides("POST https://a.com [1,2,3]");
+        assert_eq!(line, "POST https://a.com [1,2,3]");
+        assert_eq!(overrides, RequestOverrides::default());
+
+        let (line, _) = parse_request_overrides("https://a.com");
+        assert_eq!(line, "https://a.com");
+    }
+
+    #[test]
+    fn test_split_tags() {
+        assert_eq!(
+            split_tags("https://a.com/login #batch=login-pages #smoke "),
+            (
+                "https://a.com/login".to_string(),
+                vec!["batch=login-pages".to_string(), "smoke".to_string()]
+            )
+        );
+        assert_eq!(
+            split_tags("POST https://a.com a=1 [timeout=3] #case-7"),
+            (
+                "POST https://a.com a=1 [timeout=3]".to_string(),
+                vec!["case-7".to_string()]
+            )
+        );
+        for line in ["https://a.com/#section", "https://a.com #", "#batch"] {
+            assert_eq!(split_tags(line), (line.to_string(), Vec::new()));
+        }
+    }
+
+    #[test]
+    fn test_split_host_override() {
+        assert_eq!(
+            split_host_override("https://1.2.3.4/path @host:app.example.com"),
+            (
+                "https://1.2.3.4/path".to_string(),
+                Some("app.example.com".to_string())
+            )
+        );
+        assert_eq!(
+            split_host_override("POST https://1.2.3.4/login user=a @host:app.example.com "),
+            (
+                "POST https://1.2.3.4/login user=a".to_string(),
+                Some("app.example.com".to_string())
+            )
+        );
+        for line in [
+            "https://a.com",
+            "https://a.com @host:",
+            "POST https://a.com @host:x y",
+        ] {
+            assert_eq!(split_host_override(line), (line.to_string(), None));
+        }
+    }
+
+    #[test]
+    fn test_request_overrides_apply() {
+        let defaults = ClientOptions {
+            proxy: Some("http://127.0.0.1:8080".to_string()),
+            http2: false,
+            follow_redirect: true,
+            resolve: None,
+            sni: true,
+        };
+        let (_, overrides) = parse_request_overrides("https://a.com [http2,no-proxy]");
+        let options = overrides.apply(&defaults);
+        assert_eq!(options.proxy, None);
+        assert!(options.http2);
+        assert!(options.follow_redirect);
+        assert!(options.sni);
+
+        let (_, overrides) = parse_request_overrides("https://a.com [no-sni]");
+        assert_eq!(overrides.sni, Some(None));
+        assert!(!overrides.apply(&defaults).sni);
+
+        let (_, overrides) = parse_request_overrides("https://a.com [sni=cdn.example.com]");
+        assert_eq!(overrides.sni, Some(Some("cdn.example.com".to_string())));
+        assert!(overrides.apply(&defaults).sni);
+    }
+
+    #[test]
+    fn test_format_raw_request_duplicate_headers() {
+        let client = Client::new();
+        let headers =
+            crate::http::parse_headers(&["Cookie: a=1".to_string(), "Cookie: b=2".to_string()]);
+        let req = build_request(&client, "GET", "https://example.com/path?q=1", &None)
+            .headers(headers)
+            .build()
+            .unwrap();
+
+        let raw = format_raw_request(&req, false);
+        assert!(raw.starts_with("GET /path?q=1 HTTP/1.1\nHost: example.com\n"));
+        assert!(raw.contains("cookie: a=1\ncookie: b=2\n"));
+    }
+
+    #[test]
+    fn test_format_dry_run() {
+        let client = Client::new();
+        let req = build_request(
+            &client,
+            "POST",
+            "https://example.com/api",
+            &Some("a=1".to_string()),
+        )
+        .header("X-Test", "1")
+        .build()
+        .unwrap();
+
+        assert_eq!(
+            format_dry_run(&req, false),
+            "[Dry Run] POST https://example.com/api\nPOST /api HTTP/1.1\nHost: example.com\nx-test: 1\n\na=1\n"
+        );
+    }
+
     #[test]
     fn test_build_request_get() {
         let client = Client::new();
@@ -213,12 +710,65 @@ mod tests {
     #[test]
     fn test_build_request_default_to_get() {
         let client = Client::new();
-        let builder = build_request(&client, "UNKNOWN", "https://example.com", &None);
+        let builder = build_request(&client, "NOT A METHOD", "https://example.com", &None);
         let req = builder.build().unwrap();
 
-        // The default branch in build_request is _ => client.get(url)
+        // Names that are not valid methods fall back to GET
         assert_eq!(req.method(), reqwest::Method::GET);
         assert_eq!(req.url().as_str(), "https://example.com/");
         assert!(req.body().is_none());
     }
+
+    #[test]
+    fn test_is_safe_method() {
+        assert!(is_safe_method("GET"));
+        assert!(is_safe_method("HEAD"));
+        assert!(is_safe_method("OPTIONS"));
+        assert!(!is_safe_method("POST"));
+        assert!(!is_safe_method("DELETE"));
+        assert!(!is_safe_method("UNKNOWN"));
+    }
+
+    #[tokio::test]
+    async fn test_send_request_safe_methods_only() {
+        let cli = Cli::parse_from(["reqs", "--safe-methods-only"]);
+        let client = Client::new();
+        // Nothing listens on port 1, so only a request that was sent fails to connect
+        let url = "http://127.0.0.1:1/";
+
+        let request = build_request(&client, "POST", url, &Some("a=1".to_string()));
+        let err = send_request(&cli, None, request).await.unwrap_err();
+        assert!(matches!(err, SendError::UnsafeMethod(_)));
+        assert_eq!(err.kind(), crate::http::ErrorKind::UnsafeMethod);
+
+        let request = build_request(&client, "GET", url, &None);
+        let err = send_request(&cli, None, request).await.unwrap_err();
+        assert!(err.is_connect());
+    }
+
+    #[test]
+    fn test_credentials_header() {
+        let credentials = UrlCredentials {
+            username: "admin".to_string(),
+            password: Some("secret".to_string()),
+        };
+        assert_eq!(
+            credentials_header(&credentials, UrlAuth::Basic).unwrap(),
+            "Basic YWRtaW46c2VjcmV0"
+        );
+        assert_eq!(
+            credentials_header(&credentials, UrlAuth::Bearer).unwrap(),
+            "Bearer secret"
+        );
+        assert!(credentials_header(&credentials, UrlAuth::None).is_none());
+
+        let token = UrlCredentials {
+            username: "tok123".to_string(),
+            password: None,
+        };
+        assert_eq!(
+            credentials_header(&token, UrlAuth::Bearer).unwrap(),
+            "Bearer tok123"
+        );
+    }
 }
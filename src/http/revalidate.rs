@@ -0,0 +1,105 @@
+use reqwest::header::{
+    ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Cache validators of the last response for a request
+#[derive(Debug, Clone, Default)]
+struct Validator {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+}
+
+/// Per-request ETag/Last-Modified store used by --watch
+///
+/// Later rounds send conditional requests, so unchanged resources answer
+/// with a bodiless 304 instead of the full response.
+#[derive(Default)]
+pub struct Validators {
+    entries: Mutex<HashMap<String, Validator>>,
+}
+
+impl Validators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Conditional request headers for `key` (empty on the first round)
+    pub fn conditional_headers(&self, key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let Some(validator) = self
+            .entries
+            .lock()
+            .ok()
+            .and_then(|entries| entries.get(key).cloned())
+        else {
+            return headers;
+        };
+        if let Some(etag) = validator.etag {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = validator.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+        headers
+    }
+
+    /// Remember the validators of a full response for `key`
+    pub fn update(&self, key: &str, headers: &HeaderMap) {
+        let validator = Validator {
+            etag: headers.get(ETAG).cloned(),
+            last_modified: headers.get(LAST_MODIFIED).cloned(),
+        };
+        if let Ok(mut entries) = self.entries.lock() {
+            if validator.etag.is_none() && validator.last_modified.is_none() {
+                entries.remove(key);
+            } else {
+                entries.insert(key.to_string(), validator);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validators() {
+        let validators = Validators::new();
+        assert!(
+            validators
+                .conditional_headers("GET https://a.com/")
+                .is_empty()
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, "\"abc\"".parse().unwrap());
+        headers.insert(
+            LAST_MODIFIED,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        validators.update("GET https://a.com/", &headers);
+
+        let conditional = validators.conditional_headers("GET https://a.com/");
+        assert_eq!(conditional.get(IF_NONE_MATCH).unwrap(), "\"abc\"");
+        assert_eq!(
+            conditional.get(IF_MODIFIED_SINCE).unwrap(),
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+        assert!(
+            validators
+                .conditional_headers("GET https://b.com/")
+                .is_empty()
+        );
+
+        // A response without validators clears the stored ones
+        validators.update("GET https://a.com/", &HeaderMap::new());
+        assert!(
+            validators
+                .conditional_headers("GET https://a.com/")
+                .is_empty()
+        );
+    }
+}
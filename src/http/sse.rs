@@ -0,0 +1,124 @@
+use reqwest::Response;
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+use crate::constants::SSE_SAMPLE_EVENTS;
+use crate::http::Bandwidth;
+
+/// Events read from a Server-Sent Events stream (--sse)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseInfo {
+    pub events: usize,
+    /// Data of the first events
+    pub samples: Vec<String>,
+}
+
+impl SseInfo {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "events": self.events,
+            "samples": self.samples,
+        })
+    }
+}
+
+/// Incremental parser counting the events of an SSE stream
+#[derive(Debug, Default)]
+struct SseParser {
+    info: SseInfo,
+    pending: String,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Feed a chunk of the stream; incomplete lines are kept for the next chunk
+    fn feed(&mut self, chunk: &str) {
+        self.pending.push_str(chunk);
+        while let Some(end) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=end).collect();
+            self.line(line.trim_end_matches(['\n', '\r']));
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        if line.is_empty() {
+            // A blank line dispatches the event, if it carried any data
+            if !self.data.is_empty() {
+                self.info.events += 1;
+                if self.info.samples.len() < SSE_SAMPLE_EVENTS {
+                    self.info.samples.push(self.data.join("\n"));
+                }
+                self.data.clear();
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            self.data
+                .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+    }
+}
+
+/// Whether the response is an event stream
+pub fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim_start().starts_with("text/event-stream"))
+}
+
+/// Read an event stream until `max_events` events or `duration` have passed
+///
+/// Returns the events and the raw text that was read.
+pub async fn read_event_stream(
+    mut resp: Response,
+    max_events: usize,
+    duration: Duration,
+    bandwidth: Option<&Bandwidth>,
+) -> (SseInfo, String) {
+    let deadline = tokio::time::Instant::now() + duration;
+    let mut parser = SseParser::default();
+    let mut raw = String::new();
+    while parser.info.events < max_events {
+        match tokio::time::timeout_at(deadline, resp.chunk()).await {
+            Ok(Ok(Some(chunk))) => {
+                if let Some(bandwidth) = bandwidth {
+                    bandwidth.consume(chunk.len()).await;
+                }
+                let text = String::from_utf8_lossy(&chunk);
+                raw.push_str(&text);
+                parser.feed(&text);
+            }
+            // Deadline reached, stream ended or failed
+            _ => break,
+        }
+    }
+    (parser.info, raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser() {
+        let mut parser = SseParser::default();
+        parser.feed(": comment\nevent: tick\ndata: one\n\nda");
+        parser.feed(
+            "ta: two\r\ndata: lines\r\n\r\nid: 3\n\ndata:three\n\ndata: four\n\ndata: five\n",
+        );
+
+        assert_eq!(parser.info.events, 4);
+        assert_eq!(parser.info.samples, vec!["one", "two\nlines", "three"]);
+    }
+
+    #[test]
+    fn test_is_event_stream() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_event_stream(&headers));
+        headers.insert(
+            CONTENT_TYPE,
+            "text/event-stream; charset=utf-8".parse().unwrap(),
+        );
+        assert!(is_event_stream(&headers));
+    }
+}
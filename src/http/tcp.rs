@@ -0,0 +1,118 @@
+use reqwest::Url;
+use serde_json::{Value, json};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpStream, lookup_host};
+
+/// State of a port after a TCP connect attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortState {
+    /// The connection was accepted
+    Open,
+    /// The connection was refused
+    Closed,
+    /// No answer before the timeout, or the host is unreachable
+    Filtered,
+    /// The host name did not resolve
+    Unresolved,
+}
+
+impl PortState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PortState::Open => "open",
+            PortState::Closed => "closed",
+            PortState::Filtered => "filtered",
+            PortState::Unresolved => "unresolved",
+        }
+    }
+}
+
+/// Result of a TCP connect probe (--tcp-probe)
+#[derive(Debug, Clone)]
+pub struct TcpProbe {
+    pub host: String,
+    pub port: u16,
+    pub ip_addr: Option<String>,
+    pub state: PortState,
+    pub elapsed: Duration,
+}
+
+impl TcpProbe {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "host": self.host,
+            "port": self.port,
+            "state": self.state.as_str(),
+            "connect_time_ms": self.elapsed.as_millis(),
+        })
+    }
+}
+
+/// Try a TCP connection to the host and port of `url`
+///
+/// Returns None if the URL has no host.
+pub async fn probe_tcp(url: &str, timeout: Duration) -> Option<TcpProbe> {
+    let parsed = Url::parse(url.trim()).ok()?;
+    let host = parsed.host_str()?.to_string();
+    let port = parsed.port_or_known_default()?;
+    let start = Instant::now();
+
+    // IPv6 hosts keep their brackets in URLs
+    let name = host.trim_start_matches('[').trim_end_matches(']');
+    let addr = match tokio::time::timeout(timeout, lookup_host((name, port))).await {
+        Ok(Ok(mut addrs)) => addrs.next(),
+        _ => None,
+    };
+    let Some(addr) = addr else {
+        return Some(TcpProbe {
+            host,
+            port,
+            ip_addr: None,
+            state: PortState::Unresolved,
+            elapsed: start.elapsed(),
+        });
+    };
+
+    let remaining = timeout.saturating_sub(start.elapsed());
+    let state = match tokio::time::timeout(remaining, TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => PortState::Open,
+        Ok(Err(e)) if e.kind() == ErrorKind::ConnectionRefused => PortState::Closed,
+        Ok(Err(_)) | Err(_) => PortState::Filtered,
+    };
+    Some(TcpProbe {
+        host,
+        port,
+        ip_addr: Some(addr.ip().to_string()),
+        state,
+        elapsed: start.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_probe_tcp_open_and_closed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let timeout = Duration::from_secs(2);
+
+        let probe = probe_tcp(&format!("http://127.0.0.1:{}/", port), timeout)
+            .await
+            .unwrap();
+        assert_eq!(probe.state, PortState::Open);
+        assert_eq!(probe.ip_addr.as_deref(), Some("127.0.0.1"));
+
+        drop(listener);
+        let probe = probe_tcp(&format!("http://127.0.0.1:{}/", port), timeout)
+            .await
+            .unwrap();
+        assert_eq!(probe.state, PortState::Closed);
+        assert_eq!(probe.to_json()["state"], "closed");
+
+        assert!(probe_tcp("not a url", timeout).await.is_none());
+    }
+}
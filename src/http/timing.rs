@@ -0,0 +1,380 @@
+use anyhow::Result;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::{ClientSessionMemoryCache, ClientSessionStore, Resumption};
+use rustls::client::{Tls12ClientSessionValue, Tls13ClientSessionValue};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, NamedGroup, SignatureScheme};
+use serde_json::{Value, json};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::constants::TLS_SESSION_CACHE_SIZE;
+use crate::http::dns::DnsResolver;
+
+tokio::task_local! {
+    /// Timing marks of the request currently being sent on this task
+    static MARKS: Arc<Mutex<TimingMarks>>;
+}
+
+/// Timestamps captured while a connection is being established
+#[derive(Debug, Default)]
+struct TimingMarks {
+    dns_start: Option<Instant>,
+    dns_end: Option<Instant>,
+    connect_start: Option<Instant>,
+    tls_start: Option<Instant>,
+    connect_end: Option<Instant>,
+}
+
+/// Per-request timing breakdown
+///
+/// Connection phases are `None` when they did not happen for this request,
+/// e.g. when a pooled connection was reused or the URL is plain HTTP.
+#[derive(Debug, Clone, Default)]
+pub struct TimingDetail {
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub tls: Option<Duration>,
+    pub ttfb: Duration,
+    pub download: Duration,
+}
+
+impl TimingDetail {
+    fn from_marks(marks: &TimingMarks, ttfb: Duration) -> Self {
+        let between = |start: Option<Instant>, end: Option<Instant>| match (start, end) {
+            (Some(start), Some(end)) => Some(end.saturating_duration_since(start)),
+            _ => None,
+        };
+
+        TimingDetail {
+            dns: between(marks.dns_start, marks.dns_end),
+            connect: between(
+                marks.dns_end.or(marks.connect_start),
+                marks.tls_start.or(marks.connect_end),
+            ),
+            tls: between(marks.tls_start, marks.connect_end),
+            ttfb,
+            download: Duration::ZERO,
+        }
+    }
+
+    /// Timing breakdown as a JSON object with millisecond values
+    pub fn to_json(&self) -> Value {
+        json!({
+            "dns_ms": self.dns.map(duration_ms),
+            "connect_ms": self.connect.map(duration_ms),
+            "tls_ms": self.tls.map(duration_ms),
+            "ttfb_ms": duration_ms(self.ttfb),
+            "download_ms": duration_ms(self.download),
+        })
+    }
+}
+
+/// Convert a duration to fractional milliseconds (microsecond precision)
+pub fn duration_ms(duration: Duration) -> f64 {
+    (duration.as_micros() as f64) / 1000.0
+}
+
+/// Run a request future while recording its connection-level timings
+///
+/// The returned TTFB covers everything up to the response headers; the caller
+/// is responsible for filling in the download time once the body is read.
+pub async fn with_timing<F: Future>(future: F) -> (F::Output, TimingDetail) {
+    let marks = Arc::new(Mutex::new(TimingMarks::default()));
+    let start = Instant::now();
+    let output = MARKS.scope(marks.clone(), future).await;
+    let ttfb = start.elapsed();
+
+    let detail = match marks.lock() {
+        Ok(marks) => TimingDetail::from_marks(&marks, ttfb),
+        Err(_) => TimingDetail {
+            ttfb,
+            ..Default::default()
+        },
+    };
+    (output, detail)
+}
+
+/// Update the marks of the current task, if any are being recorded
+fn record(marks: &Option<Arc<Mutex<TimingMarks>>>, update: impl FnOnce(&mut TimingMarks)) {
+    if let Some(marks) = marks
+        && let Ok(mut marks) = marks.lock()
+    {
+        update(&mut marks);
+    }
+}
+
+fn current_marks() -> Option<Arc<Mutex<TimingMarks>>> {
+    MARKS.try_with(Arc::clone).ok()
+}
+
+/// DNS resolver that records lookup duration
+#[derive(Clone)]
+pub struct TimingResolver {
+    pub resolver: DnsResolver,
+}
+
+impl TimingResolver {
+    /// Look up a host with port 0, recording the lookup on the current task
+    pub fn lookup(&self, host: &str) -> impl Future<Output = io::Result<Vec<SocketAddr>>> + use<> {
+        let marks = current_marks();
+        let resolver = self.resolver.clone();
+        let host = host.to_string();
+        async move {
+            let start = Instant::now();
+            let addrs = resolver.lookup(&host, 0).await?;
+            record(&marks, |m| {
+                m.dns_start = Some(start);
+                m.dns_end = Some(Instant::now());
+            });
+            Ok(addrs)
+        }
+    }
+}
+
+impl Resolve for TimingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let lookup = self.lookup(name.as_str());
+        Box::pin(async move { Ok(Box::new(lookup.await?.into_iter()) as Addrs) })
+    }
+}
+
+/// Connector layer that records when connection establishment starts and ends
+#[derive(Debug, Clone, Default)]
+pub struct TimingLayer;
+
+impl<S> Layer<S> for TimingLayer {
+    type Service = TimingService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimingService { inner }
+    }
+}
+
+/// Connector service produced by [`TimingLayer`]
+#[derive(Debug, Clone)]
+pub struct TimingService<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for TimingService<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let marks = current_marks();
+        let start = Instant::now();
+        let connecting = self.inner.call(request);
+        Box::pin(async move {
+            let result = connecting.await;
+            record(&marks, |m| {
+                m.connect_start = Some(start);
+                m.connect_end = Some(Instant::now());
+            });
+            result
+        })
+    }
+}
+
+/// Session store wrapper that marks the start of the TLS handshake
+///
+/// rustls consults the session store while building the ClientHello, which is
+/// right after the TCP connection has been established.
+#[derive(Debug)]
+struct TimingSessionStore {
+    inner: ClientSessionMemoryCache,
+}
+
+impl TimingSessionStore {
+    fn mark_tls_start(&self) {
+        record(&current_marks(), |m| {
+            m.tls_start.get_or_insert_with(Instant::now);
+        });
+    }
+}
+
+impl ClientSessionStore for TimingSessionStore {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: NamedGroup) {
+        self.inner.set_kx_hint(server_name, group);
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        self.mark_tls_start();
+        self.inner.kx_hint(server_name)
+    }
+
+    fn set_tls12_session(&self, server_name: ServerName<'static>, value: Tls12ClientSessionValue) {
+        self.inner.set_tls12_session(server_name, value);
+    }
+
+    fn tls12_session(&self, server_name: &ServerName<'_>) -> Option<Tls12ClientSessionValue> {
+        self.mark_tls_start();
+        self.inner.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
+        self.inner.remove_tls12_session(server_name);
+    }
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: ServerName<'static>,
+        value: Tls13ClientSessionValue,
+    ) {
+        self.inner.insert_tls13_ticket(server_name, value);
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &ServerName<'static>,
+    ) -> Option<Tls13ClientSessionValue> {
+        self.mark_tls_start();
+        self.inner.take_tls13_ticket(server_name)
+    }
+}
+
+/// Certificate verifier that accepts any certificate (used when --verify-ssl is off)
+#[derive(Debug)]
+struct NoVerifier(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Build a rustls configuration that verifies certificates only if `verify_ssl` is set
+pub fn build_tls_config(verify_ssl: bool) -> Result<ClientConfig> {
+    let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous();
+
+    let config = if verify_ssl {
+        let verifier = rustls_platform_verifier::Verifier::new(provider)?;
+        builder
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth()
+    } else {
+        builder
+            .with_custom_certificate_verifier(Arc::new(NoVerifier(provider)))
+            .with_no_client_auth()
+    };
+    Ok(config)
+}
+
+/// Build a TLS configuration for an HTTP client
+///
+/// Mirrors the settings reqwest would apply itself (certificate verification and ALPN).
+pub fn build_client_tls_config(verify_ssl: bool, http2: bool) -> Result<ClientConfig> {
+    let mut config = build_tls_config(verify_ssl)?;
+    config.alpn_protocols = if http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    Ok(config)
+}
+
+/// Build the TLS configuration used in timing mode
+///
+/// The client configuration plus the session store hook that marks the TLS handshake start.
+pub fn build_timing_tls_config(verify_ssl: bool, http2: bool) -> Result<ClientConfig> {
+    let mut config = build_client_tls_config(verify_ssl, http2)?;
+    config.resumption = Resumption::store(Arc::new(TimingSessionStore {
+        inner: ClientSessionMemoryCache::new(TLS_SESSION_CACHE_SIZE),
+    }));
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_detail_from_marks() {
+        let base = Instant::now();
+        let at = |ms: u64| Some(base + Duration::from_millis(ms));
+        let marks = TimingMarks {
+            connect_start: at(0),
+            dns_start: at(0),
+            dns_end: at(5),
+            tls_start: at(15),
+            connect_end: at(40),
+        };
+        let detail = TimingDetail::from_marks(&marks, Duration::from_millis(60));
+        assert_eq!(detail.dns, Some(Duration::from_millis(5)));
+        assert_eq!(detail.connect, Some(Duration::from_millis(10)));
+        assert_eq!(detail.tls, Some(Duration::from_millis(25)));
+        assert_eq!(detail.ttfb, Duration::from_millis(60));
+    }
+
+    #[test]
+    fn test_timing_detail_reused_connection() {
+        let detail = TimingDetail::from_marks(&TimingMarks::default(), Duration::from_millis(3));
+        assert_eq!(detail.dns, None);
+        assert_eq!(detail.connect, None);
+        assert_eq!(detail.tls, None);
+
+        let json = detail.to_json();
+        assert!(json["dns_ms"].is_null());
+        assert_eq!(json["ttfb_ms"], 3.0);
+    }
+
+    #[test]
+    fn test_build_timing_tls_config() {
+        let config = build_timing_tls_config(false, true).unwrap();
+        assert_eq!(config.alpn_protocols[0], b"h2".to_vec());
+
+        let config = build_timing_tls_config(false, false).unwrap();
+        assert_eq!(config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+    }
+}
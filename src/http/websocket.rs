@@ -0,0 +1,192 @@
+use anyhow::{Result, bail};
+use futures::{SinkExt, StreamExt};
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{Connector, MaybeTlsStream, connect_async_tls_with_config};
+
+use crate::http::timing::build_tls_config;
+
+/// Result of a WebSocket upgrade attempt (--ws-probe)
+#[derive(Debug, Clone, Default)]
+pub struct WebSocketInfo {
+    pub upgraded: bool,
+    pub subprotocol: Option<String>,
+    /// First message received after sending --ws-message
+    pub first_message: Option<String>,
+}
+
+impl WebSocketInfo {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "upgraded": self.upgraded,
+            "subprotocol": self.subprotocol,
+            "first_message": self.first_message,
+        })
+    }
+}
+
+/// Options of a WebSocket probe
+pub struct WsProbeOptions<'a> {
+    pub headers: &'a HeaderMap,
+    pub protocols: &'a [String],
+    pub message: Option<&'a str>,
+    pub timeout: Duration,
+    pub verify_ssl: bool,
+}
+
+/// Handshake response of a WebSocket probe
+pub struct WsProbeResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub ip_addr: String,
+    /// Body of a rejected handshake
+    pub body: Option<String>,
+    pub info: WebSocketInfo,
+}
+
+/// Whether the URL uses the ws or wss scheme
+pub fn is_websocket_url(url: &str) -> bool {
+    let url = url.trim();
+    url.starts_with("ws://") || url.starts_with("wss://")
+}
+
+/// Attempt a WebSocket upgrade on `url`
+///
+/// A rejected handshake is still a response (e.g. 403 or 404) with `upgraded`
+/// false; only connection-level failures are errors.
+pub async fn probe_websocket(url: &str, options: &WsProbeOptions<'_>) -> Result<WsProbeResponse> {
+    let mut request = url.into_client_request()?;
+    for (name, value) in options.headers {
+        request.headers_mut().append(name, value.clone());
+    }
+    if !options.protocols.is_empty() {
+        request.headers_mut().insert(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_str(&options.protocols.join(", "))?,
+        );
+    }
+
+    let connector = if url.trim().starts_with("wss://") {
+        let mut config = build_tls_config(options.verify_ssl)?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Connector::Rustls(Arc::new(config))
+    } else {
+        Connector::Plain
+    };
+
+    let connect = connect_async_tls_with_config(request, None, false, Some(connector));
+    let (mut stream, response) = match tokio::time::timeout(options.timeout, connect).await {
+        Err(_) => bail!("WebSocket handshake timed out"),
+        Ok(Err(WsError::Http(response))) => {
+            return Ok(WsProbeResponse {
+                status: response.status(),
+                headers: response.headers().clone(),
+                ip_addr: String::new(),
+                body: response
+                    .body()
+                    .as_ref()
+                    .map(|body| String::from_utf8_lossy(body).into_owned()),
+                info: WebSocketInfo::default(),
+            });
+        }
+        Ok(Err(e)) => return Err(e.into()),
+        Ok(Ok(connection)) => connection,
+    };
+
+    let ip_addr = match stream.get_ref() {
+        MaybeTlsStream::Plain(tcp) => tcp.peer_addr().ok(),
+        MaybeTlsStream::Rustls(tls) => tls.get_ref().0.peer_addr().ok(),
+        _ => None,
+    }
+    .map(|addr| addr.ip().to_string())
+    .unwrap_or_default();
+
+    let mut info = WebSocketInfo {
+        upgraded: true,
+        subprotocol: response
+            .headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        first_message: None,
+    };
+
+    if let Some(message) = options.message {
+        stream.send(Message::text(message)).await?;
+        let first = tokio::time::timeout(options.timeout, async {
+            while let Some(message) = stream.next().await {
+                match message? {
+                    Message::Text(text) => return Ok(Some(text.to_string())),
+                    Message::Binary(data) => {
+                        return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+                    }
+                    Message::Close(_) => break,
+                    _ => {}
+                }
+            }
+            Ok::<_, WsError>(None)
+        })
+        .await;
+        info.first_message = match first {
+            Ok(message) => message?,
+            Err(_) => None,
+        };
+    }
+    let _ = stream.close(None).await;
+
+    Ok(WsProbeResponse {
+        status: response.status(),
+        headers: response.headers().clone(),
+        ip_addr,
+        body: None,
+        info,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_is_websocket_url() {
+        assert!(is_websocket_url("ws://example.com/socket"));
+        assert!(is_websocket_url(" wss://example.com"));
+        assert!(!is_websocket_url("https://example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_websocket_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            let _ = socket
+                .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 6\r\n\r\ndenied")
+                .await;
+        });
+
+        let headers = HeaderMap::new();
+        let options = WsProbeOptions {
+            headers: &headers,
+            protocols: &["chat".to_string()],
+            message: None,
+            timeout: Duration::from_secs(5),
+            verify_ssl: false,
+        };
+        let response = probe_websocket(&format!("ws://127.0.0.1:{}/", port), &options)
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+        assert!(!response.info.upgraded);
+        assert_eq!(response.body.as_deref(), Some("denied"));
+    }
+}
@@ -1,32 +1,65 @@
 use anyhow::Result;
-use clap::Parser;
+use std::sync::Arc;
 
+mod config;
 mod constants;
+mod control;
+mod expect;
 mod filter;
 mod http;
 mod mcp;
+mod outcome;
 mod output;
 mod processor;
+mod queue;
+mod score;
+mod state;
+mod suite;
+mod summary;
+mod tui;
 mod types;
 mod utils;
 
-use http::build_http_client;
+use config::load_cli;
+use http::ClientPool;
 use mcp::run_mcp_server;
 use processor::process_urls_from_stdin;
-use types::Cli;
+use suite::run_suite;
+use types::Command;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    // Parse CLI arguments merged with the config file
+    let mut cli = load_cli()?;
+    utils::init_logging(&cli);
+
+    // Resolve {{var}} placeholders in custom headers once for the whole run
+    let vars = utils::TemplateVars::from_cli(&cli)?;
+    cli.headers = cli.headers.iter().map(|h| vars.render(h)).collect();
+
+    // Multi-line --strf templates are kept in a file
+    if let Some(path) = &cli.strf_file {
+        cli.strf = Some(output::read_template_file(path)?);
+    }
 
     // If --mcp flag is set, run in MCP server mode
     if cli.mcp {
         return run_mcp_server(cli).await;
     }
 
-    // Build HTTP client from CLI configuration
-    let client = build_http_client(&cli)?;
+    // Run a YAML test suite instead of reading requests from stdin
+    if let Some(Command::Test { file, junit }) = &cli.command {
+        let exit_code = run_suite(&cli, file, junit.as_deref()).await?;
+        std::process::exit(exit_code);
+    }
+
+    // Build HTTP client pool from CLI configuration
+    let clients = Arc::new(ClientPool::new(&cli)?);
 
     // Process URLs from stdin
-    process_urls_from_stdin(cli, client).await
+    let exit_code = process_urls_from_stdin(cli, clients).await?;
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+    Ok(())
 }
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use regex::Regex;
-use reqwest::{Client, redirect::Policy};
+use reqwest::header::HeaderMap;
 use rust_mcp_sdk::mcp_server::{ServerHandler, ServerRuntime, server_runtime};
 use rust_mcp_sdk::schema::schema_utils::CallToolError;
 use rust_mcp_sdk::schema::{
@@ -12,13 +12,16 @@ use rust_mcp_sdk::schema::{
 use rust_mcp_sdk::{McpServer, StdioTransport, TransportOptions};
 use serde_json::json;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
-use crate::constants::DEFAULT_REDIRECT_LIMIT;
-use crate::filter::should_filter_response;
-use crate::http::{build_request, format_raw_request, parse_headers, parse_request_line};
-use crate::types::Cli;
-use crate::utils::normalize_url_scheme;
+use crate::filter::{BodyFilter, StatusMatcher, should_filter_response};
+use crate::http::{
+    ClientOptions, ClientPool, build_request, default_request_headers, format_raw_request,
+    parse_headers, parse_request_line, parse_request_overrides, removed_headers, request_headers,
+    send_request, uses_random_user_agent,
+};
+use crate::types::{Cli, FilterMode};
+use crate::utils::{is_in_scope, normalize_url_scheme};
 
 /// Run the MCP (Model Context Protocol) server
 pub async fn run_mcp_server(cli: Cli) -> Result<()> {
@@ -43,7 +46,10 @@ pub async fn run_mcp_server(cli: Cli) -> Result<()> {
         .map_err(|e| anyhow::anyhow!("Failed to create stdio transport: {}", e))?;
 
     // Create handler
-    let handler = ReqsServerHandler { cli: cli.clone() };
+    let handler = ReqsServerHandler {
+        clients: ClientPool::new(&cli)?,
+        cli,
+    };
 
     // Create and start server
     let server: Arc<ServerRuntime> =
@@ -59,6 +65,7 @@ pub async fn run_mcp_server(cli: Cli) -> Result<()> {
 /// Custom handler for the MCP server
 struct ReqsServerHandler {
     cli: Cli,
+    clients: ClientPool,
 }
 
 #[async_trait]
@@ -116,11 +123,15 @@ impl ServerHandler for ReqsServerHandler {
         // Extract parameters
         let params = extract_tool_parameters(args, &self.cli)?;
 
-        // Create HTTP client
-        let client = build_mcp_client(&self.cli, &params)?;
+        // Client options for this tool call
+        let options = ClientOptions {
+            follow_redirect: params.follow_redirect,
+            http2: params.http2,
+            ..self.clients.default_options().clone()
+        };
 
         // Process requests
-        let results = process_requests(requests, &client, &params).await;
+        let results = process_requests(requests, &self.clients, &options, &params, &self.cli).await;
 
         // Return results as tool response
         let result_text = results
@@ -137,14 +148,15 @@ impl ServerHandler for ReqsServerHandler {
 
 /// Tool parameters extracted from request arguments
 struct ToolParameters {
-    filter_status: Vec<u16>,
+    filter_status: Vec<StatusMatcher>,
     filter_string: Option<String>,
     filter_regex: Option<Regex>,
     include_req: bool,
     include_res: bool,
     follow_redirect: bool,
     http2: bool,
-    custom_headers: Vec<String>,
+    headers: HeaderMap,
+    random_user_agent: bool,
 }
 
 /// Extract tool parameters from arguments
@@ -152,12 +164,12 @@ fn extract_tool_parameters(
     args: &serde_json::Map<String, serde_json::Value>,
     cli: &Cli,
 ) -> std::result::Result<ToolParameters, CallToolError> {
-    let filter_status: Vec<u16> = args
+    let filter_status: Vec<StatusMatcher> = args
         .get("filter_status")
         .and_then(|v| v.as_array())
         .map(|arr| {
             arr.iter()
-                .filter_map(|v| v.as_u64().map(|n| n as u16))
+                .filter_map(|v| v.as_u64().map(|n| StatusMatcher::code(n as u16)))
                 .collect()
         })
         .unwrap_or_default();
@@ -202,6 +214,13 @@ fn extract_tool_parameters(
         })
         .unwrap_or_default();
 
+    // Custom headers from the tool call override the CLI defaults
+    let mut headers = default_request_headers(cli);
+    headers.extend(parse_headers(&custom_headers));
+    for name in removed_headers(&custom_headers) {
+        headers.remove(name);
+    }
+
     // Compile regex if provided
     let filter_regex = if let Some(regex_str) = &filter_regex_str {
         match Regex::new(regex_str) {
@@ -224,59 +243,18 @@ fn extract_tool_parameters(
         include_res,
         follow_redirect,
         http2,
-        custom_headers,
-    })
-}
-
-/// Build HTTP client for MCP requests
-fn build_mcp_client(
-    cli: &Cli,
-    params: &ToolParameters,
-) -> std::result::Result<Client, CallToolError> {
-    let redirect_policy = if params.follow_redirect {
-        Policy::limited(DEFAULT_REDIRECT_LIMIT)
-    } else {
-        Policy::none()
-    };
-
-    // First, apply headers from CLI (global default), then custom headers from tool call (overrides)
-    let mut default_headers = parse_headers(&cli.headers);
-    default_headers.extend(parse_headers(&params.custom_headers));
-
-    let mut client_builder = Client::builder()
-        .timeout(Duration::from_secs(cli.timeout))
-        .redirect(redirect_policy)
-        .default_headers(default_headers);
-
-    if !cli.verify_ssl {
-        client_builder = client_builder.danger_accept_invalid_certs(true);
-    }
-
-    if let Some(proxy_url) = &cli.proxy {
-        let proxy = reqwest::Proxy::all(proxy_url).map_err(|e| {
-            CallToolError::new(
-                RpcError::internal_error().with_message(format!("Failed to create proxy: {}", e)),
-            )
-        })?;
-        client_builder = client_builder.proxy(proxy);
-    }
-
-    if !params.http2 {
-        client_builder = client_builder.http1_only();
-    }
-
-    client_builder.build().map_err(|e| {
-        CallToolError::new(
-            RpcError::internal_error().with_message(format!("Failed to build HTTP client: {}", e)),
-        )
+        headers,
+        random_user_agent: uses_random_user_agent(cli),
     })
 }
 
 /// Process all requests and return results
 async fn process_requests(
     requests: &[serde_json::Value],
-    client: &Client,
+    clients: &ClientPool,
+    options: &ClientOptions,
     params: &ToolParameters,
+    cli: &Cli,
 ) -> Vec<serde_json::Value> {
     let mut results = Vec::new();
 
@@ -290,7 +268,8 @@ async fn process_requests(
             continue;
         }
 
-        let (method, url_str, body) = parse_request_line(req_str);
+        let (req_str, overrides) = parse_request_overrides(req_str);
+        let (method, url_str, body) = parse_request_line(&req_str);
 
         if url_str.is_empty() {
             continue;
@@ -298,20 +277,45 @@ async fn process_requests(
 
         let url_str = normalize_url_scheme(&url_str);
 
-        let request_builder = build_request(client, &method, &url_str, &body);
+        if !is_in_scope(&url_str, &cli.scope) {
+            results.push(json!({
+                "method": method,
+                "url": url_str,
+                "error": "URL is out of scope",
+            }));
+            continue;
+        }
+
+        let client = match clients.get(&overrides.apply(options)) {
+            Ok(client) => client,
+            Err(e) => {
+                results.push(json!({
+                    "method": method,
+                    "url": url_str,
+                    "error": format!("Failed to build HTTP client: {}", e),
+                }));
+                continue;
+            }
+        };
+
+        let mut request_builder = build_request(&client, &method, &url_str, &body)
+            .headers(request_headers(&params.headers, params.random_user_agent));
+        if let Some(timeout) = overrides.timeout {
+            request_builder = request_builder.timeout(timeout);
+        }
 
         // Capture raw request if needed
         let raw_request = if params.include_req {
             request_builder
                 .try_clone()
                 .and_then(|builder| builder.build().ok())
-                .map(|req| format_raw_request(&req, params.http2, None))
+                .map(|req| format_raw_request(&req, overrides.http2.unwrap_or(params.http2)))
         } else {
             None
         };
 
         let start_time = Instant::now();
-        match request_builder.send().await {
+        match send_request(cli, None, request_builder).await {
             Ok(resp) => {
                 let elapsed = start_time.elapsed();
                 let status = resp.status();
@@ -335,8 +339,13 @@ async fn process_requests(
                     status.as_u16(),
                     &body_text,
                     &params.filter_status,
-                    &params.filter_string,
-                    &params.filter_regex,
+                    &BodyFilter::new(
+                        params.filter_string.as_slice(),
+                        &[],
+                        params.filter_regex.clone().into_iter().collect(),
+                        FilterMode::And,
+                        false,
+                    ),
                 ) {
                     continue; // Skip this result
                 }
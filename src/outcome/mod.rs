@@ -0,0 +1,131 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::constants::{EXIT_ERRORS, EXIT_EXPECT_FAILED, EXIT_MATCH, EXIT_NO_MATCH};
+use crate::output::report::TestResult;
+use crate::types::Cli;
+
+/// Counters deciding the process exit code (--fail-on-* options)
+#[derive(Debug, Default)]
+pub struct RunOutcome {
+    matches: AtomicUsize,
+    errors: AtomicUsize,
+    passed: AtomicUsize,
+    failed: AtomicUsize,
+    unprocessed: AtomicUsize,
+    results: Mutex<Vec<TestResult>>,
+}
+
+impl RunOutcome {
+    /// Record a response that passed the filters and was written
+    pub fn record_match(&self) {
+        self.matches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a request that failed without a response after all retries
+    pub fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record an input line that was dropped or cut off at the --max-runtime deadline
+    pub fn record_unprocessed(&self) {
+        self.unprocessed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn unprocessed(&self) -> usize {
+        self.unprocessed.load(Ordering::Relaxed)
+    }
+
+    /// Record the verdict of a request with expectations
+    pub fn record_verdict(&self, passed: bool) {
+        let counter = if passed { &self.passed } else { &self.failed };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Keep the result of a request for --format junit/tap reports
+    pub fn record_result(&self, result: TestResult) {
+        if let Ok(mut results) = self.results.lock() {
+            results.push(result);
+        }
+    }
+
+    /// Results recorded with [`RunOutcome::record_result`]
+    pub fn results(&self) -> Vec<TestResult> {
+        self.results
+            .lock()
+            .map(|results| results.clone())
+            .unwrap_or_default()
+    }
+
+    /// Expectation totals, if any request had expectations
+    pub fn expectation_summary(&self) -> Option<String> {
+        let passed = self.passed.load(Ordering::Relaxed);
+        let failed = self.failed.load(Ordering::Relaxed);
+        (passed + failed > 0).then(|| format!("Expectations: {} passed, {} failed", passed, failed))
+    }
+
+    /// Exit code for the run, checked in the order errors, expectations, match, no match
+    pub fn exit_code(&self, cli: &Cli) -> i32 {
+        let matches = self.matches.load(Ordering::Relaxed);
+        let errors = self.errors.load(Ordering::Relaxed);
+
+        if let Some(limit) = cli.fail_on_errors
+            && errors as u64 >= limit
+        {
+            EXIT_ERRORS
+        } else if self.failed.load(Ordering::Relaxed) > 0 {
+            EXIT_EXPECT_FAILED
+        } else if cli.fail_on_match && matches > 0 {
+            EXIT_MATCH
+        } else if cli.fail_without_match && matches == 0 {
+            EXIT_NO_MATCH
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_exit_code() {
+        let outcome = RunOutcome::default();
+        assert_eq!(outcome.exit_code(&Cli::parse_from(["reqs"])), 0);
+
+        let cli = Cli::parse_from(["reqs", "--fail-without-match"]);
+        assert_eq!(outcome.exit_code(&cli), EXIT_NO_MATCH);
+        outcome.record_match();
+        assert_eq!(outcome.exit_code(&cli), 0);
+
+        let cli = Cli::parse_from(["reqs", "--fail-on-match", "--fail-on-errors", "2"]);
+        assert_eq!(outcome.exit_code(&cli), EXIT_MATCH);
+        outcome.record_error();
+        assert_eq!(outcome.exit_code(&cli), EXIT_MATCH);
+        outcome.record_error();
+        assert_eq!(outcome.exit_code(&cli), EXIT_ERRORS);
+
+        // Lines cut off by --max-runtime do not change the exit code
+        outcome.record_unprocessed();
+        assert_eq!(outcome.unprocessed(), 1);
+        assert_eq!(outcome.exit_code(&cli), EXIT_ERRORS);
+    }
+
+    #[test]
+    fn test_expectation_exit_code() {
+        let outcome = RunOutcome::default();
+        let cli = Cli::parse_from(["reqs"]);
+        assert_eq!(outcome.expectation_summary(), None);
+
+        outcome.record_verdict(true);
+        assert_eq!(outcome.exit_code(&cli), 0);
+        outcome.record_verdict(false);
+        assert_eq!(outcome.exit_code(&cli), EXIT_EXPECT_FAILED);
+        assert_eq!(
+            outcome.expectation_summary().unwrap(),
+            "Expectations: 1 passed, 1 failed"
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use colored::*;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::types::{GroupBy, SortKey};
+
+/// A rendered record held back for sorting and grouping
+pub struct BufferedRecord {
+    /// Status code, or None for records without a response (--tcp-probe)
+    pub status: Option<u16>,
+    /// Label of the status group, e.g. "200 OK"
+    pub status_label: String,
+    pub url: String,
+    pub size: u64,
+    pub elapsed: Duration,
+    /// Score of the response (--score-rules)
+    pub score: Option<i64>,
+    pub output: String,
+}
+
+impl BufferedRecord {
+    fn host(&self) -> String {
+        reqwest::Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| self.url.clone())
+    }
+
+    fn compare(&self, other: &Self, key: SortKey) -> Ordering {
+        match key {
+            // Records without a status come last
+            SortKey::Status => {
+                (self.status.is_none(), self.status).cmp(&(other.status.is_none(), other.status))
+            }
+            SortKey::Size => self.size.cmp(&other.size),
+            SortKey::Time => self.elapsed.cmp(&other.elapsed),
+            SortKey::Url => self.url.cmp(&other.url),
+            // Highest first, unscored records last
+            SortKey::Score => {
+                (self.score.is_none(), other.score).cmp(&(other.score.is_none(), self.score))
+            }
+        }
+    }
+}
+
+/// Records of a sink buffered until flush (--sort/--group-by)
+pub struct RecordBuffer {
+    sort: Option<SortKey>,
+    group_by: Option<GroupBy>,
+    /// Written before the records, e.g. the CSV header
+    pub header: String,
+    records: Vec<BufferedRecord>,
+}
+
+impl RecordBuffer {
+    pub fn new(sort: Option<SortKey>, group_by: Option<GroupBy>) -> Self {
+        RecordBuffer {
+            sort,
+            group_by,
+            header: String::new(),
+            records: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, record: BufferedRecord) {
+        self.records.push(record);
+    }
+
+    /// Take all records, sorted and grouped
+    ///
+    /// With `group_headers`, each group starts with a line naming it and its count.
+    pub fn drain(&mut self, group_headers: bool, colored: bool) -> String {
+        let mut records = std::mem::take(&mut self.records);
+        if let Some(key) = self.sort {
+            records.sort_by(|a, b| a.compare(b, key));
+        }
+        let mut output = std::mem::take(&mut self.header);
+
+        let Some(group_by) = self.group_by else {
+            for record in records {
+                output.push_str(&record.output);
+            }
+            return output;
+        };
+
+        // Status groups are ordered by code, with records without a response last
+        let mut groups: BTreeMap<(u16, String), Vec<BufferedRecord>> = BTreeMap::new();
+        for record in records {
+            let key = match group_by {
+                GroupBy::Host => (0, record.host()),
+                GroupBy::Status => (
+                    record.status.unwrap_or(u16::MAX),
+                    record.status_label.clone(),
+                ),
+            };
+            groups.entry(key).or_default().push(record);
+        }
+        for ((_, label), records) in groups {
+            if group_headers {
+                let heading = format!("=== {} ({}) ===", label, records.len());
+                if colored {
+                    output.push_str(&format!("{}\n", heading.bold()));
+                } else {
+                    output.push_str(&format!("{}\n", heading));
+                }
+            }
+            for record in records {
+                output.push_str(&record.output);
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(status: Option<u16>, url: &str, size: u64) -> BufferedRecord {
+        BufferedRecord {
+            status,
+            status_label: status.map_or("no response".to_string(), |s| s.to_string()),
+            url: url.to_string(),
+            size,
+            elapsed: Duration::from_millis(size),
+            score: status.map(|status| i64::from(status) / 100),
+            output: format!("{} {}\n", url, size),
+        }
+    }
+
+    #[test]
+    fn test_record_buffer_sort() {
+        let mut buffer = RecordBuffer::new(Some(SortKey::Size), None);
+        buffer.header = "url,size\n".to_string();
+        buffer.push(record(Some(200), "https://a.com/1", 30));
+        buffer.push(record(Some(404), "https://b.com/2", 10));
+        buffer.push(record(Some(200), "https://a.com/3", 20));
+
+        assert_eq!(
+            buffer.drain(false, false),
+            "url,size\nhttps://b.com/2 10\nhttps://a.com/3 20\nhttps://a.com/1 30\n"
+        );
+        assert_eq!(buffer.drain(false, false), "");
+    }
+
+    #[test]
+    fn test_record_buffer_sort_score() {
+        let mut buffer = RecordBuffer::new(Some(SortKey::Score), None);
+        buffer.push(record(None, "https://c.com/", 0));
+        buffer.push(record(Some(200), "https://a.com/", 1));
+        buffer.push(record(Some(500), "https://b.com/", 2));
+        assert_eq!(
+            buffer.drain(false, false),
+            "https://b.com/ 2\nhttps://a.com/ 1\nhttps://c.com/ 0\n"
+        );
+    }
+
+    #[test]
+    fn test_record_buffer_group_by() {
+        let mut buffer = RecordBuffer::new(Some(SortKey::Url), Some(GroupBy::Status));
+        buffer.push(record(None, "https://c.com/", 0));
+        buffer.push(record(Some(404), "https://b.com/", 10));
+        buffer.push(record(Some(200), "https://d.com/", 30));
+        buffer.push(record(Some(200), "https://a.com/", 20));
+
+        assert_eq!(
+            buffer.drain(true, false),
+            "=== 200 (2) ===\nhttps://a.com/ 20\nhttps://d.com/ 30\n\
+             === 404 (1) ===\nhttps://b.com/ 10\n\
+             === no response (1) ===\nhttps://c.com/ 0\n"
+        );
+
+        let mut buffer = RecordBuffer::new(None, Some(GroupBy::Host));
+        buffer.push(record(Some(200), "https://b.com/x", 1));
+        buffer.push(record(Some(200), "https://a.com/y", 2));
+        buffer.push(record(Some(500), "https://b.com/z", 3));
+        assert_eq!(
+            buffer.drain(false, false),
+            "https://a.com/y 2\nhttps://b.com/x 1\nhttps://b.com/z 3\n"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::Url;
+use reqwest::header::CONTENT_TYPE;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+use crate::constants::HTTP_VERSION_1_1;
+use crate::output::ResponseData;
+use crate::output::elastic::rfc3339;
+use crate::output::report::xml_escape;
+
+/// Collects matched requests as Burp Suite items (--export-burp)
+pub struct BurpExporter {
+    path: String,
+    items: Mutex<Vec<String>>,
+}
+
+impl BurpExporter {
+    pub fn new(path: &str) -> Self {
+        BurpExporter {
+            path: path.to_string(),
+            items: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub async fn add(&self, data: &ResponseData<'_>) {
+        let item = burp_item(data, SystemTime::now());
+        self.items.lock().await.push(item);
+    }
+
+    /// Write all items collected so far as a Burp items document
+    pub async fn save(&self) -> Result<()> {
+        let mut content = String::from("<?xml version=\"1.0\"?>\n<items>\n");
+        for item in self.items.lock().await.iter() {
+            content.push_str(item);
+        }
+        content.push_str("</items>\n");
+        tokio::fs::write(&self.path, content)
+            .await
+            .with_context(|| format!("Failed to write Burp export: {}", self.path))
+    }
+}
+
+/// One `<item>` with the base64-encoded raw request and response
+fn burp_item(data: &ResponseData, time: SystemTime) -> String {
+    let url = Url::parse(data.url_str).ok();
+    let host = url.as_ref().and_then(Url::host_str).unwrap_or_default();
+    let path = url
+        .as_ref()
+        .map_or("/".to_string(), |url| match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        });
+    let extension = path
+        .split('?')
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .and_then(|name| name.rsplit_once('.'))
+        .map_or("null", |(_, extension)| extension);
+
+    // Burp expects CRLF line endings; raw requests are kept with LF for display
+    let (request, version) = match data.req_for_display {
+        Some(raw) => {
+            let (head, body) = raw.split_once("\n\n").unwrap_or((raw.trim_end(), ""));
+            let version = head
+                .lines()
+                .next()
+                .and_then(|line| line.rsplit(' ').next())
+                .unwrap_or(HTTP_VERSION_1_1);
+            (
+                format!(
+                    "{}\r\n\r\n{}",
+                    head.lines().collect::<Vec<_>>().join("\r\n"),
+                    body
+                ),
+                version,
+            )
+        }
+        None => (
+            format!(
+                "{} {} {}\r\nHost: {}\r\n\r\n",
+                data.method, path, HTTP_VERSION_1_1, host
+            ),
+            HTTP_VERSION_1_1,
+        ),
+    };
+    let mut response = format!("{} {}\r\n", version, data.status);
+    for (name, value) in data.headers {
+        response.push_str(&format!(
+            "{}: {}\r\n",
+            name,
+            String::from_utf8_lossy(value.as_bytes())
+        ));
+    }
+    response.push_str("\r\n");
+    if let Some(body) = data.body_text {
+        response.push_str(body);
+    }
+
+    format!(
+        "  <item>
+    <time>{}</time>
+    <url><![CDATA[{}]]></url>
+    <host ip=\"{}\">{}</host>
+    <port>{}</port>
+    <protocol>{}</protocol>
+    <method><![CDATA[{}]]></method>
+    <path><![CDATA[{}]]></path>
+    <extension>{}</extension>
+    <request base64=\"true\"><![CDATA[{}]]></request>
+    <status>{}</status>
+    <responselength>{}</responselength>
+    <mimetype>{}</mimetype>
+    <response base64=\"true\"><![CDATA[{}]]></response>
+    <comment></comment>
+  </item>
+",
+        rfc3339(time),
+        data.url_str,
+        xml_escape(data.ip_addr),
+        xml_escape(host),
+        url.as_ref()
+            .and_then(Url::port_or_known_default)
+            .unwrap_or_default(),
+        url.as_ref().map_or("", Url::scheme),
+        data.method,
+        path,
+        xml_escape(extension),
+        STANDARD.encode(&request),
+        data.status.as_u16(),
+        response.len(),
+        mime_type(data),
+        STANDARD.encode(&response),
+    )
+}
+
+/// Burp's MIME type label for the response content type
+fn mime_type(data: &ResponseData) -> &'static str {
+    let content_type = data
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match content_type.as_str() {
+        t if t.contains("html") => "HTML",
+        t if t.contains("json") => "JSON",
+        t if t.contains("javascript") => "script",
+        t if t.contains("xml") => "XML",
+        t if t.contains("css") => "CSS",
+        t if t.starts_with("image/") => "image",
+        t if t.starts_with("text/") => "text",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderMap;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_burp_item() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, "text/html".parse().unwrap());
+        let data = ResponseData {
+            method: "POST",
+            url_str: "https://example.com/login.php?next=/",
+            ip_addr: "93.184.216.34",
+            size: 2,
+            elapsed: Duration::from_millis(10),
+            headers: &headers,
+            req_for_display: &Some(
+                "POST /login.php?next=/ HTTP/1.1\nHost: example.com\n\nuser=a".to_string(),
+            ),
+            body_text: &Some("ok".to_string()),
+            ..ResponseData::sample()
+        };
+
+        let item = burp_item(&data, UNIX_EPOCH);
+        assert!(item.contains("<time>1970-01-01T00:00:00.000Z</time>"));
+        assert!(item.contains("<host ip=\"93.184.216.34\">example.com</host>"));
+        assert!(item.contains("<port>443</port>"));
+        assert!(item.contains("<protocol>https</protocol>"));
+        assert!(item.contains("<path><![CDATA[/login.php?next=/]]></path>"));
+        assert!(item.contains("<extension>php</extension>"));
+        assert!(item.contains("<mimetype>HTML</mimetype>"));
+        assert!(item.contains(&format!(
+            "<request base64=\"true\"><![CDATA[{}]]></request>",
+            STANDARD.encode("POST /login.php?next=/ HTTP/1.1\r\nHost: example.com\r\n\r\nuser=a")
+        )));
+        assert!(item.contains(&format!(
+            "<response base64=\"true\"><![CDATA[{}]]></response>",
+            STANDARD.encode("HTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\r\nok")
+        )));
+    }
+}
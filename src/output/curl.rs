@@ -0,0 +1,103 @@
+use crate::constants::HTTP_VERSION_2;
+use crate::output::ResponseData;
+use crate::types::Cli;
+
+/// Equivalent curl command for a response's request (--format curl)
+///
+/// Method, headers and body come from the raw request; proxy, TLS verification
+/// and redirects follow the command line options.
+pub fn format_curl(cli: &Cli, data: &ResponseData) -> String {
+    let mut args = vec!["curl".to_string()];
+    let (head, body) = match data.req_for_display {
+        Some(raw) => match raw.split_once("\n\n") {
+            Some((head, body)) => (head, Some(body)),
+            None => (raw.trim_end(), None),
+        },
+        None => ("", None),
+    };
+    let mut lines = head.lines();
+    let http2 = lines
+        .next()
+        .is_some_and(|line| line.trim_end().ends_with(HTTP_VERSION_2));
+
+    match data.method {
+        "GET" => {}
+        "HEAD" => args.push("-I".to_string()),
+        method => args.push(format!("-X {}", method)),
+    }
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let name = name.trim();
+        // curl derives these from the URL and body
+        if name.eq_ignore_ascii_case("host") || name.eq_ignore_ascii_case("content-length") {
+            continue;
+        }
+        args.push(format!(
+            "-H {}",
+            shell_quote(&format!("{}: {}", name, value.trim()))
+        ));
+    }
+    if let Some(body) = body.filter(|body| !body.is_empty()) {
+        args.push(format!("--data-raw {}", shell_quote(body)));
+    }
+    if let Some(proxy) = &cli.proxy {
+        args.push(format!("-x {}", shell_quote(proxy)));
+    }
+    if !cli.verify_ssl {
+        args.push("-k".to_string());
+    }
+    if cli.follow_redirect {
+        args.push("-L".to_string());
+    }
+    if http2 {
+        args.push("--http2".to_string());
+    }
+    args.push(shell_quote(data.url_str));
+    format!("{}\n", args.join(" "))
+}
+
+/// Quote a value for POSIX shells
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use reqwest::header::HeaderMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_curl() {
+        let headers = HeaderMap::new();
+        let raw =
+            "POST /api HTTP/1.1\nHost: example.com\nx-token: a'b\ncontent-length: 8\n\n{\"a\": 1}";
+        let data = ResponseData {
+            method: "POST",
+            url_str: "https://example.com/api",
+            ip_addr: "93.184.216.34",
+            size: 0,
+            elapsed: Duration::ZERO,
+            headers: &headers,
+            req_for_display: &Some(raw.to_string()),
+            ..ResponseData::sample()
+        };
+
+        let cli = Cli::parse_from(["reqs", "--proxy", "http://127.0.0.1:8080", "--verify-ssl"]);
+        assert_eq!(
+            format_curl(&cli, &data),
+            "curl -X POST -H 'x-token: a'\\''b' --data-raw '{\"a\": 1}' -x 'http://127.0.0.1:8080' -L 'https://example.com/api'\n"
+        );
+
+        let data = ResponseData {
+            method: "HEAD",
+            req_for_display: &None,
+            ..data
+        };
+        let cli = Cli::parse_from(["reqs", "--follow-redirect", "false"]);
+        assert_eq!(
+            format_curl(&cli, &data),
+            "curl -I -k 'https://example.com/api'\n"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+use serde_json::{Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::http::timing::duration_ms;
+use crate::output::record::ResponseData;
+
+/// Schema of the results database; safe to run against an existing file
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS responses (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    timestamp INTEGER NOT NULL,
+    method TEXT NOT NULL,
+    url TEXT NOT NULL,
+    host TEXT,
+    ip_address TEXT,
+    status_code INTEGER NOT NULL,
+    content_length INTEGER NOT NULL,
+    response_time_ms REAL NOT NULL,
+    title TEXT,
+    headers TEXT,
+    timing TEXT,
+    raw_request TEXT,
+    response_body TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_responses_host ON responses(host);
+CREATE INDEX IF NOT EXISTS idx_responses_status ON responses(status_code);
+CREATE INDEX IF NOT EXISTS idx_responses_run ON responses(run_id);
+";
+
+/// SQLite results database (--output-db)
+///
+/// Every run is recorded in the `runs` table and appends its responses to the
+/// same file, so results of several scans can be queried together.
+pub struct ResultsDb {
+    conn: Connection,
+    run_id: i64,
+}
+
+impl ResultsDb {
+    /// Open (or create) the database and start a new run
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open results database: {}", path))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to initialize results database schema")?;
+        conn.execute("INSERT INTO runs (started_at) VALUES (?1)", [unix_time()])?;
+        let run_id = conn.last_insert_rowid();
+        Ok(ResultsDb { conn, run_id })
+    }
+
+    /// Insert a response record
+    pub fn insert(
+        &self,
+        data: &ResponseData<'_>,
+        include_request: bool,
+        include_body: bool,
+    ) -> Result<()> {
+        let headers: Map<String, Value> = data
+            .headers
+            .keys()
+            .map(|name| {
+                let values: Vec<Value> = data
+                    .headers
+                    .get_all(name)
+                    .iter()
+                    .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned().into())
+                    .collect();
+                (name.to_string(), Value::Array(values))
+            })
+            .collect();
+        let host = reqwest::Url::parse(data.url_str)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string));
+        let body = if include_body {
+            data.body_text.as_deref()
+        } else {
+            None
+        };
+
+        self.conn.execute(
+            "INSERT INTO responses (run_id, timestamp, method, url, host, ip_address, status_code,
+                content_length, response_time_ms, title, headers, timing, raw_request, response_body)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                self.run_id,
+                unix_time(),
+                data.method,
+                data.url_str,
+                host,
+                data.ip_addr,
+                data.status.as_u16(),
+                data.size as i64,
+                duration_ms(data.elapsed),
+                data.title.as_deref(),
+                Value::Object(headers).to_string(),
+                data.timing.as_ref().map(|t| t.to_json().to_string()),
+                data.req_for_display.as_deref().filter(|_| include_request),
+                body,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+    use reqwest::header::HeaderMap;
+
+    #[test]
+    fn test_results_db_appends_runs() {
+        let path = std::env::temp_dir().join(format!("reqs-db-{}.sqlite", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.append("set-cookie", "a=1".parse().unwrap());
+        headers.append("set-cookie", "b=2".parse().unwrap());
+        let data = ResponseData {
+            url_str: "https://example.com:8443/a",
+            status: StatusCode::NOT_FOUND,
+            headers: &headers,
+            body_text: &Some("body".to_string()),
+            ..ResponseData::sample()
+        };
+
+        ResultsDb::open(path)
+            .unwrap()
+            .insert(&data, false, false)
+            .unwrap();
+        let db = ResultsDb::open(path).unwrap();
+        db.insert(&data, false, true).unwrap();
+        assert_eq!(db.run_id, 2);
+
+        let (host, status, headers, body): (String, u16, String, Option<String>) = db
+            .conn
+            .query_row(
+                "SELECT host, status_code, headers, response_body FROM responses WHERE run_id = 2",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(host, "example.com");
+        assert_eq!(status, 404);
+        assert_eq!(headers, r#"{"set-cookie":["a=1","b=2"]}"#);
+        assert_eq!(body.as_deref(), Some("body"));
+
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM responses", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+}
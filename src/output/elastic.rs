@@ -0,0 +1,144 @@
+use anyhow::{Result, bail};
+use reqwest::Client;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{Value, json};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+use crate::constants::ELASTIC_BULK_SIZE;
+
+/// Buffered exporter that bulk-indexes records into Elasticsearch/OpenSearch
+///
+/// Records are batched and sent to the `_bulk` API once the buffer is full;
+/// the remainder is sent by [`ElasticExporter::flush`] when the run ends.
+pub struct ElasticExporter {
+    client: Client,
+    bulk_url: String,
+    index: String,
+    buffer: Mutex<Vec<Value>>,
+}
+
+impl ElasticExporter {
+    /// Create an exporter for the cluster at `url`
+    pub fn new(url: &str, index: &str, timeout: u64, verify_ssl: bool) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .danger_accept_invalid_certs(!verify_ssl)
+            .build()?;
+        Ok(ElasticExporter {
+            client,
+            bulk_url: format!("{}/_bulk", url.trim_end_matches('/')),
+            index: index.to_string(),
+            buffer: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Queue a record, sending a bulk request once the buffer is full
+    pub async fn export(&self, mut record: Value) -> Result<()> {
+        record["@timestamp"] = rfc3339(SystemTime::now()).into();
+
+        let batch = {
+            let mut buffer = self.buffer.lock().await;
+            buffer.push(record);
+            if buffer.len() < ELASTIC_BULK_SIZE {
+                return Ok(());
+            }
+            std::mem::take(&mut *buffer)
+        };
+        self.send(&batch).await
+    }
+
+    /// Send any buffered records
+    pub async fn flush(&self) -> Result<()> {
+        let batch = std::mem::take(&mut *self.buffer.lock().await);
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.send(&batch).await
+    }
+
+    async fn send(&self, batch: &[Value]) -> Result<()> {
+        let resp = self
+            .client
+            .post(&self.bulk_url)
+            .header(CONTENT_TYPE, "application/x-ndjson")
+            .body(bulk_body(&self.index, batch))
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: Value = resp.json().await.unwrap_or_default();
+        if !status.is_success() {
+            bail!("bulk request failed with {}: {}", status, body);
+        }
+        if body["errors"].as_bool() == Some(true) {
+            bail!("bulk request reported errors for some records");
+        }
+        Ok(())
+    }
+}
+
+/// Build an NDJSON `_bulk` request body indexing each record into `index`
+pub fn bulk_body(index: &str, records: &[Value]) -> String {
+    let action = json!({ "index": { "_index": index } }).to_string();
+    records
+        .iter()
+        .map(|record| format!("{}\n{}\n", action, record))
+        .collect()
+}
+
+/// Format a timestamp as RFC 3339 in UTC (e.g. "2024-01-02T03:04:05.678Z")
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, day_secs) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        day_secs / 3600,
+        day_secs % 3600 / 60,
+        day_secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bulk_body() {
+        let records = vec![
+            json!({"url": "https://a.com"}),
+            json!({"url": "https://b.com"}),
+        ];
+        let body = bulk_body("reqs", &records);
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], r#"{"index":{"_index":"reqs"}}"#);
+        assert_eq!(lines[1], r#"{"url":"https://a.com"}"#);
+        assert_eq!(lines[3], r#"{"url":"https://b.com"}"#);
+        assert!(body.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_210_096_789);
+        assert_eq!(rfc3339(time), "2024-02-29T12:34:56.789Z");
+    }
+}
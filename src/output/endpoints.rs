@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+use std::collections::BTreeSet;
+use tokio::sync::Mutex;
+
+/// Aggregates the endpoints found in JavaScript responses (--js-endpoints-output)
+pub struct EndpointCollector {
+    path: String,
+    endpoints: Mutex<BTreeSet<String>>,
+}
+
+impl EndpointCollector {
+    pub fn new(path: &str) -> Self {
+        EndpointCollector {
+            path: path.to_string(),
+            endpoints: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Add endpoints found in the script at `base`, resolved against its URL
+    pub async fn add(&self, base: &str, endpoints: &[String]) {
+        let base = Url::parse(base).ok();
+        let mut collected = self.endpoints.lock().await;
+        for endpoint in endpoints {
+            let resolved = base
+                .as_ref()
+                .and_then(|base| base.join(endpoint).ok())
+                .map(String::from)
+                .unwrap_or_else(|| endpoint.clone());
+            collected.insert(resolved);
+        }
+    }
+
+    /// Write all endpoints collected so far, one per line in sorted order
+    pub async fn save(&self) -> Result<()> {
+        let mut content = String::new();
+        for endpoint in self.endpoints.lock().await.iter() {
+            content.push_str(endpoint);
+            content.push('\n');
+        }
+        tokio::fs::write(&self.path, content)
+            .await
+            .with_context(|| format!("Failed to write endpoints file: {}", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_endpoint_collector() {
+        let path = std::env::temp_dir().join(format!("reqs-endpoints-{}", std::process::id()));
+        let collector = EndpointCollector::new(path.to_str().unwrap());
+        collector
+            .add(
+                "https://example.com/static/app.js",
+                &["/api/users".to_string(), "./chunk.js".to_string()],
+            )
+            .await;
+        collector
+            .add(
+                "https://example.com/static/vendor.js",
+                &[
+                    "https://cdn.example.com/lib.js".to_string(),
+                    "/api/users".to_string(),
+                ],
+            )
+            .await;
+        collector.save().await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "https://cdn.example.com/lib.js\nhttps://example.com/api/users\nhttps://example.com/static/chunk.js\n"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::{Context, Result, bail};
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+use std::path::{Path, PathBuf};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+
+use crate::types::{Cli, Compression};
+
+/// What to do with an output file that already exists (--append, --force)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExistingFile {
+    Refuse,
+    Overwrite,
+    Append,
+}
+
+impl ExistingFile {
+    pub fn from_cli(cli: &Cli) -> Self {
+        if cli.append {
+            ExistingFile::Append
+        } else if cli.force {
+            ExistingFile::Overwrite
+        } else {
+            ExistingFile::Refuse
+        }
+    }
+}
+
+/// Byte stream of an output file, compressed by --compress or its extension
+enum FileStream {
+    Plain(BufWriter<File>),
+    Gzip(GzipEncoder<BufWriter<File>>),
+    Zstd(ZstdEncoder<BufWriter<File>>),
+}
+
+/// An output file
+pub struct OutputFile {
+    stream: FileStream,
+    /// Temporary file moved over the target when closed
+    rename: Option<(PathBuf, PathBuf)>,
+    /// Whether output is appended after existing content
+    pub has_content: bool,
+}
+
+impl OutputFile {
+    /// Create an output file, checking for an existing one
+    ///
+    /// A new or overwritten file is written under a temporary name next to it
+    /// and renamed when closed, so an interrupted run leaves a previous file
+    /// untouched. Appended and `live` output goes straight to the file.
+    pub async fn create(
+        path: &str,
+        compression: Option<Compression>,
+        existing: ExistingFile,
+        live: bool,
+    ) -> Result<Self> {
+        let exists = tokio::fs::try_exists(path).await.unwrap_or(false);
+        match existing {
+            ExistingFile::Append => return Self::open(path, compression, true).await,
+            ExistingFile::Refuse if exists => bail!(
+                "Output file already exists: {} (use --force to overwrite or --append to add to it)",
+                path
+            ),
+            _ if live => return Self::open(path, compression, false).await,
+            _ => {}
+        }
+        let target = PathBuf::from(path);
+        let temp = temp_path(&target);
+        // The temporary name hides the extension, so compression follows the target
+        let compression = Compression::from_path(path).or(compression);
+        let mut file = Self::open(&temp.to_string_lossy(), compression, false).await?;
+        file.rename = Some((temp, target));
+        Ok(file)
+    }
+
+    /// Open a file in place, truncating it or appending to it
+    ///
+    /// Appended compressed output starts a new gzip member or zstd frame,
+    /// which decompressors read as one stream.
+    pub async fn open(path: &str, compression: Option<Compression>, append: bool) -> Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to create output file: {}", path))?;
+        let has_content = append && file.metadata().await.is_ok_and(|m| m.len() > 0);
+        let file = BufWriter::new(file);
+        let stream = match Compression::from_path(path).or(compression) {
+            Some(compression) => FileStream::compressed(file, compression),
+            None => FileStream::Plain(file),
+        };
+        Ok(OutputFile {
+            stream,
+            rename: None,
+            has_content,
+        })
+    }
+
+    pub fn writer(&mut self) -> &mut (dyn AsyncWrite + Unpin + Send) {
+        match &mut self.stream {
+            FileStream::Plain(file) => file,
+            FileStream::Gzip(file) => file,
+            FileStream::Zstd(file) => file,
+        }
+    }
+
+    /// Finish writing, moving a temporary file over the target
+    pub async fn close(&mut self) -> Result<()> {
+        self.writer().shutdown().await?;
+        if let Some((temp, target)) = self.rename.take() {
+            tokio::fs::rename(&temp, &target)
+                .await
+                .with_context(|| format!("Failed to write output file: {}", target.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl FileStream {
+    fn compressed(file: BufWriter<File>, compression: Compression) -> Self {
+        match compression {
+            Compression::Gzip => FileStream::Gzip(GzipEncoder::new(file)),
+            Compression::Zstd => FileStream::Zstd(ZstdEncoder::new(file)),
+        }
+    }
+}
+
+/// Hidden temporary name in the directory of `path`, e.g. ".results.jsonl.reqs-tmp"
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.reqs-tmp", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_output_file_existing() {
+        let dir = std::env::temp_dir().join(format!("reqs-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.txt");
+        let path_str = path.to_str().unwrap();
+        std::fs::write(&path, "previous\n").unwrap();
+
+        assert!(
+            OutputFile::create(path_str, None, ExistingFile::Refuse, false)
+                .await
+                .is_err()
+        );
+
+        // Overwritten only once closed
+        let mut file = OutputFile::create(path_str, None, ExistingFile::Overwrite, false)
+            .await
+            .unwrap();
+        file.writer().write_all(b"new\n").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "previous\n");
+        file.close().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\n");
+        assert!(!temp_path(&path).exists());
+
+        let mut file = OutputFile::create(path_str, None, ExistingFile::Append, false)
+            .await
+            .unwrap();
+        assert!(file.has_content);
+        file.writer().write_all(b"more\n").await.unwrap();
+        file.close().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\nmore\n");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,16 +1,365 @@
+use anyhow::{Context, Result};
 use colored::*;
 use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use std::collections::BTreeMap;
 use std::time::Duration;
 
+use crate::filter::MatchContext;
+use crate::http::timing::duration_ms;
+use crate::http::{
+    BackupFinding, ByteCount, FailedAt, PortState, RequestError, SseInfo, TcpProbe, TimingDetail,
+    WebSocketInfo,
+};
+use crate::output::elastic::rfc3339;
+use crate::state::StateChange;
+use crate::types::TimeUnit;
+use crate::utils::{
+    AsnInfo, CdnInfo, CertExpiry, FormInfo, InvalidUrl, JwtInfo, PageMeta, format_size, url_parts,
+};
+
 /// Response information for formatting
 pub struct ResponseInfo<'a> {
     pub method: &'a str,
     pub url: &'a str,
     pub ip_addr: &'a str,
+    pub resolved_ips: &'a Option<Vec<String>>,
+    pub asn: &'a Option<AsnInfo>,
+    pub cdn: &'a Option<CdnInfo>,
+    pub cert: &'a Option<CertExpiry>,
+    pub bytes: &'a Option<ByteCount>,
+    pub trace_id: &'a Option<String>,
+    pub tags: &'a [String],
     pub status: StatusCode,
     pub size: u64,
     pub elapsed: Duration,
     pub title: &'a Option<String>,
+    pub meta: &'a Option<PageMeta>,
+    pub forms: &'a Option<Vec<FormInfo>>,
+    pub endpoints: &'a Option<Vec<String>>,
+    pub jwts: &'a Option<Vec<JwtInfo>>,
+    pub reflected: &'a Option<Vec<String>>,
+    pub directory_listing: Option<bool>,
+    pub score: Option<i64>,
+    pub state: &'a Option<StateChange>,
+    pub headers: &'a HeaderMap,
+    pub req_headers: &'a Option<HeaderMap>,
+    pub extracted: &'a Option<BTreeMap<String, String>>,
+    pub timing: &'a Option<TimingDetail>,
+    pub websocket: &'a Option<WebSocketInfo>,
+    pub sse: &'a Option<SseInfo>,
+    pub match_context: &'a Option<MatchContext>,
+    pub time_unit: TimeUnit,
+}
+
+#[cfg(test)]
+impl ResponseInfo<'static> {
+    /// A `200 OK` response to `GET https://example.com` with nothing optional
+    /// set, for tests to override the fields they need
+    pub fn sample() -> Self {
+        static HEADERS: std::sync::LazyLock<HeaderMap> = std::sync::LazyLock::new(HeaderMap::new);
+        ResponseInfo {
+            method: "GET",
+            url: "https://example.com",
+            ip_addr: "1.2.3.4",
+            resolved_ips: &None,
+            asn: &None,
+            cdn: &None,
+            cert: &None,
+            bytes: &None,
+            trace_id: &None,
+            tags: &[],
+            status: StatusCode::OK,
+            size: 1234,
+            elapsed: Duration::from_secs(1),
+            title: &None,
+            meta: &None,
+            forms: &None,
+            endpoints: &None,
+            jwts: &None,
+            reflected: &None,
+            directory_listing: None,
+            score: None,
+            state: &None,
+            headers: &HEADERS,
+            req_headers: &None,
+            extracted: &None,
+            timing: &None,
+            websocket: &None,
+            sse: &None,
+            match_context: &None,
+            time_unit: TimeUnit::Human,
+        }
+    }
+}
+
+/// Plain output segment for --cert-expiry-warn, e.g. " | Cert: 2026-11-01 (14d) EXPIRING"
+fn format_cert_segment(cert: &CertExpiry) -> String {
+    let date = rfc3339(cert.not_after);
+    let mut segment = format!(" | Cert: {} ({}d)", &date[..10], cert.days_left);
+    if cert.days_left < 0 {
+        segment.push_str(" EXPIRED");
+    } else if cert.expiring {
+        segment.push_str(" EXPIRING");
+    }
+    segment
+}
+
+/// Plain output segment for --enrich-asn, e.g. " | AS13335 CLOUDFLARENET (US)"
+fn format_asn_segment(asn: &AsnInfo) -> String {
+    let mut segment = " |".to_string();
+    if let Some(number) = asn.asn {
+        segment.push_str(&format!(" AS{}", number));
+    }
+    if let Some(org) = &asn.org {
+        segment.push_str(&format!(" {}", org));
+    }
+    if let Some(country) = &asn.country {
+        segment.push_str(&format!(" ({})", country));
+    }
+    segment
+}
+
+/// Duration as a number in the unit, for templates (--time-unit)
+///
+/// Human-readable durations keep their unit, e.g. "42.3ms".
+fn duration_value(duration: Duration, unit: TimeUnit) -> String {
+    match unit {
+        TimeUnit::Ms => duration_ms(duration).to_string(),
+        TimeUnit::S => (duration_ms(duration) / 1000.0).to_string(),
+        TimeUnit::Human => format!("{:?}", duration),
+    }
+}
+
+/// Duration with its unit, for plain output lines (--time-unit)
+pub fn format_duration(duration: Duration, unit: TimeUnit) -> String {
+    match unit {
+        TimeUnit::Ms => format!("{}ms", duration_value(duration, unit)),
+        TimeUnit::S => format!("{}s", duration_value(duration, unit)),
+        TimeUnit::Human => duration_value(duration, unit),
+    }
+}
+
+/// Format an optional timing phase, using "-" when it did not happen
+fn format_phase(phase: Option<Duration>, format: impl Fn(Duration) -> String) -> String {
+    phase.map(format).unwrap_or_else(|| "-".to_string())
+}
+
+/// Format the timing breakdown segment of a plain output line
+fn format_timing_segment(timing: &TimingDetail, unit: TimeUnit) -> String {
+    let format = |d| format_duration(d, unit);
+    format!(
+        " | DNS: {} | Connect: {} | TLS: {} | TTFB: {} | Download: {}",
+        format_phase(timing.dns, format),
+        format_phase(timing.connect, format),
+        format_phase(timing.tls, format),
+        format(timing.ttfb),
+        format(timing.download)
+    )
+}
+
+/// Format the page meta segment of a plain output line
+fn format_meta_segment(meta: &PageMeta) -> String {
+    let mut segment = String::new();
+    let fields = [
+        ("Description", &meta.description),
+        ("Generator", &meta.generator),
+        ("Canonical", &meta.canonical),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            segment.push_str(&format!(" | {}: {}", label, value));
+        }
+    }
+    for (property, value) in &meta.og {
+        segment.push_str(&format!(" | og:{}: {}", property, value));
+    }
+    segment
+}
+
+/// Format the form inventory segment of a plain output line
+fn format_forms_segment(forms: &[FormInfo]) -> String {
+    let forms: Vec<String> = forms
+        .iter()
+        .map(|form| {
+            let inputs: Vec<&str> = form.inputs.iter().map(|i| i.name.as_str()).collect();
+            format!(
+                "{} {} [{}]{}",
+                form.method,
+                if form.action.is_empty() {
+                    "-"
+                } else {
+                    &form.action
+                },
+                inputs.join(","),
+                if form.csrf { " csrf" } else { "" }
+            )
+        })
+        .collect();
+    format!(" | Forms: {} ({})", forms.len(), forms.join("; "))
+}
+
+/// Format the JWT segment of a plain output line, e.g. " | JWT: 2 (none no_exp; RS256)"
+///
+/// The algorithm comes first, so the alg:none flag is left out.
+fn format_jwts_segment(jwts: &[JwtInfo]) -> String {
+    let jwts: Vec<String> = jwts
+        .iter()
+        .map(|jwt| {
+            let mut desc = jwt.header["alg"].as_str().unwrap_or("-").to_string();
+            for flag in jwt.flags.iter().filter(|&&flag| flag != "alg:none") {
+                desc.push(' ');
+                desc.push_str(flag);
+            }
+            desc
+        })
+        .collect();
+    format!(" | JWT: {} ({})", jwts.len(), jwts.join("; "))
+}
+
+/// Format the WebSocket probe segment of a plain output line
+fn format_websocket_segment(websocket: &WebSocketInfo) -> String {
+    if !websocket.upgraded {
+        return " | WS: rejected".to_string();
+    }
+    let mut segment = " | WS: upgraded".to_string();
+    if let Some(protocol) = &websocket.subprotocol {
+        segment.push_str(&format!(" | Protocol: {}", protocol));
+    }
+    if let Some(message) = &websocket.first_message {
+        segment.push_str(&format!(" | Message: {}", message.replace('\n', " ")));
+    }
+    segment
+}
+
+/// Format the event stream segment of a plain output line
+fn format_sse_segment(sse: &SseInfo) -> String {
+    let mut segment = format!(" | SSE: {} events", sse.events);
+    if let Some(sample) = sse.samples.first() {
+        segment.push_str(&format!(" | Sample: {}", sample.replace('\n', " ")));
+    }
+    segment
+}
+
+/// Placeholders of plain output templates that take no argument
+const TEMPLATE_PLACEHOLDERS: &[&str] = &[
+    "method", "url", "scheme", "host", "port", "path", "status", "code", "size", "time", "ip",
+    "title", "tags", "dns", "connect", "tls", "ttfb", "download",
+];
+
+/// Placeholders of plain output templates that take a name, e.g. %header:Server
+const NAMED_TEMPLATE_PLACEHOLDERS: &[&str] = &["header:", "req_header:", "extract:"];
+
+/// Read a --strf template from a file (--strf-file)
+///
+/// One final line break is dropped, as each record already ends with one.
+pub fn read_template_file(path: &str) -> Result<String> {
+    let template = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read template file: {}", path))?;
+    let template = template
+        .strip_suffix('\n')
+        .map(|t| t.strip_suffix('\r').unwrap_or(t))
+        .unwrap_or(&template);
+    Ok(template.to_string())
+}
+
+/// Render a --strf template
+///
+/// `%%` is a literal `%`. Unknown placeholders, and timing placeholders without
+/// --timing-detail, are kept as-is; named ones without a value are empty.
+fn render_template(template: &str, response: &ResponseInfo) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find('%') {
+        output.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if let Some(after) = rest.strip_prefix('%') {
+            output.push('%');
+            rest = after;
+            continue;
+        }
+        match template_value(rest, response) {
+            Some((value, len)) => {
+                output.push_str(&value);
+                rest = &rest[len..];
+            }
+            None => output.push('%'),
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Value of the placeholder at the start of `spec` and the length of its name
+fn template_value(spec: &str, response: &ResponseInfo) -> Option<(String, usize)> {
+    for prefix in NAMED_TEMPLATE_PLACEHOLDERS {
+        let Some(name) = spec.strip_prefix(prefix) else {
+            continue;
+        };
+        let name_len = name
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(name.len());
+        let name = &name[..name_len];
+        let value = match *prefix {
+            "header:" => header_value(response.headers, name),
+            "req_header:" => response
+                .req_headers
+                .as_ref()
+                .and_then(|headers| header_value(headers, name)),
+            _ => response
+                .extracted
+                .as_ref()
+                .and_then(|values| values.get(name).cloned()),
+        };
+        return Some((value.unwrap_or_default(), prefix.len() + name_len));
+    }
+
+    let name = TEMPLATE_PLACEHOLDERS
+        .iter()
+        .find(|name| spec.starts_with(*name))?;
+    let value = match *name {
+        "method" => response.method.to_string(),
+        "url" => response.url.to_string(),
+        part @ ("scheme" | "host" | "port" | "path") => {
+            let parts = url_parts(response.url);
+            match (part, parts) {
+                (_, None) => String::new(),
+                ("scheme", Some(parts)) => parts.scheme,
+                ("host", Some(parts)) => parts.host,
+                ("port", Some(parts)) => parts.port.map(|p| p.to_string()).unwrap_or_default(),
+                (_, Some(parts)) => parts.path,
+            }
+        }
+        "status" => response.status.to_string(),
+        "code" => response.status.as_u16().to_string(),
+        "size" => response.size.to_string(),
+        "time" => duration_value(response.elapsed, response.time_unit),
+        "ip" => response.ip_addr.to_string(),
+        "title" => response.title.clone().unwrap_or_default(),
+        "tags" => response.tags.join(","),
+        phase => {
+            let timing = response.timing.as_ref()?;
+            let value = |d| duration_value(d, response.time_unit);
+            match phase {
+                "dns" => format_phase(timing.dns, value),
+                "connect" => format_phase(timing.connect, value),
+                "tls" => format_phase(timing.tls, value),
+                "ttfb" => value(timing.ttfb),
+                _ => value(timing.download),
+            }
+        }
+    };
+    Some((value, name.len()))
+}
+
+/// All values of a header joined with ", "
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    let values: Vec<String> = headers
+        .get_all(name)
+        .iter()
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+        .collect();
+    (!values.is_empty()).then(|| values.join(", "))
 }
 
 /// Format response as plain text output
@@ -20,16 +369,7 @@ pub fn format_plain_output(
     colored: bool,
 ) -> String {
     if let Some(template_str) = template {
-        let time_str = format!("{:?}", response.elapsed);
-        let mut output = template_str
-            .replace("%method", response.method)
-            .replace("%url", response.url)
-            .replace("%status", &response.status.to_string())
-            .replace("%code", &response.status.as_u16().to_string())
-            .replace("%size", &response.size.to_string())
-            .replace("%time", &time_str)
-            .replace("%ip", response.ip_addr)
-            .replace("%title", &response.title.clone().unwrap_or_default());
+        let mut output = render_template(template_str, response);
         output.push('\n');
         output
     } else {
@@ -43,6 +383,89 @@ pub fn format_plain_output(
             String::new()
         };
 
+        let mut extra_str = response
+            .meta
+            .as_ref()
+            .map(format_meta_segment)
+            .unwrap_or_default();
+        if let Some(forms) = response.forms
+            && !forms.is_empty()
+        {
+            extra_str.push_str(&format_forms_segment(forms));
+        }
+        if let Some(endpoints) = response.endpoints
+            && !endpoints.is_empty()
+        {
+            extra_str.push_str(&format!(
+                " | Endpoints: {} ({})",
+                endpoints.len(),
+                endpoints.join(", ")
+            ));
+        }
+        if let Some(jwts) = response.jwts
+            && !jwts.is_empty()
+        {
+            extra_str.push_str(&format_jwts_segment(jwts));
+        }
+        if let Some(reflected) = response.reflected
+            && !reflected.is_empty()
+        {
+            extra_str.push_str(&format!(" | Reflected: {}", reflected.join(", ")));
+        }
+        if response.directory_listing == Some(true) {
+            extra_str.push_str(" | Directory listing");
+        }
+        if let Some(score) = response.score {
+            extra_str.push_str(&format!(" | Score: {}", score));
+        }
+        if !response.tags.is_empty() {
+            extra_str.push_str(&format!(" | Tags: {}", response.tags.join(", ")));
+        }
+        if let Some(timing) = response.timing {
+            extra_str.push_str(&format_timing_segment(timing, response.time_unit));
+        }
+        if let Some(ips) = response.resolved_ips {
+            extra_str.push_str(&format!(" | IPs: {}", ips.join(", ")));
+        }
+        if let Some(asn) = response.asn {
+            extra_str.push_str(&format_asn_segment(asn));
+        }
+        if let Some(cdn) = response.cdn {
+            if let Some(name) = cdn.cdn {
+                extra_str.push_str(&format!(" | CDN: {}", name));
+            }
+            if let Some(name) = cdn.waf {
+                extra_str.push_str(&format!(" | WAF: {}", name));
+            }
+        }
+        if let Some(cert) = response.cert {
+            extra_str.push_str(&format_cert_segment(cert));
+        }
+        if let Some(bytes) = response.bytes {
+            extra_str.push_str(&format!(
+                " | Sent: {} | Received: {}",
+                format_size(bytes.up),
+                format_size(bytes.down)
+            ));
+        }
+        if let Some(trace_id) = response.trace_id {
+            extra_str.push_str(&format!(" | Trace: {}", trace_id));
+        }
+        if let Some(websocket) = response.websocket {
+            extra_str.push_str(&format_websocket_segment(websocket));
+        }
+        if let Some(state) = response.state
+            && state.changed()
+        {
+            extra_str.push_str(&format!(" | Changed: {}", state.fields.join(", ")));
+        }
+        if let Some(sse) = response.sse {
+            extra_str.push_str(&format_sse_segment(sse));
+        }
+        if let Some(context) = response.match_context {
+            extra_str.push_str(&format!(" | Match: {}", context.context.replace('\n', " ")));
+        }
+
         if colored {
             let status_str = response.status.to_string();
             let colored_status = if response.status.is_success() {
@@ -53,45 +476,146 @@ pub fn format_plain_output(
                 status_str.red()
             };
             format!(
-                "[{}] [{}] [{}] -> {} | Size: {} {}| Time: {:?}\n",
+                "[{}] [{}] [{}] -> {} | Size: {} {}| Time: {}{}\n",
                 response.method.yellow(),
                 response.url.cyan(),
                 response.ip_addr.magenta(),
                 colored_status,
                 response.size.to_string().blue(),
                 title_str,
-                response.elapsed
+                format_duration(response.elapsed, response.time_unit),
+                extra_str
             )
         } else {
             format!(
-                "[{}] [{}] [{}] -> {} | Size: {} {}| Time: {:?}\n",
+                "[{}] [{}] [{}] -> {} | Size: {} {}| Time: {}{}\n",
                 response.method,
                 response.url,
                 response.ip_addr,
                 response.status,
                 response.size,
                 title_str,
-                response.elapsed
+                format_duration(response.elapsed, response.time_unit),
+                extra_str
             )
         }
     }
 }
 
+/// Format a TCP probe of a failed request as plain text output
+pub fn format_tcp_plain_output(
+    url: &str,
+    probe: &TcpProbe,
+    error: &str,
+    time_unit: TimeUnit,
+    colored: bool,
+) -> String {
+    let elapsed = format_duration(probe.elapsed, time_unit);
+    let ip_addr = probe.ip_addr.as_deref().unwrap_or_default();
+    let state = probe.state.as_str();
+    if colored {
+        let state = match probe.state {
+            PortState::Open => state.green(),
+            PortState::Filtered => state.yellow(),
+            PortState::Closed | PortState::Unresolved => state.red(),
+        };
+        format!(
+            "[{}] [{}] [{}] -> {} | Port: {} | Time: {} | Error: {}\n",
+            "TCP".yellow(),
+            url.cyan(),
+            ip_addr.magenta(),
+            state,
+            probe.port,
+            elapsed,
+            error
+        )
+    } else {
+        format!(
+            "[TCP] [{}] [{}] -> {} | Port: {} | Time: {} | Error: {}\n",
+            url, ip_addr, state, probe.port, elapsed, error
+        )
+    }
+}
+
+/// Format a request that got no response as plain text output (--include-errors)
+pub fn format_error_plain_output(
+    method: &str,
+    url: &str,
+    error: &RequestError,
+    colored: bool,
+) -> String {
+    let kind = match (&error.proxy, error.failed_at) {
+        (Some(proxy), Some(FailedAt::Proxy)) => {
+            format!("{} at proxy {}", error.kind.as_str(), proxy)
+        }
+        _ => error.kind.as_str().to_string(),
+    };
+    if colored {
+        format!(
+            "[{}] [{}] [{}] -> {}: {}\n",
+            "ERROR".red(),
+            method,
+            url.cyan(),
+            kind.red(),
+            error.message
+        )
+    } else {
+        format!(
+            "[ERROR] [{}] [{}] -> {}: {}\n",
+            method, url, kind, error.message
+        )
+    }
+}
+
+/// Format a backup copy found by --check-backups as plain text output
+pub fn format_backup_plain_output(finding: &BackupFinding, colored: bool) -> String {
+    if colored {
+        format!(
+            "[{}] [{}] -> {} | Size: {} | Of: {}\n",
+            "BACKUP".yellow(),
+            finding.url.cyan(),
+            finding.status.to_string().green(),
+            finding.size,
+            finding.source
+        )
+    } else {
+        format!(
+            "[BACKUP] [{}] -> {} | Size: {} | Of: {}\n",
+            finding.url, finding.status, finding.size, finding.source
+        )
+    }
+}
+
+/// Format an input URL that was rejected before sending as plain text output
+pub fn format_invalid_url_plain_output(
+    method: &str,
+    invalid: &InvalidUrl,
+    colored: bool,
+) -> String {
+    if colored {
+        format!(
+            "[{}] [{}] [{}] -> {}\n",
+            "INVALID".red(),
+            method,
+            invalid.url.cyan(),
+            invalid.reason
+        )
+    } else {
+        format!(
+            "[INVALID] [{}] [{}] -> {}\n",
+            method, invalid.url, invalid.reason
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::http::ErrorKind;
 
     #[test]
     fn test_format_plain_output_no_template() {
-        let response = ResponseInfo {
-            method: "GET",
-            url: "https://example.com",
-            ip_addr: "1.2.3.4",
-            status: StatusCode::OK,
-            size: 1234,
-            elapsed: Duration::from_secs(1),
-            title: &None,
-        };
+        let response = ResponseInfo::sample();
         let output = format_plain_output(&response, &None, false);
         assert!(output.contains("GET"));
         assert!(output.contains("https://example.com"));
@@ -100,17 +624,146 @@ mod tests {
 
     #[test]
     fn test_format_plain_output_with_template() {
-        let response = ResponseInfo {
-            method: "GET",
-            url: "https://example.com",
-            ip_addr: "1.2.3.4",
-            status: StatusCode::OK,
-            size: 1234,
-            elapsed: Duration::from_secs(1),
-            title: &None,
-        };
+        let response = ResponseInfo::sample();
         let template = Some("%method %url -> %code".to_string());
         let output = format_plain_output(&response, &template, false);
         assert_eq!(output, "GET https://example.com -> 200\n");
+
+        let template = Some("%scheme %host %port %path".to_string());
+        let output = format_plain_output(&response, &template, false);
+        assert_eq!(output, "https example.com 443 /\n");
+
+        let tags = ["batch=login".to_string(), "smoke".to_string()];
+        let response = ResponseInfo {
+            tags: &tags,
+            ..response
+        };
+        let output = format_plain_output(&response, &Some("%url %tags".to_string()), false);
+        assert_eq!(output, "https://example.com batch=login,smoke\n");
+        let output = format_plain_output(&response, &None, false);
+        assert!(output.ends_with(" | Tags: batch=login, smoke\n"));
+    }
+
+    #[test]
+    fn test_format_plain_output_with_timing() {
+        let timing = Some(TimingDetail {
+            dns: Some(Duration::from_millis(5)),
+            connect: Some(Duration::from_millis(10)),
+            tls: None,
+            ttfb: Duration::from_millis(30),
+            download: Duration::from_millis(2),
+        });
+        let response = ResponseInfo {
+            url: "http://example.com",
+            elapsed: Duration::from_millis(32),
+            timing: &timing,
+            ..ResponseInfo::sample()
+        };
+        let output = format_plain_output(&response, &None, false);
+        assert!(output.contains("| DNS: 5ms | Connect: 10ms | TLS: - | TTFB: 30ms"));
+
+        let template = Some("%url dns=%dns tls=%tls ttfb=%ttfb".to_string());
+        let output = format_plain_output(&response, &template, false);
+        assert_eq!(output, "http://example.com dns=5ms tls=- ttfb=30ms\n");
+
+        let response = ResponseInfo {
+            time_unit: TimeUnit::Ms,
+            ..response
+        };
+        let output = format_plain_output(&response, &None, false);
+        assert!(output.contains("| Time: 32ms | DNS: 5ms | Connect: 10ms | TLS: - | TTFB: 30ms"));
+        let template = Some("%url time=%time dns=%dns".to_string());
+        let output = format_plain_output(&response, &template, false);
+        assert_eq!(output, "http://example.com time=32 dns=5\n");
+
+        let response = ResponseInfo {
+            time_unit: TimeUnit::S,
+            ..response
+        };
+        let output = format_plain_output(&response, &template, false);
+        assert_eq!(output, "http://example.com time=0.032 dns=0.005\n");
+    }
+
+    #[test]
+    fn test_format_plain_output_named_placeholders() {
+        let mut headers = HeaderMap::new();
+        headers.insert("server", "nginx".parse().unwrap());
+        let mut req_headers = HeaderMap::new();
+        req_headers.insert("user-agent", "reqs/1.0".parse().unwrap());
+        let extracted = Some(BTreeMap::from([(
+            "version".to_string(),
+            "1.2.3".to_string(),
+        )]));
+        let response = ResponseInfo {
+            extracted: &extracted,
+            headers: &headers,
+            req_headers: &Some(req_headers),
+            ..ResponseInfo::sample()
+        };
+        let template = Some(
+            "%code %header:Server ua=%req_header:User-Agent v=%extract:version x=%header:X-Missing 100%% %dns %foo"
+                .to_string(),
+        );
+        assert_eq!(
+            format_plain_output(&response, &template, false),
+            "200 nginx ua=reqs/1.0 v=1.2.3 x= 100% %dns %foo\n"
+        );
+    }
+
+    #[test]
+    fn test_read_template_file() {
+        let path = std::env::temp_dir().join(format!("reqs-strf-{}.txt", std::process::id()));
+        std::fs::write(&path, "## %url\r\nStatus: %code\r\n").unwrap();
+        let template = read_template_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(template, "## %url\r\nStatus: %code");
+        assert!(read_template_file("/nonexistent/template.txt").is_err());
+    }
+
+    #[test]
+    fn test_format_backup_plain_output() {
+        let finding = BackupFinding {
+            url: "https://example.com/config.php.bak".to_string(),
+            source: "https://example.com/config.php".to_string(),
+            status: 200,
+            size: 2048,
+        };
+        assert_eq!(
+            format_backup_plain_output(&finding, false),
+            "[BACKUP] [https://example.com/config.php.bak] -> 200 | Size: 2048 | Of: https://example.com/config.php\n"
+        );
+    }
+
+    #[test]
+    fn test_format_error_plain_output() {
+        let mut error = RequestError {
+            message: "error sending request".to_string(),
+            kind: ErrorKind::ConnectRefused,
+            proxy: None,
+            failed_at: None,
+        };
+        assert_eq!(
+            format_error_plain_output("GET", "http://127.0.0.1:1/", &error, false),
+            "[ERROR] [GET] [http://127.0.0.1:1/] -> connect_refused: error sending request\n"
+        );
+
+        error.proxy = Some("http://10.0.0.7:3128/".to_string());
+        error.failed_at = Some(FailedAt::Proxy);
+        assert_eq!(
+            format_error_plain_output("GET", "https://example.com/", &error, false),
+            "[ERROR] [GET] [https://example.com/] -> connect_refused at proxy http://10.0.0.7:3128/: error sending request\n"
+        );
+    }
+
+    #[test]
+    fn test_format_invalid_url_plain_output() {
+        let invalid = InvalidUrl {
+            url: "https://exa mple.com/".to_string(),
+            reason: "invalid domain character".to_string(),
+        };
+        assert_eq!(
+            format_invalid_url_plain_output("GET", &invalid, false),
+            "[INVALID] [GET] [https://exa mple.com/] -> invalid domain character\n"
+        );
     }
 }
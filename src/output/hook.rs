@@ -0,0 +1,53 @@
+use anyhow::Result;
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::utils::run_hook;
+
+/// Pipes result records as JSON lines to an external command (--post-hook)
+///
+/// With a batch size of 1 the command runs once per record; otherwise
+/// records are collected and the command gets a batch at a time, with the
+/// remainder sent by [`PostHook::flush`] when the run ends.
+pub struct PostHook {
+    command: String,
+    batch_size: usize,
+    buffer: Mutex<Vec<String>>,
+}
+
+impl PostHook {
+    pub fn new(command: &str, batch_size: usize) -> Self {
+        PostHook {
+            command: command.to_string(),
+            batch_size,
+            buffer: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Queue a record, running the command once a batch is full
+    pub async fn send(&self, record: &Value) -> Result<()> {
+        let batch = {
+            let mut buffer = self.buffer.lock().await;
+            buffer.push(record.to_string());
+            if buffer.len() < self.batch_size {
+                return Ok(());
+            }
+            std::mem::take(&mut *buffer)
+        };
+        self.run(batch).await
+    }
+
+    /// Run the command for any records still queued
+    pub async fn flush(&self) -> Result<()> {
+        let batch = std::mem::take(&mut *self.buffer.lock().await);
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.run(batch).await
+    }
+
+    async fn run(&self, batch: Vec<String>) -> Result<()> {
+        let input: String = batch.into_iter().map(|line| line + "\n").collect();
+        run_hook(&self.command, input).await
+    }
+}
@@ -0,0 +1,42 @@
+use anyhow::{Context, Result, anyhow};
+use rdkafka::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use serde_json::Value;
+use std::time::Duration;
+
+/// Publishes every record to a Kafka topic (--output-kafka)
+///
+/// Records are sent as JSON, keyed by URL so all results for a URL land on
+/// the same partition.
+pub struct KafkaPublisher {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaPublisher {
+    pub fn new(brokers: &str, topic: &str, timeout: u64) -> Result<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("message.timeout.ms", (timeout * 1000).to_string())
+            .create()
+            .with_context(|| format!("Failed to create Kafka producer for: {}", brokers))?;
+        Ok(KafkaPublisher {
+            producer,
+            topic: topic.to_string(),
+        })
+    }
+
+    /// Send a record and wait for the broker to acknowledge it
+    pub async fn publish(&self, record: &Value) -> Result<()> {
+        let payload = record.to_string();
+        let key = record["url"].as_str().unwrap_or_default();
+        self.producer
+            .send(
+                FutureRecord::to(&self.topic).payload(&payload).key(key),
+                Duration::ZERO,
+            )
+            .await
+            .map_err(|(e, _)| anyhow!(e))?;
+        Ok(())
+    }
+}
@@ -1,3 +1,27 @@
+pub mod audit;
+pub mod buffer;
+pub mod burp;
+pub mod curl;
+pub mod database;
+pub mod elastic;
+pub mod endpoints;
+pub mod file;
 pub mod formatter;
+pub mod hook;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod notify;
+pub mod record;
+pub mod redis;
+pub mod rejects;
+pub mod report;
+pub mod sink;
+pub mod split;
 
-pub use formatter::{ResponseInfo, format_plain_output};
+pub use formatter::{
+    ResponseInfo, format_backup_plain_output, format_error_plain_output,
+    format_invalid_url_plain_output, format_plain_output, format_tcp_plain_output,
+    read_template_file,
+};
+pub use record::ResponseData;
+pub use sink::OutputSinks;
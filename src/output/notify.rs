@@ -0,0 +1,159 @@
+use anyhow::{Result, bail};
+use reqwest::Client;
+use serde_json::{Value, json};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+use crate::constants::NOTIFY_QUEUE_SIZE;
+use crate::types::NotifyFormat;
+
+/// Sends a webhook notification for every response that passes the filters
+///
+/// Notifications are queued and posted by a background task, so a slow or
+/// unreachable webhook never holds up requests. When the queue is full, new
+/// notifications are dropped with a warning.
+pub struct WebhookNotifier {
+    queue: Mutex<Option<mpsc::Sender<Value>>>,
+    worker: tokio::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl WebhookNotifier {
+    /// Create a notifier posting to `url`, each post giving up after `timeout` seconds
+    pub fn new(url: &str, format: NotifyFormat, timeout: u64) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .build()?;
+        let url = url.to_string();
+        let (tx, mut rx) = mpsc::channel::<Value>(NOTIFY_QUEUE_SIZE);
+        let worker = tokio::spawn(async move {
+            while let Some(record) = rx.recv().await {
+                if let Err(e) = post(&client, &url, &format, &record).await {
+                    error!("Error sending webhook notification: {}", e);
+                }
+            }
+        });
+        Ok(WebhookNotifier {
+            queue: Mutex::new(Some(tx)),
+            worker: tokio::sync::Mutex::new(Some(worker)),
+        })
+    }
+
+    /// Queue a notification for a response record
+    pub fn notify(&self, record: Value) {
+        let queue = self.queue.lock().unwrap();
+        let Some(tx) = queue.as_ref() else {
+            return;
+        };
+        match tx.try_send(record) {
+            Ok(()) => {}
+            Err(TrySendError::Full(record)) => warn!(
+                "Webhook notification queue is full, dropping notification for {}",
+                record["url"].as_str().unwrap_or_default()
+            ),
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+
+    /// Send the queued notifications, waiting at most `timeout` for them
+    pub async fn finish(&self, timeout: Duration) {
+        self.queue.lock().unwrap().take();
+        let Some(worker) = self.worker.lock().await.take() else {
+            return;
+        };
+        let abort = worker.abort_handle();
+        if tokio::time::timeout(timeout, worker).await.is_err() {
+            abort.abort();
+            warn!(
+                "Gave up on webhook notifications still queued after {:?}",
+                timeout
+            );
+        }
+    }
+}
+
+/// Post a notification for a response record
+async fn post(client: &Client, url: &str, format: &NotifyFormat, record: &Value) -> Result<()> {
+    let resp = client
+        .post(url)
+        .json(&webhook_payload(format, record))
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        bail!("webhook returned {}", resp.status());
+    }
+    Ok(())
+}
+
+/// Build the webhook body for a response record
+///
+/// Generic webhooks receive the record itself; Slack and Discord receive a
+/// one-line summary in the field their incoming webhooks expect.
+pub fn webhook_payload(format: &NotifyFormat, record: &Value) -> Value {
+    let mut message = format!(
+        "[reqs] {} {} {} | Size: {} | Time: {}ms",
+        record["status_code"],
+        record["method"].as_str().unwrap_or_default(),
+        record["url"].as_str().unwrap_or_default(),
+        record["content_length"],
+        record["response_time_ms"],
+    );
+    if let Some(title) = record["title"].as_str() {
+        message.push_str(&format!(" | Title: {}", title));
+    }
+
+    match format {
+        NotifyFormat::Generic => record.clone(),
+        NotifyFormat::Slack => json!({ "text": message }),
+        NotifyFormat::Discord => json!({ "content": message }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_payload() {
+        let record = json!({
+            "method": "GET",
+            "url": "https://example.com/admin",
+            "status_code": 200,
+            "content_length": 512,
+            "response_time_ms": 42,
+            "title": "Admin",
+        });
+
+        assert_eq!(webhook_payload(&NotifyFormat::Generic, &record), record);
+        assert_eq!(
+            webhook_payload(&NotifyFormat::Slack, &record)["text"],
+            "[reqs] 200 GET https://example.com/admin | Size: 512 | Time: 42ms | Title: Admin"
+        );
+        assert!(
+            webhook_payload(&NotifyFormat::Discord, &record)["content"]
+                .as_str()
+                .unwrap()
+                .starts_with("[reqs] 200 GET")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notify_does_not_wait_for_webhook() {
+        // A webhook that accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let notifier = WebhookNotifier::new(&url, NotifyFormat::Generic, 30).unwrap();
+
+        let start = std::time::Instant::now();
+        for i in 0..NOTIFY_QUEUE_SIZE * 2 {
+            notifier.notify(json!({ "url": format!("https://example.com/{}", i) }));
+        }
+        notifier.finish(Duration::from_millis(200)).await;
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Notifications after the end of the run are ignored
+        notifier.notify(json!({}));
+    }
+}
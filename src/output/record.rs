@@ -0,0 +1,758 @@
+use reqwest::header::HeaderMap;
+use serde_json::{Value, json};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::filter::MatchContext;
+use crate::http::timing::duration_ms;
+use crate::http::{
+    BackupFinding, ByteCount, RequestError, SseInfo, TcpProbe, TimingDetail, WebSocketInfo,
+};
+use crate::output::curl::format_curl;
+use crate::output::elastic::rfc3339;
+use crate::output::{
+    ResponseInfo, format_backup_plain_output, format_error_plain_output,
+    format_invalid_url_plain_output, format_plain_output, format_tcp_plain_output,
+};
+use crate::state::StateChange;
+use crate::types::{Cli, OutputFormat};
+use crate::utils::{
+    AsnInfo, CdnInfo, CertExpiry, FormInfo, InvalidUrl, JwtInfo, PageMeta, snippet, url_parts,
+};
+
+/// Response data shared by all output sinks
+pub struct ResponseData<'a> {
+    pub method: &'a str,
+    pub url_str: &'a str,
+    pub ip_addr: &'a str,
+    /// All addresses of the host (--resolved-ips)
+    pub resolved_ips: &'a Option<Vec<String>>,
+    /// ASN, organization and country of the connected IP (--enrich-asn)
+    pub asn: &'a Option<AsnInfo>,
+    /// CDN and WAF in front of the host (--detect-cdn)
+    pub cdn: &'a Option<CdnInfo>,
+    /// Expiry of the server certificate (--cert-expiry-warn)
+    pub cert: &'a Option<CertExpiry>,
+    /// Bytes sent and received (--count-bytes)
+    pub bytes: &'a Option<ByteCount>,
+    /// ID sent in the --trace-header header
+    pub trace_id: &'a Option<String>,
+    /// Labels of the input line (` #TAG`)
+    pub tags: &'a [String],
+    pub status: reqwest::StatusCode,
+    pub size: u64,
+    pub elapsed: Duration,
+    pub headers: &'a HeaderMap,
+    pub title: &'a Option<String>,
+    pub meta: &'a Option<PageMeta>,
+    pub forms: &'a Option<Vec<FormInfo>>,
+    /// Endpoints referenced in a JavaScript response
+    pub endpoints: &'a Option<Vec<String>>,
+    /// Decoded JWTs (--decode-jwt)
+    pub jwts: &'a Option<Vec<JwtInfo>>,
+    pub reflected: &'a Option<Vec<String>>,
+    pub directory_listing: Option<bool>,
+    /// Score of the response (--score-rules)
+    pub score: Option<i64>,
+    /// Change since the last run (--state-db)
+    pub state: &'a Option<StateChange>,
+    /// Headers of the sent request, when a template uses %req_header
+    pub req_headers: &'a Option<HeaderMap>,
+    /// Values of --extract patterns
+    pub extracted: &'a Option<BTreeMap<String, String>>,
+    pub req_for_display: &'a Option<String>,
+    pub body_text: &'a Option<String>,
+    pub timing: &'a Option<TimingDetail>,
+    pub websocket: &'a Option<WebSocketInfo>,
+    pub sse: &'a Option<SseInfo>,
+    /// Byte range of the filter match in the body
+    pub filter_match: Option<Range<usize>>,
+    /// Filter match and its surroundings (--match-context)
+    pub match_context: &'a Option<MatchContext>,
+}
+
+impl<'a> ResponseData<'a> {
+    /// A response with only what every response has; the optional fields are unset
+    pub fn new(
+        method: &'a str,
+        url_str: &'a str,
+        ip_addr: &'a str,
+        status: reqwest::StatusCode,
+        headers: &'a HeaderMap,
+    ) -> Self {
+        ResponseData {
+            method,
+            url_str,
+            ip_addr,
+            resolved_ips: &None,
+            asn: &None,
+            cdn: &None,
+            cert: &None,
+            bytes: &None,
+            trace_id: &None,
+            tags: &[],
+            status,
+            size: 0,
+            elapsed: Duration::ZERO,
+            headers,
+            title: &None,
+            meta: &None,
+            forms: &None,
+            endpoints: &None,
+            jwts: &None,
+            reflected: &None,
+            directory_listing: None,
+            score: None,
+            state: &None,
+            req_headers: &None,
+            extracted: &None,
+            req_for_display: &None,
+            body_text: &None,
+            timing: &None,
+            websocket: &None,
+            sse: &None,
+            filter_match: None,
+            match_context: &None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl ResponseData<'static> {
+    /// A `200 OK` response to `GET https://example.com` with nothing optional
+    /// set, for tests to override the fields they need
+    pub fn sample() -> Self {
+        static HEADERS: std::sync::LazyLock<HeaderMap> = std::sync::LazyLock::new(HeaderMap::new);
+        ResponseData {
+            size: 10,
+            elapsed: Duration::from_millis(5),
+            ..ResponseData::new(
+                "GET",
+                "https://example.com",
+                "1.2.3.4",
+                reqwest::StatusCode::OK,
+                &HEADERS,
+            )
+        }
+    }
+}
+
+/// CSV header line matching the columns written by [`format_record`]
+pub fn csv_header(cli: &Cli) -> String {
+    let header_columns: String = cli
+        .csv_headers
+        .iter()
+        .map(|name| format!(",{}", name))
+        .collect();
+    if !cli.fields.is_empty() {
+        return format!("{}{}\n", cli.fields.join(","), header_columns);
+    }
+    let mut csv_header =
+        "method,url,ip_address,status_code,content_length,response_time_ms".to_string();
+    if cli.resolved_ips {
+        csv_header.push_str(",resolved_ips");
+    }
+    if cli.enrich_asn.is_some() {
+        csv_header.push_str(",asn,as_org,country");
+    }
+    if cli.detect_cdn {
+        csv_header.push_str(",cdn,waf");
+    }
+    if cli.cert_expiry_warn.is_some() {
+        csv_header.push_str(",cert_expires,cert_days_left,cert_expiring");
+    }
+    if cli.count_bytes {
+        csv_header.push_str(",bytes_up,bytes_down");
+    }
+    if cli.trace_header.is_some() {
+        csv_header.push_str(",trace_id");
+    }
+    if cli.include_title {
+        csv_header.push_str(",title");
+    }
+    if cli.include_meta {
+        csv_header.push_str(",meta_description,meta_generator,canonical_url");
+    }
+    if cli.extract_forms {
+        csv_header.push_str(",forms");
+    }
+    if cli.extract_js_endpoints {
+        csv_header.push_str(",endpoints");
+    }
+    if cli.decode_jwt {
+        csv_header.push_str(",jwts");
+    }
+    if cli.detect_reflection {
+        csv_header.push_str(",reflected");
+    }
+    if cli.detect_listing {
+        csv_header.push_str(",directory_listing");
+    }
+    if cli.score_rules.is_some() {
+        csv_header.push_str(",score");
+    }
+    if cli.state_db.is_some() {
+        csv_header.push_str(",changed");
+    }
+    for spec in &cli.extract {
+        csv_header.push(',');
+        csv_header.push_str(
+            spec.split_once('=')
+                .map_or(spec.as_str(), |(name, _)| name.trim()),
+        );
+    }
+    if cli.timing_detail {
+        csv_header.push_str(",dns_ms,connect_ms,tls_ms,ttfb_ms,download_ms");
+    }
+    if cli.match_context.is_some() {
+        csv_header.push_str(",match_context");
+    }
+    csv_header.push_str(&header_columns);
+    csv_header.push('\n');
+    csv_header
+}
+
+/// Response record as a JSON object (used by JSONL output and exporters)
+pub fn record_json(cli: &Cli, data: &ResponseData) -> Value {
+    let mut json_output = json!({
+        "method": data.method,
+        "url": data.url_str,
+        "ip_address": data.ip_addr,
+        "status_code": data.status.as_u16(),
+        "content_length": data.size,
+        "response_time_ms": data.elapsed.as_millis(),
+    });
+    if let Some(parts) = url_parts(data.url_str) {
+        json_output["scheme"] = parts.scheme.into();
+        json_output["host"] = parts.host.into();
+        json_output["port"] = parts.port.into();
+        json_output["path"] = parts.path.into();
+    }
+    if !data.tags.is_empty() {
+        json_output["tags"] = data.tags.into();
+    }
+    if let Some(ips) = data.resolved_ips {
+        json_output["resolved_ips"] = ips.as_slice().into();
+    }
+    if let Some(asn) = data.asn {
+        json_output["asn"] = asn.asn.into();
+        json_output["as_org"] = asn.org.as_deref().into();
+        json_output["country"] = asn.country.as_deref().into();
+    }
+    if let Some(cdn) = data.cdn {
+        json_output["cdn"] = cdn.cdn.into();
+        json_output["waf"] = cdn.waf.into();
+    }
+    if let Some(cert) = data.cert {
+        json_output["cert_expires"] = rfc3339(cert.not_after).into();
+        json_output["cert_days_left"] = cert.days_left.into();
+        json_output["cert_expiring"] = cert.expiring.into();
+    }
+    if let Some(bytes) = data.bytes {
+        json_output["bytes_up"] = bytes.up.into();
+        json_output["bytes_down"] = bytes.down.into();
+    }
+    if let Some(trace_id) = data.trace_id {
+        json_output["trace_id"] = trace_id.as_str().into();
+    }
+    if let Some(t) = data.title {
+        json_output["title"] = t.as_str().into();
+    }
+    if let Some(meta) = data.meta {
+        json_output["meta"] = meta.to_json();
+    }
+    if let Some(forms) = data.forms {
+        json_output["forms"] = forms.iter().map(FormInfo::to_json).collect();
+    }
+    if let Some(endpoints) = data.endpoints {
+        json_output["endpoints"] = endpoints.as_slice().into();
+    }
+    if let Some(jwts) = data.jwts {
+        json_output["jwts"] = jwts.iter().map(JwtInfo::to_json).collect();
+    }
+    if let Some(reflected) = data.reflected {
+        json_output["reflected"] = reflected.as_slice().into();
+    }
+    if let Some(listing) = data.directory_listing {
+        json_output["directory_listing"] = listing.into();
+    }
+    if let Some(score) = data.score {
+        json_output["score"] = score.into();
+    }
+    if let Some(state) = data.state {
+        json_output["changed"] = state.changed().into();
+        json_output["state"] = state.to_json();
+    }
+    if let Some(extracted) = data.extracted {
+        json_output["extracted"] = json!(extracted);
+    }
+    if let Some(timing) = data.timing {
+        json_output["timing"] = timing.to_json();
+    }
+    if let Some(websocket) = data.websocket {
+        json_output["websocket"] = websocket.to_json();
+    }
+    if let Some(sse) = data.sse {
+        json_output["sse"] = sse.to_json();
+    }
+    if let Some(context) = data.match_context {
+        json_output["match_context"] = context.to_json();
+    }
+    if cli.include_req
+        && let Some(req) = data.req_for_display
+    {
+        json_output["raw_request"] = req.as_str().into();
+    }
+    if cli.include_res
+        && let Some(body) = response_body(cli, data)
+    {
+        json_output["response_body"] = body.into();
+    }
+    json_output
+}
+
+/// Response body for --include-res, cut to --res-max-chars
+fn response_body<'b>(cli: &Cli, data: &'b ResponseData) -> Option<Cow<'b, str>> {
+    let body = data.body_text.as_deref()?;
+    Some(match cli.res_max_chars {
+        Some(max_chars) => {
+            let around = data
+                .filter_match
+                .clone()
+                .filter(|_| cli.res_snippet_around_match);
+            snippet(body, max_chars, around)
+        }
+        None => Cow::Borrowed(body),
+    })
+}
+
+/// Format a response record in the given output format
+pub fn format_record(
+    cli: &Cli,
+    format: &OutputFormat,
+    colored: bool,
+    data: &ResponseData,
+) -> String {
+    match format {
+        OutputFormat::Plain => {
+            let response_info = ResponseInfo {
+                method: data.method,
+                url: data.url_str,
+                ip_addr: data.ip_addr,
+                resolved_ips: data.resolved_ips,
+                asn: data.asn,
+                cdn: data.cdn,
+                cert: data.cert,
+                bytes: data.bytes,
+                trace_id: data.trace_id,
+                tags: data.tags,
+                status: data.status,
+                size: data.size,
+                elapsed: data.elapsed,
+                title: data.title,
+                meta: data.meta,
+                forms: data.forms,
+                endpoints: data.endpoints,
+                jwts: data.jwts,
+                reflected: data.reflected,
+                directory_listing: data.directory_listing,
+                score: data.score,
+                state: data.state,
+                headers: data.headers,
+                req_headers: data.req_headers,
+                extracted: data.extracted,
+                timing: data.timing,
+                websocket: data.websocket,
+                sse: data.sse,
+                match_context: data.match_context,
+                time_unit: cli.time_unit,
+            };
+            let mut s = format_plain_output(&response_info, &cli.strf, colored);
+            if cli.include_req
+                && let Some(raw_req) = data.req_for_display
+            {
+                s.push_str(&format!("[Raw Request]\n{}\n", raw_req));
+            }
+            if cli.include_res
+                && let Some(body) = response_body(cli, data)
+            {
+                s.push_str(&format!("[Response Body]\n{}\n", body));
+            }
+            s
+        }
+        OutputFormat::Jsonl => format_jsonl_line(cli, &record_json(cli, data)),
+        OutputFormat::Csv if !cli.fields.is_empty() => {
+            let mut csv_line = format_csv_fields(&cli.fields, &record_json(cli, data));
+            csv_line.pop();
+            csv_line.push_str(&format_csv_headers(cli, data.headers));
+            csv_line.push('\n');
+            csv_line
+        }
+        OutputFormat::Csv => {
+            let mut csv_line = format!(
+                "\"{}\",\"{}\",\"{}\",\"{}\",\"{}\",\"{}\"",
+                data.method,
+                data.url_str,
+                data.ip_addr,
+                data.status.as_u16(),
+                data.size,
+                data.elapsed.as_millis()
+            );
+            if cli.resolved_ips {
+                let ips = data.resolved_ips.as_deref().unwrap_or_default();
+                csv_line.push_str(&format!(",\"{}\"", ips.join(",")));
+            }
+            if cli.enrich_asn.is_some() {
+                let asn = data.asn.clone().unwrap_or_default();
+                csv_line.push_str(&format!(
+                    ",\"{}\",\"{}\",\"{}\"",
+                    asn.asn.map(|asn| asn.to_string()).unwrap_or_default(),
+                    asn.org.unwrap_or_default(),
+                    asn.country.unwrap_or_default()
+                ));
+            }
+            if cli.detect_cdn {
+                let cdn = data.cdn.clone().unwrap_or_default();
+                csv_line.push_str(&format!(
+                    ",\"{}\",\"{}\"",
+                    cdn.cdn.unwrap_or_default(),
+                    cdn.waf.unwrap_or_default()
+                ));
+            }
+            if cli.cert_expiry_warn.is_some() {
+                match data.cert {
+                    Some(cert) => csv_line.push_str(&format!(
+                        ",\"{}\",\"{}\",\"{}\"",
+                        rfc3339(cert.not_after),
+                        cert.days_left,
+                        cert.expiring
+                    )),
+                    None => csv_line.push_str(",\"\",\"\",\"\""),
+                }
+            }
+            if cli.count_bytes {
+                match data.bytes {
+                    Some(bytes) => {
+                        csv_line.push_str(&format!(",\"{}\",\"{}\"", bytes.up, bytes.down))
+                    }
+                    None => csv_line.push_str(",\"\",\"\""),
+                }
+            }
+            if cli.trace_header.is_some() {
+                csv_line.push_str(&format!(
+                    ",\"{}\"",
+                    data.trace_id.as_deref().unwrap_or_default()
+                ));
+            }
+            if cli.include_title {
+                csv_line.push_str(&format!(
+                    ",\"{}\"",
+                    data.title.as_deref().unwrap_or_default()
+                ));
+            }
+            if cli.include_meta {
+                let meta = data.meta.clone().unwrap_or_default();
+                csv_line.push_str(&format!(
+                    ",\"{}\",\"{}\",\"{}\"",
+                    meta.description.unwrap_or_default(),
+                    meta.generator.unwrap_or_default(),
+                    meta.canonical.unwrap_or_default()
+                ));
+            }
+            if cli.extract_forms {
+                let forms = data.forms.as_ref().map_or(0, Vec::len);
+                csv_line.push_str(&format!(",\"{}\"", forms));
+            }
+            if cli.extract_js_endpoints {
+                let endpoints = data.endpoints.as_ref().map_or(0, Vec::len);
+                csv_line.push_str(&format!(",\"{}\"", endpoints));
+            }
+            if cli.decode_jwt {
+                let jwts = data.jwts.as_ref().map_or(0, Vec::len);
+                csv_line.push_str(&format!(",\"{}\"", jwts));
+            }
+            if cli.detect_reflection {
+                let reflected = data.reflected.as_deref().unwrap_or_default();
+                csv_line.push_str(&format!(",\"{}\"", reflected.join(";")));
+            }
+            if cli.detect_listing {
+                let listing = data.directory_listing.unwrap_or_default();
+                csv_line.push_str(&format!(",\"{}\"", listing));
+            }
+            if cli.score_rules.is_some() {
+                let score = data
+                    .score
+                    .map(|score| score.to_string())
+                    .unwrap_or_default();
+                csv_line.push_str(&format!(",\"{}\"", score));
+            }
+            if cli.state_db.is_some() {
+                let changed = data.state.as_ref().is_some_and(StateChange::changed);
+                csv_line.push_str(&format!(",\"{}\"", changed));
+            }
+            for spec in &cli.extract {
+                let name = spec
+                    .split_once('=')
+                    .map_or(spec.as_str(), |(name, _)| name.trim());
+                let value = data.extracted.as_ref().and_then(|values| values.get(name));
+                csv_line.push_str(&format!(",\"{}\"", value.map_or("", String::as_str)));
+            }
+            if cli.timing_detail {
+                let phase = |d: Option<Duration>| d.map(|d| duration_ms(d).to_string());
+                match data.timing {
+                    Some(timing) => csv_line.push_str(&format!(
+                        ",\"{}\",\"{}\",\"{}\",\"{}\",\"{}\"",
+                        phase(timing.dns).unwrap_or_default(),
+                        phase(timing.connect).unwrap_or_default(),
+                        phase(timing.tls).unwrap_or_default(),
+                        duration_ms(timing.ttfb),
+                        duration_ms(timing.download)
+                    )),
+                    // Cached responses were not timed
+                    None => csv_line.push_str(",\"\",\"\",\"\",\"\",\"\""),
+                }
+            }
+            if cli.match_context.is_some() {
+                let context = data.match_context.as_ref().map(|c| c.context.as_str());
+                csv_line.push_str(&format!(
+                    ",\"{}\"",
+                    context.unwrap_or_default().replace('"', "\"\"")
+                ));
+            }
+            csv_line.push_str(&format_csv_headers(cli, data.headers));
+            csv_line.push('\n');
+            csv_line
+        }
+        OutputFormat::Curl => format_curl(cli, data),
+        // Report formats are rendered once at the end of the run
+        OutputFormat::Junit | OutputFormat::Tap => String::new(),
+    }
+}
+
+/// Format a JSON record as a JSONL line, keeping only the --fields if given
+///
+/// The object is built by hand so the fields keep the order they were given in.
+fn format_jsonl_line(cli: &Cli, record: &Value) -> String {
+    if cli.fields.is_empty() {
+        return serde_json::to_string(record).unwrap_or_default() + "\n";
+    }
+    let fields: Vec<String> = cli
+        .fields
+        .iter()
+        .map(|field| {
+            format!(
+                "{}:{}",
+                Value::from(field.as_str()),
+                record.get(field).unwrap_or(&Value::Null)
+            )
+        })
+        .collect();
+    format!("{{{}}}\n", fields.join(","))
+}
+
+/// Format the --csv-headers columns of a response, each starting with a comma
+///
+/// Repeated headers are joined with ", ".
+fn format_csv_headers(cli: &Cli, headers: &HeaderMap) -> String {
+    cli.csv_headers
+        .iter()
+        .map(|name| {
+            let values: Vec<String> = headers
+                .get_all(name)
+                .iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+                .collect();
+            format!(",\"{}\"", values.join(", ").replace('"', "\"\""))
+        })
+        .collect()
+}
+
+/// Format the --fields of a JSON record as a CSV line
+///
+/// Strings are written as-is and objects as JSON, with quotes doubled.
+fn format_csv_fields(fields: &[String], record: &Value) -> String {
+    let columns: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            let value = match record.get(field) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => s.clone(),
+                Some(other) => other.to_string(),
+            };
+            format!("\"{}\"", value.replace('"', "\"\""))
+        })
+        .collect();
+    format!("{}\n", columns.join(","))
+}
+
+/// Format the TCP probe record of a failed request (--tcp-probe)
+///
+/// CSV records carry the port state in the status column.
+pub fn format_tcp_record(
+    cli: &Cli,
+    format: &OutputFormat,
+    colored: bool,
+    method: &str,
+    url: &str,
+    probe: &TcpProbe,
+    error: &RequestError,
+) -> String {
+    let ip_addr = probe.ip_addr.as_deref().unwrap_or_default();
+    let record = || {
+        json!({
+            "method": method,
+            "url": url,
+            "ip_address": ip_addr,
+            "tcp": probe.to_json(),
+            "error": error.message,
+            "error_kind": error.kind.as_str(),
+        })
+    };
+    match format {
+        OutputFormat::Plain => {
+            format_tcp_plain_output(url, probe, &error.message, cli.time_unit, colored)
+        }
+        OutputFormat::Jsonl => format_jsonl_line(cli, &record()),
+        OutputFormat::Csv if !cli.fields.is_empty() => format_csv_fields(&cli.fields, &record()),
+        OutputFormat::Csv => format!(
+            "\"{}\",\"{}\",\"{}\",\"{}\",\"\",\"{}\"\n",
+            method,
+            url,
+            ip_addr,
+            probe.state.as_str(),
+            probe.elapsed.as_millis()
+        ),
+        // No HTTP request was answered, so there is nothing to reproduce
+        OutputFormat::Curl | OutputFormat::Junit | OutputFormat::Tap => String::new(),
+    }
+}
+
+/// Format the record of a request that got no response (--include-errors)
+///
+/// CSV records carry the error kind in the status column.
+pub fn format_error_record(
+    cli: &Cli,
+    format: &OutputFormat,
+    colored: bool,
+    method: &str,
+    url: &str,
+    error: &RequestError,
+    tags: &[String],
+) -> String {
+    let record = || {
+        let mut record = json!({
+            "method": method,
+            "url": url,
+            "error": error.message,
+            "error_kind": error.kind.as_str(),
+        });
+        if let Some(proxy) = &error.proxy {
+            record["proxy"] = proxy.as_str().into();
+        }
+        if let Some(failed_at) = error.failed_at {
+            record["failed_at"] = failed_at.as_str().into();
+        }
+        if !tags.is_empty() {
+            record["tags"] = tags.into();
+        }
+        record
+    };
+    match format {
+        OutputFormat::Plain => format_error_plain_output(method, url, error, colored),
+        OutputFormat::Jsonl => format_jsonl_line(cli, &record()),
+        OutputFormat::Csv if !cli.fields.is_empty() => format_csv_fields(&cli.fields, &record()),
+        OutputFormat::Csv => format!(
+            "\"{}\",\"{}\",\"\",\"{}\",\"\",\"\"\n",
+            method,
+            url,
+            error.kind.as_str()
+        ),
+        OutputFormat::Curl | OutputFormat::Junit | OutputFormat::Tap => String::new(),
+    }
+}
+
+/// Format the record of a backup copy found by --check-backups
+pub fn format_backup_record(
+    cli: &Cli,
+    format: &OutputFormat,
+    colored: bool,
+    finding: &BackupFinding,
+) -> String {
+    match format {
+        OutputFormat::Plain => format_backup_plain_output(finding, colored),
+        OutputFormat::Jsonl => format_jsonl_line(cli, &finding.to_json()),
+        OutputFormat::Csv if !cli.fields.is_empty() => {
+            format_csv_fields(&cli.fields, &finding.to_json())
+        }
+        OutputFormat::Csv => format!(
+            "\"GET\",\"{}\",\"\",\"{}\",\"{}\",\"\"\n",
+            finding.url, finding.status, finding.size
+        ),
+        OutputFormat::Curl | OutputFormat::Junit | OutputFormat::Tap => String::new(),
+    }
+}
+
+/// Format the record of an input URL that was rejected before sending
+///
+/// CSV records carry `invalid` in the status column.
+pub fn format_invalid_url_record(
+    cli: &Cli,
+    format: &OutputFormat,
+    colored: bool,
+    method: &str,
+    invalid: &InvalidUrl,
+) -> String {
+    let record = || {
+        json!({
+            "method": method,
+            "url": invalid.url,
+            "error": invalid.to_string(),
+        })
+    };
+    match format {
+        OutputFormat::Plain => format_invalid_url_plain_output(method, invalid, colored),
+        OutputFormat::Jsonl => format_jsonl_line(cli, &record()),
+        OutputFormat::Csv if !cli.fields.is_empty() => format_csv_fields(&cli.fields, &record()),
+        OutputFormat::Csv => format!(
+            "\"{}\",\"{}\",\"\",\"invalid\",\"\",\"\"\n",
+            method,
+            invalid.url.replace('"', "\"\"")
+        ),
+        OutputFormat::Curl | OutputFormat::Junit | OutputFormat::Tap => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_csv_headers() {
+        let cli = Cli::parse_from(["reqs", "--csv-headers", "Server,Set-Cookie,Location"]);
+        assert!(csv_header(&cli).ends_with("response_time_ms,server,set-cookie,location\n"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("server", "nginx".parse().unwrap());
+        headers.append("set-cookie", "a=1".parse().unwrap());
+        headers.append("set-cookie", "b=\"2\"".parse().unwrap());
+        assert_eq!(
+            format_csv_headers(&cli, &headers),
+            ",\"nginx\",\"a=1, b=\"\"2\"\"\",\"\""
+        );
+    }
+
+    #[test]
+    fn test_csv_timing_columns_of_cached_response() {
+        let cli = Cli::parse_from(["reqs", "--format", "csv", "--timing-detail"]);
+        let line = format_record(&cli, &cli.format, false, &ResponseData::sample());
+        assert!(csv_header(&cli).contains(",dns_ms,connect_ms,tls_ms,ttfb_ms,download_ms"));
+        assert!(line.ends_with(",\"5\",\"\",\"\",\"\",\"\",\"\"\n"));
+        assert_eq!(
+            line.matches("\",\"").count(),
+            csv_header(&cli).matches(',').count()
+        );
+    }
+}
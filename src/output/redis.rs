@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+use redis::aio::MultiplexedConnection;
+use serde_json::Value;
+
+/// Publishes every record to a Redis list (--output-redis)
+///
+/// Records are pushed with LPUSH, so consumers popping from the right side
+/// (BRPOP, or another reqs with --input-redis) read them in order.
+pub struct RedisPublisher {
+    conn: MultiplexedConnection,
+    key: String,
+}
+
+impl RedisPublisher {
+    pub async fn connect(url: &str, key: &str) -> Result<Self> {
+        let client =
+            redis::Client::open(url).with_context(|| format!("Invalid Redis URL: {}", url))?;
+        let conn = client
+            .get_multiplexed_async_connection()
+            .await
+            .with_context(|| format!("Failed to connect to Redis: {}", url))?;
+        Ok(RedisPublisher {
+            conn,
+            key: key.to_string(),
+        })
+    }
+
+    /// Push a record as one line of JSON
+    pub async fn publish(&self, record: &Value) -> Result<()> {
+        let _: i64 = redis::cmd("LPUSH")
+            .arg(&self.key)
+            .arg(record.to_string())
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+}
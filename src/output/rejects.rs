@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use tokio::fs::File;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+use tracing::error;
+
+/// Destination of the reject log
+enum RejectTarget {
+    Stderr(tokio::io::Stderr),
+    File(BufWriter<File>),
+}
+
+/// Input lines that were skipped and why (--validate-urls)
+///
+/// Each reject is a JSON line with the input `line` and the `reason`.
+pub struct RejectLog {
+    name: String,
+    target: Mutex<RejectTarget>,
+}
+
+impl RejectLog {
+    /// Open the reject log; "-" writes to stderr
+    pub async fn open(path: &str) -> Result<Self> {
+        let target = if path == "-" {
+            RejectTarget::Stderr(tokio::io::stderr())
+        } else {
+            let file = File::create(path)
+                .await
+                .with_context(|| format!("Failed to create reject log: {}", path))?;
+            RejectTarget::File(BufWriter::new(file))
+        };
+        Ok(RejectLog {
+            name: if path == "-" { "stderr" } else { path }.to_string(),
+            target: Mutex::new(target),
+        })
+    }
+
+    pub async fn write(&self, line: &str, reason: &str) {
+        let record = format!("{}\n", json!({ "line": line, "reason": reason }));
+        let mut target = self.target.lock().await;
+        if let Err(e) = target.writer().write_all(record.as_bytes()).await {
+            error!("Error writing to reject log {}: {}", self.name, e);
+        }
+    }
+
+    pub async fn flush(&self) -> Result<()> {
+        Ok(self.target.lock().await.writer().flush().await?)
+    }
+}
+
+impl RejectTarget {
+    fn writer(&mut self) -> &mut (dyn AsyncWrite + Unpin + Send) {
+        match self {
+            RejectTarget::Stderr(stderr) => stderr,
+            RejectTarget::File(file) => file,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reject_log() {
+        let path = std::env::temp_dir().join(format!("reqs-rejects-{}", std::process::id()));
+        let log = RejectLog::open(path.to_str().unwrap()).await.unwrap();
+        log.write("ftp://example.com", "unsupported scheme: ftp")
+            .await;
+        log.write("{\"url\":", "invalid JSON").await;
+        log.flush().await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            content,
+            "{\"line\":\"ftp://example.com\",\"reason\":\"unsupported scheme: ftp\"}\n\
+             {\"line\":\"{\\\"url\\\":\",\"reason\":\"invalid JSON\"}\n"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use crate::types::OutputFormat;
+
+/// Result of a single test case in a report
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub name: String,
+    pub elapsed: Duration,
+    /// Failure reason, or None if the test passed
+    pub failure: Option<String>,
+}
+
+/// Render test results in a report format (--format junit/tap)
+pub fn render_report(format: &OutputFormat, suite: &str, results: &[TestResult]) -> String {
+    match format {
+        OutputFormat::Junit => render_junit(suite, results),
+        OutputFormat::Tap => render_tap(results),
+        _ => String::new(),
+    }
+}
+
+/// Render test results as a JUnit XML report
+pub fn render_junit(suite: &str, results: &[TestResult]) -> String {
+    let failures = results.iter().filter(|r| r.failure.is_some()).count();
+    let total: Duration = results.iter().map(|r| r.elapsed).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        results.len(),
+        failures,
+        total.as_secs_f64()
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        xml_escape(suite),
+        results.len(),
+        failures,
+        total.as_secs_f64()
+    ));
+    for result in results {
+        let open = format!(
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            xml_escape(&result.name),
+            xml_escape(suite),
+            result.elapsed.as_secs_f64()
+        );
+        match &result.failure {
+            Some(reason) => xml.push_str(&format!(
+                "{}>\n      <failure message=\"{}\"/>\n    </testcase>\n",
+                open,
+                xml_escape(reason)
+            )),
+            None => xml.push_str(&format!("{}/>\n", open)),
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Render test results as a TAP version 13 stream
+pub fn render_tap(results: &[TestResult]) -> String {
+    let mut tap = format!("TAP version 13\n1..{}\n", results.len());
+    for (i, result) in results.iter().enumerate() {
+        let name = result.name.replace('#', "\\#");
+        match &result.failure {
+            None => tap.push_str(&format!("ok {} - {}\n", i + 1, name)),
+            Some(reason) => {
+                // JSON strings are valid YAML scalars, so the message needs no extra escaping
+                tap.push_str(&format!(
+                    "not ok {} - {}\n  ---\n  message: {}\n  duration_ms: {}\n  ...\n",
+                    i + 1,
+                    name,
+                    serde_json::Value::from(reason.as_str()),
+                    result.elapsed.as_millis()
+                ));
+            }
+        }
+    }
+    tap
+}
+
+/// Escape text for use in XML attributes
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&apos;".to_string(),
+            c if c.is_control() && c != '\t' => format!("&#{};", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_junit() {
+        let results = vec![
+            TestResult {
+                name: "health".to_string(),
+                elapsed: Duration::from_millis(120),
+                failure: None,
+            },
+            TestResult {
+                name: "login <admin>".to_string(),
+                elapsed: Duration::from_millis(80),
+                failure: Some("expected status 200, got 500".to_string()),
+            },
+        ];
+
+        let xml = render_junit("api", &results);
+        assert!(xml.contains("<testsuites tests=\"2\" failures=\"1\" time=\"0.200\">"));
+        assert!(xml.contains("<testcase name=\"health\" classname=\"api\" time=\"0.120\"/>"));
+        assert!(xml.contains("<testcase name=\"login &lt;admin&gt;\""));
+        assert!(xml.contains("<failure message=\"expected status 200, got 500\"/>"));
+    }
+
+    #[test]
+    fn test_render_tap() {
+        let results = vec![
+            TestResult {
+                name: "GET https://a.com/#top".to_string(),
+                elapsed: Duration::from_millis(12),
+                failure: None,
+            },
+            TestResult {
+                name: "GET https://b.com".to_string(),
+                elapsed: Duration::from_millis(30),
+                failure: Some("body does not match /\"ok\"/".to_string()),
+            },
+        ];
+
+        assert_eq!(
+            render_tap(&results),
+            "TAP version 13\n1..2\nok 1 - GET https://a.com/\\#top\nnot ok 2 - GET https://b.com\n  ---\n  message: \"body does not match /\\\"ok\\\"/\"\n  duration_ms: 30\n  ...\n"
+        );
+        assert_eq!(render_report(&OutputFormat::Plain, "reqs", &results), "");
+    }
+}
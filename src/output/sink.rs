@@ -0,0 +1,809 @@
+use anyhow::{Result, bail};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::error;
+
+use crate::constants::NOTIFY_DRAIN_TIMEOUT;
+use crate::control::RunControl;
+use crate::http::{BackupFinding, RequestError, TcpProbe};
+use crate::output::audit::{AuditEntry, AuditLog};
+use crate::output::buffer::{BufferedRecord, RecordBuffer};
+use crate::output::burp::BurpExporter;
+use crate::output::database::ResultsDb;
+use crate::output::elastic::ElasticExporter;
+use crate::output::endpoints::EndpointCollector;
+use crate::output::file::{ExistingFile, OutputFile};
+use crate::output::hook::PostHook;
+#[cfg(feature = "kafka")]
+use crate::output::kafka::KafkaPublisher;
+use crate::output::notify::WebhookNotifier;
+use crate::output::record::{
+    ResponseData, csv_header, format_backup_record, format_error_record, format_invalid_url_record,
+    format_record, format_tcp_record, record_json,
+};
+use crate::output::redis::RedisPublisher;
+use crate::output::rejects::RejectLog;
+use crate::output::report::{TestResult, render_report};
+use crate::output::split::{OutputSplit, SplitFiles};
+use crate::tui::{TuiRecord, TuiSession};
+use crate::types::{Cli, Compression, OutputFormat};
+use crate::utils::InvalidUrl;
+
+/// Destination a sink writes formatted records to
+enum SinkTarget {
+    Stdout(tokio::io::Stdout),
+    File(OutputFile),
+    Split(SplitFiles),
+}
+
+impl SinkTarget {
+    /// Write the output of the record for `url`
+    async fn write(&mut self, url: &str, output: &str) -> Result<()> {
+        match self {
+            SinkTarget::Stdout(stdout) => stdout.write_all(output.as_bytes()).await?,
+            SinkTarget::File(file) => file.writer().write_all(output.as_bytes()).await?,
+            SinkTarget::Split(files) => files.write(url, output).await?,
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        match self {
+            SinkTarget::Stdout(stdout) => stdout.flush().await?,
+            SinkTarget::File(file) => file.writer().flush().await?,
+            SinkTarget::Split(files) => files.flush().await?,
+        }
+        Ok(())
+    }
+
+    /// Flush and close the file, writing the trailer of compressed output
+    async fn finish(&mut self) -> Result<()> {
+        match self {
+            SinkTarget::Stdout(stdout) => stdout.flush().await?,
+            SinkTarget::File(file) => file.close().await?,
+            SinkTarget::Split(files) => files.finish().await?,
+        }
+        Ok(())
+    }
+}
+
+/// A single output destination with its own format
+struct OutputSink {
+    name: String,
+    format: OutputFormat,
+    colored: bool,
+    urls_only: bool,
+    csv_header_written: bool,
+    /// Records held back for --sort/--group-by
+    buffer: Option<RecordBuffer>,
+    target: SinkTarget,
+}
+
+impl OutputSink {
+    async fn file(path: &str, format: OutputFormat, cli: &Cli) -> Result<Self> {
+        // Output followed during the run is written in place
+        let live = cli.line_buffered || cli.flush_interval.is_some();
+        let file =
+            OutputFile::create(path, cli.compress, ExistingFile::from_cli(cli), live).await?;
+        Ok(OutputSink {
+            name: path.to_string(),
+            format,
+            colored: false,
+            urls_only: false,
+            // An appended CSV file already has its header
+            csv_header_written: file.has_content,
+            buffer: None,
+            target: SinkTarget::File(file),
+        })
+    }
+
+    /// Sink writing records across the files of a directory (--output-split)
+    async fn split(dir: &str, split: OutputSplit, cli: &Cli) -> Result<Self> {
+        let extension = match cli.format {
+            OutputFormat::Plain => "txt",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Curl => "sh",
+            OutputFormat::Junit | OutputFormat::Tap => {
+                bail!("--output-split does not support report formats")
+            }
+        };
+        let suffix = match cli.compress {
+            Some(Compression::Gzip) => ".gz",
+            Some(Compression::Zstd) => ".zst",
+            None => "",
+        };
+        // Every file starts with its own CSV header
+        let header = if matches!(cli.format, OutputFormat::Csv) && !cli.match_only_url {
+            csv_header(cli)
+        } else {
+            String::new()
+        };
+        let files = SplitFiles::create(
+            dir,
+            split,
+            format!("{}{}", extension, suffix),
+            cli.compress,
+            header,
+            ExistingFile::from_cli(cli),
+        )
+        .await?;
+        Ok(OutputSink {
+            name: dir.to_string(),
+            format: cli.format.clone(),
+            colored: false,
+            urls_only: false,
+            csv_header_written: true,
+            buffer: None,
+            target: SinkTarget::Split(files),
+        })
+    }
+
+    fn render(&self, cli: &Cli, data: &ResponseData<'_>) -> String {
+        if self.urls_only {
+            return format!("{}\n", data.url_str);
+        }
+        format_record(cli, &self.format, self.colored, data)
+    }
+
+    /// The CSV header if this is the first CSV record of the sink, else nothing
+    fn csv_header_once(&mut self, cli: &Cli) -> String {
+        if matches!(self.format, OutputFormat::Csv) && !self.urls_only && !self.csv_header_written {
+            self.csv_header_written = true;
+            return csv_header(cli);
+        }
+        String::new()
+    }
+
+    /// Write a rendered record after the CSV header, if due, or hold it back if the sink is buffered
+    async fn emit(
+        &mut self,
+        cli: &Cli,
+        output: String,
+        record: impl FnOnce(String) -> BufferedRecord,
+    ) {
+        let header = self.csv_header_once(cli);
+        if let Some(buffer) = &mut self.buffer {
+            // The header stays ahead of the records once they are sorted
+            buffer.header.push_str(&header);
+            buffer.push(record(output));
+            return;
+        }
+        let record = record(header + &output);
+        let mut result = self.target.write(&record.url, &record.output).await;
+        if cli.line_buffered && result.is_ok() {
+            result = self.target.flush().await;
+        }
+        if let Err(e) = result {
+            error!("Error writing to output {}: {}", self.name, e);
+        }
+    }
+
+    /// Write the buffered records, sorted and grouped
+    async fn release(&mut self) -> Result<()> {
+        let Some(buffer) = &mut self.buffer else {
+            return Ok(());
+        };
+        let group_headers = matches!(self.format, OutputFormat::Plain) && !self.urls_only;
+        let output = buffer.drain(group_headers, self.colored);
+        self.target.write("", &output).await?;
+        Ok(())
+    }
+}
+
+/// Fans out every response record to all configured outputs
+///
+/// The primary sink is stdout, or the --output file when one is given; the
+/// --output-plain/--output-jsonl/--output-csv files, the --output-db database and
+/// the --export-elastic exporter are written alongside it, and --notify-webhook
+/// is called for each record, which is also published to --output-redis and
+/// --output-kafka and piped to --post-hook. Endpoints of JavaScript responses are collected
+/// for --js-endpoints-output, and the requests and responses for --export-burp.
+/// Skipped input lines go to the --validate-urls reject log, and every request
+/// sent to the --audit-log journal.
+pub struct OutputSinks {
+    sinks: Vec<Mutex<OutputSink>>,
+    database: Option<Mutex<ResultsDb>>,
+    elastic: Option<ElasticExporter>,
+    notifier: Option<WebhookNotifier>,
+    redis: Option<RedisPublisher>,
+    post_hook: Option<PostHook>,
+    #[cfg(feature = "kafka")]
+    kafka: Option<KafkaPublisher>,
+    endpoints: Option<EndpointCollector>,
+    burp: Option<BurpExporter>,
+    rejects: Option<RejectLog>,
+    audit: Option<AuditLog>,
+    tui: Option<TuiSession>,
+}
+
+impl OutputSinks {
+    /// Open all outputs requested on the command line
+    pub async fn from_cli(cli: &Cli, control: Option<Arc<RunControl>>) -> Result<Self> {
+        let mut sinks = Vec::new();
+
+        // The TUI replaces stdout output
+        let primary = match (&cli.output, cli.output_split) {
+            (Some(dir), Some(split)) => Some(OutputSink::split(dir, split, cli).await?),
+            (Some(path), None) => Some(OutputSink::file(path, cli.format.clone(), cli).await?),
+            (None, _) if cli.tui => None,
+            (None, _) => Some(OutputSink {
+                name: "stdout".to_string(),
+                format: cli.format.clone(),
+                colored: !cli.no_color,
+                urls_only: false,
+                csv_header_written: false,
+                buffer: None,
+                target: SinkTarget::Stdout(tokio::io::stdout()),
+            }),
+        };
+        if let Some(mut primary) = primary {
+            primary.urls_only = cli.match_only_url;
+            if cli.sort.is_some() || cli.group_by.is_some() {
+                primary.buffer = Some(RecordBuffer::new(cli.sort, cli.group_by));
+            }
+            sinks.push(primary);
+        }
+
+        let extra = [
+            (&cli.output_plain, OutputFormat::Plain),
+            (&cli.output_jsonl, OutputFormat::Jsonl),
+            (&cli.output_csv, OutputFormat::Csv),
+        ];
+        for (path, format) in extra {
+            if let Some(path) = path {
+                sinks.push(OutputSink::file(path, format, cli).await?);
+            }
+        }
+
+        let database = match &cli.output_db {
+            Some(path) => Some(Mutex::new(ResultsDb::open(path)?)),
+            None => None,
+        };
+
+        let elastic = match &cli.export_elastic {
+            Some(url) => Some(ElasticExporter::new(
+                url,
+                &cli.elastic_index,
+                cli.timeout,
+                cli.verify_ssl,
+            )?),
+            None => None,
+        };
+
+        let notifier = match &cli.notify_webhook {
+            Some(url) => Some(WebhookNotifier::new(
+                url,
+                cli.notify_format.clone(),
+                cli.timeout,
+            )?),
+            None => None,
+        };
+
+        let redis = match &cli.output_redis {
+            Some(url) => Some(RedisPublisher::connect(url, &cli.output_redis_key).await?),
+            None => None,
+        };
+
+        #[cfg(feature = "kafka")]
+        let kafka = match &cli.output_kafka {
+            Some(brokers) => Some(KafkaPublisher::new(brokers, &cli.kafka_topic, cli.timeout)?),
+            None => None,
+        };
+        #[cfg(not(feature = "kafka"))]
+        if cli.output_kafka.is_some() {
+            bail!("--output-kafka requires reqs built with the \"kafka\" feature");
+        }
+
+        let post_hook = cli
+            .post_hook
+            .as_deref()
+            .map(|command| PostHook::new(command, cli.post_hook_batch as usize));
+
+        let rejects = match &cli.validate_urls {
+            Some(path) => Some(RejectLog::open(path).await?),
+            None => None,
+        };
+
+        let audit = match &cli.audit_log {
+            Some(path) => Some(AuditLog::open(path).await?),
+            None => None,
+        };
+
+        Ok(OutputSinks {
+            sinks: sinks.into_iter().map(Mutex::new).collect(),
+            database,
+            elastic,
+            notifier,
+            redis,
+            post_hook,
+            #[cfg(feature = "kafka")]
+            kafka,
+            endpoints: cli
+                .js_endpoints_output
+                .as_deref()
+                .map(EndpointCollector::new),
+            burp: cli.export_burp.as_deref().map(BurpExporter::new),
+            rejects,
+            audit,
+            tui: if cli.tui {
+                Some(TuiSession::start(control)?)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Write a response record to every sink
+    pub async fn write(&self, cli: &Cli, data: &ResponseData<'_>) {
+        for sink in &self.sinks {
+            let mut sink = sink.lock().await;
+            let output = sink.render(cli, data);
+            sink.emit(cli, output, |output| BufferedRecord {
+                status: Some(data.status.as_u16()),
+                status_label: data.status.to_string(),
+                url: data.url_str.to_string(),
+                size: data.size,
+                elapsed: data.elapsed,
+                score: data.score,
+                output,
+            })
+            .await;
+        }
+
+        if let Some(tui) = &self.tui {
+            tui.send(TuiRecord {
+                method: data.method.to_string(),
+                url: data.url_str.to_string(),
+                host: host_of(data.url_str),
+                status: Some(data.status.as_u16()),
+                status_label: data.status.to_string(),
+                size: data.size,
+                elapsed: data.elapsed,
+                title: data.title.clone(),
+                request: data.req_for_display.clone().filter(|_| cli.include_req),
+                headers: data
+                    .headers
+                    .iter()
+                    .map(|(name, value)| {
+                        format!("{}: {}\n", name, String::from_utf8_lossy(value.as_bytes()))
+                    })
+                    .collect(),
+                body: data.body_text.clone(),
+            });
+        }
+
+        if let Some(collector) = &self.endpoints
+            && let Some(endpoints) = data.endpoints
+        {
+            collector.add(data.url_str, endpoints).await;
+        }
+        if let Some(burp) = &self.burp {
+            burp.add(data).await;
+        }
+
+        if let Some(database) = &self.database
+            && let Err(e) = database
+                .lock()
+                .await
+                .insert(data, cli.include_req, cli.include_res)
+        {
+            error!("Error writing to results database: {}", e);
+        }
+
+        if self.elastic.is_none()
+            && self.notifier.is_none()
+            && self.post_hook.is_none()
+            && !self.publishes()
+        {
+            return;
+        }
+        let record = record_json(cli, data);
+        if let Some(redis) = &self.redis
+            && let Err(e) = redis.publish(&record).await
+        {
+            error!("Error publishing to Redis: {:#}", e);
+        }
+        #[cfg(feature = "kafka")]
+        if let Some(kafka) = &self.kafka
+            && let Err(e) = kafka.publish(&record).await
+        {
+            error!("Error publishing to Kafka: {:#}", e);
+        }
+        if let Some(hook) = &self.post_hook
+            && let Err(e) = hook.send(&record).await
+        {
+            error!("Error running post hook: {:#}", e);
+        }
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(record.clone());
+        }
+        if let Some(elastic) = &self.elastic
+            && let Err(e) = elastic.export(record).await
+        {
+            error!("Error exporting to Elasticsearch: {}", e);
+        }
+    }
+
+    /// Whether records are published to --output-redis or --output-kafka
+    fn publishes(&self) -> bool {
+        #[cfg(feature = "kafka")]
+        if self.kafka.is_some() {
+            return true;
+        }
+        self.redis.is_some()
+    }
+
+    /// Write the TCP probe record of a failed request to every sink
+    pub async fn write_tcp_probe(
+        &self,
+        cli: &Cli,
+        method: &str,
+        url: &str,
+        probe: &TcpProbe,
+        error: &RequestError,
+    ) {
+        for sink in &self.sinks {
+            let mut sink = sink.lock().await;
+            let output = if sink.urls_only {
+                format!("{}\n", url)
+            } else {
+                format_tcp_record(cli, &sink.format, sink.colored, method, url, probe, error)
+            };
+            sink.emit(cli, output, |output| BufferedRecord {
+                status: None,
+                status_label: format!("TCP {}", probe.state.as_str()),
+                url: url.to_string(),
+                size: 0,
+                elapsed: probe.elapsed,
+                score: None,
+                output,
+            })
+            .await;
+        }
+    }
+
+    /// Write a backup copy found by --check-backups to every sink
+    pub async fn write_backup(&self, cli: &Cli, finding: &BackupFinding) {
+        for sink in &self.sinks {
+            let mut sink = sink.lock().await;
+            let output = if sink.urls_only {
+                format!("{}\n", finding.url)
+            } else {
+                format_backup_record(cli, &sink.format, sink.colored, finding)
+            };
+            sink.emit(cli, output, |output| BufferedRecord {
+                status: Some(finding.status),
+                status_label: finding.status.to_string(),
+                url: finding.url.clone(),
+                size: finding.size,
+                elapsed: Duration::ZERO,
+                score: None,
+                output,
+            })
+            .await;
+        }
+    }
+
+    /// Write the record of a request that got no response to every sink (--include-errors)
+    pub async fn write_error(
+        &self,
+        cli: &Cli,
+        method: &str,
+        url: &str,
+        error: &RequestError,
+        tags: &[String],
+    ) {
+        for sink in &self.sinks {
+            let mut sink = sink.lock().await;
+            if sink.urls_only {
+                continue;
+            }
+            let output =
+                format_error_record(cli, &sink.format, sink.colored, method, url, error, tags);
+            sink.emit(cli, output, |output| BufferedRecord {
+                status: None,
+                status_label: "ERROR".to_string(),
+                url: url.to_string(),
+                size: 0,
+                elapsed: Duration::ZERO,
+                score: None,
+                output,
+            })
+            .await;
+        }
+    }
+
+    /// Write the record of an input URL that was rejected before sending to every sink
+    pub async fn write_invalid_url(&self, cli: &Cli, method: &str, invalid: &InvalidUrl) {
+        for sink in &self.sinks {
+            let mut sink = sink.lock().await;
+            if sink.urls_only {
+                continue;
+            }
+            let output =
+                format_invalid_url_record(cli, &sink.format, sink.colored, method, invalid);
+            sink.emit(cli, output, |output| BufferedRecord {
+                status: None,
+                status_label: "INVALID".to_string(),
+                url: invalid.url.clone(),
+                size: 0,
+                elapsed: Duration::ZERO,
+                score: None,
+                output,
+            })
+            .await;
+        }
+        self.write_failure(method, &invalid.url, &invalid.to_string());
+    }
+
+    /// Record a skipped input line in the --validate-urls reject log
+    pub async fn write_reject(&self, line: &str, reason: &str) {
+        if let Some(rejects) = &self.rejects {
+            rejects.write(line, reason).await;
+        }
+    }
+
+    /// Journal a request about to be sent in the --audit-log
+    pub async fn write_audit(&self, entry: &AuditEntry<'_>) {
+        if let Some(audit) = &self.audit {
+            audit.write(entry).await;
+        }
+    }
+
+    /// Show a request that failed without a response in the TUI
+    pub fn write_failure(&self, method: &str, url: &str, error: &str) {
+        if let Some(tui) = &self.tui {
+            tui.send(TuiRecord {
+                method: method.to_string(),
+                url: url.to_string(),
+                host: host_of(url),
+                status_label: error.to_string(),
+                ..Default::default()
+            });
+        }
+    }
+
+    /// Whether the user closed the TUI
+    pub fn tui_quit(&self) -> bool {
+        self.tui.as_ref().is_some_and(TuiSession::quit)
+    }
+
+    /// Wait for the user to close the TUI at the end of the run
+    pub async fn finish_tui(&self) -> Result<()> {
+        match &self.tui {
+            Some(tui) => tui.finish().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Write the end-of-run test report to every sink with a report format
+    pub async fn write_report(&self, suite: &str, results: &[TestResult]) {
+        for sink in &self.sinks {
+            let mut sink = sink.lock().await;
+            if !sink.format.is_report() || sink.urls_only {
+                continue;
+            }
+            let report = render_report(&sink.format, suite, results);
+            if let Err(e) = sink.target.write("", &report).await {
+                error!("Error writing to output {}: {}", sink.name, e);
+            }
+        }
+    }
+
+    /// Flush buffered output of every sink, writing records held back for --sort/--group-by
+    pub async fn flush(&self) -> Result<()> {
+        for sink in &self.sinks {
+            let mut sink = sink.lock().await;
+            sink.release().await?;
+            sink.target.flush().await?;
+        }
+        if let Some(collector) = &self.endpoints {
+            collector.save().await?;
+        }
+        if let Some(burp) = &self.burp {
+            burp.save().await?;
+        }
+        if let Some(rejects) = &self.rejects {
+            rejects.flush().await?;
+        }
+        if let Some(elastic) = &self.elastic
+            && let Err(e) = elastic.flush().await
+        {
+            error!("Error exporting to Elasticsearch: {}", e);
+        }
+        if let Some(hook) = &self.post_hook
+            && let Err(e) = hook.flush().await
+        {
+            error!("Error running post hook: {:#}", e);
+        }
+        Ok(())
+    }
+
+    /// Flush the records written so far to every output (--flush-interval)
+    pub async fn flush_streams(&self) {
+        for sink in &self.sinks {
+            let mut sink = sink.lock().await;
+            if let Err(e) = sink.target.flush().await {
+                error!("Error flushing output {}: {}", sink.name, e);
+            }
+        }
+    }
+
+    /// Flush every output and close the files at the end of the run
+    pub async fn close(&self) -> Result<()> {
+        self.flush().await?;
+        for sink in &self.sinks {
+            sink.lock().await.target.finish().await?;
+        }
+        if let Some(notifier) = &self.notifier {
+            notifier.finish(NOTIFY_DRAIN_TIMEOUT).await;
+        }
+        Ok(())
+    }
+}
+
+/// Host of a URL, or the URL itself if it has none
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_output_sinks_fan_out() {
+        let dir = std::env::temp_dir().join(format!("reqs-sinks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let jsonl = dir.join("out.jsonl");
+        let csv = dir.join("out.csv");
+
+        let cli = Cli::parse_from([
+            "reqs",
+            "--output-jsonl",
+            jsonl.to_str().unwrap(),
+            "--output-csv",
+            csv.to_str().unwrap(),
+            "--output",
+            dir.join("out.txt").to_str().unwrap(),
+        ]);
+        let sinks = OutputSinks::from_cli(&cli, None).await.unwrap();
+
+        let data = ResponseData::sample();
+        sinks.write(&cli, &data).await;
+        sinks.write(&cli, &data).await;
+        sinks.close().await.unwrap();
+
+        let jsonl = std::fs::read_to_string(jsonl).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        assert!(jsonl.contains("\"status_code\":200"));
+
+        let csv = std::fs::read_to_string(csv).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.starts_with("method,url,"));
+
+        let plain = std::fs::read_to_string(dir.join("out.txt")).unwrap();
+        assert!(plain.starts_with("[GET] [https://example.com]"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_output_sinks_line_buffered() {
+        let path = std::env::temp_dir().join(format!("reqs-live-{}.jsonl", std::process::id()));
+        let cli = Cli::parse_from([
+            "reqs",
+            "--format",
+            "jsonl",
+            "--line-buffered",
+            "--force",
+            "--output",
+            path.to_str().unwrap(),
+        ]);
+        let sinks = OutputSinks::from_cli(&cli, None).await.unwrap();
+        let data = ResponseData::sample();
+        sinks.write(&cli, &data).await;
+
+        // Visible in the file itself before the run ends
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 1);
+
+        sinks.close().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_output_sinks_compressed() {
+        use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+        use tokio::io::AsyncReadExt;
+
+        let dir = std::env::temp_dir().join(format!("reqs-compress-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let jsonl = dir.join("out.jsonl.gz");
+        let csv = dir.join("out.csv.zst");
+
+        let cli = Cli::parse_from([
+            "reqs",
+            "--format",
+            "jsonl",
+            "--output",
+            jsonl.to_str().unwrap(),
+            "--output-csv",
+            csv.to_str().unwrap(),
+        ]);
+        let sinks = OutputSinks::from_cli(&cli, None).await.unwrap();
+        let data = ResponseData::sample();
+        sinks.write(&cli, &data).await;
+        sinks.write(&cli, &data).await;
+        sinks.close().await.unwrap();
+
+        let gz = std::fs::read(jsonl).unwrap();
+        let mut jsonl = String::new();
+        GzipDecoder::new(gz.as_slice())
+            .read_to_string(&mut jsonl)
+            .await
+            .unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        assert!(jsonl.contains("\"status_code\":200"));
+
+        let zst = std::fs::read(csv).unwrap();
+        let mut csv = String::new();
+        ZstdDecoder::new(zst.as_slice())
+            .read_to_string(&mut csv)
+            .await
+            .unwrap();
+        assert_eq!(csv.lines().count(), 3);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_output_sinks_fields() {
+        let dir = std::env::temp_dir().join(format!("reqs-fields-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let jsonl = dir.join("out.jsonl");
+        let csv = dir.join("out.csv");
+
+        let cli = Cli::parse_from([
+            "reqs",
+            "--fields",
+            "url,status_code,title",
+            "--output-jsonl",
+            jsonl.to_str().unwrap(),
+            "--output-csv",
+            csv.to_str().unwrap(),
+            "--output",
+            dir.join("out.txt").to_str().unwrap(),
+        ]);
+        let sinks = OutputSinks::from_cli(&cli, None).await.unwrap();
+
+        let data = ResponseData {
+            title: &Some("Say \"hi\"".to_string()),
+            ..ResponseData::sample()
+        };
+        sinks.write(&cli, &data).await;
+        sinks.close().await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(jsonl).unwrap(),
+            "{\"url\":\"https://example.com\",\"status_code\":200,\"title\":\"Say \\\"hi\\\"\"}\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(csv).unwrap(),
+            "url,status_code,title\n\"https://example.com\",\"200\",\"Say \"\"hi\"\"\"\n"
+        );
+        assert!(
+            Cli::try_parse_from(["reqs", "--fields", "url,bogus"]).is_err(),
+            "unknown fields are rejected"
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{Context, Result, bail};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+use crate::constants::SPLIT_OPEN_FILES;
+use crate::output::file::{ExistingFile, OutputFile};
+use crate::types::Compression;
+use crate::utils::parse_size;
+
+/// How --output-split divides the results
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputSplit {
+    /// One file per host
+    Host,
+    /// A new file once this many bytes are written
+    Size(u64),
+}
+
+/// Parse --output-split: "host" or a size such as "100MB"
+pub fn parse_output_split(value: &str) -> Result<OutputSplit, String> {
+    if value.trim().eq_ignore_ascii_case("host") {
+        return Ok(OutputSplit::Host);
+    }
+    match parse_size(value) {
+        Ok(0) => Err(format!("Invalid split '{}'. Size must be above 0", value)),
+        Ok(size) => Ok(OutputSplit::Size(size)),
+        Err(_) => Err(format!(
+            "Invalid split '{}'. Expected host or a size such as 100MB",
+            value
+        )),
+    }
+}
+
+/// Results written across the files of a directory (--output-split)
+///
+/// Files are named after the host, or numbered "part-0001" and up when split by
+/// size. Only a limited number stay open; a host seen again after its file was
+/// closed is appended to.
+pub struct SplitFiles {
+    dir: PathBuf,
+    split: OutputSplit,
+    /// File extension, including the compression suffix
+    extension: String,
+    compression: Option<Compression>,
+    /// Written at the start of every file (the CSV header)
+    header: String,
+    /// Whether files from an earlier run are appended to (--append)
+    append: bool,
+    open: HashMap<String, OutputFile>,
+    created: HashSet<String>,
+    part: u32,
+    /// Uncompressed bytes written to the current part
+    part_size: u64,
+}
+
+impl SplitFiles {
+    pub async fn create(
+        dir: &str,
+        split: OutputSplit,
+        extension: String,
+        compression: Option<Compression>,
+        header: String,
+        existing: ExistingFile,
+    ) -> Result<Self> {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("Failed to create output directory: {}", dir))?;
+        if existing == ExistingFile::Refuse && std::fs::read_dir(dir)?.next().is_some() {
+            bail!(
+                "Output directory is not empty: {} (use --force to overwrite or --append to add to it)",
+                dir
+            );
+        }
+        Ok(SplitFiles {
+            dir: PathBuf::from(dir),
+            split,
+            extension,
+            compression,
+            header,
+            append: existing == ExistingFile::Append,
+            open: HashMap::new(),
+            created: HashSet::new(),
+            part: 1,
+            part_size: 0,
+        })
+    }
+
+    /// Write the output of a record to the file of its host or the current part
+    pub async fn write(&mut self, url: &str, output: &str) -> Result<()> {
+        let name = match self.split {
+            OutputSplit::Host => host_file_name(url),
+            OutputSplit::Size(max) => {
+                let size = output.len() as u64;
+                if self.part_size > 0 && self.part_size + size > max {
+                    self.finish().await?;
+                    self.part += 1;
+                    self.part_size = 0;
+                }
+                self.part_size += size;
+                format!("part-{:04}", self.part)
+            }
+        };
+        let file = self.file(&name).await?;
+        file.writer().write_all(output.as_bytes()).await?;
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<()> {
+        for file in self.open.values_mut() {
+            file.writer().flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Close every open file
+    pub async fn finish(&mut self) -> Result<()> {
+        for (_, mut file) in self.open.drain() {
+            file.close().await?;
+        }
+        Ok(())
+    }
+
+    async fn file(&mut self, name: &str) -> Result<&mut OutputFile> {
+        if !self.open.contains_key(name) {
+            if self.open.len() >= SPLIT_OPEN_FILES
+                && let Some(evicted) = self.open.keys().next().cloned()
+                && let Some(mut file) = self.open.remove(&evicted)
+            {
+                file.close().await?;
+            }
+            let path = self.dir.join(format!("{}.{}", name, self.extension));
+            let reopened = !self.created.insert(name.to_string());
+            let append = reopened || self.append;
+            let mut file =
+                OutputFile::open(&path.to_string_lossy(), self.compression, append).await?;
+            if !file.has_content {
+                file.writer().write_all(self.header.as_bytes()).await?;
+            }
+            self.open.insert(name.to_string(), file);
+        }
+        Ok(self.open.get_mut(name).expect("file opened above"))
+    }
+}
+
+/// File name for the host of a URL, with characters unsafe in paths replaced
+fn host_file_name(url: &str) -> String {
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "other".to_string());
+    host.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output_split() {
+        assert_eq!(parse_output_split("host"), Ok(OutputSplit::Host));
+        assert_eq!(
+            parse_output_split("100MB"),
+            Ok(OutputSplit::Size(100 * 1024 * 1024))
+        );
+        assert!(parse_output_split("0").is_err());
+        assert!(parse_output_split("path").is_err());
+    }
+
+    #[test]
+    fn test_host_file_name() {
+        assert_eq!(
+            host_file_name("https://a.example.com:8443/x"),
+            "a.example.com"
+        );
+        assert_eq!(host_file_name("http://[::1]/"), "___1_");
+        assert_eq!(host_file_name("not a url"), "other");
+    }
+
+    #[tokio::test]
+    async fn test_split_files() {
+        let dir = std::env::temp_dir().join(format!("reqs-split-{}", std::process::id()));
+        let dir_str = dir.to_str().unwrap();
+
+        let mut files = SplitFiles::create(
+            dir_str,
+            OutputSplit::Host,
+            "csv".to_string(),
+            None,
+            "url\n".to_string(),
+            ExistingFile::Overwrite,
+        )
+        .await
+        .unwrap();
+        files.write("https://a.com/1", "a1\n").await.unwrap();
+        files.write("https://b.com/1", "b1\n").await.unwrap();
+        files.write("https://a.com/2", "a2\n").await.unwrap();
+        files.finish().await.unwrap();
+        // Reopened files are appended to without a second header
+        files.write("https://a.com/3", "a3\n").await.unwrap();
+        files.finish().await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("a.com.csv")).unwrap(),
+            "url\na1\na2\na3\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("b.com.csv")).unwrap(),
+            "url\nb1\n"
+        );
+
+        let mut files = SplitFiles::create(
+            dir_str,
+            OutputSplit::Size(6),
+            "txt".to_string(),
+            None,
+            String::new(),
+            ExistingFile::Overwrite,
+        )
+        .await
+        .unwrap();
+        for line in ["one\n", "two\n", "three\n"] {
+            files.write("https://a.com/", line).await.unwrap();
+        }
+        files.finish().await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("part-0001.txt")).unwrap(),
+            "one\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("part-0003.txt")).unwrap(),
+            "three\n"
+        );
+
+        assert!(
+            SplitFiles::create(
+                dir_str,
+                OutputSplit::Host,
+                "txt".to_string(),
+                None,
+                String::new(),
+                ExistingFile::Refuse,
+            )
+            .await
+            .is_err()
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,347 +1,1789 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
+use futures::future;
 use futures::stream::{self, StreamExt};
+use jiff::Zoned;
 use regex::Regex;
-use reqwest::Client;
-use serde_json::json;
-use std::io::{self, BufRead};
+use reqwest::header::{ACCEPT, AUTHORIZATION, HOST, HeaderMap, HeaderValue};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use std::io::{self, BufRead, IsTerminal};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::fs::File;
-use tokio::io::{AsyncWriteExt, BufWriter};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::Mutex;
 use tokio::task;
+use tracing::{debug, error, info, warn};
 
-use crate::filter::should_filter_response;
-use crate::http::{build_request, format_raw_request, parse_request_line};
-use crate::output::{ResponseInfo, format_plain_output};
-use crate::types::{Cli, OutputFormat};
-use crate::utils::{apply_random_delay, apply_rate_limit, extract_title, normalize_url_scheme};
+use crate::constants::{QUEUE_RETRY_MAX, QUEUE_RETRY_MIN};
+use crate::control::RunControl;
+use crate::expect::{Expectation, format_verdict};
+use crate::filter::{
+    BodyFilter, MatchContext, TitleFilter, UrlFilter, is_status_filtered, should_filter_response,
+};
+use crate::http::{
+    BackupFinding, Bandwidth, ByteCount, CachedResponse, ClientPool, ConnectRacer, ErrorKind,
+    ExactClient, FailedAt, HostAddresses, HostBreaker, LivenessCache, ProxyPool, Reflection,
+    RequestError, RequestOverrides, ResponseCache, SendError, Validators, WsProbeOptions,
+    backup_urls, build_request, classify_chain, credentials_header, default_request_headers,
+    failed_at, format_dry_run, format_raw_request, is_backup_hit, is_event_stream, is_safe_method,
+    is_websocket_url, load_har, mutate_headers, mutate_method, new_trace_id, omits_client_accept,
+    parse_har_request, parse_jsonl_request, parse_reqs_record, parse_request_line,
+    parse_request_overrides, probe_tcp, probe_websocket, read_event_stream, request_headers,
+    request_size, response_head_size, send_request, split_host_override, split_tags,
+    uses_random_user_agent, with_timing,
+};
+use crate::outcome::RunOutcome;
+use crate::output::audit::AuditEntry;
+use crate::output::report::TestResult;
+use crate::output::{OutputSinks, ResponseData};
+use crate::queue::{Claim, RedisQueue};
+use crate::score::ScoreRules;
+use crate::state::StateDb;
+use crate::summary::Summary;
+use crate::types::{Cli, InputFormat, OutputFormat, SchemeProbe};
+use crate::utils::{
+    AsnDb, CertMonitor, Extractors, RateLimiter, Redactor, TemplateVars, WindowGate,
+    apply_random_delay, detect_cdn, expand_line, extract_forms, extract_js_endpoints, extract_meta,
+    extract_title, filter_through, find_jwts, follow_lines, is_directory_listing, is_in_scope,
+    is_javascript, normalize_url, normalize_url_scheme, replace_host, split_credentials,
+    url_authority, validate_url,
+};
 
 /// Context for request processing
 struct ProcessingContext {
-    output_writer: Option<Arc<Mutex<BufWriter<File>>>>,
-    parsed_filter_regex: Arc<Option<Regex>>,
-    csv_header_written: Arc<Mutex<bool>>,
+    outputs: OutputSinks,
+    body_filter: BodyFilter,
+    title_filter: Option<TitleFilter>,
+    summary: Option<Mutex<Summary>>,
+    default_headers: HeaderMap,
+    liveness: Option<LivenessCache>,
+    racer: Option<ConnectRacer>,
+    host_addresses: Option<HostAddresses>,
+    asn_db: Option<AsnDb>,
+    rate_limiter: RateLimiter,
+    proxy_pool: Option<ProxyPool>,
+    bandwidth: Option<Bandwidth>,
+    cert_monitor: Option<CertMonitor>,
+    breaker: Option<HostBreaker>,
+    cache: Option<ResponseCache>,
+    validators: Option<Validators>,
+    outcome: RunOutcome,
+    expectation: Option<Expectation>,
+    score_rules: Option<ScoreRules>,
+    vars: TemplateVars,
+    state: Option<Mutex<StateDb>>,
+    extractors: Extractors,
+    redactor: Redactor,
+    control: Option<Arc<RunControl>>,
+    /// End of the run set by --max-runtime
+    deadline: Option<Instant>,
+    window: Option<WindowGate>,
 }
 
 /// Process URLs from stdin and send HTTP requests
-pub async fn process_urls_from_stdin(cli: Cli, client: Client) -> Result<()> {
-    let parsed_filter_regex: Arc<Option<Regex>> = Arc::new(
-        if let Some(regex_str) = &cli.filter_regex {
-            match Regex::new(regex_str) {
-                Ok(re) => Some(re),
-                Err(e) => {
-                    eprintln!(
-                        "[Warning] Invalid regex provided for --filter-regex: {}. Disabling regex filtering.",
-                        e
-                    );
-                    None
-                }
+///
+/// Returns the process exit code selected by the --fail-on-* options.
+pub async fn process_urls_from_stdin(mut cli: Cli, clients: Arc<ClientPool>) -> Result<i32> {
+    if cli.duration.is_some() && cli.concurrency == 0 {
+        bail!("--duration requires a --concurrency limit");
+    }
+
+    if let Some(start_at) = &cli.start_at {
+        let wait = start_at.wait_from(&Zoned::now());
+        if !wait.is_zero() {
+            info!("Waiting {}s for the scheduled start", wait.as_secs());
+            tokio::time::sleep(wait).await;
+        }
+    }
+    let run_start = Instant::now();
+
+    let filter_regexes = cli
+        .filter_regex
+        .iter()
+        .filter_map(|regex_str| match Regex::new(regex_str) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                warn!(
+                    "Invalid regex provided for --filter-regex: {}. Disabling this regex filter.",
+                    e
+                );
+                None
             }
-        } else {
-            None
-        },
+        })
+        .collect();
+    let body_filter = BodyFilter::new(
+        &cli.filter_string,
+        &cli.filter_string_not,
+        filter_regexes,
+        cli.filter_mode,
+        cli.filter_string_ci,
     );
 
-    let last_request_time = Arc::new(Mutex::new(Instant::now()));
+    // Runtime control of pause, concurrency and rate from the TUI or the control socket
+    let control = (cli.tui || cli.control.is_some()).then(|| {
+        let rate_limit = cli
+            .rate_limit
+            .as_ref()
+            .and_then(|rules| rules.default_value().copied());
+        Arc::new(RunControl::new(cli.concurrency, rate_limit))
+    });
+    if let (Some(control), Some(addr)) = (&control, &cli.control) {
+        control.listen(addr).await?;
+    }
 
-    let output_writer: Option<Arc<Mutex<BufWriter<File>>>> = if let Some(output_path) = &cli.output
-    {
-        let file = File::create(output_path).await?;
-        Some(Arc::new(Mutex::new(BufWriter::new(file))))
-    } else {
-        None
+    let context = Arc::new(ProcessingContext {
+        outputs: OutputSinks::from_cli(&cli, control.clone()).await?,
+        body_filter,
+        title_filter: TitleFilter::new(
+            cli.filter_title.as_deref(),
+            cli.filter_title_not.as_deref(),
+        )?,
+        summary: (cli.summary || cli.repeat > 1 || cli.duration.is_some())
+            .then(|| Mutex::new(Summary::default())),
+        default_headers: default_request_headers(&cli),
+        cache: match &cli.cache {
+            Some(dir) => Some(ResponseCache::new(dir, cli.cache_ttl)?),
+            None => None,
+        },
+        liveness: (cli.pre_check && !cli.dry_run)
+            .then(|| LivenessCache::new(cli.pre_check_timeout)),
+        asn_db: cli.enrich_asn.as_deref().map(AsnDb::open).transpose()?,
+        rate_limiter: RateLimiter::new(cli.rate_limit.clone()),
+        proxy_pool: cli.proxy_pool.as_deref().map(ProxyPool::load).transpose()?,
+        bandwidth: cli.max_bandwidth.map(Bandwidth::new),
+        cert_monitor: cli.cert_expiry_warn.map(CertMonitor::new),
+        host_addresses: cli
+            .resolved_ips
+            .then(|| HostAddresses::new(clients.resolver().clone())),
+        racer: (cli.proxy.is_none() && cli.proxy_pool.is_none() && !cli.dry_run).then(|| {
+            ConnectRacer::new(clients.resolver().clone(), Duration::from_secs(cli.timeout))
+        }),
+        breaker: cli.max_host_errors.map(HostBreaker::new),
+        validators: cli.watch.map(|_| Validators::new()),
+        outcome: RunOutcome::default(),
+        expectation: Expectation::new(&cli.expect_status, cli.expect_body_regex.as_deref())?,
+        score_rules: cli
+            .score_rules
+            .as_deref()
+            .map(ScoreRules::load)
+            .transpose()?,
+        vars: TemplateVars::from_cli(&cli)?,
+        extractors: Extractors::parse(&cli.extract)?,
+        redactor: Redactor::parse(&cli.redact, &cli.redact_pattern)?,
+        state: match &cli.state_db {
+            Some(path) => Some(Mutex::new(StateDb::open(path)?)),
+            None => None,
+        },
+        control,
+        deadline: cli.max_runtime.map(|max_runtime| run_start + max_runtime),
+        window: cli.allowed_window.map(WindowGate::new),
+    });
+
+    // HAR entries are read in place of stdin
+    let input: Vec<String> = match &cli.har {
+        Some(path) => {
+            let lines = load_har(
+                path,
+                &cli.har_host,
+                &cli.har_method,
+                &context.default_headers,
+            )?;
+            cli.input_format = InputFormat::Har;
+            lines
+        }
+        // --follow and --input-redis read their input as it arrives instead
+        None if cli.follow.is_some() || cli.input_redis.is_some() => Vec::new(),
+        None => io::stdin().lock().lines().map_while(Result::ok).collect(),
+    };
+    // --pre-hook rewrites the whole input before it is expanded and filtered
+    let input = match &cli.pre_hook {
+        Some(command) => {
+            let output = filter_through(command, input.join("\n") + "\n").await?;
+            output.lines().map(str::to_string).collect()
+        }
+        None => input,
     };
+    let url_filter = UrlFilter::new(&cli.match_url, &cli.exclude_url)?;
+    let lines: Vec<String> = input
+        .iter()
+        .flat_map(|line| input_lines(&cli, &url_filter, line))
+        .collect();
 
-    let context = Arc::new(ProcessingContext {
-        output_writer: output_writer.clone(),
-        parsed_filter_regex,
-        csv_header_written: Arc::new(Mutex::new(false)),
+    // Periodically flush file output so it can be followed during the run
+    let flusher = cli.flush_interval.map(|interval| {
+        let context = context.clone();
+        task::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                context.outputs.flush_streams().await;
+            }
+        })
     });
 
-    let stdin = io::stdin();
-    let handles = stdin
-        .lock()
-        .lines()
-        .map_while(Result::ok)
-        .map(|url| {
-            let client = client.clone();
-            let cli = cli.clone();
-            let last_request_time = last_request_time.clone();
-            let context = context.clone();
-            task::spawn(async move {
-                if url.trim().is_empty() {
-                    return;
+    if let Some(source) = &cli.follow {
+        run_follow(&cli, &clients, &context, &url_filter, source).await;
+    } else if let Some(url) = &cli.input_redis {
+        let queue = Arc::new(RedisQueue::connect(url, &cli.input_key).await?);
+        run_queue(&cli, &clients, &context, &url_filter, queue).await;
+    } else {
+        loop {
+            run_round(&cli, &clients, &context, &lines, run_start).await;
+
+            // In watch mode, re-send the input every interval until interrupted
+            let Some(interval) = cli.watch else {
+                break;
+            };
+            if context.outputs.tui_quit() || past_deadline(&context) {
+                break;
+            }
+            context.outputs.flush().await?;
+            debug!(
+                interval_ms = interval.as_millis() as u64,
+                "Waiting for next watch round"
+            );
+            match context.deadline {
+                Some(deadline) => {
+                    tokio::time::sleep(
+                        interval.min(deadline.saturating_duration_since(Instant::now())),
+                    )
+                    .await
                 }
+                None => tokio::time::sleep(interval).await,
+            }
+            if past_deadline(&context) {
+                break;
+            }
+        }
+    }
 
-                apply_random_delay(&cli.random_delay).await;
-                apply_rate_limit(cli.rate_limit, &last_request_time).await;
+    if cli.format.is_report() {
+        context
+            .outputs
+            .write_report("reqs", &context.outcome.results())
+            .await;
+    }
 
-                let (method, url_str, body) = parse_request_line(&url);
+    // Ensure all buffered output is written to file before exiting
+    if let Some(flusher) = flusher {
+        flusher.abort();
+    }
+    context.outputs.close().await?;
+    context.outputs.finish_tui().await?;
 
-                if url_str.is_empty() {
-                    return;
-                }
+    if let Some(summary) = &context.summary {
+        eprint!("{}", summary.lock().await.render(run_start.elapsed()));
+    }
+    if let Some(expectations) = context.outcome.expectation_summary() {
+        eprintln!("{}", expectations);
+    }
+    if context.outcome.unprocessed() > 0 {
+        eprintln!(
+            "Max runtime reached: {} targets not processed",
+            context.outcome.unprocessed()
+        );
+    }
+    if let Some(breaker) = &context.breaker {
+        let skipped = breaker.skipped();
+        if !skipped.is_empty() {
+            let hosts: Vec<String> = skipped
+                .iter()
+                .map(|(host, count)| format!("{} ({})", host, count))
+                .collect();
+            eprintln!("Skipped after too many errors: {}", hosts.join(", "));
+        }
+    }
 
-                let url_str = normalize_url_scheme(&url_str);
+    Ok(context.outcome.exit_code(&cli))
+}
 
-                process_single_request(&client, &cli, &method, &url_str, &body, &context).await;
-            })
+/// Expand an input line and drop it if filtered out by --match-url, --exclude-url or --shard
+fn input_lines(cli: &Cli, url_filter: &Option<UrlFilter>, line: &str) -> Vec<String> {
+    if line.trim().is_empty() {
+        return Vec::new();
+    }
+    let lines = match cli.input_format {
+        // JSON records are taken verbatim; braces in them are not expansion groups
+        InputFormat::Jsonl | InputFormat::ReqsJsonl | InputFormat::Har => vec![line.to_string()],
+        InputFormat::Plain => expand_line(line, &cli.ports, cli.encode),
+    };
+    lines
+        .into_iter()
+        .filter(|line| {
+            url_filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(line))
         })
-        .collect::<Vec<_>>();
+        .filter(|line| cli.shard.is_none_or(|shard| shard.contains(line)))
+        .collect()
+}
 
-    let concurrency_limit = if cli.concurrency == 0 {
-        None
+/// Number of requests allowed in flight, or None for unlimited
+fn concurrency_limit(cli: &Cli, context: &ProcessingContext) -> Option<usize> {
+    // Under runtime control the pool is sized for the highest concurrency it can be raised to
+    match &context.control {
+        Some(control) => control.pool_limit(),
+        None if cli.concurrency == 0 => None,
+        None => Some(cli.concurrency),
+    }
+}
+
+/// Send every input line once (or per --repeat/--duration)
+async fn run_round(
+    cli: &Cli,
+    clients: &Arc<ClientPool>,
+    context: &Arc<ProcessingContext>,
+    lines: &[String],
+    run_start: Instant,
+) {
+    // In benchmark mode, cycle through the input until the deadline or repeat it N times
+    let jobs: Box<dyn Iterator<Item = String> + Send + '_> = if let Some(duration) = cli.duration {
+        let deadline = run_start + duration;
+        Box::new(
+            lines
+                .iter()
+                .cloned()
+                .cycle()
+                .take_while(move |_| Instant::now() < deadline),
+        )
     } else {
-        Some(cli.concurrency)
+        Box::new((0..cli.repeat).flat_map(move |_| lines.iter().cloned()))
     };
 
-    stream::iter(handles)
-        .for_each_concurrent(concurrency_limit, |h| async {
-            h.await.unwrap();
+    // Jobs are spawned lazily so the concurrency limit bounds in-flight requests
+    stream::iter(jobs)
+        .for_each_concurrent(concurrency_limit(cli, context), |line| async move {
+            run_job(clients, cli, context, line).await;
         })
         .await;
+}
 
-    // Ensure all buffered output is written to file before exiting
-    if let Some(writer) = &context.output_writer {
-        let mut writer = writer.lock().await;
-        writer.flush().await?;
+/// Send input lines as they arrive until the input ends (--follow)
+async fn run_follow(
+    cli: &Cli,
+    clients: &Arc<ClientPool>,
+    context: &Arc<ProcessingContext>,
+    url_filter: &Option<UrlFilter>,
+    source: &str,
+) {
+    info!(source = %source, "Following input");
+    let lines = follow_lines(source);
+    // Waiting for the next line stops at the --max-runtime deadline or when the TUI quits
+    let incoming = stream::unfold(lines, |mut lines| async move {
+        let line = match context.deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), lines.recv())
+                .await
+                .ok()
+                .flatten(),
+            None => lines.recv().await,
+        };
+        line.map(|line| (line, lines))
+    })
+    .take_while(|_| future::ready(!context.outputs.tui_quit() && !past_deadline(context)));
+
+    incoming
+        .flat_map(|line| stream::iter(input_lines(cli, url_filter, &line)))
+        .for_each_concurrent(concurrency_limit(cli, context), |line| async move {
+            run_job(clients, cli, context, line).await;
+        })
+        .await;
+}
+
+/// Send items of a Redis work queue as they are taken, acknowledging each once processed (--input-redis)
+async fn run_queue(
+    cli: &Cli,
+    clients: &Arc<ClientPool>,
+    context: &Arc<ProcessingContext>,
+    url_filter: &Option<UrlFilter>,
+    queue: Arc<RedisQueue>,
+) {
+    info!(key = %cli.input_key, "Consuming input queue");
+    // A failing server is retried with exponential backoff until it is back
+    let items = stream::unfold(queue.clone(), |queue| async move {
+        let mut backoff = QUEUE_RETRY_MIN;
+        while !context.outputs.tui_quit() && !past_deadline(context) {
+            match queue.pop().await {
+                Ok(Some(item)) => return Some((item, queue)),
+                Ok(None) => backoff = QUEUE_RETRY_MIN,
+                Err(e) => {
+                    warn!("{:#}. Retrying in {}s", e, backoff.as_secs());
+                    let wait = match context.deadline {
+                        Some(deadline) => {
+                            backoff.min(deadline.saturating_duration_since(Instant::now()))
+                        }
+                        None => backoff,
+                    };
+                    tokio::time::sleep(wait).await;
+                    backoff = (backoff * 2).min(QUEUE_RETRY_MAX);
+                }
+            }
+        }
+        None
+    });
+
+    items
+        .then(|item| {
+            let queue = queue.clone();
+            async move {
+                let lines = input_lines(cli, url_filter, &item);
+                // Items expanding to no requests are done as soon as they are taken
+                if lines.is_empty() {
+                    acknowledge(&queue, &item).await;
+                }
+                let claim = Arc::new(Claim::new(item, lines.len()));
+                stream::iter(lines.into_iter().map(move |line| (line, claim.clone())))
+            }
+        })
+        .flatten()
+        .for_each_concurrent(concurrency_limit(cli, context), |(line, claim)| {
+            let queue = queue.clone();
+            async move {
+                let finished = run_job(clients, cli, context, line).await;
+                if claim.done(finished) {
+                    acknowledge(&queue, &claim.item).await;
+                }
+            }
+        })
+        .await;
+}
+
+/// Acknowledge a processed queue item, leaving it in the processing list on failure
+async fn acknowledge(queue: &RedisQueue, item: &str) {
+    if let Err(e) = queue.ack(item).await {
+        warn!("{:#}", e);
     }
+}
 
-    Ok(())
+/// Send one input line on its own task, honoring --allowed-window and --max-runtime
+///
+/// Returns false if the line was dropped at the deadline.
+async fn run_job(
+    clients: &Arc<ClientPool>,
+    cli: &Cli,
+    context: &Arc<ProcessingContext>,
+    line: String,
+) -> bool {
+    let clients = clients.clone();
+    let cli = cli.clone();
+    let context = context.clone();
+    task::spawn(async move {
+        if let Some(window) = &context.window {
+            window.wait().await;
+        }
+        let work = process_line(&clients, &cli, &line, &context);
+        // At the --max-runtime deadline, remaining and in-flight lines are dropped
+        let finished = match context.deadline {
+            Some(deadline) if Instant::now() >= deadline => false,
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), work).await.is_ok(),
+            None => {
+                work.await;
+                true
+            }
+        };
+        if !finished {
+            context.outcome.record_unprocessed();
+        }
+        finished
+    })
+    .await
+    .unwrap()
+}
+
+/// Parse an input line and send the request it describes
+async fn process_line(clients: &ClientPool, cli: &Cli, line: &str, context: &ProcessingContext) {
+    let _permit = match &context.control {
+        Some(control) => Some(control.acquire().await),
+        None => None,
+    };
+    if context.outputs.tui_quit() {
+        return;
+    }
+    let raw_line = context.vars.render(line);
+    let (line, mut overrides) = match cli.input_format {
+        InputFormat::Plain => {
+            // " #TAG" labels end the line
+            let (line, tags) = split_tags(&raw_line);
+            // "@host:NAME" may come before or after the option group
+            let (line, host) = split_host_override(&line);
+            let (line, mut overrides) = parse_request_overrides(&line);
+            let (line, inner_host) = split_host_override(&line);
+            overrides.host = host.or(inner_host);
+            overrides.tags = tags;
+            (line, overrides)
+        }
+        InputFormat::Jsonl => match parse_jsonl_request(&raw_line) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Skipping invalid JSON input line: {}", e);
+                context
+                    .outputs
+                    .write_reject(&raw_line, &format!("invalid JSON input: {}", e))
+                    .await;
+                return;
+            }
+        },
+        InputFormat::Har => match parse_har_request(&raw_line) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Skipping invalid HAR request: {}", e);
+                context
+                    .outputs
+                    .write_reject(&raw_line, &format!("invalid HAR request: {}", e))
+                    .await;
+                return;
+            }
+        },
+        InputFormat::ReqsJsonl => match parse_reqs_record(&raw_line) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Skipping invalid reqs record: {}", e);
+                context
+                    .outputs
+                    .write_reject(&raw_line, &format!("invalid reqs record: {}", e))
+                    .await;
+                return;
+            }
+        },
+    };
+    let (method, url_str, body) = parse_request_line(&line);
+    let method = overrides.method.clone().unwrap_or(method);
+    let method = mutate_method(&cli.mutate, method);
+    let body = overrides.body.clone().or(body);
+
+    if url_str.is_empty() {
+        context.outputs.write_reject(&raw_line, "missing URL").await;
+        return;
+    }
+
+    // With --probe-both-schemes, scheme-less input is tried over https and http
+    let probe = cli
+        .probe_both_schemes
+        .as_ref()
+        .filter(|_| !has_http_scheme(&url_str));
+    let urls = match probe {
+        Some(_) => vec![
+            format!("https://{}", url_str.trim()),
+            format!("http://{}", url_str.trim()),
+        ],
+        None => vec![normalize_url_scheme(&url_str)],
+    };
+    let validated = match &cli.validate_urls {
+        Some(_) => validate_url(&url_str).map(|_| ()),
+        None => Ok(()),
+    };
+    let urls = match validated.and_then(|()| {
+        urls.iter()
+            .map(|url| normalize_url(url))
+            .collect::<Result<Vec<_>, _>>()
+    }) {
+        Ok(urls) => urls,
+        Err(invalid) => {
+            warn!(url = %invalid.url, "Skipping malformed URL: {}", invalid.reason);
+            context.outcome.record_error();
+            if let Some(summary) = &context.summary {
+                summary.lock().await.record_error(&invalid.url);
+            }
+            context
+                .outputs
+                .write_invalid_url(cli, &method, &invalid)
+                .await;
+            context
+                .outputs
+                .write_reject(&raw_line, &invalid.reason)
+                .await;
+            return;
+        }
+    };
+
+    // Credentials in the URL are sent as a header and kept out of the output
+    let urls: Vec<String> = urls
+        .into_iter()
+        .map(|url| {
+            let (stripped, credentials) = split_credentials(&url);
+            if let Some(credentials) = credentials
+                && !context.default_headers.contains_key(AUTHORIZATION)
+                && !overrides.headers.contains_key(AUTHORIZATION)
+                && let Some(value) = credentials_header(&credentials, cli.url_auth)
+            {
+                overrides.headers.insert(AUTHORIZATION, value);
+            }
+            if cli.show_credentials { url } else { stripped }
+        })
+        .collect();
+
+    if !is_in_scope(&urls[0], &cli.scope) {
+        warn!(url = %urls[0], "Skipping out-of-scope URL");
+        context
+            .outputs
+            .write_reject(&raw_line, "out of scope")
+            .await;
+        return;
+    }
+
+    // Refused here so that dry runs and cached responses are covered too
+    if cli.safe_methods_only && !is_safe_method(&method) {
+        let err = SendError::UnsafeMethod(method.clone());
+        error!(url = %urls[0], error_kind = err.kind().as_str(), "{}", err);
+        context.outcome.record_error();
+        if let Some(summary) = &context.summary {
+            summary.lock().await.record_error(&urls[0]);
+        }
+        context
+            .outputs
+            .write_failure(&method, &urls[0], &err.to_string());
+        if cli.include_errors {
+            let error = RequestError {
+                message: err.to_string(),
+                kind: err.kind(),
+                proxy: None,
+                failed_at: None,
+            };
+            context
+                .outputs
+                .write_error(cli, &method, &urls[0], &error, &overrides.tags)
+                .await;
+        }
+        return;
+    }
+
+    // Delays and rate limits depend on the host group of the URL
+    if !cli.dry_run {
+        let delay = cli
+            .random_delay
+            .as_ref()
+            .and_then(|rules| rules.lookup(&urls[0]));
+        apply_random_delay(delay.map(|(_, distribution)| distribution)).await;
+        let default_rate = match &context.control {
+            Some(control) => control.rate_limit(),
+            None => context.rate_limiter.default_rate(),
+        };
+        context.rate_limiter.wait(&urls[0], default_rate).await;
+    }
+
+    if overrides.host.is_none() {
+        overrides.host = cli.host_header.clone();
+    }
+    if overrides.sni.is_none() {
+        overrides.sni = cli.sni.clone().map(Some);
+    }
+    // A proxy given for the request takes precedence over the pool
+    let pool_start = match &context.proxy_pool {
+        Some(pool) if overrides.proxy.is_none() => {
+            let start = pool.start();
+            overrides.proxy = Some(Some(pool.get(start, 0).to_string()));
+            Some((pool, start))
+        }
+        _ => None,
+    };
+    // Pin the name the request is sent to (SNI, else Host) to the URL's address
+    let mut client_options = overrides.apply(clients.default_options());
+    if let Some(name) = overrides.connect_name() {
+        match clients.resolver().connect_ip(&urls[0]).await {
+            Some(ip) => client_options.resolve = Some((name.to_string(), ip)),
+            None => {
+                error!(
+                    url = %urls[0],
+                    error_kind = ErrorKind::Dns.as_str(),
+                    "Failed to resolve the address to send {} to",
+                    name
+                );
+                context.outcome.record_error();
+                context
+                    .outputs
+                    .write_failure(&method, &urls[0], "unresolved host");
+                if cli.include_errors {
+                    let error = RequestError {
+                        message: "unresolved host".to_string(),
+                        kind: ErrorKind::Dns,
+                        proxy: None,
+                        failed_at: None,
+                    };
+                    context
+                        .outputs
+                        .write_error(cli, &method, &urls[0], &error, &overrides.tags)
+                        .await;
+                }
+                return;
+            }
+        }
+    }
+    let retries = overrides.retries.unwrap_or(cli.retry);
+    let mut attempt_clients = Vec::new();
+    let rotations = match pool_start {
+        Some((pool, _)) if cli.retry_rotate_proxy => pool.count().min(retries as usize + 1),
+        _ => 1,
+    };
+    for offset in 0..rotations {
+        let mut options = client_options.clone();
+        if let Some((pool, start)) = pool_start {
+            options.proxy = Some(pool.get(start, offset).to_string());
+        }
+        // reqwest adds `Accept: */*` to requests that have none
+        let exact =
+            (omits_client_accept(cli) && options.proxy.is_none() && cli.auth_ntlm.is_none())
+                .then(|| clients.exact(&options))
+                .transpose();
+        match clients
+            .get(&options)
+            .and_then(|client| Ok((client, exact?)))
+        {
+            Ok((client, exact)) => attempt_clients.push(AttemptClient {
+                proxy: options.proxy.map(|proxy| split_credentials(&proxy).0),
+                client,
+                exact,
+            }),
+            Err(e) => {
+                error!(url = %urls[0], "Failed to build HTTP client: {}", e);
+                return;
+            }
+        }
+    }
+
+    let mut alive = Vec::new();
+    let mut last_failure = None;
+    for url in &urls {
+        if let Some(liveness) = &context.liveness
+            && !liveness.is_alive(url).await
+        {
+            info!(url = %url, "Skipping URL of unreachable host");
+            continue;
+        }
+        if let Some(breaker) = &context.breaker
+            && !breaker.allow(url)
+        {
+            info!(url = %url, "Skipping URL of host over the error limit");
+            if let Some(summary) = &context.summary {
+                summary.lock().await.record_skipped(url);
+            }
+            continue;
+        }
+        // Race the addresses of multi-homed hosts unless a proxy connects for us
+        let race = match &context.racer {
+            Some(racer) if overrides.proxy.as_ref().is_none_or(Option::is_none) => {
+                racer.race(url).await
+            }
+            _ => None,
+        };
+        let result = if cli.ws_probe && is_websocket_url(url) {
+            process_ws_probe(cli, &method, url, &overrides, context)
+                .await
+                .map_err(|e| RequestError {
+                    message: e.to_string(),
+                    kind: classify_chain(e.as_ref()),
+                    proxy: None,
+                    failed_at: None,
+                })
+        } else {
+            process_single_request(
+                &attempt_clients,
+                cli,
+                &method,
+                url,
+                &body,
+                &overrides,
+                context,
+            )
+            .await
+            .map_err(|e| {
+                let last = &attempt_clients[retries as usize % attempt_clients.len()];
+                let message = match &race {
+                    Some(report) if e.is_connect() && !report.failed.is_empty() => {
+                        format!("{} (tried {})", e, report)
+                    }
+                    _ => e.to_string(),
+                };
+                RequestError {
+                    message,
+                    kind: e.kind(),
+                    proxy: last.proxy.clone(),
+                    failed_at: last.proxy.as_ref().map(|_| failed_at(&e)),
+                }
+            })
+        };
+        if let Some(breaker) = &context.breaker
+            && breaker.record(url, result.is_ok())
+        {
+            warn!(
+                url = %url,
+                "{} consecutive errors, skipping the remaining requests to this host",
+                cli.max_host_errors.unwrap_or_default()
+            );
+        }
+        match result {
+            Ok(()) => {
+                if cli.check_backups && !cli.dry_run {
+                    check_backups(cli, &attempt_clients[0], url, &overrides, context).await;
+                }
+                alive.push(&url[..url.find("://").unwrap_or_default()]);
+                if matches!(probe, Some(SchemeProbe::Fallback)) {
+                    break;
+                }
+            }
+            Err(err) => {
+                if probe.is_some() {
+                    debug!(url = %url, "Scheme probe failed: {}", err.message);
+                }
+                last_failure = Some((url, err));
+            }
+        }
+    }
+
+    if probe.is_some() && !alive.is_empty() {
+        info!(input = %url_str, schemes = %alive.join(","), "Alive schemes");
+    } else if let Some((url, err)) = last_failure {
+        context.outcome.record_error();
+        if cli.format.is_report() {
+            context.outcome.record_result(TestResult {
+                name: format!("{} {}", method, url),
+                elapsed: Duration::ZERO,
+                failure: Some(err.message.clone()),
+            });
+        }
+        if expectation_for(context, &overrides, url).is_some() {
+            report_verdict(cli, context, &method, url, None, Err(err.message.clone()));
+        }
+        if let Some(summary) = &context.summary {
+            summary.lock().await.record_error(url);
+        }
+        context.outputs.write_failure(&method, url, &err.message);
+        if cli.include_errors {
+            context
+                .outputs
+                .write_error(cli, &method, url, &err, &overrides.tags)
+                .await;
+        }
+        error!(
+            url = %url,
+            error_kind = err.kind.as_str(),
+            proxy = err.proxy.as_deref(),
+            failed_at = err.failed_at.map(|at| at.as_str()),
+            "Error after {} attempts: {}",
+            overrides.retries.unwrap_or(cli.retry) + 1,
+            err.message
+        );
+        // Tell closed or firewalled ports apart from hosts that just don't speak HTTP
+        if cli.tcp_probe {
+            let timeout = overrides
+                .timeout
+                .unwrap_or(Duration::from_secs(cli.timeout));
+            if let Some(probe) = probe_tcp(url, timeout).await {
+                context
+                    .outputs
+                    .write_tcp_probe(cli, &method, url, &probe, &err)
+                    .await;
+            }
+        }
+    }
+}
+
+/// Probe the backup copies of an input file and report those found (--check-backups)
+async fn check_backups(
+    cli: &Cli,
+    attempt: &AttemptClient,
+    url_str: &str,
+    overrides: &RequestOverrides,
+    context: &ProcessingContext,
+) {
+    for backup_url in backup_urls(url_str) {
+        let default_rate = match &context.control {
+            Some(control) => control.rate_limit(),
+            None => context.rate_limiter.default_rate(),
+        };
+        context.rate_limiter.wait(&backup_url, default_rate).await;
+        context
+            .outputs
+            .write_audit(&audit_entry(cli, "GET", &backup_url, 1, overrides))
+            .await;
+        let request = prepare_request(
+            &attempt.client,
+            cli,
+            "GET",
+            &backup_url,
+            &None,
+            overrides,
+            context,
+        );
+        match send_request(cli, attempt.exact.as_ref(), request).await {
+            Ok(resp) if is_backup_hit(resp.status(), resp.headers()) => {
+                let finding = BackupFinding {
+                    size: resp.content_length().unwrap_or(0),
+                    status: resp.status().as_u16(),
+                    url: backup_url,
+                    source: url_str.to_string(),
+                };
+                info!(url = %finding.url, "Found backup copy of {}", url_str);
+                context.outputs.write_backup(cli, &finding).await;
+            }
+            Ok(resp) => {
+                debug!(url = %backup_url, status = resp.status().as_u16(), "No backup copy")
+            }
+            Err(e) => debug!(url = %backup_url, "Backup probe failed: {}", e),
+        }
+    }
+}
+
+/// Whether the --max-runtime deadline has passed
+fn past_deadline(context: &ProcessingContext) -> bool {
+    context
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Whether the input already specifies an http(s) or ws(s) scheme
+fn has_http_scheme(url: &str) -> bool {
+    let url = url.trim();
+    url.starts_with("http://") || url.starts_with("https://") || is_websocket_url(url)
+}
+
+/// Build the request for an input line with the configured headers and timeout
+fn prepare_request(
+    client: &Client,
+    cli: &Cli,
+    method: &str,
+    url_str: &str,
+    body: &Option<String>,
+    overrides: &RequestOverrides,
+    context: &ProcessingContext,
+) -> RequestBuilder {
+    let mut headers = request_headers(&context.default_headers, uses_random_user_agent(cli));
+    headers.extend(overrides.headers.clone());
+    mutate_headers(&cli.mutate, &mut headers);
+    let mut url = split_credentials(url_str).0;
+    // The URL carries the SNI name, so the Host header is set explicitly
+    if let Some(Some(_)) = &overrides.sni
+        && !headers.contains_key(HOST)
+    {
+        let host_url = match &overrides.host {
+            Some(host) => replace_host(&url, host),
+            None => url.clone(),
+        };
+        if let Some(value) = url_authority(&host_url).and_then(|v| HeaderValue::from_str(&v).ok()) {
+            headers.insert(HOST, value);
+        }
+    }
+    if let Some(name) = overrides.connect_name() {
+        url = replace_host(&url, name);
+    }
+    let mut request_builder = build_request(client, method, &url, body).headers(headers);
+    if let Some(timeout) = overrides.timeout {
+        request_builder = request_builder.timeout(timeout);
+    }
+    if cli.sse && !context.default_headers.contains_key(ACCEPT) {
+        request_builder = request_builder.header(ACCEPT, "text/event-stream");
+    }
+    request_builder
+}
+
+/// Client for an attempt at a request and the proxy it goes through
+struct AttemptClient {
+    /// Proxy URL without its credentials, for output
+    proxy: Option<String>,
+    client: Client,
+    /// Client for requests that must go out without reqwest's implicit headers
+    exact: Option<ExactClient>,
 }
 
 /// Process a single HTTP request with retries
+///
+/// Attempts take turns with `attempt_clients`, which hold more than one client
+/// only when retries rotate through the proxy pool. Returns the last error if
+/// no response was received after all attempts.
 async fn process_single_request(
-    client: &Client,
+    attempt_clients: &[AttemptClient],
     cli: &Cli,
     method: &str,
     url_str: &str,
     body: &Option<String>,
+    overrides: &RequestOverrides,
     context: &ProcessingContext,
-) {
+) -> Result<(), SendError> {
+    let http2 = overrides.http2.unwrap_or(cli.http2);
+    let expectation = expectation_for(context, overrides, url_str);
+    let client = &attempt_clients[0].client;
+    // Requests carry canaries, the output keeps the URL as given
+    let reflection = cli
+        .detect_reflection
+        .then(|| Reflection::inject(url_str))
+        .flatten();
+    let send_url = reflection.as_ref().map_or(url_str, |r| r.url.as_str());
+
+    if cli.dry_run {
+        let mut request_builder =
+            prepare_request(client, cli, method, send_url, body, overrides, context);
+        if let Some(name) = &cli.trace_header {
+            request_builder = request_builder.header(name, new_trace_id());
+        }
+        match request_builder.build() {
+            Ok(req) => print!(
+                "{}",
+                context.redactor.raw_request(&format_dry_run(&req, http2))
+            ),
+            Err(e) => error!(url = %url_str, "Failed to build request: {}", e),
+        }
+        return Ok(());
+    }
+
+    let cache_key = context.cache.as_ref().and_then(|_| {
+        prepare_request(client, cli, method, send_url, body, overrides, context)
+            .build()
+            .ok()
+            .map(|req| ResponseCache::key(&req))
+    });
+    if let (Some(cache), Some(key)) = (&context.cache, &cache_key)
+        && let Some(cached) = cache.get(key).await
+    {
+        debug!(url = %url_str, "Serving response from cache");
+        let status = StatusCode::from_u16(cached.status).unwrap_or_default();
+        if let Some(summary) = &context.summary {
+            summary
+                .lock()
+                .await
+                .record_response(url_str, cached.status, cached.elapsed());
+        }
+
+        let req_for_display = if uses_raw_request(cli) {
+            prepare_request(client, cli, method, send_url, body, overrides, context)
+                .build()
+                .ok()
+                .map(|req| format_raw_request(&req, http2))
+        } else {
+            None
+        };
+        let req_headers = if uses_req_headers(cli) {
+            prepare_request(client, cli, method, send_url, body, overrides, context)
+                .build()
+                .ok()
+                .map(|req| req.headers().clone())
+        } else {
+            None
+        };
+        let headers = cached.header_map();
+        let body_text = Some(cached.body.clone());
+        let response_data = ResponseData {
+            tags: &overrides.tags,
+            size: cached.size,
+            elapsed: cached.elapsed(),
+            req_headers: &req_headers,
+            req_for_display: &req_for_display,
+            body_text: &body_text,
+            ..ResponseData::new(method, url_str, &cached.ip_addr, status, &headers)
+        };
+        emit_response(cli, context, response_data, expectation.as_ref()).await;
+        return Ok(());
+    }
+
+    let retries = overrides.retries.unwrap_or(cli.retry);
     let mut attempts = 0;
     let mut last_error = None;
 
-    while attempts <= cli.retry {
+    while attempts <= retries {
         if attempts > 0 && cli.delay > 0 {
             tokio::time::sleep(Duration::from_millis(cli.delay)).await;
         }
 
-        let request_builder = build_request(client, method, url_str, body);
+        let attempt = &attempt_clients[attempts as usize % attempt_clients.len()];
+        let AttemptClient { proxy, client, .. } = attempt;
+        let validator_key = format!("{} {}", method, url_str);
+        let mut request_builder =
+            prepare_request(client, cli, method, send_url, body, overrides, context);
+        if let Some(validators) = &context.validators {
+            request_builder =
+                request_builder.headers(validators.conditional_headers(&validator_key));
+        }
+        let trace_id = cli.trace_header.as_ref().map(|_| new_trace_id());
+        if let (Some(name), Some(id)) = (&cli.trace_header, &trace_id) {
+            request_builder = request_builder.header(name, id.as_str());
+        }
 
-        let req_for_display = if cli.include_req {
+        let req_for_display = if uses_raw_request(cli) {
+            request_builder
+                .try_clone()
+                .and_then(|builder| builder.build().ok())
+                .map(|req| format_raw_request(&req, http2))
+        } else {
+            None
+        };
+        let req_headers = if uses_req_headers(cli) {
             request_builder
                 .try_clone()
                 .and_then(|builder| builder.build().ok())
-                .map(|req| format_raw_request(&req, cli.http2, Some(&cli.headers)))
+                .map(|req| req.headers().clone())
         } else {
             None
         };
 
+        debug!(
+            method = %method,
+            url = %url_str,
+            attempt = attempts + 1,
+            trace_id = trace_id.as_deref().unwrap_or_default(),
+            "Sending request"
+        );
+        context
+            .outputs
+            .write_audit(&AuditEntry {
+                proxy: proxy.as_deref(),
+                trace_id: trace_id.as_deref(),
+                ..audit_entry(cli, method, url_str, attempts + 1, overrides)
+            })
+            .await;
+        let bytes_up = cli.count_bytes.then(|| {
+            request_builder
+                .try_clone()
+                .and_then(|builder| builder.build().ok())
+                .map_or(0, |req| request_size(&req))
+        });
         let start_time = Instant::now();
-        match request_builder.send().await {
+        let (send_result, mut timing) = if cli.timing_detail {
+            let (result, timing) =
+                with_timing(send_request(cli, attempt.exact.as_ref(), request_builder)).await;
+            (result, Some(timing))
+        } else {
+            (
+                send_request(cli, attempt.exact.as_ref(), request_builder).await,
+                None,
+            )
+        };
+
+        match send_result {
             Ok(resp) => {
                 let elapsed = start_time.elapsed();
                 let status = resp.status();
                 let size = resp.content_length().unwrap_or(0);
+                let headers = resp.headers().clone();
                 let ip_addr = resp
                     .remote_addr()
                     .map(|s| s.ip().to_string())
                     .unwrap_or_default();
+                let cert = context.cert_monitor.as_ref().and_then(|monitor| {
+                    let tls = resp.extensions().get::<reqwest::tls::TlsInfo>()?;
+                    monitor.check(url_str, tls.peer_certificate()?)
+                });
+                debug!(
+                    url = %url_str,
+                    final_url = %resp.url(),
+                    ip = %ip_addr,
+                    status = status.as_u16(),
+                    elapsed_ms = elapsed.as_millis() as u64,
+                    "Received response"
+                );
+
+                if let Some(summary) = &context.summary {
+                    summary
+                        .lock()
+                        .await
+                        .record_response(url_str, status.as_u16(), elapsed);
+                }
+
+                if let Some(validators) = &context.validators {
+                    if status == StatusCode::NOT_MODIFIED {
+                        debug!(url = %url_str, "Unchanged since the last watch round");
+                        return Ok(());
+                    }
+                    validators.update(&validator_key, &headers);
+                }
 
-                let body_text = if cli.include_res
-                    || cli.filter_string.is_some()
-                    || cli.filter_regex.is_some()
-                    || cli.include_title
+                // Drop responses excluded by status before downloading their body,
+                // unless the body is needed for the cache, expectations or the state
+                if context.cache.is_none()
+                    && expectation.is_none()
+                    && context.state.is_none()
+                    && is_status_filtered(status.as_u16(), &cli.filter_status)
                 {
-                    Some(resp.text().await.unwrap_or_default())
-                } else {
-                    None
-                };
+                    return Ok(());
+                }
 
-                let title = if cli.include_title {
-                    body_text.as_ref().and_then(|body| extract_title(body))
+                let download_start = Instant::now();
+                let mut sse = None;
+                let mut drained = None;
+                let body_text = if cli.sse && is_event_stream(&headers) {
+                    // Streams never end on their own, so read a bounded number of events
+                    let (info, raw) = read_event_stream(
+                        resp,
+                        cli.sse_events,
+                        cli.sse_duration,
+                        context.bandwidth.as_ref(),
+                    )
+                    .await;
+                    sse = Some(info);
+                    Some(raw)
+                } else if needs_body(cli)
+                    || context.cache.is_some()
+                    || expectation.as_ref().is_some_and(Expectation::needs_body)
+                    || context
+                        .score_rules
+                        .as_ref()
+                        .is_some_and(ScoreRules::needs_body)
+                {
+                    Some(match &context.bandwidth {
+                        Some(bandwidth) => bandwidth.read_text(resp).await.unwrap_or_default(),
+                        None => resp.text().await.unwrap_or_default(),
+                    })
+                } else if timing.is_some() || bytes_up.is_some() {
+                    // Drain the body so the download phase and its size can be measured
+                    let body = match &context.bandwidth {
+                        Some(bandwidth) => bandwidth.read_body(resp).await.map(|body| body.len()),
+                        None => resp.bytes().await.map(|body| body.len()),
+                    };
+                    drained = body.ok().map(|len| len as u64);
+                    None
                 } else {
                     None
                 };
-
-                if should_filter_response(
-                    status.as_u16(),
-                    &body_text,
-                    &cli.filter_status,
-                    &cli.filter_string,
-                    context.parsed_filter_regex.as_ref(),
-                ) {
-                    return; // Skip output if it doesn't pass filters
+                if let Some(timing) = timing.as_mut() {
+                    timing.download = download_start.elapsed();
+                }
+                let bytes = bytes_up.map(|up| ByteCount {
+                    up,
+                    down: response_head_size(status, &headers)
+                        + body_text
+                            .as_ref()
+                            .map(|body| body.len() as u64)
+                            .or(drained)
+                            .unwrap_or(size),
+                });
+                if let (Some(summary), Some(bytes)) = (&context.summary, bytes) {
+                    summary.lock().await.record_bytes(bytes);
                 }
 
-                // Write CSV header if needed
-                if let OutputFormat::Csv = cli.format {
-                    write_csv_header(cli, &context.output_writer, &context.csv_header_written)
-                        .await;
+                if let (Some(cache), Some(key), Some(body)) =
+                    (&context.cache, &cache_key, &body_text)
+                {
+                    let entry = CachedResponse {
+                        status: status.as_u16(),
+                        size,
+                        elapsed_ms: elapsed.as_millis() as u64,
+                        ip_addr: ip_addr.clone(),
+                        headers: CachedResponse::headers_from(&headers),
+                        body: body.clone(),
+                        ..Default::default()
+                    };
+                    if let Err(e) = cache.put(key, entry).await {
+                        warn!(url = %url_str, "Failed to write response cache: {}", e);
+                    }
                 }
 
+                let reflected = reflection
+                    .as_ref()
+                    .map(|r| r.reflected(body_text.as_deref().unwrap_or_default()));
                 let response_data = ResponseData {
-                    method,
-                    url_str,
-                    ip_addr: &ip_addr,
-                    status,
+                    cert: &cert,
+                    bytes: &bytes,
+                    trace_id: &trace_id,
+                    tags: &overrides.tags,
                     size,
                     elapsed,
-                    title: &title,
+                    reflected: &reflected,
+                    req_headers: &req_headers,
                     req_for_display: &req_for_display,
                     body_text: &body_text,
+                    timing: &timing,
+                    sse: &sse,
+                    ..ResponseData::new(method, url_str, &ip_addr, status, &headers)
                 };
-                let output_str = format_response_output(cli, &response_data);
-
-                write_output(output_str, &context.output_writer).await;
-                return; // Success, exit retry loop
+                emit_response(cli, context, response_data, expectation.as_ref()).await;
+                return Ok(()); // Success, exit retry loop
             }
             Err(err) => {
                 attempts += 1;
-                if attempts <= cli.retry {
-                    eprintln!(
-                        "[{}] - Attempt {} failed: {}. Retrying...",
-                        url_str, attempts, err
-                    );
+                if attempts <= retries {
+                    match proxy {
+                        Some(proxy) if failed_at(&err) == FailedAt::Proxy => warn!(
+                            url = %url_str,
+                            "Attempt {} failed at proxy {}: {}. Retrying...",
+                            attempts,
+                            proxy,
+                            err
+                        ),
+                        _ => warn!(
+                            url = %url_str,
+                            "Attempt {} failed: {}. Retrying...",
+                            attempts,
+                            err
+                        ),
+                    }
                 }
                 last_error = Some(err);
             }
         }
     }
 
-    if let Some(err) = last_error {
-        eprintln!(
-            "[{}] - Error after {} attempts: {}",
-            url_str,
-            cli.retry + 1,
-            err
-        );
+    match last_error {
+        Some(err) => Err(err),
+        None => Ok(()),
     }
 }
 
-/// Write CSV header if not yet written
-async fn write_csv_header(
+/// Probe a ws:// or wss:// URL with a WebSocket upgrade (--ws-probe), with retries
+///
+/// The handshake response goes through the usual filters and outputs, with the
+/// upgrade result attached as the `websocket` field.
+async fn process_ws_probe(
     cli: &Cli,
-    output_writer: &Option<Arc<Mutex<BufWriter<File>>>>,
-    csv_header_written: &Arc<Mutex<bool>>,
-) {
-    let mut header_written = csv_header_written.lock().await;
-    if !*header_written {
-        let mut csv_header =
-            "method,url,ip_address,status_code,content_length,response_time_ms".to_string();
-        if cli.include_title {
-            csv_header.push_str(",title");
-        }
-        csv_header.push('\n');
-
-        if let Some(writer) = output_writer {
-            let mut writer = writer.lock().await;
-            if let Err(e) = writer.write_all(csv_header.as_bytes()).await {
-                eprintln!("Error writing to output file: {}", e);
+    method: &str,
+    url_str: &str,
+    overrides: &RequestOverrides,
+    context: &ProcessingContext,
+) -> Result<()> {
+    if cli.dry_run {
+        println!("[Dry Run] WebSocket upgrade {}", url_str);
+        return Ok(());
+    }
+
+    let mut headers = request_headers(&context.default_headers, uses_random_user_agent(cli));
+    headers.extend(overrides.headers.clone());
+    let options = WsProbeOptions {
+        headers: &headers,
+        protocols: &cli.ws_protocols,
+        message: cli.ws_message.as_deref(),
+        timeout: overrides
+            .timeout
+            .unwrap_or(Duration::from_secs(cli.timeout)),
+        verify_ssl: cli.verify_ssl,
+    };
+
+    let retries = overrides.retries.unwrap_or(cli.retry);
+    let mut attempts = 0;
+    let response = loop {
+        if attempts > 0 && cli.delay > 0 {
+            tokio::time::sleep(Duration::from_millis(cli.delay)).await;
+        }
+        debug!(url = %url_str, attempt = attempts + 1, "Sending WebSocket upgrade");
+        context
+            .outputs
+            .write_audit(&audit_entry(cli, method, url_str, attempts + 1, overrides))
+            .await;
+        let start_time = Instant::now();
+        match probe_websocket(&split_credentials(url_str).0, &options).await {
+            Ok(response) => break (response, start_time.elapsed()),
+            Err(err) => {
+                attempts += 1;
+                if attempts > retries {
+                    return Err(err);
+                }
+                warn!(url = %url_str, "Attempt {} failed: {}. Retrying...", attempts, err);
             }
-        } else {
-            print!("{}", csv_header);
         }
-        *header_written = true;
+    };
+    let (response, elapsed) = response;
+
+    if let Some(summary) = &context.summary {
+        summary
+            .lock()
+            .await
+            .record_response(url_str, response.status.as_u16(), elapsed);
     }
+
+    let websocket = Some(response.info);
+    let req_headers = uses_req_headers(cli).then_some(headers);
+    let response_data = ResponseData {
+        tags: &overrides.tags,
+        size: response.body.as_ref().map_or(0, |body| body.len() as u64),
+        elapsed,
+        req_headers: &req_headers,
+        body_text: &response.body,
+        websocket: &websocket,
+        ..ResponseData::new(
+            method,
+            url_str,
+            &response.ip_addr,
+            response.status,
+            &response.headers,
+        )
+    };
+    let expectation = expectation_for(context, overrides, url_str);
+    emit_response(cli, context, response_data, expectation.as_ref()).await;
+    Ok(())
+}
+
+/// Whether the response body is needed for output, filters, the title or exports
+fn needs_body(cli: &Cli) -> bool {
+    cli.include_res
+        || !cli.filter_string.is_empty()
+        || !cli.filter_string_not.is_empty()
+        || !cli.filter_regex.is_empty()
+        || cli.include_title
+        || cli.filter_title.is_some()
+        || cli.filter_title_not.is_some()
+        || cli.include_meta
+        || cli.extract_forms
+        || cli.extract_js_endpoints
+        || cli.decode_jwt
+        || cli.detect_reflection
+        || cli.detect_listing
+        || cli.state_db.is_some()
+        || !cli.extract.is_empty()
+        || cli.export_burp.is_some()
+}
+
+/// Whether the raw request is needed, for --include-req, --export-burp or --format curl
+fn uses_raw_request(cli: &Cli) -> bool {
+    cli.include_req || cli.export_burp.is_some() || matches!(cli.format, OutputFormat::Curl)
 }
 
-/// Response data for formatting
-struct ResponseData<'a> {
+/// Whether the --strf template needs the headers of the sent request
+fn uses_req_headers(cli: &Cli) -> bool {
+    cli.strf
+        .as_deref()
+        .is_some_and(|template| template.contains("%req_header:"))
+}
+
+/// Audit log entry for a request with the options it is sent with
+fn audit_entry<'a>(
+    cli: &'a Cli,
     method: &'a str,
-    url_str: &'a str,
-    ip_addr: &'a str,
-    status: reqwest::StatusCode,
-    size: u64,
-    elapsed: Duration,
-    title: &'a Option<String>,
-    req_for_display: &'a Option<String>,
-    body_text: &'a Option<String>,
+    url: &'a str,
+    attempt: u32,
+    overrides: &'a RequestOverrides,
+) -> AuditEntry<'a> {
+    AuditEntry {
+        method,
+        url,
+        attempt,
+        proxy: match &overrides.proxy {
+            Some(proxy) => proxy.as_deref(),
+            None => cli.proxy.as_deref(),
+        },
+        http2: overrides.http2.unwrap_or(cli.http2),
+        follow_redirect: overrides.follow_redirect.unwrap_or(cli.follow_redirect),
+        host: overrides.host.as_deref(),
+        sni: match &overrides.sni {
+            Some(sni) => Some(sni.as_deref()),
+            None if cli.no_sni => Some(None),
+            None => None,
+        },
+        trace_id: None,
+    }
 }
 
-/// Format response output
-fn format_response_output(cli: &Cli, data: &ResponseData) -> String {
-    match cli.format {
-        OutputFormat::Plain => {
-            let response_info = ResponseInfo {
-                method: data.method,
-                url: data.url_str,
-                ip_addr: data.ip_addr,
-                status: data.status,
-                size: data.size,
-                elapsed: data.elapsed,
-                title: data.title,
-            };
-            let mut s = format_plain_output(
-                &response_info,
-                &cli.strf,
-                cli.output.is_none() && !cli.no_color,
-            );
-            if let Some(raw_req) = data.req_for_display {
-                s.push_str(&format!("[Raw Request]\n{}\n", raw_req));
-            }
-            if cli.include_res
-                && let Some(body) = data.body_text
-            {
-                s.push_str(&format!("[Response Body]\n{}\n", body));
-            }
-            s
-        }
-        OutputFormat::Jsonl => {
-            let mut json_output = json!({
-                "method": data.method,
-                "url": data.url_str,
-                "ip_address": data.ip_addr,
-                "status_code": data.status.as_u16(),
-                "content_length": data.size,
-                "response_time_ms": data.elapsed.as_millis(),
-            });
-            if let Some(t) = data.title {
-                json_output["title"] = t.as_str().into();
-            }
-            if let Some(req) = data.req_for_display {
-                json_output["raw_request"] = req.as_str().into();
-            }
-            if cli.include_res
-                && let Some(body) = data.body_text
-            {
-                json_output["response_body"] = body.as_str().into();
-            }
-            serde_json::to_string(&json_output).unwrap_or_default() + "\n"
-        }
-        OutputFormat::Csv => {
-            let time_str = format!("{:?}", data.elapsed);
-            let mut csv_line = format!(
-                "\"{}\",\"{}\",\"{}\",\"{}\",\"{}\",\"{}\"",
+/// Expectations for a request: the global ones combined with its inline options
+fn expectation_for(
+    context: &ProcessingContext,
+    overrides: &RequestOverrides,
+    url_str: &str,
+) -> Option<Expectation> {
+    Expectation::merge(
+        context.expectation.as_ref(),
+        &overrides.expect_status,
+        overrides.expect_body.as_deref(),
+    )
+    .unwrap_or_else(|e| {
+        error!(url = %url_str, "{:#}", e);
+        context.expectation.clone()
+    })
+}
+
+/// Print the PASS/FAIL line of a request and count it for the exit code
+fn report_verdict(
+    cli: &Cli,
+    context: &ProcessingContext,
+    method: &str,
+    url_str: &str,
+    status: Option<u16>,
+    result: Result<(), String>,
+) {
+    let colored = !cli.no_color && io::stderr().is_terminal();
+    eprintln!(
+        "{}",
+        format_verdict(method, url_str, status, &result, colored)
+    );
+    context.outcome.record_verdict(result.is_ok());
+}
+
+/// Check expectations, apply the response filters and write the response to the outputs if it passes
+async fn emit_response(
+    cli: &Cli,
+    context: &ProcessingContext,
+    data: ResponseData<'_>,
+    expectation: Option<&Expectation>,
+) {
+    let status = data.status.as_u16();
+    let verdict = expectation.map(|e| e.check(status, data.body_text.as_deref()));
+    if cli.format.is_report() {
+        context.outcome.record_result(TestResult {
+            name: format!("{} {}", data.method, data.url_str),
+            elapsed: data.elapsed,
+            failure: verdict.clone().and_then(Result::err),
+        });
+    }
+    if let Some(result) = verdict {
+        report_verdict(
+            cli,
+            context,
+            data.method,
+            data.url_str,
+            Some(status),
+            result,
+        );
+    }
+
+    // The title is extracted once for the output, the title filter and the state
+    let page_title =
+        if cli.include_title || context.title_filter.is_some() || context.state.is_some() {
+            data.body_text.as_ref().and_then(|body| extract_title(body))
+        } else {
+            None
+        };
+    let directory_listing = cli
+        .detect_listing
+        .then(|| is_directory_listing(data.body_text.as_deref().unwrap_or_default()));
+
+    let extracted = match data.body_text {
+        Some(body) if !context.extractors.is_empty() => Some(context.extractors.extract(body)),
+        _ => None,
+    };
+
+    // Every response updates the state, including those filtered from the output
+    let state = match &context.state {
+        Some(state_db) => {
+            let update = state_db.lock().await.update(
                 data.method,
                 data.url_str,
-                data.ip_addr,
-                data.status.as_u16(),
-                data.size,
-                time_str
+                status,
+                page_title.as_deref(),
+                data.body_text.as_deref().unwrap_or_default(),
             );
-            if cli.include_title {
-                csv_line.push_str(&format!(
-                    ",\"{}\"",
-                    data.title.as_deref().unwrap_or_default()
-                ));
-            }
-            csv_line.push('\n');
-            csv_line
+            update
+                .inspect_err(
+                    |e| error!(url = %data.url_str, "Error updating state database: {}", e),
+                )
+                .ok()
         }
+        None => None,
+    };
+
+    if should_filter_response(
+        data.status.as_u16(),
+        data.body_text,
+        &cli.filter_status,
+        &context.body_filter,
+    ) {
+        return; // Skip output if it doesn't pass filters
+    }
+    if let Some(title_filter) = &context.title_filter
+        && !title_filter.matches(page_title.as_deref())
+    {
+        return;
+    }
+    // Sized by the body when it was read, as chunked responses have no length
+    let score = context.score_rules.as_ref().map(|rules| {
+        let body = data.body_text.as_deref().unwrap_or_default();
+        let size = data
+            .body_text
+            .as_ref()
+            .map_or(data.size, |body| body.len() as u64);
+        rules.score(status, data.headers, body, size)
+    });
+    if let (Some(min_score), Some(score)) = (cli.min_score, score)
+        && score < min_score
+    {
+        return;
     }
-}
 
-/// Write output to file or stdout
-async fn write_output(output_str: String, output_writer: &Option<Arc<Mutex<BufWriter<File>>>>) {
-    if let Some(writer) = output_writer {
-        let mut writer = writer.lock().await;
-        if let Err(e) = writer.write_all(output_str.as_bytes()).await {
-            eprintln!("Error writing to output file: {}", e);
+    // Secrets are masked after filtering and extraction, which see the real values
+    let redactor = &context.redactor;
+    let redacted = (!redactor.is_empty()).then(|| {
+        (
+            redactor.headers(data.headers),
+            data.req_headers
+                .as_ref()
+                .map(|headers| redactor.headers(headers)),
+            data.req_for_display
+                .as_ref()
+                .map(|raw| redactor.raw_request(raw)),
+            data.body_text.as_ref().map(|body| redactor.body(body)),
+        )
+    });
+    // Extracted values that are part of a masked secret are masked whole
+    let extracted = match (&redacted, extracted, data.body_text) {
+        (Some(_), Some(values), Some(body)) => Some(redactor.values(body, values)),
+        (_, extracted, _) => extracted,
+    };
+    let data = ResponseData {
+        directory_listing,
+        score,
+        state: &state,
+        extracted: &extracted,
+        ..data
+    };
+    // Detected before redaction hides the cookie names
+    let cdn = cli
+        .detect_cdn
+        .then(|| detect_cdn(data.ip_addr.parse().ok(), data.headers));
+    let data = match &redacted {
+        Some((headers, req_headers, req_for_display, body_text)) => ResponseData {
+            headers,
+            req_headers,
+            req_for_display,
+            body_text,
+            ..data
+        },
+        None => data,
+    };
+    // Derived from the response as it is printed, so they do not reveal redacted secrets
+    let title = match &redacted {
+        Some(_) if cli.include_title => {
+            data.body_text.as_ref().and_then(|body| extract_title(body))
         }
+        _ => page_title.filter(|_| cli.include_title),
+    };
+    let meta = if cli.include_meta {
+        data.body_text.as_ref().and_then(|body| extract_meta(body))
+    } else {
+        None
+    };
+    let forms = if cli.extract_forms {
+        data.body_text.as_ref().map(|body| extract_forms(body))
+    } else {
+        None
+    };
+    let endpoints = if cli.extract_js_endpoints && is_javascript(data.url_str, data.headers) {
+        data.body_text
+            .as_ref()
+            .map(|body| extract_js_endpoints(body))
     } else {
-        print!("{}", output_str);
+        None
+    };
+    let jwts = cli
+        .decode_jwt
+        .then(|| find_jwts(data.headers, data.body_text.as_deref(), SystemTime::now()));
+    // Located in the body as it is printed, so offsets hold after redaction
+    let filter_match = if cli.res_snippet_around_match || cli.match_context.is_some() {
+        data.body_text
+            .as_deref()
+            .and_then(|body| context.body_filter.find_match(body))
+    } else {
+        None
+    };
+    let match_context = cli
+        .match_context
+        .zip(filter_match.clone())
+        .zip(data.body_text.as_deref())
+        .map(|((chars, range), body)| MatchContext::new(body, range, chars));
+    let resolved_ips = match &context.host_addresses {
+        Some(hosts) => hosts.get(data.url_str).await,
+        None => None,
+    };
+    let asn = context
+        .asn_db
+        .as_ref()
+        .zip(data.ip_addr.parse().ok())
+        .and_then(|(db, ip)| db.lookup(ip));
+    let data = ResponseData {
+        title: &title,
+        meta: &meta,
+        forms: &forms,
+        endpoints: &endpoints,
+        jwts: &jwts,
+        resolved_ips: &resolved_ips,
+        asn: &asn,
+        cdn: &cdn,
+        filter_match,
+        match_context: &match_context,
+        ..data
+    };
+    context.outcome.record_match();
+    context.outputs.write(cli, &data).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::EXIT_ERRORS;
+    use clap::Parser;
+    use serde_json::{Value, json};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve every request with the status of its path, 404 for unknown paths
+    async fn serve(statuses: &'static [(&'static str, u16)]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => head.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let head = String::from_utf8_lossy(&head);
+                let path = head.split_whitespace().nth(1).unwrap_or_default();
+                let status = statuses
+                    .iter()
+                    .find(|(p, _)| *p == path)
+                    .map_or(404, |(_, status)| *status);
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        base
+    }
+
+    /// Run reqs over `requests` (method, URL), given as a HAR file in place of stdin
+    async fn run(name: &str, args: &[&str], requests: &[(&str, String)]) -> i32 {
+        let entries: Vec<Value> = requests
+            .iter()
+            .map(|(method, url)| json!({ "request": { "method": method, "url": url } }))
+            .collect();
+        let path = std::env::temp_dir().join(format!("reqs-{}-{}.har", name, std::process::id()));
+        std::fs::write(&path, json!({ "log": { "entries": entries } }).to_string()).unwrap();
+
+        let mut argv = vec!["reqs", "--har", path.to_str().unwrap()];
+        argv.extend_from_slice(args);
+        let cli = Cli::parse_from(argv);
+        let clients = Arc::new(ClientPool::new(&cli).unwrap());
+        let code = process_urls_from_stdin(cli, clients).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        code
+    }
+
+    #[tokio::test]
+    async fn test_state_recorded_for_status_filtered_response() {
+        let base = serve(&[("/", 200)]).await;
+        let db = std::env::temp_dir().join(format!("reqs-filtered-{}.sqlite", std::process::id()));
+        let db = db.to_str().unwrap();
+        let requests = [
+            ("GET", format!("{}/", base)),
+            ("GET", format!("{}/nope", base)),
+        ];
+        run(
+            "filtered",
+            &["--state-db", db, "--filter-status", "200"],
+            &requests,
+        )
+        .await;
+
+        let state = StateDb::open(db).unwrap();
+        let change = state
+            .update("GET", &format!("{}/nope", base), 404, None, "ok")
+            .unwrap();
+        assert!(!change.new);
+        assert!(!change.changed());
+        drop(state);
+        std::fs::remove_file(db).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_safe_methods_only_before_dry_run_and_cache() {
+        let base = serve(&[("/", 200)]).await;
+        let cache = std::env::temp_dir().join(format!("reqs-unsafe-{}", std::process::id()));
+        let cache = cache.to_str().unwrap();
+        let post = [("POST", format!("{}/", base))];
+        let get = [("GET", format!("{}/", base))];
+        let safe = ["--safe-methods-only", "--fail-on-errors", "1"];
+
+        let dry_run = [&safe[..], &["--dry-run"]].concat();
+        assert_eq!(run("unsafe", &dry_run, &post).await, EXIT_ERRORS);
+        assert_eq!(run("unsafe", &dry_run, &get).await, 0);
+
+        // The POST response is cached, then refused rather than served from the cache
+        assert_eq!(run("unsafe", &["--cache", cache], &post).await, 0);
+        let cached = [&safe[..], &["--cache", cache]].concat();
+        assert_eq!(run("unsafe", &cached, &post).await, EXIT_ERRORS);
+        std::fs::remove_dir_all(cache).unwrap();
     }
 }
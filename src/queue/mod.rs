@@ -0,0 +1,178 @@
+use anyhow::{Context, Result};
+use redis::AsyncConnectionConfig;
+use redis::aio::MultiplexedConnection;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::sync::Mutex;
+
+use crate::constants::{QUEUE_POLL_TIMEOUT, QUEUE_PROCESSING_SUFFIX};
+
+/// Redis list consumed as a work queue (--input-redis)
+///
+/// Items are moved atomically to `<key>:processing` when taken and removed
+/// from it once processed, so items held by a worker that dies can be
+/// pushed back onto the queue. A connection that fails is dropped and
+/// opened again on the next command, so the queue survives Redis restarts.
+pub struct RedisQueue {
+    client: redis::Client,
+    /// Connection blocked waiting for items
+    pop_conn: Mutex<Option<MultiplexedConnection>>,
+    /// Separate connection so acknowledgements are not held up by a pending pop
+    ack_conn: Mutex<Option<MultiplexedConnection>>,
+    key: String,
+    processing_key: String,
+}
+
+impl RedisQueue {
+    pub async fn connect(url: &str, key: &str) -> Result<Self> {
+        let client =
+            redis::Client::open(url).with_context(|| format!("Invalid Redis URL: {}", url))?;
+        let queue = RedisQueue {
+            client,
+            pop_conn: Mutex::new(None),
+            ack_conn: Mutex::new(None),
+            key: key.to_string(),
+            processing_key: format!("{}{}", key, QUEUE_PROCESSING_SUFFIX),
+        };
+        // Fail early on an unreachable server
+        queue
+            .connection(&queue.pop_conn)
+            .await
+            .with_context(|| format!("Failed to connect to Redis: {}", url))?;
+        Ok(queue)
+    }
+
+    /// Take the next item, or None if none arrived within the poll timeout
+    pub async fn pop(&self) -> Result<Option<String>> {
+        let mut conn = self.connection(&self.pop_conn).await?;
+        let result = redis::cmd("BLMOVE")
+            .arg(&self.key)
+            .arg(&self.processing_key)
+            .arg("RIGHT")
+            .arg("LEFT")
+            .arg(QUEUE_POLL_TIMEOUT.as_secs_f64())
+            .query_async(&mut conn)
+            .await;
+        if result.is_err() {
+            self.pop_conn.lock().await.take();
+        }
+        result.with_context(|| format!("Failed to read from Redis list: {}", self.key))
+    }
+
+    /// Remove a processed item from the processing list
+    pub async fn ack(&self, item: &str) -> Result<()> {
+        let mut conn = self.connection(&self.ack_conn).await?;
+        let result: redis::RedisResult<i64> = redis::cmd("LREM")
+            .arg(&self.processing_key)
+            .arg(1)
+            .arg(item)
+            .query_async(&mut conn)
+            .await;
+        if result.is_err() {
+            self.ack_conn.lock().await.take();
+        }
+        result
+            .with_context(|| format!("Failed to acknowledge item in: {}", self.processing_key))?;
+        Ok(())
+    }
+
+    /// The open connection of a slot, connecting first if there is none
+    async fn connection(
+        &self,
+        slot: &Mutex<Option<MultiplexedConnection>>,
+    ) -> Result<MultiplexedConnection> {
+        let mut slot = slot.lock().await;
+        if let Some(conn) = &*slot {
+            return Ok(conn.clone());
+        }
+        // A pop may block for the whole poll timeout, longer than the default response timeout
+        let config = AsyncConnectionConfig::new().set_response_timeout(None);
+        let conn = self
+            .client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await
+            .context("Failed to connect to Redis")?;
+        Ok(slot.insert(conn).clone())
+    }
+}
+
+/// A queue item being processed as one or more requests
+///
+/// The item is to be acknowledged when its last request finishes, unless
+/// one of them was cut off by --max-runtime.
+pub struct Claim {
+    pub item: String,
+    remaining: AtomicUsize,
+    unfinished: AtomicBool,
+}
+
+impl Claim {
+    pub fn new(item: String, requests: usize) -> Self {
+        Claim {
+            item,
+            remaining: AtomicUsize::new(requests),
+            unfinished: AtomicBool::new(false),
+        }
+    }
+
+    /// Record one finished (or dropped) request of the item
+    ///
+    /// Returns true when this was the last request and the item can be acknowledged.
+    pub fn done(&self, finished: bool) -> bool {
+        if !finished {
+            self.unfinished.store(true, Ordering::Relaxed);
+        }
+        self.remaining.fetch_sub(1, Ordering::AcqRel) == 1
+            && !self.unfinished.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_acknowledged_after_last_request() {
+        let claim = Claim::new("https://example.com/{a,b,c}".to_string(), 3);
+        assert!(!claim.done(true));
+        assert!(!claim.done(true));
+        assert!(claim.done(true));
+    }
+
+    #[test]
+    fn test_claim_unfinished_request() {
+        let claim = Claim::new("https://example.com/{a,b}".to_string(), 2);
+        assert!(!claim.done(false));
+        assert!(!claim.done(true));
+
+        // The dropped request may also be the last one to report
+        let claim = Claim::new("https://example.com/{a,b}".to_string(), 2);
+        assert!(!claim.done(true));
+        assert!(!claim.done(false));
+    }
+
+    #[test]
+    fn test_claim_concurrent_requests() {
+        let claim = std::sync::Arc::new(Claim::new("item".to_string(), 64));
+        let handles: Vec<_> = (0..64)
+            .map(|_| {
+                let claim = claim.clone();
+                std::thread::spawn(move || claim.done(true))
+            })
+            .collect();
+        let acks = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .filter(|ack| *ack)
+            .count();
+        assert_eq!(acks, 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_redis_url() {
+        assert!(
+            RedisQueue::connect("not a url", "reqs:queue")
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::{Context, Result, bail};
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::utils::parse_size;
+
+/// Rules file of --score-rules, as written
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    status: BTreeMap<String, i64>,
+    #[serde(default)]
+    keywords: BTreeMap<String, i64>,
+    #[serde(default)]
+    technologies: BTreeMap<String, i64>,
+    #[serde(default)]
+    sizes: Vec<SizeRuleFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SizeRuleFile {
+    min: Option<String>,
+    max: Option<String>,
+    points: i64,
+}
+
+/// Status code, or class such as 5xx
+#[derive(Debug, Clone, PartialEq)]
+enum StatusMatch {
+    Code(u16),
+    Class(u16),
+}
+
+impl StatusMatch {
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.strip_suffix("xx") {
+            Some(class) => match class.parse() {
+                Ok(class @ 1..=5) => Some(StatusMatch::Class(class)),
+                _ => None,
+            },
+            None => value.parse().ok().map(StatusMatch::Code),
+        }
+    }
+
+    fn matches(&self, status: u16) -> bool {
+        match self {
+            StatusMatch::Code(code) => status == *code,
+            StatusMatch::Class(class) => status / 100 == *class,
+        }
+    }
+}
+
+/// Points given to each response (--score-rules)
+///
+/// A response scores the sum of the points of every rule it matches.
+#[derive(Debug, Clone, Default)]
+pub struct ScoreRules {
+    status: Vec<(StatusMatch, i64)>,
+    /// Lowercase keywords found in the body
+    keywords: Vec<(String, i64)>,
+    /// Lowercase names found in the Server and X-Powered-By headers
+    technologies: Vec<(String, i64)>,
+    /// Inclusive body size ranges
+    sizes: Vec<(u64, u64, i64)>,
+}
+
+impl ScoreRules {
+    /// Load score rules from a YAML file
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read score rules: {}", path))?;
+        Self::parse(&content).with_context(|| format!("Invalid score rules: {}", path))
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        let file: RulesFile = serde_yaml::from_str(content)?;
+        let mut rules = ScoreRules::default();
+        for (status, points) in file.status {
+            match StatusMatch::parse(&status) {
+                Some(status) => rules.status.push((status, points)),
+                None => bail!(
+                    "Invalid status '{}'. Expected a code or class such as 5xx",
+                    status
+                ),
+            }
+        }
+        let lowercase = |(name, points): (String, i64)| (name.to_lowercase(), points);
+        rules.keywords = file.keywords.into_iter().map(lowercase).collect();
+        rules.technologies = file.technologies.into_iter().map(lowercase).collect();
+        for size in file.sizes {
+            let bound = |value: Option<String>, default| match value {
+                Some(value) => parse_size(&value).map_err(anyhow::Error::msg),
+                None => Ok(default),
+            };
+            let min = bound(size.min, 0)?;
+            let max = bound(size.max, u64::MAX)?;
+            rules.sizes.push((min, max, size.points));
+        }
+        Ok(rules)
+    }
+
+    /// Whether scoring needs the response body
+    pub fn needs_body(&self) -> bool {
+        !self.keywords.is_empty() || !self.sizes.is_empty()
+    }
+
+    /// Score of a response
+    pub fn score(&self, status: u16, headers: &HeaderMap, body: &str, size: u64) -> i64 {
+        let mut score = 0;
+        for (status_match, points) in &self.status {
+            if status_match.matches(status) {
+                score += points;
+            }
+        }
+        if !self.keywords.is_empty() {
+            let body = body.to_lowercase();
+            for (keyword, points) in &self.keywords {
+                if body.contains(keyword.as_str()) {
+                    score += points;
+                }
+            }
+        }
+        if !self.technologies.is_empty() {
+            let software = ["server", "x-powered-by"]
+                .iter()
+                .flat_map(|name| headers.get_all(*name))
+                .filter_map(|value| value.to_str().ok())
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase();
+            for (technology, points) in &self.technologies {
+                if software.contains(technology.as_str()) {
+                    score += points;
+                }
+            }
+        }
+        for (min, max, points) in &self.sizes {
+            if (*min..=*max).contains(&size) {
+                score += points;
+            }
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+status:
+  200: 10
+  401: 30
+  5xx: 20
+keywords:
+  admin: 25
+  Password: 40
+technologies:
+  jenkins: 50
+  php: 10
+sizes:
+  - max: 0
+    points: -20
+  - min: 100KB
+    points: 5
+"#;
+
+    #[test]
+    fn test_score_rules() {
+        let rules = ScoreRules::parse(RULES).unwrap();
+        assert!(rules.needs_body());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("server", "Jetty(9.4)".parse().unwrap());
+        headers.insert("x-jenkins", "2.401".parse().unwrap());
+        assert_eq!(rules.score(200, &headers, "<h1>Admin login</h1>", 20), 35);
+
+        headers.insert("x-powered-by", "PHP/8.2".parse().unwrap());
+        assert_eq!(rules.score(503, &headers, "", 0), 10);
+        assert_eq!(
+            rules.score(401, &HeaderMap::new(), "password", 200 * 1024),
+            75
+        );
+    }
+
+    #[test]
+    fn test_score_rules_invalid() {
+        assert!(ScoreRules::parse("status:\n  6xx: 10\n").is_err());
+        assert!(ScoreRules::parse("status:\n  ok: 10\n").is_err());
+        assert!(ScoreRules::parse("sizes:\n  - min: big\n    points: 1\n").is_err());
+        assert!(ScoreRules::parse("colors:\n  red: 1\n").is_err());
+    }
+}
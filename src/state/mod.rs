@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde_json::{Value, json};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::http::cache::fnv1a;
+
+/// Schema of the state database; safe to run against an existing file
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS url_state (
+    method TEXT NOT NULL,
+    url TEXT NOT NULL,
+    status_code INTEGER NOT NULL,
+    title TEXT,
+    body_hash TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (method, url)
+);
+";
+
+/// Difference of a response from the last recorded one (--state-db)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StateChange {
+    /// Whether the URL had no recorded state yet
+    pub new: bool,
+    /// Fields that differ from the last run: status, title and body
+    pub fields: Vec<&'static str>,
+}
+
+impl StateChange {
+    pub fn changed(&self) -> bool {
+        !self.fields.is_empty()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "changed": self.changed(),
+            "new": self.new,
+            "changed_fields": self.fields,
+        })
+    }
+}
+
+/// Per-URL status, title and body hash kept between runs
+pub struct StateDb {
+    conn: Connection,
+}
+
+impl StateDb {
+    /// Open (or create) the state database
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open state database: {}", path))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to initialize state database schema")?;
+        Ok(StateDb { conn })
+    }
+
+    /// Compare a response with the recorded state of its URL and record it
+    pub fn update(
+        &self,
+        method: &str,
+        url: &str,
+        status: u16,
+        title: Option<&str>,
+        body: &str,
+    ) -> Result<StateChange> {
+        let body_hash = format!("{:016x}", fnv1a(body.as_bytes()));
+        let previous: Option<(u16, Option<String>, String)> = self
+            .conn
+            .query_row(
+                "SELECT status_code, title, body_hash FROM url_state WHERE method = ?1 AND url = ?2",
+                params![method, url],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        let change = match previous {
+            None => StateChange {
+                new: true,
+                fields: Vec::new(),
+            },
+            Some((old_status, old_title, old_hash)) => {
+                let mut fields = Vec::new();
+                if old_status != status {
+                    fields.push("status");
+                }
+                if old_title.as_deref() != title {
+                    fields.push("title");
+                }
+                if old_hash != body_hash {
+                    fields.push("body");
+                }
+                StateChange { new: false, fields }
+            }
+        };
+
+        self.conn.execute(
+            "INSERT OR REPLACE INTO url_state (method, url, status_code, title, body_hash, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![method, url, status, title, body_hash, unix_time()],
+        )?;
+        Ok(change)
+    }
+}
+
+fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_db_detects_changes() {
+        let path = std::env::temp_dir().join(format!("reqs-state-{}.sqlite", std::process::id()));
+        let path = path.to_str().unwrap();
+        let url = "https://example.com/";
+
+        let db = StateDb::open(path).unwrap();
+        let change = db.update("GET", url, 200, Some("Home"), "hello").unwrap();
+        assert!(change.new);
+        assert!(!change.changed());
+        drop(db);
+
+        // State survives across runs
+        let db = StateDb::open(path).unwrap();
+        let change = db.update("GET", url, 200, Some("Home"), "hello").unwrap();
+        assert_eq!(change, StateChange::default());
+        let change = db.update("GET", url, 500, None, "defaced").unwrap();
+        assert_eq!(change.fields, vec!["status", "title", "body"]);
+        assert_eq!(change.to_json()["changed"], true);
+        drop(db);
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+}
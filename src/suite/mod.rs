@@ -0,0 +1,444 @@
+use anyhow::{Context, Result, anyhow, bail};
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::constants::EXIT_EXPECT_FAILED;
+use crate::expect::{Expectation, verdict_label};
+use crate::http::client::build_http_client;
+use crate::http::{
+    build_request, default_request_headers, request_headers, send_request, uses_random_user_agent,
+};
+use crate::output::report::{TestResult, render_junit, render_report};
+use crate::types::Cli;
+use crate::utils::{TemplateVars, parse_duration};
+
+/// A YAML test suite run by `reqs test <file>`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub tests: Vec<TestCase>,
+}
+
+/// A request and the assertions checked against its response
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub expect: Assertions,
+    /// Variables extracted from the response for later steps (`{{name}}`)
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub extract: BTreeMap<String, Extractor>,
+}
+
+/// Where a variable is extracted from in a response
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Extractor {
+    /// First capture group (or the whole match) of a regex on the body
+    Regex(String),
+    /// Dotted path in a JSON body
+    Json(String),
+    /// Value of a response header
+    Header(String),
+}
+
+/// Assertions of a test case
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Assertions {
+    #[serde(default)]
+    status: Option<StatusList>,
+    /// Header name -> substring expected in its value
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// Dotted JSON path (e.g. `data.items.0.id`) -> expected value
+    #[serde(default)]
+    json: BTreeMap<String, Value>,
+    #[serde(default)]
+    body_regex: Option<String>,
+    #[serde(default)]
+    max_latency: Option<String>,
+}
+
+/// A single status code or a list of accepted ones
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum StatusList {
+    One(u16),
+    Many(Vec<u16>),
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+impl Suite {
+    /// Load and validate a suite file
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read test suite: {}", path))?;
+        let suite: Suite = serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse test suite: {}", path))?;
+        if suite.tests.is_empty() {
+            bail!("Test suite {} has no tests", path);
+        }
+        for case in &suite.tests {
+            case.expect
+                .compile()
+                .with_context(|| format!("Invalid assertions in test '{}'", case.name()))?;
+            for (name, extractor) in &case.extract {
+                if let Extractor::Regex(re) = extractor {
+                    Regex::new(re).with_context(|| {
+                        format!("Invalid regex for '{}' in test '{}'", name, case.name())
+                    })?;
+                }
+            }
+        }
+        Ok(suite)
+    }
+}
+
+impl TestCase {
+    /// Display name (the request line if no name is given)
+    pub fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", self.method.to_uppercase(), self.url))
+    }
+}
+
+impl Assertions {
+    /// Status/body expectation and latency limit of the assertions
+    fn compile(&self) -> Result<(Option<Expectation>, Option<Duration>)> {
+        let status = match &self.status {
+            Some(StatusList::One(status)) => vec![*status],
+            Some(StatusList::Many(statuses)) => statuses.clone(),
+            None => Vec::new(),
+        };
+        let expectation = Expectation::new(&status, self.body_regex.as_deref())?;
+        let max_latency = self
+            .max_latency
+            .as_deref()
+            .map(parse_duration)
+            .transpose()
+            .map_err(|e| anyhow!("max_latency: {}", e))?;
+        Ok((expectation, max_latency))
+    }
+
+    /// Check a response, returning the reason of the first failed assertion
+    pub fn check(
+        &self,
+        status: u16,
+        headers: &HeaderMap,
+        body: &str,
+        elapsed: Duration,
+    ) -> Result<(), String> {
+        let (expectation, max_latency) = self.compile().map_err(|e| e.to_string())?;
+        if let Some(expectation) = expectation {
+            expectation.check(status, Some(body))?;
+        }
+
+        for (name, expected) in &self.headers {
+            match headers.get(name.as_str()).and_then(|v| v.to_str().ok()) {
+                Some(value) if value.contains(expected.as_str()) => {}
+                Some(value) => {
+                    return Err(format!(
+                        "header {} is '{}', expected '{}'",
+                        name, value, expected
+                    ));
+                }
+                None => return Err(format!("header {} is missing", name)),
+            }
+        }
+
+        if !self.json.is_empty() {
+            let document: Value =
+                serde_json::from_str(body).map_err(|e| format!("body is not valid JSON: {}", e))?;
+            for (path, expected) in &self.json {
+                match json_lookup(&document, path) {
+                    Some(value) if value == expected => {}
+                    Some(value) => {
+                        return Err(format!("json {} is {}, expected {}", path, value, expected));
+                    }
+                    None => return Err(format!("json {} is missing", path)),
+                }
+            }
+        }
+
+        if let Some(max_latency) = max_latency
+            && elapsed > max_latency
+        {
+            return Err(format!(
+                "latency {}ms exceeds {}ms",
+                elapsed.as_millis(),
+                max_latency.as_millis()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Extractor {
+    /// Extract the value from a response
+    fn extract(&self, headers: &HeaderMap, body: &str) -> Option<String> {
+        match self {
+            Extractor::Regex(re) => {
+                let captures = Regex::new(re).ok()?.captures(body)?;
+                let value = captures.get(1).or_else(|| captures.get(0))?;
+                Some(value.as_str().to_string())
+            }
+            Extractor::Json(path) => {
+                let document: Value = serde_json::from_str(body).ok()?;
+                match json_lookup(&document, path)? {
+                    Value::String(value) => Some(value.clone()),
+                    value => Some(value.to_string()),
+                }
+            }
+            Extractor::Header(name) => headers
+                .get(name.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+        }
+    }
+}
+
+/// Extract the variables of a step, failing on the first one that is not found
+fn extract_vars(
+    case: &TestCase,
+    headers: &HeaderMap,
+    body: &str,
+    vars: &mut TemplateVars,
+) -> Result<(), String> {
+    for (name, extractor) in &case.extract {
+        let value = extractor
+            .extract(headers, body)
+            .ok_or_else(|| format!("could not extract {}", name))?;
+        debug!(name = %name, value = %value, "Extracted variable");
+        vars.insert(name.clone(), value);
+    }
+    Ok(())
+}
+
+/// Look up a dotted path (object keys and array indices) in a JSON document
+fn json_lookup<'a>(document: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(document, |value, segment| match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// Run a suite file, print a PASS/FAIL line per test and return the exit code
+pub async fn run_suite(cli: &Cli, path: &str, junit: Option<&str>) -> Result<i32> {
+    let suite = Suite::load(path)?;
+    let suite_name = suite.name.clone().unwrap_or_else(|| path.to_string());
+    let client = build_http_client(cli)?;
+    let default_headers = default_request_headers(cli);
+    let colored = !cli.no_color && std::io::stdout().is_terminal();
+
+    let mut results = Vec::new();
+    let mut vars = TemplateVars::from_cli(cli)?;
+    for case in &suite.tests {
+        let method = case.method.to_uppercase();
+        let url = vars.render(&case.url);
+        let body = case.body.as_ref().map(|body| vars.render(body));
+        let mut request_builder = build_request(&client, &method, &url, &body).headers(
+            request_headers(&default_headers, uses_random_user_agent(cli)),
+        );
+        for (name, value) in &case.headers {
+            request_builder = request_builder.header(name, vars.render(value));
+        }
+
+        let start = Instant::now();
+        let sent = send_request(cli, None, request_builder)
+            .await
+            .map_err(|e| e.to_string());
+        let (status, result) = match sent {
+            Ok(resp) => {
+                let status = resp.status().as_u16();
+                let headers = resp.headers().clone();
+                let body = resp.text().await.unwrap_or_default();
+                let elapsed = start.elapsed();
+                let result = case
+                    .expect
+                    .check(status, &headers, &body, elapsed)
+                    .and_then(|()| extract_vars(case, &headers, &body, &mut vars));
+                (Some(status), result)
+            }
+            Err(e) => (None, Err(e)),
+        };
+        let elapsed = start.elapsed();
+
+        let mut line = format!("{} {}", verdict_label(result.is_ok(), colored), case.name());
+        if let Some(status) = status {
+            line.push_str(&format!(" -> {}", status));
+        }
+        line.push_str(&format!(" ({}ms)", elapsed.as_millis()));
+        if let Err(reason) = &result {
+            line.push_str(&format!(": {}", reason));
+        }
+        if !cli.format.is_report() {
+            println!("{}", line);
+        }
+
+        results.push(TestResult {
+            name: case.name(),
+            elapsed,
+            failure: result.err(),
+        });
+    }
+
+    let failed = results.iter().filter(|r| r.failure.is_some()).count();
+    if cli.format.is_report() {
+        // --format junit/tap replaces the PASS/FAIL lines with the report
+        let report = render_report(&cli.format, &suite_name, &results);
+        match &cli.output {
+            Some(path) => std::fs::write(path, report)
+                .with_context(|| format!("Failed to write output file: {}", path))?,
+            None => print!("{}", report),
+        }
+    } else {
+        println!(
+            "\n{} tests: {} passed, {} failed",
+            results.len(),
+            results.len() - failed,
+            failed
+        );
+    }
+
+    if let Some(junit_path) = junit {
+        std::fs::write(junit_path, render_junit(&suite_name, &results))
+            .with_context(|| format!("Failed to write JUnit report: {}", junit_path))?;
+    }
+
+    Ok(if failed > 0 { EXIT_EXPECT_FAILED } else { 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SUITE: &str = r#"
+name: api
+tests:
+  - name: health
+    url: https://api.example.com/health
+    expect:
+      status: 200
+      headers:
+        content-type: application/json
+      json:
+        status: ok
+        data.items.1.id: 7
+      max_latency: 500ms
+  - method: post
+    url: https://api.example.com/login
+    headers:
+      Content-Type: application/json
+    body: '{"user":"a"}'
+    expect:
+      status: [200, 302]
+"#;
+
+    #[test]
+    fn test_parse_suite() {
+        let suite: Suite = serde_yaml::from_str(SUITE).unwrap();
+        assert_eq!(suite.name.as_deref(), Some("api"));
+        assert_eq!(suite.tests.len(), 2);
+        assert_eq!(suite.tests[0].method, "GET");
+        assert_eq!(suite.tests[1].name(), "POST https://api.example.com/login");
+        assert!(suite.tests[1].expect.compile().is_ok());
+    }
+
+    #[test]
+    fn test_assertions_check() {
+        let suite: Suite = serde_yaml::from_str(SUITE).unwrap();
+        let assertions = &suite.tests[0].expect;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "content-type",
+            "application/json; charset=utf-8".parse().unwrap(),
+        );
+        let body = json!({"status": "ok", "data": {"items": [{"id": 3}, {"id": 7}]}}).to_string();
+        let fast = Duration::from_millis(20);
+
+        assert_eq!(assertions.check(200, &headers, &body, fast), Ok(()));
+        assert_eq!(
+            assertions.check(200, &headers, &body, Duration::from_secs(1)),
+            Err("latency 1000ms exceeds 500ms".to_string())
+        );
+        assert_eq!(
+            assertions.check(200, &HeaderMap::new(), &body, fast),
+            Err("header content-type is missing".to_string())
+        );
+        assert_eq!(
+            assertions.check(
+                200,
+                &headers,
+                r#"{"status":"down","data":{"items":[{},{"id":7}]}}"#,
+                fast
+            ),
+            Err(r#"json status is "down", expected "ok""#.to_string())
+        );
+        assert!(assertions.check(500, &headers, &body, fast).is_err());
+    }
+
+    #[test]
+    fn test_extract_vars() {
+        let case: TestCase = serde_yaml::from_str(
+            r#"
+url: https://a.com/login
+extract:
+  csrf:
+    regex: '"csrf":\s*"([^"]+)"'
+  user_id:
+    json: data.id
+  session:
+    header: set-cookie
+"#,
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("set-cookie", "sid=abc".parse().unwrap());
+        let body = r#"{"data":{"id":42},"csrf": "t0k"}"#;
+
+        let mut vars = TemplateVars::default();
+        extract_vars(&case, &headers, body, &mut vars).unwrap();
+        assert_eq!(
+            vars.render("{{csrf}} {{user_id}} {{session}}"),
+            "t0k 42 sid=abc"
+        );
+
+        assert_eq!(
+            extract_vars(&case, &HeaderMap::new(), body, &mut vars),
+            Err("could not extract session".to_string())
+        );
+    }
+
+    #[test]
+    fn test_json_lookup() {
+        let doc = json!({"a": {"b": [1, {"c": true}]}});
+        assert_eq!(json_lookup(&doc, "a.b.1.c"), Some(&json!(true)));
+        assert_eq!(json_lookup(&doc, "a.b.5"), None);
+        assert_eq!(json_lookup(&doc, "a.x"), None);
+    }
+}
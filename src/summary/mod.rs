@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::http::ByteCount;
+use crate::utils::format_size;
+
+/// Aggregated statistics for a single host
+#[derive(Debug, Default)]
+struct HostStats {
+    latencies: Vec<Duration>,
+    errors: u64,
+    /// Requests not sent because of --max-host-errors
+    skipped: u64,
+}
+
+impl HostStats {
+    fn requests(&self) -> u64 {
+        self.latencies.len() as u64 + self.errors
+    }
+}
+
+/// Run summary collected while requests are processed
+#[derive(Debug, Default)]
+pub struct Summary {
+    hosts: BTreeMap<String, HostStats>,
+    status_counts: BTreeMap<u16, u64>,
+    /// Totals of --count-bytes
+    bytes: ByteCount,
+}
+
+impl Summary {
+    /// Record a received response
+    pub fn record_response(&mut self, url: &str, status: u16, elapsed: Duration) {
+        self.hosts
+            .entry(host_of(url))
+            .or_default()
+            .latencies
+            .push(elapsed);
+        *self.status_counts.entry(status).or_default() += 1;
+    }
+
+    /// Record the bytes sent and received for a request
+    pub fn record_bytes(&mut self, bytes: ByteCount) {
+        self.bytes.up += bytes.up;
+        self.bytes.down += bytes.down;
+    }
+
+    /// Record a request that failed without a response
+    pub fn record_error(&mut self, url: &str) {
+        self.hosts.entry(host_of(url)).or_default().errors += 1;
+    }
+
+    /// Record a request that was skipped without being sent
+    pub fn record_skipped(&mut self, url: &str) {
+        self.hosts.entry(host_of(url)).or_default().skipped += 1;
+    }
+
+    /// Render the summary report
+    pub fn render(&self, total_elapsed: Duration) -> String {
+        let requests: u64 = self.hosts.values().map(HostStats::requests).sum();
+        let errors: u64 = self.hosts.values().map(|h| h.errors).sum();
+        let skipped: u64 = self.hosts.values().map(|h| h.skipped).sum();
+
+        let mut report = format!(
+            "[Summary] Requests: {} | Responses: {} | Errors: {}{} | Elapsed: {:?}\n",
+            requests,
+            requests - errors,
+            errors,
+            format_skipped(skipped),
+            total_elapsed
+        );
+
+        if requests > 0 {
+            let mut latencies: Vec<Duration> = self
+                .hosts
+                .values()
+                .flat_map(|h| h.latencies.iter().copied())
+                .collect();
+            latencies.sort();
+            let mean = if latencies.is_empty() {
+                "-".to_string()
+            } else {
+                format!(
+                    "{:?}",
+                    latencies.iter().sum::<Duration>() / latencies.len() as u32
+                )
+            };
+            report.push_str(&format!(
+                "[Summary] RPS: {:.2} | Error rate: {:.1}% | Latency min: {} | mean: {} | p50: {} | p90: {} | p99: {} | max: {}\n",
+                requests as f64 / total_elapsed.as_secs_f64().max(f64::EPSILON),
+                errors as f64 / requests as f64 * 100.0,
+                format_percentile(&latencies, 0.0),
+                mean,
+                format_percentile(&latencies, 50.0),
+                format_percentile(&latencies, 90.0),
+                format_percentile(&latencies, 99.0),
+                format_percentile(&latencies, 100.0),
+            ));
+        }
+
+        if !self.status_counts.is_empty() {
+            let statuses = self
+                .status_counts
+                .iter()
+                .map(|(status, count)| format!("{}={}", status, count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            report.push_str(&format!("[Summary] Status: {}\n", statuses));
+        }
+
+        if self.bytes != ByteCount::default() {
+            report.push_str(&format!(
+                "[Summary] Sent: {} | Received: {}\n",
+                format_size(self.bytes.up),
+                format_size(self.bytes.down)
+            ));
+        }
+
+        for (host, stats) in &self.hosts {
+            let mut latencies = stats.latencies.clone();
+            latencies.sort();
+            let error_rate = stats.errors as f64 / stats.requests() as f64 * 100.0;
+            report.push_str(&format!(
+                "[Host] {} | Requests: {} | Errors: {} ({:.1}%){} | p50: {} | p90: {} | p99: {}\n",
+                host,
+                stats.requests(),
+                stats.errors,
+                error_rate,
+                format_skipped(stats.skipped),
+                format_percentile(&latencies, 50.0),
+                format_percentile(&latencies, 90.0),
+                format_percentile(&latencies, 99.0),
+            ));
+        }
+
+        report
+    }
+}
+
+/// Nearest-rank percentile over sorted latencies
+pub fn percentile(sorted: &[Duration], pct: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn format_percentile(sorted: &[Duration], pct: f64) -> String {
+    percentile(sorted, pct)
+        .map(|d| format!("{:?}", d))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn format_skipped(skipped: u64) -> String {
+    if skipped > 0 {
+        format!(" | Skipped: {}", skipped)
+    } else {
+        String::new()
+    }
+}
+
+/// Host (with port, if any) of a URL, used as the grouping key
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| {
+            u.host_str().map(|host| match u.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            })
+        })
+        .unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(
+            percentile(&latencies, 50.0),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(
+            percentile(&latencies, 90.0),
+            Some(Duration::from_millis(90))
+        );
+        assert_eq!(
+            percentile(&latencies, 99.0),
+            Some(Duration::from_millis(99))
+        );
+        assert_eq!(
+            percentile(&[Duration::from_millis(7)], 99.0),
+            Some(Duration::from_millis(7))
+        );
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_summary_render() {
+        let mut summary = Summary::default();
+        summary.record_response("https://example.com/a", 200, Duration::from_millis(10));
+        summary.record_response("https://example.com/b", 404, Duration::from_millis(30));
+        summary.record_error("https://example.com/c");
+        summary.record_error("http://dead.example.com:8080/");
+        summary.record_skipped("http://dead.example.com:8080/other");
+        summary.record_bytes(ByteCount {
+            up: 100,
+            down: 1000,
+        });
+        summary.record_bytes(ByteCount {
+            up: 100,
+            down: 1048,
+        });
+
+        let report = summary.render(Duration::from_secs(1));
+        assert!(report.contains("Requests: 4 | Responses: 2 | Errors: 2 | Skipped: 1"));
+        assert!(report.contains("Status: 200=1, 404=1"));
+        assert!(report.contains("Sent: 200 B | Received: 2.0 KB"));
+        assert!(report.contains(
+            "RPS: 4.00 | Error rate: 50.0% | Latency min: 10ms | mean: 20ms | p50: 10ms"
+        ));
+        assert!(report.contains("max: 30ms"));
+        assert!(report.contains(
+            "[Host] example.com | Requests: 3 | Errors: 1 (33.3%) | p50: 10ms | p90: 30ms"
+        ));
+        assert!(report.contains(
+            "[Host] dead.example.com:8080 | Requests: 1 | Errors: 1 (100.0%) | Skipped: 1 | p50: -"
+        ));
+    }
+}
@@ -0,0 +1,538 @@
+use anyhow::{Result, anyhow};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::constants::{CONTROL_CONCURRENCY_STEP, CONTROL_RATE_STEP, TUI_TICK};
+use crate::control::RunControl;
+
+/// A result shown in the TUI
+#[derive(Debug, Clone, Default)]
+pub struct TuiRecord {
+    pub method: String,
+    pub url: String,
+    pub host: String,
+    /// Status code, or None for requests that failed without a response
+    pub status: Option<u16>,
+    /// Status line, or the error of a failed request
+    pub status_label: String,
+    pub size: u64,
+    pub elapsed: Duration,
+    pub title: Option<String>,
+    /// Raw request (with --include-req)
+    pub request: Option<String>,
+    pub headers: String,
+    pub body: Option<String>,
+}
+
+enum TuiEvent {
+    Record(Box<TuiRecord>),
+    Done,
+}
+
+/// Column the result table is sorted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortColumn {
+    Arrival,
+    Status,
+    Size,
+    Time,
+    Host,
+}
+
+impl SortColumn {
+    fn next(self) -> Self {
+        match self {
+            SortColumn::Arrival => SortColumn::Status,
+            SortColumn::Status => SortColumn::Size,
+            SortColumn::Size => SortColumn::Time,
+            SortColumn::Time => SortColumn::Host,
+            SortColumn::Host => SortColumn::Arrival,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortColumn::Arrival => "arrival",
+            SortColumn::Status => "status",
+            SortColumn::Size => "size",
+            SortColumn::Time => "time",
+            SortColumn::Host => "host",
+        }
+    }
+}
+
+/// Interactive result browser running while the scan goes on (--tui)
+///
+/// The terminal is driven from its own thread; results are sent over a channel.
+pub struct TuiSession {
+    sender: Sender<TuiEvent>,
+    handle: Mutex<Option<JoinHandle<Result<()>>>>,
+    quit: Arc<AtomicBool>,
+}
+
+impl TuiSession {
+    /// Take over the terminal and start the UI thread
+    ///
+    /// With a run control, the UI can pause the run and change its concurrency and rate.
+    pub fn start(control: Option<Arc<RunControl>>) -> Result<Self> {
+        let terminal = ratatui::try_init()?;
+        let (sender, receiver) = channel();
+        let quit = Arc::new(AtomicBool::new(false));
+        let app = App::new(receiver, quit.clone(), control);
+        let handle = std::thread::spawn(move || {
+            let result = app.run(terminal);
+            ratatui::try_restore()?;
+            result
+        });
+        Ok(TuiSession {
+            sender,
+            handle: Mutex::new(Some(handle)),
+            quit,
+        })
+    }
+
+    pub fn send(&self, record: TuiRecord) {
+        let _ = self.sender.send(TuiEvent::Record(Box::new(record)));
+    }
+
+    /// Whether the user closed the UI; remaining requests are skipped
+    pub fn quit(&self) -> bool {
+        self.quit.load(Ordering::Relaxed)
+    }
+
+    /// Mark the scan as done and wait until the user closes the UI
+    pub async fn finish(&self) -> Result<()> {
+        let _ = self.sender.send(TuiEvent::Done);
+        let Some(handle) = self.handle.lock().await.take() else {
+            return Ok(());
+        };
+        tokio::task::spawn_blocking(move || handle.join())
+            .await?
+            .map_err(|_| anyhow!("TUI thread panicked"))?
+    }
+}
+
+/// State of the UI
+struct App {
+    receiver: Receiver<TuiEvent>,
+    quit: Arc<AtomicBool>,
+    control: Option<Arc<RunControl>>,
+    records: Vec<TuiRecord>,
+    /// Indices of the visible records, filtered and sorted
+    view: Vec<usize>,
+    table: TableState,
+    sort: SortColumn,
+    filter: String,
+    editing_filter: bool,
+    detail: bool,
+    detail_scroll: u16,
+    done: bool,
+}
+
+impl App {
+    fn new(
+        receiver: Receiver<TuiEvent>,
+        quit: Arc<AtomicBool>,
+        control: Option<Arc<RunControl>>,
+    ) -> Self {
+        App {
+            receiver,
+            quit,
+            control,
+            records: Vec::new(),
+            view: Vec::new(),
+            table: TableState::default(),
+            sort: SortColumn::Arrival,
+            filter: String::new(),
+            editing_filter: false,
+            detail: false,
+            detail_scroll: 0,
+            done: false,
+        }
+    }
+
+    fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
+        loop {
+            self.receive();
+            terminal.draw(|frame| self.draw(frame))?;
+            if event::poll(TUI_TICK)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key)
+            {
+                self.quit.store(true, Ordering::Relaxed);
+                // Let paused workers run so they can see the quit
+                if let Some(control) = &self.control {
+                    control.set_paused(false);
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    /// Take the results that arrived since the last frame
+    fn receive(&mut self) {
+        let mut changed = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(TuiEvent::Record(record)) => {
+                    self.records.push(*record);
+                    changed = true;
+                }
+                Ok(TuiEvent::Done) | Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+        if changed {
+            self.refresh();
+        }
+    }
+
+    /// Rebuild the visible rows, keeping the selected record selected
+    fn refresh(&mut self) {
+        let selected = self.selected_index();
+        self.view = (0..self.records.len())
+            .filter(|&i| matches_filter(&self.records[i], &self.filter))
+            .collect();
+        let records = &self.records;
+        match self.sort {
+            SortColumn::Arrival => {}
+            SortColumn::Status => self.view.sort_by_key(|&i| records[i].status),
+            SortColumn::Size => self.view.sort_by_key(|&i| records[i].size),
+            SortColumn::Time => self.view.sort_by_key(|&i| records[i].elapsed),
+            SortColumn::Host => self
+                .view
+                .sort_by(|&a, &b| records[a].host.cmp(&records[b].host)),
+        }
+        let position = selected
+            .and_then(|index| self.view.iter().position(|&i| i == index))
+            .or((!self.view.is_empty()).then_some(0));
+        self.table.select(position);
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        self.table
+            .selected()
+            .and_then(|row| self.view.get(row).copied())
+    }
+
+    /// Handle a key press; returns false when the UI should close
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => self.editing_filter = false,
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.refresh();
+                }
+                KeyCode::Char(c) => {
+                    self.filter.push(c);
+                    self.refresh();
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Esc if self.detail => self.detail = false,
+            KeyCode::Esc => return false,
+            KeyCode::Enter => {
+                self.detail = !self.detail;
+                self.detail_scroll = 0;
+            }
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.refresh();
+            }
+            KeyCode::Char(c @ ('p' | '+' | '-' | ']' | '[')) => {
+                if let Some(control) = &self.control {
+                    match c {
+                        'p' => control.toggle_pause(),
+                        '+' => control.adjust_concurrency(CONTROL_CONCURRENCY_STEP),
+                        '-' => control.adjust_concurrency(-CONTROL_CONCURRENCY_STEP),
+                        ']' => control.adjust_rate_limit(CONTROL_RATE_STEP),
+                        _ => control.adjust_rate_limit(-CONTROL_RATE_STEP),
+                    };
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') if self.detail => {
+                self.detail_scroll = self.detail_scroll.saturating_add(1)
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.detail => {
+                self.detail_scroll = self.detail_scroll.saturating_sub(1)
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::PageDown => self.table.scroll_down_by(20),
+            KeyCode::PageUp => self.table.scroll_up_by(20),
+            KeyCode::Home | KeyCode::Char('g') => self.table.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.table.select_last(),
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header_area, main_area, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let status = self.control.as_ref().map(|control| control.status());
+        let state = if self.done {
+            "done"
+        } else if status.is_some_and(|status| status.paused) {
+            "paused"
+        } else {
+            "running"
+        };
+        let settings = status.map_or(String::new(), |status| {
+            format!(
+                " | concurrency: {} | rate: {}",
+                match status.concurrency {
+                    0 => "unlimited".to_string(),
+                    concurrency => concurrency.to_string(),
+                },
+                status
+                    .rate_limit
+                    .map_or("unlimited".to_string(), |rate| format!("{}/s", rate))
+            )
+        });
+        let filter = if self.filter.is_empty() && !self.editing_filter {
+            String::new()
+        } else {
+            format!(
+                " | filter: {}{}",
+                self.filter,
+                if self.editing_filter { "_" } else { "" }
+            )
+        };
+        frame.render_widget(
+            Line::from(format!(
+                " reqs | {} | {}/{} results | sort: {}{}{}",
+                state,
+                self.view.len(),
+                self.records.len(),
+                self.sort.label(),
+                settings,
+                filter
+            ))
+            .style(Style::new().add_modifier(Modifier::REVERSED)),
+            header_area,
+        );
+
+        let (table_area, detail_area) = if self.detail {
+            let [table, detail] =
+                Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)])
+                    .areas(main_area);
+            (table, Some(detail))
+        } else {
+            (main_area, None)
+        };
+
+        let rows = self.view.iter().map(|&i| {
+            let record = &self.records[i];
+            let color = match record.status {
+                Some(200..=299) => Color::Green,
+                Some(300..=399) => Color::Yellow,
+                _ => Color::Red,
+            };
+            let target = match &record.title {
+                Some(title) => format!("{} ({})", record.url, title),
+                None => record.url.clone(),
+            };
+            Row::new(vec![
+                record
+                    .status
+                    .map_or("ERR".to_string(), |status| status.to_string()),
+                record.size.to_string(),
+                format!("{}ms", record.elapsed.as_millis()),
+                record.method.clone(),
+                record.host.clone(),
+                target,
+            ])
+            .style(Style::new().fg(color))
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(6),
+                Constraint::Length(9),
+                Constraint::Length(8),
+                Constraint::Length(7),
+                Constraint::Length(24),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(
+            Row::new(vec!["Status", "Size", "Time", "Method", "Host", "URL"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered())
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        if let Some(area) = detail_area {
+            let text = self
+                .selected_index()
+                .map(|i| detail_text(&self.records[i]))
+                .unwrap_or_default();
+            frame.render_widget(
+                Paragraph::new(text)
+                    .block(Block::bordered().title(" Detail "))
+                    .wrap(Wrap { trim: false })
+                    .scroll((self.detail_scroll, 0)),
+                area,
+            );
+        }
+
+        frame.render_widget(
+            Line::from(
+                " j/k move  Enter detail  s sort  / filter (status:4xx host:api size:>1000 text)  p pause  +/- concurrency  ]/[ rate  q quit",
+            )
+            .style(Style::new().fg(Color::DarkGray)),
+            footer_area,
+        );
+    }
+}
+
+/// Text of the detail pane: request, status, response headers and body
+fn detail_text(record: &TuiRecord) -> String {
+    let mut text = match &record.request {
+        Some(request) => format!("{}\n", request.trim_end()),
+        None => format!("{} {}\n", record.method, record.url),
+    };
+    text.push_str(&format!("\n{}\n{}", record.status_label, record.headers));
+    if let Some(body) = &record.body {
+        text.push_str(&format!("\n{}", body));
+    }
+    text
+}
+
+/// Whether a record matches every term of a filter
+///
+/// Terms are `status:` (e.g. 404 or 4xx), `host:` (substring), `size:` (e.g.
+/// >1000 or <500) and plain text searched in the URL and title.
+fn matches_filter(record: &TuiRecord, filter: &str) -> bool {
+    filter.split_whitespace().all(|term| {
+        let term = term.to_ascii_lowercase();
+        if let Some(status) = term.strip_prefix("status:") {
+            let code = record.status.map(|s| s.to_string()).unwrap_or_default();
+            return match status.strip_suffix("xx") {
+                Some(class) => code.starts_with(class),
+                None => code == status,
+            };
+        }
+        if let Some(host) = term.strip_prefix("host:") {
+            return record.host.to_ascii_lowercase().contains(host);
+        }
+        if let Some(size) = term.strip_prefix("size:") {
+            let (compare, value): (fn(u64, u64) -> bool, &str) =
+                match (size.strip_prefix('>'), size.strip_prefix('<')) {
+                    (Some(value), _) => (|a, b| a > b, value),
+                    (_, Some(value)) => (|a, b| a < b, value),
+                    _ => (|a, b| a == b, size),
+                };
+            return value.parse().is_ok_and(|value| compare(record.size, value));
+        }
+        record.url.to_ascii_lowercase().contains(&term)
+            || record
+                .title
+                .as_ref()
+                .is_some_and(|title| title.to_ascii_lowercase().contains(&term))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(status: Option<u16>, host: &str, size: u64) -> TuiRecord {
+        TuiRecord {
+            method: "GET".to_string(),
+            url: format!("https://{}/admin", host),
+            host: host.to_string(),
+            status,
+            size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_filter() {
+        let found = record(Some(403), "api.example.com", 1500);
+        assert!(matches_filter(&found, ""));
+        assert!(matches_filter(&found, "status:4xx host:API size:>1000"));
+        assert!(matches_filter(&found, "status:403 admin"));
+        assert!(!matches_filter(&found, "status:2xx"));
+        assert!(!matches_filter(&found, "size:<1000"));
+        assert!(!matches_filter(&found, "size:abc"));
+        assert!(!matches_filter(&record(None, "a.com", 0), "status:4xx"));
+    }
+
+    #[test]
+    fn test_app_sort_and_filter() {
+        let (sender, receiver) = channel();
+        let control = Arc::new(RunControl::new(4, Some(20)));
+        let mut app = App::new(
+            receiver,
+            Arc::new(AtomicBool::new(false)),
+            Some(control.clone()),
+        );
+        for r in [
+            record(Some(500), "b.com", 30),
+            record(Some(200), "a.com", 10),
+            record(Some(404), "c.com", 20),
+        ] {
+            sender.send(TuiEvent::Record(Box::new(r))).unwrap();
+        }
+        app.receive();
+        assert!(!app.done);
+        assert_eq!(app.view, vec![0, 1, 2]);
+
+        app.table.select(Some(2));
+        app.handle_key(KeyEvent::from(KeyCode::Char('s')));
+        assert_eq!(app.sort, SortColumn::Status);
+        assert_eq!(app.view, vec![1, 2, 0]);
+        // The selection follows the record
+        assert_eq!(app.selected_index(), Some(2));
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('/')));
+        for c in "status:5xx".chars() {
+            app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(app.view, vec![0]);
+
+        for c in ['p', '-', ']'] {
+            app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        let status = control.status();
+        assert!(status.paused);
+        assert_eq!(status.concurrency, 3);
+        assert_eq!(status.rate_limit, Some(30));
+
+        drop(sender);
+        app.receive();
+        assert!(app.done);
+        assert!(!app.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+    }
+}
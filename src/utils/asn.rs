@@ -0,0 +1,139 @@
+use anyhow::{Context, Result, bail};
+use maxminddb::Reader;
+use serde_json::Value;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use crate::constants::DEFAULT_MMDB_PATHS;
+
+/// ASN, organization and country of an address (--enrich-asn)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AsnInfo {
+    pub asn: Option<u32>,
+    pub org: Option<String>,
+    pub country: Option<String>,
+}
+
+impl AsnInfo {
+    /// Read the fields from a MaxMind GeoLite2/GeoIP2 or ipinfo-style record
+    fn merge_record(&mut self, record: &Value) {
+        let asn = record["autonomous_system_number"].as_u64().or_else(|| {
+            record["asn"]
+                .as_str()
+                .and_then(|asn| asn.trim_start_matches("AS").parse().ok())
+        });
+        let org = record["autonomous_system_organization"]
+            .as_str()
+            .or_else(|| record["as_name"].as_str());
+        let country = record["country"]["iso_code"]
+            .as_str()
+            .or_else(|| record["country"].as_str())
+            .or_else(|| record["country_code"].as_str());
+
+        if self.asn.is_none() {
+            self.asn = asn.and_then(|asn| u32::try_from(asn).ok());
+        }
+        if self.org.is_none() {
+            self.org = org.map(str::to_string);
+        }
+        if self.country.is_none() {
+            self.country = country.map(str::to_string);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == AsnInfo::default()
+    }
+}
+
+/// MMDB databases used to annotate connected addresses
+pub struct AsnDb {
+    readers: Vec<Reader<Vec<u8>>>,
+}
+
+impl AsnDb {
+    /// Open the given databases, or the default ones under ~/.config/reqs when none are given
+    pub fn open(paths: &[String]) -> Result<Self> {
+        let paths: Vec<PathBuf> = if paths.is_empty() {
+            let home = std::env::var_os("HOME").map(PathBuf::from);
+            DEFAULT_MMDB_PATHS
+                .iter()
+                .filter_map(|path| Some(home.as_ref()?.join(path)))
+                .filter(|path| path.exists())
+                .collect()
+        } else {
+            paths.iter().map(PathBuf::from).collect()
+        };
+        if paths.is_empty() {
+            bail!(
+                "--enrich-asn needs an MMDB file; none given and none found at ~/{}",
+                DEFAULT_MMDB_PATHS.join(", ~/")
+            );
+        }
+
+        let readers = paths
+            .iter()
+            .map(|path| {
+                Reader::open_readfile(path)
+                    .with_context(|| format!("Failed to open MMDB file: {}", path.display()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(AsnDb { readers })
+    }
+
+    /// Fields found for `ip` across the databases, the first database winning
+    pub fn lookup(&self, ip: IpAddr) -> Option<AsnInfo> {
+        let mut info = AsnInfo::default();
+        for reader in &self.readers {
+            if let Ok(result) = reader.lookup(ip)
+                && let Ok(Some(record)) = result.decode::<Value>()
+            {
+                info.merge_record(&record);
+            }
+        }
+        (!info.is_empty()).then_some(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_record() {
+        // GeoLite2-ASN, then GeoLite2-Country
+        let mut info = AsnInfo::default();
+        info.merge_record(&json!({
+            "autonomous_system_number": 13335,
+            "autonomous_system_organization": "CLOUDFLARENET"
+        }));
+        info.merge_record(
+            &json!({"country": {"iso_code": "US", "names": {"en": "United States"}}}),
+        );
+        assert_eq!(
+            info,
+            AsnInfo {
+                asn: Some(13335),
+                org: Some("CLOUDFLARENET".to_string()),
+                country: Some("US".to_string()),
+            }
+        );
+
+        // ipinfo country_asn
+        let mut info = AsnInfo::default();
+        info.merge_record(&json!({"asn": "AS15169", "as_name": "Google LLC", "country": "US"}));
+        assert_eq!(info.asn, Some(15169));
+        assert_eq!(info.org.as_deref(), Some("Google LLC"));
+        assert_eq!(info.country.as_deref(), Some("US"));
+
+        let mut info = AsnInfo::default();
+        info.merge_record(&json!({"city": {"names": {"en": "Paris"}}}));
+        assert!(info.is_empty());
+    }
+
+    #[test]
+    fn test_asn_db_open_missing() {
+        assert!(AsnDb::open(&["/nonexistent/GeoLite2-ASN.mmdb".to_string()]).is_err());
+    }
+}
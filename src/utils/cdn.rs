@@ -0,0 +1,260 @@
+use reqwest::header::{HeaderMap, SERVER, SET_COOKIE, VIA};
+use std::net::IpAddr;
+
+/// CDN and WAF fronting a host (--detect-cdn)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CdnInfo {
+    pub cdn: Option<&'static str>,
+    pub waf: Option<&'static str>,
+}
+
+/// A provider's signatures
+struct Provider {
+    name: &'static str,
+    /// Whether traffic through the CDN also passes its WAF
+    waf: bool,
+    /// Published address ranges (not exhaustive)
+    ranges: &'static [&'static str],
+    /// Response headers only the provider sets
+    headers: &'static [&'static str],
+    /// Lowercase substrings of the Server or Via header
+    server: &'static [&'static str],
+    /// Cookie name prefixes set by the provider's WAF or bot protection
+    waf_cookies: &'static [&'static str],
+}
+
+const PROVIDERS: &[Provider] = &[
+    Provider {
+        name: "Cloudflare",
+        waf: true,
+        ranges: &[
+            "173.245.48.0/20",
+            "103.21.244.0/22",
+            "103.22.200.0/22",
+            "103.31.4.0/22",
+            "141.101.64.0/18",
+            "108.162.192.0/18",
+            "190.93.240.0/20",
+            "188.114.96.0/20",
+            "197.234.240.0/22",
+            "198.41.128.0/17",
+            "162.158.0.0/15",
+            "104.16.0.0/13",
+            "104.24.0.0/14",
+            "172.64.0.0/13",
+            "131.0.72.0/22",
+            "2400:cb00::/32",
+            "2606:4700::/32",
+            "2803:f800::/32",
+            "2405:b500::/32",
+            "2405:8100::/32",
+            "2a06:98c0::/29",
+            "2c0f:f248::/32",
+        ],
+        headers: &["cf-ray", "cf-cache-status"],
+        server: &["cloudflare"],
+        waf_cookies: &["__cf_bm", "cf_clearance", "__cfduid"],
+    },
+    Provider {
+        name: "Akamai",
+        waf: false,
+        ranges: &[
+            "2.16.0.0/13",
+            "23.0.0.0/12",
+            "23.32.0.0/11",
+            "23.64.0.0/14",
+            "23.192.0.0/11",
+            "96.6.0.0/15",
+            "96.16.0.0/15",
+            "104.64.0.0/10",
+            "184.24.0.0/13",
+            "184.50.0.0/15",
+            "184.84.0.0/14",
+        ],
+        headers: &["akamai-grn", "x-akamai-transformed", "akamai-cache-status"],
+        server: &["akamaighost", "akamainetstorage"],
+        waf_cookies: &["ak_bmsc", "bm_sz", "_abck"],
+    },
+    Provider {
+        name: "CloudFront",
+        waf: false,
+        ranges: &[
+            "3.160.0.0/14",
+            "13.32.0.0/15",
+            "13.224.0.0/14",
+            "13.249.0.0/16",
+            "18.64.0.0/14",
+            "18.154.0.0/15",
+            "18.160.0.0/15",
+            "18.164.0.0/15",
+            "18.172.0.0/15",
+            "52.84.0.0/15",
+            "54.182.0.0/16",
+            "54.192.0.0/16",
+            "54.230.0.0/16",
+            "54.239.128.0/18",
+            "99.84.0.0/16",
+            "99.86.0.0/16",
+            "108.156.0.0/14",
+            "143.204.0.0/16",
+            "205.251.192.0/19",
+            "2600:9000::/28",
+        ],
+        headers: &["x-amz-cf-id", "x-amz-cf-pop"],
+        server: &["cloudfront"],
+        waf_cookies: &["aws-waf-token"],
+    },
+    Provider {
+        name: "Imperva",
+        waf: true,
+        ranges: &[
+            "45.60.0.0/16",
+            "45.64.64.0/22",
+            "45.223.0.0/16",
+            "103.28.248.0/22",
+            "107.154.0.0/16",
+            "149.126.72.0/21",
+            "185.11.124.0/22",
+            "192.230.64.0/18",
+            "198.143.32.0/19",
+            "199.83.128.0/21",
+            "2a02:e980::/29",
+        ],
+        headers: &["x-iinfo"],
+        server: &["incapsula", "imperva"],
+        waf_cookies: &["incap_ses_", "visid_incap_", "nlbi_", "reese84"],
+    },
+];
+
+/// Identify the CDN and WAF in front of a response from its address, headers and cookies
+pub fn detect_cdn(ip: Option<IpAddr>, headers: &HeaderMap) -> CdnInfo {
+    let server = [SERVER, VIA]
+        .iter()
+        .flat_map(|name| headers.get_all(name))
+        .filter_map(|value| value.to_str().ok())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+    // "x-cdn: Incapsula" and similar
+    let x_cdn = headers
+        .get("x-cdn")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let cookies: Vec<&str> = headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|cookie| cookie.split('=').next())
+        .map(str::trim)
+        .collect();
+
+    let mut info = CdnInfo::default();
+    for provider in PROVIDERS {
+        let waf_cookie = cookies
+            .iter()
+            .any(|cookie| provider.waf_cookies.iter().any(|p| cookie.starts_with(p)));
+        let fronted = ip.is_some_and(|ip| provider.ranges.iter().any(|r| in_range(ip, r)))
+            || provider.headers.iter().any(|h| headers.contains_key(*h))
+            || provider
+                .server
+                .iter()
+                .any(|s| server.contains(s) || x_cdn.contains(s));
+
+        if fronted && info.cdn.is_none() {
+            info.cdn = Some(provider.name);
+        }
+        if ((fronted && provider.waf) || waf_cookie) && info.waf.is_none() {
+            info.waf = Some(match provider.name {
+                "CloudFront" => "AWS WAF",
+                name => name,
+            });
+        }
+    }
+    info
+}
+
+/// Whether `ip` is in the CIDR range `range`
+fn in_range(ip: IpAddr, range: &str) -> bool {
+    let Some((network, prefix)) = range.split_once('/') else {
+        return false;
+    };
+    let (Ok(network), Ok(prefix)) = (network.parse::<IpAddr>(), prefix.parse::<u32>()) else {
+        return false;
+    };
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                value.parse().unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn test_in_range() {
+        assert!(in_range("104.16.1.1".parse().unwrap(), "104.16.0.0/13"));
+        assert!(!in_range("104.24.0.1".parse().unwrap(), "104.16.0.0/13"));
+        assert!(in_range("2606:4700::1".parse().unwrap(), "2606:4700::/32"));
+        assert!(!in_range("10.0.0.1".parse().unwrap(), "2606:4700::/32"));
+        assert!(in_range("10.0.0.1".parse().unwrap(), "0.0.0.0/0"));
+    }
+
+    #[test]
+    fn test_detect_cdn() {
+        let cloudflare = CdnInfo {
+            cdn: Some("Cloudflare"),
+            waf: Some("Cloudflare"),
+        };
+        assert_eq!(
+            detect_cdn("104.16.1.1".parse().ok(), &HeaderMap::new()),
+            cloudflare
+        );
+        assert_eq!(
+            detect_cdn(None, &headers(&[("server", "cloudflare")])),
+            cloudflare
+        );
+
+        let info = detect_cdn(
+            None,
+            &headers(&[
+                ("via", "1.1 abc.cloudfront.net (CloudFront)"),
+                ("set-cookie", "aws-waf-token=abc; Path=/"),
+            ]),
+        );
+        assert_eq!(info.cdn, Some("CloudFront"));
+        assert_eq!(info.waf, Some("AWS WAF"));
+
+        let info = detect_cdn(None, &headers(&[("x-cdn", "Incapsula")]));
+        assert_eq!(info.waf, Some("Imperva"));
+
+        // Bot protection cookies reveal the WAF even without the CDN
+        let info = detect_cdn(None, &headers(&[("set-cookie", "ak_bmsc=1; Path=/")]));
+        assert_eq!(info.cdn, None);
+        assert_eq!(info.waf, Some("Akamai"));
+
+        assert_eq!(
+            detect_cdn("192.0.2.1".parse().ok(), &headers(&[("server", "nginx")])),
+            CdnInfo::default()
+        );
+    }
+}
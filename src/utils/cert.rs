@@ -0,0 +1,194 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::utils::url_authority;
+
+/// Expiry of the certificate a response was served with (--cert-expiry-warn)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertExpiry {
+    pub not_after: SystemTime,
+    /// Whole days left, negative once expired
+    pub days_left: i64,
+    /// Whether it expires within the warning window
+    pub expiring: bool,
+}
+
+impl CertExpiry {
+    /// Expiry of a DER certificate relative to `now`
+    pub fn from_der(der: &[u8], window: Duration, now: SystemTime) -> Option<Self> {
+        let not_after = UNIX_EPOCH + Duration::from_secs(parse_not_after(der)?);
+        let secs_left = match not_after.duration_since(now) {
+            Ok(left) => left.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        Some(CertExpiry {
+            not_after,
+            days_left: secs_left.div_euclid(86_400),
+            expiring: secs_left <= window.as_secs() as i64,
+        })
+    }
+}
+
+/// Checks certificate expiry and warns once per host about expiring ones
+pub struct CertMonitor {
+    window: Duration,
+    warned: Mutex<HashSet<String>>,
+}
+
+impl CertMonitor {
+    pub fn new(window: Duration) -> Self {
+        CertMonitor {
+            window,
+            warned: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Expiry of the certificate `url` was served with
+    pub fn check(&self, url: &str, der: &[u8]) -> Option<CertExpiry> {
+        let expiry = CertExpiry::from_der(der, self.window, SystemTime::now())?;
+        if expiry.expiring
+            && let Some(host) = url_authority(url)
+            && self
+                .warned
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(host.clone())
+        {
+            if expiry.days_left < 0 {
+                warn!(host = %host, "Certificate expired {} days ago", -expiry.days_left);
+            } else {
+                warn!(host = %host, "Certificate expires in {} days", expiry.days_left);
+            }
+        }
+        Some(expiry)
+    }
+}
+
+/// Split a DER element into its tag, contents and the bytes after it
+fn read_tlv(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |len, &b| (len << 8) | b as usize);
+        (len, &rest[count..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// notAfter of an X.509 certificate in seconds since the epoch
+fn parse_not_after(der: &[u8]) -> Option<u64> {
+    let (_, certificate, _) = read_tlv(der)?;
+    let (_, tbs, _) = read_tlv(certificate)?;
+    let (tag, _, mut fields) = read_tlv(tbs)?;
+    // The version is optional, the serial number always present
+    if tag == 0xa0 {
+        fields = read_tlv(fields)?.2;
+    }
+    // Signature algorithm and issuer precede the validity
+    let fields = read_tlv(fields)?.2;
+    let fields = read_tlv(fields)?.2;
+    let (_, validity, _) = read_tlv(fields)?;
+    let (_, _, not_after) = read_tlv(validity)?;
+    let (tag, time, _) = read_tlv(not_after)?;
+    parse_time(tag, std::str::from_utf8(time).ok()?)
+}
+
+/// Parse an ASN.1 UTCTime (0x17) or GeneralizedTime (0x18) in UTC
+fn parse_time(tag: u8, time: &str) -> Option<u64> {
+    let time = time.strip_suffix('Z')?;
+    let (year, rest) = match tag {
+        0x17 => {
+            let year: i64 = time.get(..2)?.parse().ok()?;
+            (
+                if year < 50 { 2000 + year } else { 1900 + year },
+                &time[2..],
+            )
+        }
+        0x18 => (time.get(..4)?.parse().ok()?, &time[4..]),
+        _ => return None,
+    };
+    if rest.len() != 10 || !rest.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |i: usize| rest[i..i + 2].parse::<i64>().unwrap_or_default();
+    let (month, day) = (field(0), field(2));
+    let secs =
+        days_from_civil(year, month, day) * 86_400 + field(4) * 3_600 + field(6) * 60 + field(8);
+    u64::try_from(secs).ok()
+}
+
+/// Days since 1970-01-01 of a civil date (Howard Hinnant's algorithm)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if contents.len() < 0x80 {
+            out.push(contents.len() as u8);
+        } else {
+            out.extend([0x82, (contents.len() >> 8) as u8, contents.len() as u8]);
+        }
+        out.extend(contents);
+        out
+    }
+
+    /// A certificate skeleton with only the fields up to the validity
+    fn certificate(not_after: &[u8]) -> Vec<u8> {
+        let validity = [tlv(0x17, b"240101000000Z"), not_after.to_vec()].concat();
+        let tbs = [
+            tlv(0xa0, &tlv(0x02, &[2])),
+            tlv(0x02, &[1]),
+            tlv(0x30, &[]),
+            tlv(0x30, &[0; 200]),
+            tlv(0x30, &validity),
+        ]
+        .concat();
+        tlv(0x30, &[tlv(0x30, &tbs), tlv(0x30, &[])].concat())
+    }
+
+    #[test]
+    fn test_parse_not_after() {
+        let der = certificate(&tlv(0x17, b"261101120000Z"));
+        assert_eq!(parse_not_after(&der), Some(1_793_534_400));
+        let der = certificate(&tlv(0x18, b"20500101000000Z"));
+        assert_eq!(parse_not_after(&der), Some(2_524_608_000));
+        assert_eq!(parse_not_after(&der[..40]), None);
+        assert_eq!(parse_not_after(b"not a certificate"), None);
+    }
+
+    #[test]
+    fn test_cert_expiry() {
+        let der = certificate(&tlv(0x17, b"261101120000Z"));
+        let day = Duration::from_secs(86_400);
+        let now = UNIX_EPOCH + Duration::from_secs(1_793_534_400) - day * 10;
+
+        let expiry = CertExpiry::from_der(&der, day * 30, now).unwrap();
+        assert_eq!(expiry.days_left, 10);
+        assert!(expiry.expiring);
+        assert!(!CertExpiry::from_der(&der, day * 7, now).unwrap().expiring);
+
+        let expiry = CertExpiry::from_der(&der, day, now + day * 12).unwrap();
+        assert_eq!(expiry.days_left, -2);
+        assert!(expiry.expiring);
+    }
+}
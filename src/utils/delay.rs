@@ -1,40 +1,151 @@
 use rand::Rng;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
 use crate::constants::MICROSECONDS_PER_SECOND;
+use crate::utils::host_matches;
 
-/// Apply random delay between requests if configured
-pub async fn apply_random_delay(random_delay_str: &Option<String>) {
-    if let Some(delay_str) = random_delay_str {
-        let parts: Vec<&str> = delay_str.split(':').collect();
-        if parts.len() == 2 {
-            if let (Ok(min_delay), Ok(max_delay)) =
-                (parts[0].parse::<u64>(), parts[1].parse::<u64>())
-            {
-                if max_delay >= min_delay {
-                    let delay = rand::thread_rng().gen_range(min_delay..=max_delay);
-                    tokio::time::sleep(Duration::from_millis(delay)).await;
-                } else {
-                    eprintln!(
-                        "[Warning] Invalid --random-delay format: MAX must be greater than or equal to MIN. Got: {}",
-                        delay_str
-                    );
+/// Settings for groups of hosts, e.g. "api.example.com=5,*.example.org=20,*=50"
+///
+/// A value without a pattern applies to every host, like "*=VALUE". Patterns
+/// match like --scope; hosts that match none use the "*" value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostRules<T> {
+    rules: Vec<(String, T)>,
+}
+
+impl<T> HostRules<T> {
+    /// Parse a comma-separated rule list with `parse_value` for the values
+    pub fn parse(
+        value: &str,
+        parse_value: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in value.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let (pattern, value) = rule.rsplit_once('=').unwrap_or(("*", rule));
+            let pattern = pattern.trim().to_lowercase();
+            if pattern.is_empty() {
+                return Err(format!("Missing host pattern in '{}'", rule));
+            }
+            rules.push((pattern, parse_value(value.trim())?));
+        }
+        if rules.is_empty() {
+            return Err("Expected VALUE or HOST=VALUE[,HOST=VALUE...]".to_string());
+        }
+        Ok(HostRules { rules })
+    }
+
+    /// The pattern and value for the host of `url`, a specific pattern winning over "*"
+    pub fn lookup(&self, url: &str) -> Option<(&str, &T)> {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(|h| h.to_lowercase()));
+        host.and_then(|host| {
+            self.rules
+                .iter()
+                .find(|(pattern, _)| pattern != "*" && host_matches(&host, pattern))
+        })
+        .or_else(|| self.rules.iter().find(|(pattern, _)| pattern == "*"))
+        .map(|(pattern, value)| (pattern.as_str(), value))
+    }
+
+    /// Value for hosts no specific pattern matches
+    pub fn default_value(&self) -> Option<&T> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| pattern == "*")
+            .map(|(_, value)| value)
+    }
+}
+
+/// Parse --rate-limit: requests per second, overall or per host group
+pub fn parse_rate_limit(value: &str) -> Result<HostRules<u64>, String> {
+    HostRules::parse(value, |rate| match rate.parse::<u64>() {
+        Ok(rate) if rate > 0 => Ok(rate),
+        _ => Err(format!(
+            "Invalid rate '{}'. Expected requests per second above 0",
+            rate
+        )),
+    })
+}
+
+/// Distribution of random delays in milliseconds (--random-delay)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DelayDistribution {
+    /// "MIN:MAX" or "uniform:MIN:MAX"
+    Uniform { min: u64, max: u64 },
+    /// "normal:MEAN:STDDEV", negative samples count as no delay
+    Normal { mean: f64, stddev: f64 },
+    /// "exp:MEAN", many short delays and a few long ones
+    Exponential { mean: f64 },
+}
+
+impl DelayDistribution {
+    /// Parse a distribution, e.g. "100:500", "normal:300:50" or "exp:200"
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("Invalid delay '{}'. {}", value, reason);
+        let format_error = || {
+            invalid(
+                "Expected MIN:MAX, uniform:MIN:MAX, normal:MEAN:STDDEV or exp:MEAN \
+                 in non-negative milliseconds",
+            )
+        };
+        let number = |n: &str| {
+            n.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n >= 0.0)
+                .ok_or_else(format_error)
+        };
+        let parts: Vec<&str> = value.split(':').collect();
+        match parts.as_slice() {
+            ["exp", mean] => Ok(DelayDistribution::Exponential {
+                mean: number(mean)?,
+            }),
+            ["uniform", min, max] | [min, max] => {
+                let (min, max) = (number(min)? as u64, number(max)? as u64);
+                if max < min {
+                    return Err(invalid("MAX must be greater than or equal to MIN"));
                 }
-            } else {
-                eprintln!(
-                    "[Warning] Invalid --random-delay format: Could not parse min/max values. Got: {}",
-                    delay_str
-                );
+                Ok(DelayDistribution::Uniform { min, max })
             }
-        } else {
-            eprintln!(
-                "[Warning] Invalid --random-delay format. Expected MIN:MAX. Got: {}",
-                delay_str
-            );
+            ["normal", mean, stddev] => Ok(DelayDistribution::Normal {
+                mean: number(mean)?,
+                stddev: number(stddev)?,
+            }),
+            _ => Err(format_error()),
         }
     }
+
+    /// Draw a delay
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        let millis = match *self {
+            DelayDistribution::Uniform { min, max } => rng.gen_range(min..=max) as f64,
+            DelayDistribution::Normal { mean, stddev } => {
+                // Box-Muller transform
+                let (u1, u2) = (1.0 - rng.r#gen::<f64>(), rng.r#gen::<f64>());
+                let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+                mean + stddev * z
+            }
+            DelayDistribution::Exponential { mean } => -mean * (1.0 - rng.r#gen::<f64>()).ln(),
+        };
+        Duration::from_secs_f64(millis.max(0.0) / 1000.0)
+    }
+}
+
+/// Parse --random-delay: a delay distribution, overall or per host group
+pub fn parse_random_delay(value: &str) -> Result<HostRules<DelayDistribution>, String> {
+    HostRules::parse(value, DelayDistribution::parse)
+}
+
+/// Sleep for a delay drawn from the distribution
+pub async fn apply_random_delay(delay: Option<&DelayDistribution>) {
+    if let Some(delay) = delay {
+        let delay = delay.sample(&mut rand::thread_rng());
+        tokio::time::sleep(delay).await;
+    }
 }
 
 /// Apply rate limiting to control request frequency
@@ -51,3 +162,168 @@ pub async fn apply_rate_limit(rate_limit: Option<u64>, last_request_time: &Arc<M
         *last_req_guard = Instant::now();
     }
 }
+
+/// Rate limiters of host groups (--rate-limit)
+///
+/// Hosts matching the same pattern share one limiter, and hosts that match
+/// none share the "*" limiter.
+pub struct RateLimiter {
+    rules: Option<HostRules<u64>>,
+    groups: std::sync::Mutex<HashMap<String, Arc<Mutex<Instant>>>>,
+}
+
+impl RateLimiter {
+    pub fn new(rules: Option<HostRules<u64>>) -> Self {
+        RateLimiter {
+            rules,
+            groups: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rate of hosts no specific pattern matches
+    pub fn default_rate(&self) -> Option<u64> {
+        self.rules.as_ref()?.default_value().copied()
+    }
+
+    /// Wait for the turn of a request to `url`
+    ///
+    /// `default_rate` applies to hosts no specific pattern matches, so runtime
+    /// control can change it.
+    pub async fn wait(&self, url: &str, default_rate: Option<u64>) {
+        let (group, rate) = match self.rules.as_ref().and_then(|rules| rules.lookup(url)) {
+            Some((pattern, &rate)) if pattern != "*" => (pattern, Some(rate)),
+            _ => ("*", default_rate),
+        };
+        if rate.is_none() {
+            return;
+        }
+        let last_request_time = self
+            .groups
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(group.to_string())
+            // Backdated by a second, the longest interval, so the first request goes right away
+            .or_insert_with(|| {
+                let start = Instant::now();
+                Arc::new(Mutex::new(
+                    start.checked_sub(Duration::from_secs(1)).unwrap_or(start),
+                ))
+            })
+            .clone();
+        apply_rate_limit(rate, &last_request_time).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_limit() {
+        let rules = parse_rate_limit("100").unwrap();
+        assert_eq!(rules.default_value(), Some(&100));
+        assert_eq!(rules.lookup("https://a.com/"), Some(("*", &100)));
+
+        let rules = parse_rate_limit("*=50, api.example.com=5,*.slow.example.org=1").unwrap();
+        assert_eq!(
+            rules.lookup("https://API.example.com/x"),
+            Some(("api.example.com", &5))
+        );
+        assert_eq!(
+            rules.lookup("https://a.slow.example.org/"),
+            Some(("*.slow.example.org", &1))
+        );
+        assert_eq!(rules.lookup("https://www.example.com/"), Some(("*", &50)));
+
+        let rules = parse_rate_limit("api.example.com=5").unwrap();
+        assert_eq!(rules.default_value(), None);
+        assert_eq!(rules.lookup("https://other.com/"), None);
+
+        assert!(parse_rate_limit("0").is_err());
+        assert!(parse_rate_limit("a.com=fast").is_err());
+        assert!(parse_rate_limit("=5").is_err());
+        assert!(parse_rate_limit("").is_err());
+    }
+
+    #[test]
+    fn test_parse_random_delay() {
+        let uniform = |min, max| DelayDistribution::Uniform { min, max };
+        let rules = parse_random_delay("100:500").unwrap();
+        assert_eq!(rules.default_value(), Some(&uniform(100, 500)));
+
+        let rules = parse_random_delay("fragile.example.com=500:1500,*=0:50").unwrap();
+        assert_eq!(
+            rules.lookup("https://fragile.example.com/"),
+            Some(("fragile.example.com", &uniform(500, 1500)))
+        );
+        assert_eq!(rules.lookup("https://a.com/"), Some(("*", &uniform(0, 50))));
+
+        let rules = parse_random_delay("a.com=exp:200,*=normal:300:50").unwrap();
+        assert_eq!(
+            rules.lookup("https://a.com/"),
+            Some(("a.com", &DelayDistribution::Exponential { mean: 200.0 }))
+        );
+        assert_eq!(
+            rules.default_value(),
+            Some(&DelayDistribution::Normal {
+                mean: 300.0,
+                stddev: 50.0
+            })
+        );
+        assert_eq!(DelayDistribution::parse("uniform:1:2"), Ok(uniform(1, 2)));
+
+        assert!(parse_random_delay("500:100").is_err());
+        assert!(parse_random_delay("100").is_err());
+        assert!(parse_random_delay("exp:-5").is_err());
+        assert!(parse_random_delay("normal:300").is_err());
+        assert!(parse_random_delay("poisson:3").is_err());
+    }
+
+    #[test]
+    fn test_delay_distribution_sample() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mean_ms = |delay: DelayDistribution, rng: &mut StdRng| {
+            let total: Duration = (0..10_000).map(|_| delay.sample(rng)).sum();
+            total.as_secs_f64() * 1000.0 / 10_000.0
+        };
+
+        let uniform = DelayDistribution::Uniform { min: 100, max: 200 };
+        assert!((0..1000).all(|_| {
+            let ms = uniform.sample(&mut rng).as_millis();
+            (100..=200).contains(&ms)
+        }));
+
+        let normal = DelayDistribution::Normal {
+            mean: 300.0,
+            stddev: 50.0,
+        };
+        assert!((mean_ms(normal, &mut rng) - 300.0).abs() < 5.0);
+
+        let exp = DelayDistribution::Exponential { mean: 200.0 };
+        assert!((mean_ms(exp, &mut rng) - 200.0).abs() < 10.0);
+
+        // Negative normal samples are clamped to no delay
+        let normal = DelayDistribution::Normal {
+            mean: 0.0,
+            stddev: 100.0,
+        };
+        assert!((0..100).all(|_| normal.sample(&mut rng) < Duration::from_secs(1)));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_groups() {
+        let limiter = RateLimiter::new(Some(parse_rate_limit("slow.test=2").unwrap()));
+        let start = Instant::now();
+        // Unmatched hosts are unlimited, and each group has its own limiter
+        for _ in 0..5 {
+            limiter.wait("https://fast.test/", None).await;
+        }
+        limiter.wait("https://slow.test/", None).await;
+        assert!(start.elapsed() < Duration::from_millis(400));
+        limiter.wait("https://slow.test/", None).await;
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+}
@@ -0,0 +1,72 @@
+use regex::Regex;
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use crate::constants::JS_ENDPOINT_PATTERN;
+
+static JS_ENDPOINT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(JS_ENDPOINT_PATTERN).unwrap());
+
+/// Whether a response is JavaScript, by content type or URL extension
+pub fn is_javascript(url: &str, headers: &HeaderMap) -> bool {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if content_type.contains("javascript") || content_type.contains("ecmascript") {
+        return true;
+    }
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    path.ends_with(".js") || path.ends_with(".mjs")
+}
+
+/// Extract quoted paths and URLs from JavaScript source, in order of appearance without duplicates
+pub fn extract_js_endpoints(source: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    JS_ENDPOINT_RE
+        .captures_iter(source)
+        .filter_map(|caps| caps.get(1))
+        .map(|m| m.as_str().to_string())
+        .filter(|endpoint| seen.insert(endpoint.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_js_endpoints() {
+        let source = r#"
+            fetch("/api/v1/users?active=1");
+            axios.post('/api/v1/login', data);
+            const cdn = "https://cdn.example.com/lib.js";
+            load(`./chunks/main.chunk.js`);
+            get("/api/v1/users?active=1");
+            const text = "hello world";
+            const mime = "text/plain";
+            const legacy = "admin/config.php";
+        "#;
+        assert_eq!(
+            extract_js_endpoints(source),
+            vec![
+                "/api/v1/users?active=1",
+                "/api/v1/login",
+                "https://cdn.example.com/lib.js",
+                "./chunks/main.chunk.js",
+                "text/plain",
+                "admin/config.php",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_javascript() {
+        let mut headers = HeaderMap::new();
+        assert!(is_javascript("https://a.com/app.js?v=2", &headers));
+        assert!(!is_javascript("https://a.com/", &headers));
+        headers.insert(CONTENT_TYPE, "application/javascript".parse().unwrap());
+        assert!(is_javascript("https://a.com/bundle", &headers));
+    }
+}
@@ -0,0 +1,348 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::borrow::Cow;
+
+use tracing::warn;
+
+use crate::constants::{HTTP_METHODS, MAX_EXPANSION};
+use crate::types::PayloadEncoding;
+
+/// Expand brace groups in the URL of an input line into one line per combination
+///
+/// Supports alternatives (`https://{dev,prod}.example.com`) and numeric ranges
+/// (`/item/{1-100}`, zero-padded with `{001-100}`). Bare hostnames are further
+/// expanded into one `host:port` per entry of `ports` (--ports). Only the URL is
+/// expanded, so request bodies containing braces are left untouched.
+pub fn expand_line(line: &str, ports: &[u16], encoding: PayloadEncoding) -> Vec<String> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let url_index = match parts.as_slice() {
+        [method, _, ..] if HTTP_METHODS.contains(&method.to_uppercase().as_str()) => 1,
+        [_, ..] => 0,
+        [] => return vec![line.to_string()],
+    };
+
+    let url = parts[url_index];
+    let Some(url_start) = line.find(url) else {
+        return vec![line.to_string()];
+    };
+    let (prefix, suffix) = (&line[..url_start], &line[url_start + url.len()..]);
+
+    // Ranges are expanded up front, so an oversized line would exhaust memory
+    let size = expansion_size(url).saturating_mul(ports.len().max(1) as u64);
+    if size > MAX_EXPANSION {
+        warn!(
+            "Skipping input line expanding to {} requests (limit {}): {}",
+            size, MAX_EXPANSION, line
+        );
+        return Vec::new();
+    }
+
+    expand_braces(url, encoding)
+        .into_iter()
+        .flat_map(|url| {
+            if ports.is_empty() || !is_bare_host(&url) {
+                vec![url]
+            } else {
+                ports
+                    .iter()
+                    .map(|port| format!("{}:{}", url, port))
+                    .collect()
+            }
+        })
+        .map(|url| format!("{}{}{}", prefix, url, suffix))
+        .collect()
+}
+
+/// Whether the input is just a hostname (no scheme, port or path)
+fn is_bare_host(url: &str) -> bool {
+    !url.is_empty() && !url.contains(['/', ':', '?', '#'])
+}
+
+/// Expand every brace group in a string
+///
+/// Groups that are neither a comma list nor a numeric range stay literal.
+pub fn expand_braces(input: &str, encoding: PayloadEncoding) -> Vec<String> {
+    let mut search_from = 0;
+    while let Some(open) = input[search_from..].find('{').map(|i| i + search_from) {
+        let Some(close) = input[open..].find('}').map(|i| i + open) else {
+            break;
+        };
+
+        if let Some(items) = expand_group(&input[open + 1..close]) {
+            let (head, tail) = (&input[..open], &input[close + 1..]);
+            return items
+                .iter()
+                .flat_map(|item| {
+                    let item = if in_authority(head) {
+                        Cow::Borrowed(item.as_str())
+                    } else {
+                        encode_payload(item, encoding)
+                    };
+                    expand_braces(&format!("{}{}{}", head, item, tail), encoding)
+                })
+                .collect();
+        }
+        search_from = open + 1;
+    }
+    vec![input.to_string()]
+}
+
+/// Number of strings `expand_braces` produces for the input, without expanding it
+fn expansion_size(input: &str) -> u64 {
+    let mut size: u64 = 1;
+    let mut search_from = 0;
+    while let Some(open) = input[search_from..].find('{').map(|i| i + search_from) {
+        let Some(close) = input[open..].find('}').map(|i| i + open) else {
+            break;
+        };
+        match group_size(&input[open + 1..close]) {
+            Some(items) => {
+                size = size.saturating_mul(items);
+                search_from = close + 1;
+            }
+            None => search_from = open + 1,
+        }
+    }
+    size
+}
+
+/// Whether text ending at a brace group leaves it in the scheme or host, which is not encoded
+fn in_authority(head: &str) -> bool {
+    let rest = head.split_once("://").map_or(head, |(_, rest)| rest);
+    !rest.contains(['/', '?', '#'])
+}
+
+/// Encode a brace group item for substitution into a URL (--encode)
+pub fn encode_payload(item: &str, encoding: PayloadEncoding) -> Cow<'_, str> {
+    match encoding {
+        PayloadEncoding::None => Cow::Borrowed(item),
+        PayloadEncoding::Url => Cow::Owned(percent_encode(item)),
+        PayloadEncoding::DoubleUrl => Cow::Owned(percent_encode(&percent_encode(item))),
+        PayloadEncoding::Base64 => Cow::Owned(STANDARD.encode(item)),
+        PayloadEncoding::Html => {
+            let mut encoded = String::with_capacity(item.len());
+            for c in item.chars() {
+                match c {
+                    '&' => encoded.push_str("&amp;"),
+                    '<' => encoded.push_str("&lt;"),
+                    '>' => encoded.push_str("&gt;"),
+                    '"' => encoded.push_str("&quot;"),
+                    '\'' => encoded.push_str("&#39;"),
+                    c => encoded.push(c),
+                }
+            }
+            Cow::Owned(encoded)
+        }
+    }
+}
+
+/// Percent-encode every byte except unreserved characters (RFC 3986)
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Number of items of a single brace group, or None if the group is not expandable
+fn group_size(group: &str) -> Option<u64> {
+    if group.contains(',') {
+        return Some(group.split(',').count() as u64);
+    }
+    let (start, end) = group.split_once('-')?;
+    let (from, to) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+    Some(from.abs_diff(to).saturating_add(1))
+}
+
+/// Items of a single brace group, or None if the group is not expandable
+fn expand_group(group: &str) -> Option<Vec<String>> {
+    if group.contains(',') {
+        return Some(group.split(',').map(str::to_string).collect());
+    }
+
+    let (start, end) = group.split_once('-')?;
+    let (from, to) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+    let width = if start.len() > 1 && start.starts_with('0') {
+        start.len()
+    } else {
+        0
+    };
+
+    let numbers: Box<dyn Iterator<Item = u64>> = if from <= to {
+        Box::new(from..=to)
+    } else {
+        Box::new((to..=from).rev())
+    };
+    Some(
+        numbers
+            .map(|n| format!("{:0width$}", n, width = width))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_braces_alternatives() {
+        assert_eq!(
+            expand_braces(
+                "https://{dev,staging,prod}.example.com/health",
+                PayloadEncoding::None
+            ),
+            vec![
+                "https://dev.example.com/health",
+                "https://staging.example.com/health",
+                "https://prod.example.com/health",
+            ]
+        );
+        assert_eq!(
+            expand_braces("https://{,www.}example.com", PayloadEncoding::None),
+            vec!["https://example.com", "https://www.example.com"]
+        );
+    }
+
+    #[test]
+    fn test_expand_braces_ranges() {
+        assert_eq!(
+            expand_braces("https://a.com/item/{1-100}", PayloadEncoding::None).len(),
+            100
+        );
+        assert_eq!(
+            expand_braces("https://a.com/{3-1}", PayloadEncoding::None),
+            vec!["https://a.com/3", "https://a.com/2", "https://a.com/1"]
+        );
+        assert_eq!(
+            expand_braces("https://a.com/{08-10}", PayloadEncoding::None),
+            vec!["https://a.com/08", "https://a.com/09", "https://a.com/10"]
+        );
+    }
+
+    #[test]
+    fn test_expand_braces_multiple_and_literal() {
+        assert_eq!(
+            expand_braces("https://{a,b}.com/{id}/{1-2}", PayloadEncoding::None),
+            vec![
+                "https://a.com/{id}/1",
+                "https://a.com/{id}/2",
+                "https://b.com/{id}/1",
+                "https://b.com/{id}/2",
+            ]
+        );
+        assert_eq!(
+            expand_braces("https://a.com/{", PayloadEncoding::None),
+            vec!["https://a.com/{"]
+        );
+    }
+
+    #[test]
+    fn test_expand_line_keeps_body() {
+        assert_eq!(
+            expand_line(
+                r#"POST https://{a,b}.com {"x":1,"y":2}"#,
+                &[],
+                PayloadEncoding::None
+            ),
+            vec![
+                r#"POST https://a.com {"x":1,"y":2}"#,
+                r#"POST https://b.com {"x":1,"y":2}"#,
+            ]
+        );
+        assert_eq!(
+            expand_line("https://a.com/{1-2} [http2]", &[], PayloadEncoding::None),
+            vec!["https://a.com/1 [http2]", "https://a.com/2 [http2]"]
+        );
+    }
+
+    #[test]
+    fn test_expand_line_limit() {
+        assert!(expand_line("https://x/{1-99999999999}", &[], PayloadEncoding::None).is_empty());
+        assert!(
+            expand_line(
+                "https://x/{1-1000}/{1-1000}/{1-10}",
+                &[],
+                PayloadEncoding::None
+            )
+            .is_empty()
+        );
+        assert_eq!(expansion_size("https://{a,b}.com/{id}/{01-10}"), 20);
+        assert_eq!(
+            expand_line("https://x/{1-1000}", &[], PayloadEncoding::None).len(),
+            1000
+        );
+    }
+
+    #[test]
+    fn test_expand_line_ports() {
+        assert_eq!(
+            expand_line("{a,b}.example.com", &[80, 8443], PayloadEncoding::None),
+            vec![
+                "a.example.com:80",
+                "a.example.com:8443",
+                "b.example.com:80",
+                "b.example.com:8443",
+            ]
+        );
+        assert_eq!(
+            expand_line("example.com [http2]", &[443], PayloadEncoding::None),
+            vec!["example.com:443 [http2]"]
+        );
+        assert_eq!(
+            expand_line("https://example.com", &[80], PayloadEncoding::None),
+            vec!["https://example.com"]
+        );
+        assert_eq!(
+            expand_line("example.com:8080", &[80], PayloadEncoding::None),
+            vec!["example.com:8080"]
+        );
+        assert_eq!(
+            expand_line("example.com/admin", &[80], PayloadEncoding::None),
+            vec!["example.com/admin"]
+        );
+    }
+
+    #[test]
+    fn test_encode_payload() {
+        assert_eq!(
+            encode_payload("<a href='x'>", PayloadEncoding::Url),
+            "%3Ca%20href%3D%27x%27%3E"
+        );
+        assert_eq!(encode_payload("a/b", PayloadEncoding::DoubleUrl), "a%252Fb");
+        assert_eq!(
+            encode_payload("<a href='x'>&", PayloadEncoding::Html),
+            "&lt;a href=&#39;x&#39;&gt;&amp;"
+        );
+        assert_eq!(
+            encode_payload("1 OR 1=1", PayloadEncoding::Base64),
+            "MSBPUiAxPTE="
+        );
+        assert_eq!(encode_payload("<a>", PayloadEncoding::None), "<a>");
+    }
+
+    #[test]
+    fn test_expand_line_encode() {
+        assert_eq!(
+            expand_line(
+                "https://{a,b}.example.com/search?q={<x>,'} [http2]",
+                &[],
+                PayloadEncoding::Url
+            ),
+            vec![
+                "https://a.example.com/search?q=%3Cx%3E [http2]",
+                "https://a.example.com/search?q=%27 [http2]",
+                "https://b.example.com/search?q=%3Cx%3E [http2]",
+                "https://b.example.com/search?q=%27 [http2]",
+            ]
+        );
+        assert_eq!(
+            expand_line("example.com/{1-2}", &[], PayloadEncoding::Base64),
+            vec!["example.com/MQ==", "example.com/Mg=="]
+        );
+    }
+}
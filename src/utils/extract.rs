@@ -0,0 +1,68 @@
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Named regexes extracting values from response bodies (--extract NAME=REGEX)
+#[derive(Debug, Clone, Default)]
+pub struct Extractors {
+    patterns: Vec<(String, Regex)>,
+}
+
+impl Extractors {
+    /// Compile `NAME=REGEX` specs
+    pub fn parse(specs: &[String]) -> Result<Self> {
+        let mut patterns = Vec::new();
+        for spec in specs {
+            let Some((name, pattern)) = spec.split_once('=') else {
+                bail!("Invalid --extract '{}', expected NAME=REGEX", spec);
+            };
+            let regex = Regex::new(pattern)
+                .with_context(|| format!("Invalid regex for --extract {}", name))?;
+            patterns.push((name.trim().to_string(), regex));
+        }
+        Ok(Extractors { patterns })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Values found in `body`: the first capture group, or the whole match
+    ///
+    /// Names without a match are left out.
+    pub fn extract(&self, body: &str) -> BTreeMap<String, String> {
+        self.patterns
+            .iter()
+            .filter_map(|(name, regex)| {
+                let caps = regex.captures(body)?;
+                let value = caps.get(1).or_else(|| caps.get(0))?;
+                Some((name.clone(), value.as_str().to_string()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extractors() {
+        let extractors = Extractors::parse(&[
+            "version=\"version\":\\s*\"([^\"]+)\"".to_string(),
+            "server=nginx/[0-9.]+".to_string(),
+            "missing=nothing-here".to_string(),
+        ])
+        .unwrap();
+        let values = extractors.extract(r#"{"version": "1.2.3", "via": "nginx/1.25.3"}"#);
+        assert_eq!(values.get("version").map(String::as_str), Some("1.2.3"));
+        assert_eq!(
+            values.get("server").map(String::as_str),
+            Some("nginx/1.25.3")
+        );
+        assert!(!values.contains_key("missing"));
+
+        assert!(Extractors::parse(&["no-equals".to_string()]).is_err());
+        assert!(Extractors::parse(&["bad=(".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::thread;
+
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+
+use crate::constants::{FOLLOW_BUFFER, FOLLOW_POLL_INTERVAL};
+
+/// Read input lines as they are written (--follow)
+///
+/// "-" follows stdin until it is closed. A regular file is read from the
+/// start and then tailed, and re-read from the start when it is truncated.
+/// A FIFO is reopened each time its writer closes it, so it can be fed by
+/// several producers over time.
+pub fn follow_lines(source: &str) -> mpsc::Receiver<String> {
+    let (tx, rx) = mpsc::channel(FOLLOW_BUFFER);
+    let source = source.to_string();
+    thread::spawn(move || {
+        let result = if source == "-" {
+            read_lines(io::stdin().lock(), &tx).map(|_| ())
+        } else {
+            tail_file(&source, &tx)
+        };
+        if let Err(e) = result {
+            error!(source = %source, "Failed to follow input: {}", e);
+        }
+    });
+    rx
+}
+
+/// Send complete lines until EOF, returning the number of bytes read
+///
+/// A line still being written is kept in `partial` for the next call, so a
+/// multibyte character split across reads is decoded once it is complete.
+/// Invalid UTF-8 is replaced rather than ending the follow.
+/// Returns `None` once the receiver is dropped.
+fn read_chunk(
+    reader: &mut impl BufRead,
+    partial: &mut Vec<u8>,
+    tx: &mpsc::Sender<String>,
+) -> io::Result<Option<u64>> {
+    let mut read = 0;
+    loop {
+        let n = reader.read_until(b'\n', partial)?;
+        if n == 0 {
+            return Ok(Some(read));
+        }
+        read += n as u64;
+        if !partial.ends_with(b"\n") {
+            // The rest of the line has not been written yet
+            continue;
+        }
+        let line = decode_line(partial);
+        partial.clear();
+        if tx.blocking_send(line).is_err() {
+            return Ok(None);
+        }
+    }
+}
+
+/// Decode a line read from the input, without its line ending
+fn decode_line(bytes: &[u8]) -> String {
+    let line = String::from_utf8_lossy(bytes);
+    if let Cow::Owned(_) = line {
+        warn!("Replaced invalid UTF-8 in input line: {}", line.trim_end());
+    }
+    line.trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// Send every line of a reader until EOF
+fn read_lines(mut reader: impl BufRead, tx: &mpsc::Sender<String>) -> io::Result<bool> {
+    let mut partial = Vec::new();
+    if read_chunk(&mut reader, &mut partial, tx)?.is_none() {
+        return Ok(false);
+    }
+    if !partial.is_empty() && tx.blocking_send(decode_line(&partial)).is_err() {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Follow a file or FIFO with tail semantics until the receiver is dropped
+fn tail_file(path: &str, tx: &mpsc::Sender<String>) -> io::Result<()> {
+    loop {
+        let file = File::open(path)?;
+        if !file.metadata()?.is_file() {
+            // A FIFO reaches EOF when its writer closes; opening it again
+            // blocks until the next writer arrives
+            if !read_lines(BufReader::new(file), tx)? {
+                return Ok(());
+            }
+            continue;
+        }
+
+        let mut reader = BufReader::new(file);
+        let mut partial = Vec::new();
+        let mut position = 0;
+        loop {
+            match read_chunk(&mut reader, &mut partial, tx)? {
+                None => return Ok(()),
+                Some(read) => position += read,
+            }
+            thread::sleep(FOLLOW_POLL_INTERVAL);
+            if tx.is_closed() {
+                return Ok(());
+            }
+            // A shorter file was truncated or replaced; start it over
+            if fs::metadata(path).map_or(true, |m| m.len() < position) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::time::Duration;
+
+    async fn recv(rx: &mut mpsc::Receiver<String>) -> Option<String> {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    #[tokio::test]
+    async fn test_follow_appended_lines() {
+        let path = std::env::temp_dir().join(format!("reqs-follow-{}.txt", std::process::id()));
+        fs::write(&path, "https://a.example\n").unwrap();
+        let mut rx = follow_lines(path.to_str().unwrap());
+        assert_eq!(recv(&mut rx).await.as_deref(), Some("https://a.example"));
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "https://b.exa").unwrap();
+        file.flush().unwrap();
+        tokio::time::sleep(FOLLOW_POLL_INTERVAL * 2).await;
+        writeln!(file, "mple\r").unwrap();
+        assert_eq!(recv(&mut rx).await.as_deref(), Some("https://b.example"));
+
+        // Truncation restarts from the beginning of the file
+        fs::write(&path, "https://c.example\n").unwrap();
+        assert_eq!(recv(&mut rx).await.as_deref(), Some("https://c.example"));
+
+        drop(rx);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_lines_sends_final_partial_line() {
+        let (tx, mut rx) = mpsc::channel(4);
+        assert!(read_lines("one\ntwo".as_bytes(), &tx).unwrap());
+        assert_eq!(rx.try_recv().unwrap(), "one");
+        assert_eq!(rx.try_recv().unwrap(), "two");
+    }
+
+    #[test]
+    fn test_read_lines_invalid_utf8() {
+        let (tx, mut rx) = mpsc::channel(4);
+        assert!(read_lines(&b"bad \xff\nhttps://a.example\n"[..], &tx).unwrap());
+        assert_eq!(rx.try_recv().unwrap(), "bad \u{fffd}");
+        assert_eq!(rx.try_recv().unwrap(), "https://a.example");
+    }
+
+    #[test]
+    fn test_read_chunk_split_character() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut partial = Vec::new();
+        let text = "https://a.example/é\n".as_bytes();
+        let split = text.len() - 2;
+        read_chunk(&mut &text[..split], &mut partial, &tx).unwrap();
+        assert!(rx.try_recv().is_err());
+        read_chunk(&mut &text[split..], &mut partial, &tx).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), "https://a.example/é");
+    }
+}
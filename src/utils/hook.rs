@@ -0,0 +1,81 @@
+use anyhow::{Context, Result, bail};
+use std::process::{ExitStatus, Output, Stdio};
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, Command};
+
+/// Run a hook command with `input` on its stdin, its output going to ours (--post-hook)
+pub async fn run_hook(command: &str, input: String) -> Result<()> {
+    let child = spawn(command, Stdio::inherit())?;
+    let output = feed(child, input).await?;
+    check(command, output.status)
+}
+
+/// Pipe `input` through a hook command and return what it prints (--pre-hook)
+pub async fn filter_through(command: &str, input: String) -> Result<String> {
+    let child = spawn(command, Stdio::piped())?;
+    let output = feed(child, input).await?;
+    check(command, output.status)?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Start a command through the shell with a piped stdin
+fn spawn(command: &str, stdout: Stdio) -> Result<Child> {
+    Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(stdout)
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run hook: {}", command))
+}
+
+/// Write the input and wait for the command to exit
+///
+/// Stdin is written alongside reading stdout so a command producing output
+/// before it has read all of its input does not block.
+async fn feed(mut child: Child, input: String) -> Result<Output> {
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = tokio::spawn(async move {
+        // A command may exit without reading all of its input
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+    let output = child.wait_with_output().await?;
+    writer.await?;
+    Ok(output)
+}
+
+fn check(command: &str, status: ExitStatus) -> Result<()> {
+    if !status.success() {
+        bail!("Hook exited with {}: {}", status, command);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_filter_through() {
+        let output = filter_through("grep -v skip | tr a-z A-Z", "a\nskip\nb\n".to_string())
+            .await
+            .unwrap();
+        assert_eq!(output, "A\nB\n");
+    }
+
+    #[tokio::test]
+    async fn test_hook_failure() {
+        assert!(
+            run_hook("cat >/dev/null; exit 3", "x\n".to_string())
+                .await
+                .is_err()
+        );
+        assert!(
+            filter_through("true", String::new())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
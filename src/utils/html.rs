@@ -1,9 +1,48 @@
 use scraper::{Html, Selector};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
 use std::sync::LazyLock;
 
-use crate::constants::TITLE_SELECTOR;
+use crate::constants::{
+    CANONICAL_SELECTOR, CSRF_FIELD_NAMES, FORM_FIELD_SELECTOR, FORM_SELECTOR, META_SELECTOR,
+    TITLE_SELECTOR,
+};
 
 static TITLE_SEL: LazyLock<Selector> = LazyLock::new(|| Selector::parse(TITLE_SELECTOR).unwrap());
+static META_SEL: LazyLock<Selector> = LazyLock::new(|| Selector::parse(META_SELECTOR).unwrap());
+static CANONICAL_SEL: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(CANONICAL_SELECTOR).unwrap());
+static FORM_SEL: LazyLock<Selector> = LazyLock::new(|| Selector::parse(FORM_SELECTOR).unwrap());
+static FORM_FIELD_SEL: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(FORM_FIELD_SELECTOR).unwrap());
+
+/// Meta tags of an HTML page (--include-meta)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageMeta {
+    pub description: Option<String>,
+    pub generator: Option<String>,
+    pub canonical: Option<String>,
+    /// OpenGraph properties without the `og:` prefix
+    pub og: BTreeMap<String, String>,
+}
+
+impl PageMeta {
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none()
+            && self.generator.is_none()
+            && self.canonical.is_none()
+            && self.og.is_empty()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "description": self.description,
+            "generator": self.generator,
+            "canonical": self.canonical,
+            "og": self.og,
+        })
+    }
+}
 
 /// Extract title from HTML content
 pub fn extract_title(html: &str) -> Option<String> {
@@ -11,6 +50,120 @@ pub fn extract_title(html: &str) -> Option<String> {
     document.select(&TITLE_SEL).next().map(|t| t.inner_html())
 }
 
+/// A named field of an HTML form
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormInput {
+    pub name: String,
+    /// Input type, or the element name for select, textarea and button
+    pub kind: String,
+}
+
+/// A form of an HTML page (--extract-forms)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormInfo {
+    /// Action attribute as written in the page, empty for the page itself
+    pub action: String,
+    pub method: String,
+    pub inputs: Vec<FormInput>,
+    /// Whether a hidden field looks like a CSRF token
+    pub csrf: bool,
+}
+
+impl FormInfo {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "action": self.action,
+            "method": self.method,
+            "inputs": self
+                .inputs
+                .iter()
+                .map(|input| json!({"name": input.name, "type": input.kind}))
+                .collect::<Vec<_>>(),
+            "csrf": self.csrf,
+        })
+    }
+}
+
+/// Extract the forms of HTML content with their fields
+pub fn extract_forms(html: &str) -> Vec<FormInfo> {
+    let document = Html::parse_document(html);
+    document
+        .select(&FORM_SEL)
+        .map(|form| {
+            let inputs: Vec<FormInput> = form
+                .select(&FORM_FIELD_SEL)
+                .map(|field| {
+                    let element = field.value();
+                    let kind = match element.name() {
+                        "input" => element.attr("type").unwrap_or("text").to_ascii_lowercase(),
+                        other => other.to_string(),
+                    };
+                    FormInput {
+                        name: element.attr("name").unwrap_or_default().to_string(),
+                        kind,
+                    }
+                })
+                .collect();
+            let csrf = inputs.iter().any(|input| {
+                let name = input.name.to_ascii_lowercase();
+                input.kind == "hidden" && CSRF_FIELD_NAMES.iter().any(|c| name.contains(c))
+            });
+            FormInfo {
+                action: form.value().attr("action").unwrap_or_default().to_string(),
+                method: form
+                    .value()
+                    .attr("method")
+                    .unwrap_or("get")
+                    .to_ascii_uppercase(),
+                inputs,
+                csrf,
+            }
+        })
+        .collect()
+}
+
+/// Extract the description, generator, canonical URL and og: tags from HTML content
+///
+/// Returns None if the page has none of them.
+pub fn extract_meta(html: &str) -> Option<PageMeta> {
+    let document = Html::parse_document(html);
+    let mut meta = PageMeta::default();
+    for element in document.select(&META_SEL) {
+        let element = element.value();
+        let Some(content) = element.attr("content").map(str::trim) else {
+            continue;
+        };
+        let name = element
+            .attr("name")
+            .or_else(|| element.attr("property"))
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        // The first tag wins when a page repeats one
+        match name.as_str() {
+            "description" => {
+                meta.description.get_or_insert_with(|| content.to_string());
+            }
+            "generator" => {
+                meta.generator.get_or_insert_with(|| content.to_string());
+            }
+            _ => {
+                if let Some(property) = name.strip_prefix("og:") {
+                    meta.og
+                        .entry(property.to_string())
+                        .or_insert_with(|| content.to_string());
+                }
+            }
+        }
+    }
+    meta.canonical = document
+        .select(&CANONICAL_SEL)
+        .next()
+        .and_then(|link| link.value().attr("href"))
+        .map(|href| href.trim().to_string());
+
+    (!meta.is_empty()).then_some(meta)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -38,4 +191,56 @@ mod tests {
         "#;
         assert_eq!(extract_title(html), None);
     }
+
+    #[test]
+    fn test_extract_meta() {
+        let html = r#"
+            <html><head>
+            <meta name="Description" content=" Example shop ">
+            <meta name="description" content="ignored">
+            <meta name="generator" content="WordPress 6.4">
+            <meta property="og:title" content="Shop">
+            <meta property="og:image" content="https://example.com/a.png">
+            <meta charset="utf-8">
+            <link rel="canonical" href="https://example.com/">
+            </head></html>
+        "#;
+        let meta = extract_meta(html).unwrap();
+        assert_eq!(meta.description.as_deref(), Some("Example shop"));
+        assert_eq!(meta.generator.as_deref(), Some("WordPress 6.4"));
+        assert_eq!(meta.canonical.as_deref(), Some("https://example.com/"));
+        assert_eq!(meta.og.get("title").map(String::as_str), Some("Shop"));
+        assert_eq!(meta.og.len(), 2);
+
+        assert_eq!(
+            extract_meta("<html><head><title>x</title></head></html>"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_forms() {
+        let html = r#"
+            <form action="/login" method="post">
+              <input type="hidden" name="csrf_token" value="x">
+              <input name="user">
+              <input type="password" name="pass">
+              <select name="lang"></select>
+              <input type="submit" value="Go">
+            </form>
+            <form action="/search"><textarea name="q"></textarea></form>
+        "#;
+        let forms = extract_forms(html);
+        assert_eq!(forms.len(), 2);
+        assert_eq!(forms[0].action, "/login");
+        assert_eq!(forms[0].method, "POST");
+        assert!(forms[0].csrf);
+        let kinds: Vec<_> = forms[0].inputs.iter().map(|i| i.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["hidden", "text", "password", "select"]);
+        assert_eq!(forms[1].method, "GET");
+        assert!(!forms[1].csrf);
+        assert_eq!(forms[1].to_json()["inputs"][0]["type"], "textarea");
+
+        assert!(extract_forms("<p>no forms</p>").is_empty());
+    }
 }
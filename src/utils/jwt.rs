@@ -0,0 +1,135 @@
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::constants::{JWT_LONG_EXPIRY, JWT_PATTERN};
+
+static JWT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(JWT_PATTERN).unwrap());
+
+/// A JWT found in a response (--decode-jwt)
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtInfo {
+    /// "body" or "header:NAME"
+    pub source: String,
+    pub header: Value,
+    pub claims: Value,
+    /// Notable properties: "alg:none", "no_exp", "expired", "long_expiry"
+    pub flags: Vec<&'static str>,
+}
+
+impl JwtInfo {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "source": self.source,
+            "header": self.header,
+            "claims": self.claims,
+            "flags": self.flags,
+        })
+    }
+}
+
+/// Decode the JWTs in response headers and the body, without verifying them
+///
+/// Each token is reported once, at the first place it appears.
+pub fn find_jwts(headers: &HeaderMap, body: Option<&str>, now: SystemTime) -> Vec<JwtInfo> {
+    let mut seen = HashSet::new();
+    let header_values = headers.iter().filter_map(|(name, value)| {
+        let value = value.to_str().ok()?;
+        Some((format!("header:{}", name), value))
+    });
+    header_values
+        .chain(body.map(|body| ("body".to_string(), body)))
+        .flat_map(|(source, text)| {
+            JWT_RE
+                .find_iter(text)
+                .map(move |m| (source.clone(), m.as_str()))
+        })
+        .filter(|(_, token)| seen.insert(token.to_string()))
+        .filter_map(|(source, token)| decode_jwt(source, token, now))
+        .collect()
+}
+
+fn decode_jwt(source: String, token: &str, now: SystemTime) -> Option<JwtInfo> {
+    let mut parts = token.split('.');
+    let header = decode_part(parts.next()?)?;
+    let claims = decode_part(parts.next()?)?;
+
+    let mut flags = Vec::new();
+    if header["alg"]
+        .as_str()
+        .is_some_and(|alg| alg.eq_ignore_ascii_case("none"))
+    {
+        flags.push("alg:none");
+    }
+    match claims["exp"].as_f64() {
+        None => flags.push("no_exp"),
+        Some(exp) => {
+            let now = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+            let exp = Duration::from_secs_f64(exp.max(0.0));
+            if exp < now {
+                flags.push("expired");
+            } else if exp - now > JWT_LONG_EXPIRY {
+                flags.push("long_expiry");
+            }
+        }
+    }
+    Some(JwtInfo {
+        source,
+        header,
+        claims,
+        flags,
+    })
+}
+
+/// Base64url-decoded JSON object of a token part
+fn decode_part(part: &str) -> Option<Value> {
+    let bytes = URL_SAFE_NO_PAD.decode(part.trim_end_matches('=')).ok()?;
+    serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .filter(Value::is_object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(header: &str, claims: &str) -> String {
+        format!(
+            "{}.{}.sig",
+            URL_SAFE_NO_PAD.encode(header),
+            URL_SAFE_NO_PAD.encode(claims)
+        )
+    }
+
+    #[test]
+    fn test_find_jwts() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let session = token(r#"{"alg":"HS256"}"#, r#"{"sub":"1","exp":1700003600}"#);
+        let unsigned = token(r#"{"alg":"none"}"#, r#"{"sub":"2"}"#);
+        let expired = token(r#"{"alg":"RS256"}"#, r#"{"exp":1600000000}"#);
+        let long = token(r#"{"alg":"RS256"}"#, r#"{"exp":1900000000}"#);
+
+        let mut headers = HeaderMap::new();
+        let cookie = format!("session={}; Path=/; HttpOnly", session);
+        headers.insert("set-cookie", cookie.parse().unwrap());
+        let body = format!(
+            r#"{{"token":"{}","again":"{}","others":["{}","{}","{}"],"bad":"eyJhbGciOi.eyJ4.y"}}"#,
+            unsigned, session, expired, long, session
+        );
+
+        let jwts = find_jwts(&headers, Some(&body), now);
+        assert_eq!(jwts.len(), 4);
+        assert_eq!(jwts[0].source, "header:set-cookie");
+        assert_eq!(jwts[0].claims["sub"], "1");
+        assert!(jwts[0].flags.is_empty());
+        assert_eq!(jwts[1].source, "body");
+        assert_eq!(jwts[1].flags, vec!["alg:none", "no_exp"]);
+        assert_eq!(jwts[2].flags, vec!["expired"]);
+        assert_eq!(jwts[3].flags, vec!["long_expiry"]);
+        assert_eq!(jwts[3].to_json()["header"]["alg"], "RS256");
+    }
+}
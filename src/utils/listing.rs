@@ -0,0 +1,28 @@
+use crate::constants::DIRECTORY_LISTING_MARKERS;
+
+/// Whether a response body looks like an open directory index (--detect-listing)
+pub fn is_directory_listing(body: &str) -> bool {
+    let body = body.to_lowercase();
+    DIRECTORY_LISTING_MARKERS
+        .iter()
+        .any(|marker| body.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_directory_listing() {
+        assert!(is_directory_listing(
+            "<html><head><title>Index of /backup</title></head>"
+        ));
+        assert!(is_directory_listing(
+            "<title>Directory listing for /</title>"
+        ));
+        assert!(is_directory_listing(
+            "<pre><A HREF=\"/\">[To Parent Directory]</A>"
+        ));
+        assert!(!is_directory_listing("<title>Index of products</title>"));
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::IsTerminal;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+use crate::types::Cli;
+
+/// Log level selected by --silent, --quiet and -v/-vv/-vvv (warnings by default)
+///
+/// Logging is off with --tui, which owns the terminal.
+pub fn log_level(cli: &Cli) -> LevelFilter {
+    if cli.silent || cli.tui {
+        LevelFilter::OFF
+    } else if cli.quiet {
+        LevelFilter::ERROR
+    } else {
+        match cli.verbose {
+            0 => LevelFilter::WARN,
+            1 => LevelFilter::INFO,
+            2 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Install the global logger writing to stderr
+///
+/// Dependencies only log warnings and errors unless -vvv is given.
+pub fn init_logging(cli: &Cli) {
+    let level = log_level(cli);
+    let dependency_level = if cli.verbose >= 3 {
+        level
+    } else {
+        level.min(LevelFilter::WARN)
+    };
+    let filter = Targets::new()
+        .with_target(env!("CARGO_PKG_NAME"), level)
+        .with_default(dependency_level);
+
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_target(false);
+    if cli.log_json {
+        tracing_subscriber::registry()
+            .with(layer.json().with_filter(filter))
+            .init();
+    } else {
+        tracing_subscriber::registry()
+            .with(
+                layer
+                    .without_time()
+                    .with_ansi(!cli.no_color && std::io::stderr().is_terminal())
+                    .with_filter(filter),
+            )
+            .init();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_log_level() {
+        let level = |args: &[&str]| log_level(&Cli::parse_from(args));
+        assert_eq!(level(&["reqs"]), LevelFilter::WARN);
+        assert_eq!(level(&["reqs", "-v"]), LevelFilter::INFO);
+        assert_eq!(level(&["reqs", "-vv"]), LevelFilter::DEBUG);
+        assert_eq!(level(&["reqs", "-vvv"]), LevelFilter::TRACE);
+        assert_eq!(level(&["reqs", "--quiet"]), LevelFilter::ERROR);
+        assert_eq!(level(&["reqs", "--silent"]), LevelFilter::OFF);
+        assert_eq!(level(&["reqs", "--tui", "-v"]), LevelFilter::OFF);
+    }
+}
@@ -1,7 +1,49 @@
+pub mod asn;
+pub mod cdn;
+pub mod cert;
 pub mod delay;
+pub mod duration;
+pub mod endpoints;
+pub mod expand;
+pub mod extract;
+pub mod follow;
+pub mod hook;
 pub mod html;
+pub mod jwt;
+pub mod listing;
+pub mod log;
+pub mod redact;
+pub mod schedule;
+pub mod shard;
+pub mod size;
+pub mod snippet;
+pub mod template;
 pub mod url;
 
-pub use delay::{apply_random_delay, apply_rate_limit};
-pub use html::extract_title;
-pub use url::normalize_url_scheme;
+pub use asn::{AsnDb, AsnInfo};
+pub use cdn::{CdnInfo, detect_cdn};
+pub use cert::{CertExpiry, CertMonitor};
+pub use delay::{
+    DelayDistribution, HostRules, RateLimiter, apply_random_delay, parse_random_delay,
+    parse_rate_limit,
+};
+pub use duration::parse_duration;
+pub use endpoints::{extract_js_endpoints, is_javascript};
+pub use expand::expand_line;
+pub use extract::Extractors;
+pub use follow::follow_lines;
+pub use hook::{filter_through, run_hook};
+pub use html::{FormInfo, PageMeta, extract_forms, extract_meta, extract_title};
+pub use jwt::{JwtInfo, find_jwts};
+pub use listing::is_directory_listing;
+pub use log::init_logging;
+pub use redact::Redactor;
+pub use schedule::{StartAt, TimeWindow, WindowGate, parse_start_at, parse_time_window};
+pub use shard::{Shard, parse_shard};
+pub use size::{format_size, parse_size};
+pub use snippet::{snippet, surrounding};
+pub use template::TemplateVars;
+pub use url::{
+    InvalidUrl, UrlCredentials, host_matches, is_in_scope, normalize_url, normalize_url_scheme,
+    replace_host, split_credentials, url_authority, url_parts, validate_url,
+};
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::BTreeMap;
+
+/// Replacement for redacted values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Masks header values and body patterns in the output (--redact, --redact-pattern)
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    headers: Vec<HeaderName>,
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    /// Compile the header names and body regexes to redact
+    pub fn parse(headers: &[String], patterns: &[String]) -> Result<Self> {
+        let headers = headers
+            .iter()
+            .map(|name| {
+                HeaderName::from_bytes(name.trim().as_bytes())
+                    .with_context(|| format!("Invalid header name for --redact: {}", name))
+            })
+            .collect::<Result<_>>()?;
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid regex for --redact-pattern: {}", pattern))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Redactor { headers, patterns })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Copy of `headers` with the values of redacted headers masked
+    pub fn headers(&self, headers: &HeaderMap) -> HeaderMap {
+        let mut redacted = headers.clone();
+        for name in &self.headers {
+            let count = redacted.get_all(name).iter().count();
+            redacted.remove(name);
+            for _ in 0..count {
+                redacted.append(name, HeaderValue::from_static(REDACTED));
+            }
+        }
+        redacted
+    }
+
+    /// Mask matches of the body patterns in `text`
+    pub fn body(&self, text: &str) -> String {
+        self.patterns.iter().fold(text.to_string(), |text, regex| {
+            regex.replace_all(&text, REDACTED).into_owned()
+        })
+    }
+
+    /// Mask values derived from `body` (--extract)
+    ///
+    /// Values containing a match of the body patterns have it masked, and a
+    /// value that is only part of a match, such as a capture group inside a
+    /// secret, is masked whole.
+    pub fn values(&self, body: &str, values: BTreeMap<String, String>) -> BTreeMap<String, String> {
+        let secrets: Vec<&str> = self
+            .patterns
+            .iter()
+            .flat_map(|regex| regex.find_iter(body).map(|m| m.as_str()))
+            .collect();
+        values
+            .into_iter()
+            .map(|(name, value)| {
+                let value = if !value.is_empty() && secrets.iter().any(|s| s.contains(&value)) {
+                    REDACTED.to_string()
+                } else {
+                    self.body(&value)
+                };
+                (name, value)
+            })
+            .collect()
+    }
+
+    /// Mask redacted header lines and body patterns in a raw request
+    pub fn raw_request(&self, raw: &str) -> String {
+        let (head, body) = match raw.split_once("\n\n") {
+            Some((head, body)) => (head, Some(body)),
+            None => (raw, None),
+        };
+        let mut redacted = String::with_capacity(raw.len());
+        for line in head.split_inclusive('\n') {
+            match line.split_once(':') {
+                Some((name, _))
+                    if self
+                        .headers
+                        .iter()
+                        .any(|h| h.as_str().eq_ignore_ascii_case(name)) =>
+                {
+                    let ending = if line.ends_with('\n') { "\n" } else { "" };
+                    redacted.push_str(&format!("{}: {}{}", name, REDACTED, ending));
+                }
+                _ => redacted.push_str(line),
+            }
+        }
+        if let Some(body) = body {
+            redacted.push_str("\n\n");
+            redacted.push_str(body);
+        }
+        self.body(&redacted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redactor() {
+        let redactor = Redactor::parse(
+            &["Authorization".to_string(), "set-cookie".to_string()],
+            &["sk_live_[0-9a-z]+".to_string()],
+        )
+        .unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.append("set-cookie", "a=1".parse().unwrap());
+        headers.append("set-cookie", "b=2".parse().unwrap());
+        headers.insert("server", "nginx".parse().unwrap());
+        let headers = redactor.headers(&headers);
+        assert_eq!(headers.get_all("set-cookie").iter().count(), 2);
+        assert!(headers.get_all("set-cookie").iter().all(|v| v == REDACTED));
+        assert_eq!(headers["server"], "nginx");
+
+        assert_eq!(
+            redactor.body(r#"{"key":"sk_live_abc123"}"#),
+            r#"{"key":"[REDACTED]"}"#
+        );
+        assert_eq!(
+            redactor.raw_request(
+                "POST /pay HTTP/1.1\nHost: example.com\nauthorization: Bearer t\n\nkey=sk_live_x1"
+            ),
+            "POST /pay HTTP/1.1\nHost: example.com\nauthorization: [REDACTED]\n\nkey=[REDACTED]"
+        );
+
+        let values = redactor.values(
+            r#"{"key":"sk_live_abc123","note":"key is sk_live_abc123"}"#,
+            BTreeMap::from([
+                ("id".to_string(), "abc123".to_string()),
+                ("note".to_string(), "key is sk_live_abc123".to_string()),
+                ("empty".to_string(), String::new()),
+            ]),
+        );
+        assert_eq!(values["id"], REDACTED);
+        assert_eq!(values["note"], "key is [REDACTED]");
+        assert_eq!(values["empty"], "");
+
+        assert!(Redactor::parse(&["bad header".to_string()], &[]).is_err());
+        assert!(Redactor::parse(&[], &["(".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use jiff::civil::{DateTime, Time};
+use jiff::{ToSpan, Zoned};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+/// When to start the run (--start-at)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StartAt {
+    /// The next time the clock shows this time
+    Time(Time),
+    /// A date and time; in the past it does not delay the start
+    DateTime(DateTime),
+}
+
+/// Parse --start-at: "HH:MM[:SS]" or "YYYY-MM-DD HH:MM[:SS]" in local time
+pub fn parse_start_at(value: &str) -> Result<StartAt, String> {
+    let value = value.trim();
+    // A time parse would accept a date and time too, dropping the date
+    if let Ok(datetime) = value.replacen(' ', "T", 1).parse::<DateTime>() {
+        return Ok(StartAt::DateTime(datetime));
+    }
+    value.parse::<Time>().map(StartAt::Time).map_err(|_| {
+        format!(
+            "Invalid start time '{}'. Expected HH:MM or YYYY-MM-DD HH:MM",
+            value
+        )
+    })
+}
+
+impl StartAt {
+    /// Time left until the start, zero if it has passed
+    pub fn wait_from(&self, now: &Zoned) -> Duration {
+        let start = match self {
+            StartAt::Time(time) => next_occurrence(now, *time),
+            StartAt::DateTime(datetime) => datetime.to_zoned(now.time_zone().clone()).ok(),
+        };
+        start.map_or(Duration::ZERO, |start| until(now, &start))
+    }
+}
+
+/// Daily window during which requests may be sent (--allowed-window)
+///
+/// A window whose end is before its start spans midnight, e.g. "22:00-06:00".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeWindow {
+    start: Time,
+    end: Time,
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.strftime("%H:%M"),
+            self.end.strftime("%H:%M")
+        )
+    }
+}
+
+/// Parse --allowed-window: "HH:MM-HH:MM" in local time
+pub fn parse_time_window(value: &str) -> Result<TimeWindow, String> {
+    let invalid = || format!("Invalid window '{}'. Expected HH:MM-HH:MM", value);
+    let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+    let (Ok(start), Ok(end)) = (start.trim().parse(), end.trim().parse()) else {
+        return Err(invalid());
+    };
+    if start == end {
+        return Err(format!(
+            "Invalid window '{}'. Start and end are equal",
+            value
+        ));
+    }
+    Ok(TimeWindow { start, end })
+}
+
+impl TimeWindow {
+    pub fn contains(&self, time: Time) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Time left until the window opens, zero inside it
+    pub fn wait_from(&self, now: &Zoned) -> Duration {
+        if self.contains(now.time()) {
+            return Duration::ZERO;
+        }
+        next_occurrence(now, self.start).map_or(Duration::ZERO, |start| until(now, &start))
+    }
+}
+
+/// Holds back requests outside the allowed window (--allowed-window)
+pub struct WindowGate {
+    window: TimeWindow,
+    closed: AtomicBool,
+}
+
+impl WindowGate {
+    pub fn new(window: TimeWindow) -> Self {
+        WindowGate {
+            window,
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Wait until the window is open; requests in flight are not affected
+    pub async fn wait(&self) {
+        loop {
+            let wait = self.window.wait_from(&Zoned::now());
+            if wait.is_zero() {
+                if self.closed.swap(false, Ordering::Relaxed) {
+                    info!(window = %self.window, "Allowed window opened, resuming");
+                }
+                return;
+            }
+            if !self.closed.swap(true, Ordering::Relaxed) {
+                info!(
+                    window = %self.window,
+                    "Outside the allowed window, pausing for {}s",
+                    wait.as_secs()
+                );
+            }
+            // Re-checked on wake-up in case the clock jumped
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// The next time after `now` the clock shows `time`
+fn next_occurrence(now: &Zoned, time: Time) -> Option<Zoned> {
+    let today = now.with().time(time).build().ok()?;
+    if today > *now {
+        return Some(today);
+    }
+    let tomorrow = now.checked_add(1.day()).ok()?;
+    tomorrow.with().time(time).build().ok()
+}
+
+fn until(now: &Zoned, later: &Zoned) -> Duration {
+    Duration::try_from(later.timestamp().duration_since(now.timestamp())).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(datetime: &str) -> Zoned {
+        datetime
+            .parse::<DateTime>()
+            .unwrap()
+            .to_zoned(jiff::tz::TimeZone::UTC)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_start_at() {
+        assert_eq!(
+            parse_start_at("02:00"),
+            Ok(StartAt::Time(Time::constant(2, 0, 0, 0)))
+        );
+        assert!(matches!(
+            parse_start_at("2026-10-20 02:30"),
+            Ok(StartAt::DateTime(_))
+        ));
+        assert!(parse_start_at("2am").is_err());
+    }
+
+    #[test]
+    fn test_start_at_wait() {
+        let start = parse_start_at("02:00").unwrap();
+        let hour = Duration::from_secs(3600);
+        assert_eq!(start.wait_from(&at("2026-10-18T01:00")), hour);
+        assert_eq!(start.wait_from(&at("2026-10-18T03:00")), hour * 23);
+
+        let start = parse_start_at("2026-10-18 02:00").unwrap();
+        assert_eq!(start.wait_from(&at("2026-10-18T01:30")), hour / 2);
+        assert_eq!(start.wait_from(&at("2026-10-19T00:00")), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_time_window() {
+        let window = parse_time_window("01:00-05:00").unwrap();
+        assert_eq!(window.to_string(), "01:00-05:00");
+        assert!(window.contains(Time::constant(1, 0, 0, 0)));
+        assert!(!window.contains(Time::constant(5, 0, 0, 0)));
+        assert_eq!(window.wait_from(&at("2026-10-18T03:00")), Duration::ZERO);
+        assert_eq!(
+            window.wait_from(&at("2026-10-18T06:00")),
+            Duration::from_secs(19 * 3600)
+        );
+
+        // Spanning midnight
+        let window = parse_time_window("22:00-06:00").unwrap();
+        assert!(window.contains(Time::constant(23, 0, 0, 0)));
+        assert!(window.contains(Time::constant(2, 0, 0, 0)));
+        assert_eq!(
+            window.wait_from(&at("2026-10-18T21:00")),
+            Duration::from_secs(3600)
+        );
+
+        assert!(parse_time_window("01:00").is_err());
+        assert!(parse_time_window("01:00-01:00").is_err());
+        assert!(parse_time_window("1am-5am").is_err());
+    }
+}
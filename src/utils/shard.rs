@@ -0,0 +1,69 @@
+use crate::http::cache::fnv1a;
+
+/// One of several shards of the input (--shard N/M)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    /// Shard number, from 1 to `count`
+    pub index: u64,
+    pub count: u64,
+}
+
+impl Shard {
+    /// Whether an input line belongs to this shard
+    ///
+    /// Lines are assigned by a hash of their trimmed text, so every process
+    /// given the same input and shard count splits it the same way.
+    pub fn contains(&self, line: &str) -> bool {
+        fnv1a(line.trim().as_bytes()) % self.count == self.index - 1
+    }
+}
+
+/// Parse a shard such as "2/5"
+pub fn parse_shard(value: &str) -> Result<Shard, String> {
+    let invalid = || format!("Invalid shard '{}'. Expected N/M, e.g. 2/5", value);
+    let (index, count) = value.trim().split_once('/').ok_or_else(invalid)?;
+    let index: u64 = index.trim().parse().map_err(|_| invalid())?;
+    let count: u64 = count.trim().parse().map_err(|_| invalid())?;
+    if count == 0 || index == 0 || index > count {
+        return Err(format!(
+            "Invalid shard '{}'. N must be between 1 and M",
+            value
+        ));
+    }
+    Ok(Shard { index, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shard() {
+        assert_eq!(parse_shard("2/5"), Ok(Shard { index: 2, count: 5 }));
+        assert_eq!(parse_shard(" 1 / 1 "), Ok(Shard { index: 1, count: 1 }));
+        assert!(parse_shard("0/5").is_err());
+        assert!(parse_shard("6/5").is_err());
+        assert!(parse_shard("2").is_err());
+        assert!(parse_shard("a/b").is_err());
+    }
+
+    #[test]
+    fn test_shards_partition_input() {
+        let lines: Vec<String> = (0..100)
+            .map(|i| format!("https://example.com/{}", i))
+            .collect();
+        let shards: Vec<Shard> = (1..=3).map(|index| Shard { index, count: 3 }).collect();
+        for line in &lines {
+            assert_eq!(shards.iter().filter(|s| s.contains(line)).count(), 1);
+        }
+        for shard in &shards {
+            let size = lines.iter().filter(|line| shard.contains(line)).count();
+            assert!(size > 15, "shard {:?} got {} lines", shard, size);
+        }
+        // Surrounding whitespace does not move a line to another shard
+        assert_eq!(
+            shards[0].contains("https://example.com/0"),
+            shards[0].contains(" https://example.com/0 ")
+        );
+    }
+}
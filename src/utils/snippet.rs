@@ -0,0 +1,101 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Marker for text cut off a snippet
+const ELLIPSIS: &str = "...";
+
+/// Cut `text` to at most `max_chars` characters (--res-max-chars)
+///
+/// The snippet starts at the beginning of the text, or is centered on the
+/// byte range `around` when one is given. Cut-off ends are marked with "...".
+pub fn snippet(text: &str, max_chars: usize, around: Option<Range<usize>>) -> Cow<'_, str> {
+    let total = text.chars().count();
+    if total <= max_chars {
+        return Cow::Borrowed(text);
+    }
+    let start = match around {
+        Some(range) => {
+            let match_start = char_index(text, range.start);
+            let match_end = char_index(text, range.end);
+            ((match_start + match_end) / 2)
+                .saturating_sub(max_chars / 2)
+                .min(total - max_chars)
+        }
+        None => 0,
+    };
+    let end = start + max_chars;
+    let byte_start = byte_offset(text, start);
+    let byte_end = byte_offset(text, end);
+
+    let mut cut = String::with_capacity(byte_end - byte_start + 2 * ELLIPSIS.len());
+    if start > 0 {
+        cut.push_str(ELLIPSIS);
+    }
+    cut.push_str(&text[byte_start..byte_end]);
+    if end < total {
+        cut.push_str(ELLIPSIS);
+    }
+    Cow::Owned(cut)
+}
+
+/// The byte range `range` of `text` with up to `chars` characters on each side
+///
+/// Cut-off ends are marked with "...".
+pub fn surrounding(text: &str, range: Range<usize>, chars: usize) -> String {
+    let start = char_index(text, range.start).saturating_sub(chars);
+    let end = char_index(text, range.end) + chars;
+    let byte_start = byte_offset(text, start);
+    let byte_end = byte_offset(text, end);
+
+    let mut cut = String::with_capacity(byte_end - byte_start + 2 * ELLIPSIS.len());
+    if byte_start > 0 {
+        cut.push_str(ELLIPSIS);
+    }
+    cut.push_str(&text[byte_start..byte_end]);
+    if byte_end < text.len() {
+        cut.push_str(ELLIPSIS);
+    }
+    cut
+}
+
+/// Number of characters before byte offset `byte`
+fn char_index(text: &str, byte: usize) -> usize {
+    text.char_indices().take_while(|(i, _)| *i < byte).count()
+}
+
+/// Byte offset of the character at index `chars`, or the end of the text
+fn byte_offset(text: &str, chars: usize) -> usize {
+    text.char_indices()
+        .nth(chars)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet() {
+        assert_eq!(snippet("short", 10, None), "short");
+        assert_eq!(snippet("0123456789abcdef", 4, None), "0123...");
+
+        let text = "aaaaaaaaaaTOKENbbbbbbbbbb";
+        let at = text.find("TOKEN").unwrap();
+        assert_eq!(snippet(text, 9, Some(at..at + 5)), "...aaTOKENbb...");
+        assert_eq!(snippet(text, 9, Some(0..1)), "aaaaaaaaa...");
+        assert_eq!(
+            snippet(text, 9, Some(text.len() - 1..text.len())),
+            "...bbbbbbbbb"
+        );
+
+        assert_eq!(snippet("héllo wörld", 5, None), "héllo...");
+    }
+
+    #[test]
+    fn test_surrounding() {
+        let text = "0123456789";
+        assert_eq!(surrounding(text, 4..6, 2), "...234567...");
+        assert_eq!(surrounding(text, 1..2, 3), "01234...");
+        assert_eq!(surrounding(text, 8..10, 5), "...3456789");
+    }
+}
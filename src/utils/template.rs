@@ -0,0 +1,140 @@
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+
+use crate::types::Cli;
+
+/// Variables for `{{name}}` placeholders in requests
+///
+/// Values come from --var, then --vars-file, then the environment.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVars {
+    vars: HashMap<String, String>,
+}
+
+impl TemplateVars {
+    /// Collect the variables given on the command line
+    pub fn from_cli(cli: &Cli) -> Result<Self> {
+        let mut vars = HashMap::new();
+        if let Some(path) = &cli.vars_file {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read vars file: {}", path))?;
+            vars.extend(parse_vars_file(&content).with_context(|| format!("In {}", path))?);
+        }
+        for var in &cli.var {
+            let Some((name, value)) = var.split_once('=') else {
+                bail!("Invalid --var '{}', expected KEY=VALUE", var);
+            };
+            vars.insert(name.trim().to_string(), value.to_string());
+        }
+        Ok(TemplateVars { vars })
+    }
+
+    /// Set a variable, replacing any earlier value
+    pub fn insert(&mut self, name: String, value: String) {
+        self.vars.insert(name, value);
+    }
+
+    /// Value of a variable, falling back to the environment
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.vars
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    }
+
+    /// Replace `{{name}}` placeholders in `text` with their values
+    ///
+    /// Whitespace inside the braces is ignored; unknown placeholders are left as-is.
+    pub fn render(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(open) = rest.find("{{") {
+            let Some(close) = rest[open + 2..].find("}}").map(|i| i + open + 2) else {
+                break;
+            };
+            output.push_str(&rest[..open]);
+            match self.get(rest[open + 2..close].trim()) {
+                Some(value) => output.push_str(&value),
+                None => output.push_str(&rest[open..close + 2]),
+            }
+            rest = &rest[close + 2..];
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+/// Parse `KEY=VALUE` lines; blank lines and `#` comments are skipped
+fn parse_vars_file(content: &str) -> Result<HashMap<String, String>> {
+    let mut vars = HashMap::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            bail!("line {}: expected KEY=VALUE", number + 1);
+        };
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        vars.insert(name.trim().to_string(), value.to_string());
+    }
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_render_template() {
+        let mut vars = TemplateVars::default();
+        vars.insert("host".to_string(), "api.example.com".to_string());
+        vars.insert("id".to_string(), "42".to_string());
+        assert_eq!(
+            vars.render("https://{{host}}/users/{{ id }}"),
+            "https://api.example.com/users/42"
+        );
+        assert_eq!(
+            vars.render("{{reqs_missing_var}} {{id}} {{"),
+            "{{reqs_missing_var}} 42 {{"
+        );
+        assert_eq!(vars.render("no placeholders"), "no placeholders");
+    }
+
+    #[test]
+    fn test_template_vars_precedence() {
+        let path = std::env::temp_dir().join(format!("reqs-vars-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "# staging\nhost = staging.example.com\ntoken=\"abc\"\n",
+        )
+        .unwrap();
+
+        let cli = Cli::parse_from([
+            "reqs",
+            "--vars-file",
+            path.to_str().unwrap(),
+            "--var",
+            "host=prod.example.com",
+        ]);
+        let vars = TemplateVars::from_cli(&cli).unwrap();
+        assert_eq!(
+            vars.render("https://{{host}}/?t={{token}}"),
+            "https://prod.example.com/?t=abc"
+        );
+        assert_eq!(vars.get("PATH"), std::env::var("PATH").ok());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_vars_file_invalid() {
+        assert!(parse_vars_file("ok=1\nnot a var\n").is_err());
+        assert!(TemplateVars::from_cli(&Cli::parse_from(["reqs", "--var", "x"])).is_err());
+    }
+}